@@ -0,0 +1,114 @@
+//! Agent action loop with a statically and a dynamically dispatched computer
+//!
+//! Each iteration runs 1000 mock `move` actions, requested in a single response
+//! from a local stand-in for the API. Run with `cargo bench --bench agent_dispatch`.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use openai_cua_desktop::agent::{Agent, DynAgent};
+use openai_cua_desktop::api::OpenAIClient;
+use openai_cua_desktop::computer::Computer;
+use openai_cua_desktop::mock::MockComputer;
+use serde_json::json;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::runtime::Runtime;
+
+/// Actions the model requests per run
+const ACTIONS: usize = 1000;
+
+/// Serve the actions on even requests and the final answer on odd ones
+async fn serve(listener: TcpListener) {
+    let actions: Vec<_> = (0..ACTIONS)
+        .map(|i| json!({
+            "type": "computer_call",
+            "id": format!("cc_{}", i),
+            "call_id": format!("call_{}", i),
+            "action": { "type": "move", "x": i % 100, "y": i % 80 },
+            "pending_safety_checks": [],
+        }))
+        .collect();
+    let bodies = [
+        json!({ "output": actions }).to_string(),
+        json!({ "output": [{ "type": "message", "role": "assistant", "content": [{ "type": "output_text", "text": "Done" }] }] }).to_string(),
+    ];
+    
+    let mut requests = 0;
+    while let Ok((stream, _)) = listener.accept().await {
+        let _ = respond(stream, &bodies[requests % 2]).await;
+        requests += 1;
+    }
+}
+
+/// Read one request and answer it with `body`
+async fn respond(mut stream: TcpStream, body: &str) -> std::io::Result<()> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 65536];
+    let header_end = loop {
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            return Ok(());
+        }
+        buffer.extend_from_slice(&chunk[..n]);
+        if let Some(pos) = buffer.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos + 4;
+        }
+    };
+    let head = String::from_utf8_lossy(&buffer[..header_end]).to_lowercase();
+    let content_length = head
+        .lines()
+        .find_map(|line| line.strip_prefix("content-length:"))
+        .and_then(|value| value.trim().parse::<usize>().ok())
+        .unwrap_or(0);
+    while buffer.len() < header_end + content_length {
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            break;
+        }
+        buffer.extend_from_slice(&chunk[..n]);
+    }
+    
+    let response = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+fn client(base_url: &str) -> OpenAIClient {
+    OpenAIClient::new("bench_key".to_string(), None, Some("bench_model".to_string())).with_base_url(base_url)
+}
+
+fn computer() -> MockComputer {
+    let computer = MockComputer::new("linux", 100, 80);
+    computer.set_screenshot("c2NyZWVu");
+    computer
+}
+
+fn bench_dispatch(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let listener = runtime.block_on(TcpListener::bind("127.0.0.1:0")).unwrap();
+    let base_url = format!("http://{}/v1", listener.local_addr().unwrap());
+    runtime.spawn(serve(listener));
+    
+    let mut group = c.benchmark_group("1000 mock actions");
+    group.sample_size(20);
+    group.bench_function("Agent<MockComputer>", |b| {
+        b.to_async(&runtime).iter(|| async {
+            let agent = Agent::new(client(&base_url), computer(), Vec::new(), None).with_print_steps(false);
+            agent.run("Move around").await.unwrap()
+        })
+    });
+    group.bench_function("DynAgent", |b| {
+        b.to_async(&runtime).iter(|| async {
+            let computer: Box<dyn Computer> = Box::new(computer());
+            let agent: DynAgent = Agent::new(client(&base_url), computer, Vec::new(), None).with_print_steps(false);
+            agent.run("Move around").await.unwrap()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_dispatch);
+criterion_main!(benches);
@@ -1,15 +1,133 @@
 // src/agent.rs - Updated to match OpenAI CUA approach
 
-use crate::computer::Computer;
-use crate::api::OpenAIClient;
+use crate::computer::{
+    area_contains, ActionRecord, Computer, ScreenArea, DEFAULT_PAGE_SOURCE_LIMIT, DRAG_AND_DROP_STEPS, MAX_KEY_HOLD_MS,
+};
+use crate::api::{self, ApiResponse, ModelParameters, OpenAIClient, Usage};
+use crate::compression::{self, CompressionConfig, ScreenshotCompressor, ScreenshotFormat, ScreenshotProcessor};
 use crate::error::CuaError;
+use crate::fallback::FallbackComputer;
+use crate::rate_limiter::ActionRateLimiter;
+use crate::observer::{AgentObserver, CompositeObserver, UsageStats};
+use crate::ocr;
+use crate::sanitizer::InputSanitizer;
+use crate::script::{self, AgentScript};
+use crate::telemetry::EventLog;
+use crate::tools::{self, registry::ToolRegistry, search::WebSearchTool, ToolFn};
+use crate::vision;
+use crate::watermark::{self, WatermarkConfig};
+use futures::future::BoxFuture;
+use futures::stream::{self, StreamExt};
+use image::imageops::FilterType;
+use jsonschema::JSONSchema;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
-use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant, SystemTime};
 
 /// Safety check callback type
 pub type SafetyCheckCallback = Box<dyn Fn(&str) -> bool + Send + Sync>;
 
+/// Observation filter type; items it rejects are kept in the conversation but not handled
+pub type ObservationFilter = Box<dyn Fn(&Value) -> bool + Send + Sync>;
+
+/// Observation filter that passes only `computer_call` and `computer_call_output` items
+///
+/// Messages and function calls are not handled, so use it only without function tools.
+pub fn computer_call_only(item: &Value) -> bool {
+    matches!(
+        item.get("type").and_then(|t| t.as_str()),
+        Some("computer_call") | Some("computer_call_output")
+    )
+}
+
+/// Hook run around computer actions with the action type and a value
+///
+/// Before an action the value is the action's parameters; after it, the base64
+/// screenshot sent to the model. Returning `Err` from a before-hook vetoes the action.
+pub type ActionHook = Box<dyn Fn(&str, &Value) -> BoxFuture<'static, Result<(), CuaError>> + Send + Sync>;
+
+/// Creates the computer each task of [`Agent::run_batch_tasks`] runs on
+pub type ComputerFactory<C> = dyn Fn() -> Result<C, CuaError> + Send + Sync;
+
+/// Question the image annotation model answers about each screenshot
+const ANNOTATION_PROMPT: &str = "Describe what you see in one sentence.";
+
+/// How many recent screenshot descriptions are kept for reuse
+const ANNOTATION_CACHE_SIZE: usize = 8;
+
+/// Question asked with the marked screenshot when vision grounding is enabled
+const GROUNDING_PROMPT: &str = "The red circle marks where the next action will point. \
+    Is it on the intended target? Answer yes or no.";
+
+/// Instruction sent with the schema when structured output mode is enabled
+const STRUCTURED_OUTPUT_PROMPT: &str = "When the task is complete, give your final answer as a single JSON value \
+    conforming to this JSON Schema, with no other text:";
+
+/// Request for the one-paragraph session summary, followed by the transcript
+const SUMMARY_PROMPT: &str = "Summarize in one paragraph what was done in this computer-use session, \
+    as a list of the actions taken, e.g. \"Opened Chrome, navigated to gmail.com, composed an email, sent it.\" \
+    Transcript:";
+
+/// Model `Agent::summarize_session` uses unless set with `Agent::with_summary_model`
+pub const DEFAULT_SUMMARY_MODEL: &str = "gpt-4o-mini";
+
+/// Times an adaptive-delay agent waits and retries a rate-limited request before giving up
+pub const MAX_RATE_LIMIT_RETRIES: u32 = 5;
+
+/// Wait before retrying a rate-limited request that came without a `Retry-After` header
+pub const DEFAULT_RATE_LIMIT_DELAY: Duration = Duration::from_secs(1);
+
+/// How often a paused agent checks whether the battery has recovered
+pub const BATTERY_POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Actions that can be repeated without changing the outcome, the only ones
+/// [`ErrorRecoveryStrategy::RetryN`] retries
+///
+/// A failed click or keypress may still have reached the application, so
+/// repeating it could click twice or type a key twice.
+pub const IDEMPOTENT_ACTIONS: &[&str] = &["screenshot", "move", "hover", "wait", "mouse_up"];
+
+/// One line describing a conversation item for the session summary, or `None` for outputs and reasoning
+fn transcript_line(item: &Value) -> Option<String> {
+    match item.get("type").and_then(|t| t.as_str()) {
+        Some("computer_call") => Some(format!("Action: {}", item.get("action")?)),
+        Some("function_call") => Some(format!(
+            "Tool call: {}({})",
+            item.get("name")?.as_str()?,
+            item.get("arguments").and_then(|a| a.as_str()).unwrap_or("")
+        )),
+        _ => {
+            let role = item.get("role")?.as_str()?;
+            let text = item.get("content")?.as_str().or_else(|| message_text(item))?;
+            Some(format!("{}: {}", role, text))
+        }
+    }
+}
+
+/// The text of a `message` item, if it has any
+fn message_text(item: &Value) -> Option<&str> {
+    item.get("content")
+        .and_then(|c| c.as_array())
+        .and_then(|content| content.first())
+        .and_then(|text_obj| text_obj.get("text"))
+        .and_then(|t| t.as_str())
+}
+
+/// Hex hash identifying a base64 screenshot
+fn screenshot_hash(screenshot: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    screenshot.hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
 /// Default safety check callback that always returns true
 pub fn default_safety_check_callback(_message: &str) -> bool {
     println!("Safety check: {}", _message);
@@ -21,441 +139,4727 @@ pub fn default_safety_check_callback(_message: &str) -> bool {
     input.trim().to_lowercase() == "y"
 }
 
+/// Statistics for one request/response cycle of the agent loop
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TurnRecord {
+    /// 1-based index of the turn over the agent's lifetime
+    pub turn: usize,
+    /// Size of the screenshots captured during the turn (base64 bytes)
+    pub screenshot_bytes: usize,
+    /// Size of the screenshots actually uploaded after compression (base64 bytes)
+    pub uploaded_bytes: usize,
+    /// Hash of the last screenshot uploaded during the turn, as in [`AgentState::last_screenshot_hash`]
+    pub screenshot_hash: Option<String>,
+    /// Screenshots not uploaded because they matched the previous one (see `with_screenshot_diff_threshold`)
+    pub skipped_uploads: usize,
+}
+
+impl TurnRecord {
+    /// Ratio of uploaded to captured screenshot size, if any screenshot was taken
+    pub fn compression_ratio(&self) -> Option<f64> {
+        if self.screenshot_bytes == 0 {
+            None
+        } else {
+            Some(self.uploaded_bytes as f64 / self.screenshot_bytes as f64)
+        }
+    }
+}
+
+/// Outcome of one task run by [`Agent::run_batch_tasks`]
+#[derive(Debug)]
+pub struct AgentResult {
+    /// The task's input
+    pub input: String,
+    /// Conversation items at the end of the task, empty if it failed
+    pub items: Vec<Value>,
+    /// How long the task took
+    pub duration: Duration,
+    /// Why the task failed, if it did
+    pub error: Option<CuaError>,
+    /// The final answer parsed as JSON, in structured output mode (see `Agent::with_structured_output_mode`)
+    pub structured: Option<Value>,
+}
+
+/// Conversation saved by [`Agent::save_state`], with enough context to resume it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// Model the conversation was run with
+    pub model: String,
+    /// Turns the agent had completed when the checkpoint was saved
+    pub turns: usize,
+    /// Seconds since the Unix epoch
+    pub timestamp: u64,
+    /// Conversation items to pass to `Agent::run_full_turn` to resume
+    pub items: Vec<Value>,
+}
+
+impl Checkpoint {
+    /// Read a checkpoint written by [`Agent::save_state`]
+    pub fn load(path: &Path) -> Result<Self, CuaError> {
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    }
+}
+
+/// Snapshot of an agent's runtime state, see [`Agent::introspect`]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AgentState {
+    /// Request/response cycles so far
+    pub turn_count: usize,
+    pub total_tokens: UsageStats,
+    /// Items in the conversation as of the last completed turn or step
+    pub current_items_count: usize,
+    pub last_action_type: Option<String>,
+    /// Hex hash of the last captured screenshot
+    pub last_screenshot_hash: Option<String>,
+    pub warnings_count: usize,
+}
+
+/// What the agent does when a computer action fails
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ErrorRecoveryStrategy {
+    /// Stop and return the error
+    #[default]
+    Abort,
+    /// Show the model the error and a screenshot of the current screen, then continue
+    TakeScreenshotAndContinue,
+    /// Retry an idempotent action (see [`IDEMPOTENT_ACTIONS`]) up to N more times
+    /// before returning the error; other actions fail on the first error
+    RetryN(u32),
+    /// Tell the model about the error and let it decide what to do
+    AskModel,
+}
+
+/// A computer action the model wants to perform, returned without being executed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlannedAction {
+    /// Action type as used by the CUA model (e.g. "click", "type")
+    pub action_type: String,
+    /// The full action object from the `computer_call`
+    pub params: Value,
+}
+
+impl PlannedAction {
+    /// The action of a `computer_call` item, or `None` for other items
+    fn from_computer_call(item: &Value) -> Option<Self> {
+        if item.get("type").and_then(|t| t.as_str()) != Some("computer_call") {
+            return None;
+        }
+        let action = item.get("action")?;
+        Some(Self {
+            action_type: action.get("type").and_then(|t| t.as_str()).unwrap_or("").to_string(),
+            params: action.clone(),
+        })
+    }
+}
+
+/// Outcome of a single [`Agent::step`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct StepResult {
+    /// Items the step added: the response output and the outputs of the handled calls
+    pub new_items: Vec<Value>,
+    /// Whether the model gave its final answer
+    pub is_complete: bool,
+    /// The last computer action performed during the step
+    pub last_action: Option<PlannedAction>,
+}
+
+/// How [`Agent::merge_fork`] combines the conversations of an agent and its fork
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeStrategy {
+    /// Keep the conversation with more items, preferring the agent's own on a tie
+    TakeLongest,
+    /// Keep the conversation with fewer items, preferring the agent's own on a tie
+    TakeShortest,
+    /// Keep the agent's conversation followed by the fork's items after the fork point
+    Concatenate,
+}
+
+/// Build a synthetic `computer_call_output` item carrying a PNG screenshot
+fn screenshot_item(base64: &str) -> Value {
+    json!({
+        "type": "computer_call_output",
+        "call_id": format!("call_{}", uuid::Uuid::new_v4().simple()),
+        "acknowledged_safety_checks": [],
+        "output": {
+            "type": "input_image",
+            "image_url": format!("data:image/png;base64,{}", base64),
+        },
+    })
+}
+
+/// Screen points an action touches, in the screen space of [`perform_screen_action`]
+fn action_points(action_type: &str, action: &Value) -> Vec<(i32, i32)> {
+    let field = |value: &Value, name: &str| value.get(name).and_then(|v| v.as_i64()).map(|v| v as i32);
+    let mut points = Vec::new();
+    for (x, y) in [("x", "y"), ("from_x", "from_y"), ("to_x", "to_y")] {
+        if let (Some(x), Some(y)) = (field(action, x), field(action, y)) {
+            points.push((x, y));
+        }
+    }
+    if action_type == "drag_by_delta" {
+        let (start_x, start_y) = (field(action, "start_x").unwrap_or(0), field(action, "start_y").unwrap_or(0));
+        points.push((start_x, start_y));
+        // An end point past the coordinate range is rejected by `drag_by_delta` itself
+        let end_x = start_x.checked_add(field(action, "delta_x").unwrap_or(0));
+        let end_y = start_y.checked_add(field(action, "delta_y").unwrap_or(0));
+        points.extend(end_x.zip(end_y));
+    }
+    if let Some(path) = action.get("path").and_then(|p| p.as_array()) {
+        points.extend(path.iter().filter_map(|point| Some((field(point, "x")?, field(point, "y")?))));
+    }
+    points
+}
+
+/// Perform a `computer_call` action whose coordinates are already in screen space
+///
+/// Returns `false` for actions that are not performed, such as unknown action
+/// types and `goto`, which needs browser navigation the computers lack.
+async fn perform_screen_action<C: Computer>(computer: &C, action_type: &str, action: &Value) -> Result<bool, CuaError> {
+    let field = |name: &str| action.get(name).and_then(|v| v.as_i64()).unwrap_or(0) as i32;
+    let text = || action.get("text").and_then(|t| t.as_str()).unwrap_or("");
+    match action_type {
+        // The screenshot is taken after every action
+        "screenshot" => {}
+        "click" => {
+            let button = match action.get("button").and_then(|b| b.as_str()).unwrap_or("left") {
+                // The API's name for the middle button
+                "wheel" => "middle",
+                button => button,
+            };
+            computer.click(field("x"), field("y"), button).await?;
+        }
+        "double_click" => computer.double_click(field("x"), field("y")).await?,
+        "scroll" => computer.scroll(field("x"), field("y"), field("scroll_x"), field("scroll_y")).await?,
+        "scroll_horizontal" => computer.scroll_horizontal(field("x"), field("y"), field("amount")).await?,
+        "scroll_vertical" => computer.scroll_vertical(field("x"), field("y"), field("amount")).await?,
+        "type" => computer.type_text(text()).await?,
+        "middle_click" => computer.mouse_wheel_click(field("x"), field("y")).await?,
+        "type_at" => computer.type_text_at(field("x"), field("y"), text()).await?,
+        "wait" => {
+            let ms = action.get("ms").and_then(|m| m.as_u64()).unwrap_or(1000) as u32;
+            computer.wait(ms).await?;
+        }
+        "move" => computer.move_cursor(field("x"), field("y")).await?,
+        "hover" => {
+            let duration_ms = action.get("duration_ms").and_then(|d| d.as_u64()).unwrap_or(1000) as u32;
+            computer.hover(field("x"), field("y"), duration_ms).await?;
+        }
+        "press_and_hold" => {
+            let key = action.get("key").and_then(|k| k.as_str()).unwrap_or("");
+            let duration_ms = action.get("duration_ms").and_then(|d| d.as_u64()).unwrap_or(1000).min(MAX_KEY_HOLD_MS as u64) as u32;
+            computer.press_and_hold(key, duration_ms).await?;
+        }
+        "keypress" => {
+            if let Some(keys) = action.get("keys").and_then(|k| k.as_array()) {
+                let keys: Vec<String> = keys.iter().filter_map(|k| k.as_str().map(str::to_string)).collect();
+                computer.keypress(&keys).await?;
+            }
+        }
+        "drag" => {
+            let path: Vec<HashMap<String, i32>> = action_points(action_type, action)
+                .into_iter()
+                .map(|(x, y)| HashMap::from([("x".to_string(), x), ("y".to_string(), y)]))
+                .collect();
+            if !path.is_empty() {
+                computer.drag(&path).await?;
+            }
+        }
+        "mouse_down" | "mouse_up" => {
+            let button = action.get("button").and_then(|b| b.as_str()).unwrap_or("left");
+            if action_type == "mouse_down" {
+                computer.mouse_button_down(button).await?;
+            } else {
+                computer.mouse_button_up(button).await?;
+            }
+        }
+        "drag_and_drop" => computer.drag_and_drop(field("from_x"), field("from_y"), field("to_x"), field("to_y")).await?,
+        "drag_by_delta" => {
+            let steps = action.get("steps").and_then(|s| s.as_u64()).unwrap_or(DRAG_AND_DROP_STEPS as u64) as u32;
+            computer.drag_by_delta(field("start_x"), field("start_y"), field("delta_x"), field("delta_y"), steps).await?;
+        }
+        _ => return Ok(false),
+    }
+    Ok(true)
+}
+
+/// Progress through the API requests of one turn
+#[derive(Default)]
+struct TurnState {
+    /// IDs of the items already in the conversation, to skip duplicates
+    processed_ids: HashSet<String>,
+    /// ID of the latest response, for stateless continuation
+    previous_response_id: Option<String>,
+    /// Items added after the latest response, which the API has not seen yet
+    unsent: Vec<Value>,
+}
+
+impl TurnState {
+    /// State for a turn starting from `items`
+    fn new(items: &[Value]) -> Self {
+        let processed_ids = items
+            .iter()
+            .filter_map(|item| item.get("id").and_then(|id| id.as_str()))
+            .map(str::to_string)
+            .collect();
+        Self { processed_ids, ..Default::default() }
+    }
+}
+
 /// Agent that manages the interaction between the model and computer
-pub struct Agent {
-    client: OpenAIClient,
-    computer: Box<dyn Computer>,
+///
+/// Generic over the computer so its actions are statically dispatched; use
+/// [`DynAgent`] when the computer type is only known at runtime. The client,
+/// computer and callbacks are shared with forks (see [`Agent::fork`]).
+pub struct Agent<C: Computer> {
+    client: Arc<OpenAIClient>,
+    computer: Arc<C>,
+    fallback_active: Option<Arc<AtomicBool>>,
     tools: Vec<Value>,
+    tool_fns: Vec<Arc<dyn ToolFn>>,
+    initial_items: Vec<Value>,
+    history: Mutex<Vec<Value>>,
     print_steps: bool,
     debug: bool,
     show_images: bool,
-    acknowledge_safety_check: SafetyCheckCallback,
+    validate_model: bool,
+    model_validated: AtomicBool,
+    input_sanitizer: Option<Arc<InputSanitizer>>,
+    /// Latest message from the model, the context passed to the input sanitizer
+    action_context: Mutex<String>,
+    action_history: Mutex<Vec<ActionRecord>>,
+    screenshot_compressor: Option<Arc<dyn ScreenshotCompressor>>,
+    screenshot_pipeline: Option<Arc<Vec<Box<dyn ScreenshotProcessor>>>>,
+    screenshot_diff_threshold: Option<f64>,
+    /// Last screenshot sent after an action: (captured, uploaded, MIME type)
+    last_upload: Mutex<Option<(String, String, &'static str)>>,
+    compression_config: CompressionConfig,
+    turn_records: Mutex<Vec<TurnRecord>>,
+    /// Screenshots sent to the model by [`screenshot_hash`], one copy each, for the turn records
+    uploaded_screenshots: Mutex<HashMap<String, String>>,
+    max_screenshot_size: Option<(u32, u32)>,
+    screenshot_scale: Mutex<f64>,
+    /// Capturable screen area, looked up on the first pointer action; `None` if the lookup failed
+    recording_area: OnceLock<Option<ScreenArea>>,
+    watermark: Option<WatermarkConfig>,
+    system_prompt: Option<String>,
+    thought_prefix: Option<String>,
+    strip_thought_prefix: bool,
+    stateless_continuation: bool,
+    rate_limiter: Option<Arc<ActionRateLimiter>>,
+    observation_filter: Option<Arc<ObservationFilter>>,
+    cursor_in_screenshot: bool,
+    screenshot_format: ScreenshotFormat,
+    annotation_model: Option<String>,
+    /// Recent (uploaded image, description) pairs, newest last
+    annotation_cache: Mutex<VecDeque<(String, String)>>,
+    summary_model: String,
+    session_summary: Mutex<Option<(usize, String)>>,
+    before_action_hook: Option<Arc<ActionHook>>,
+    after_action_hook: Option<Arc<ActionHook>>,
+    cost_budget: Option<f64>,
+    usage: Mutex<UsageStats>,
+    last_screenshot: Mutex<Option<String>>,
+    observers: CompositeObserver,
+    warnings_emitted: AtomicUsize,
+    settle_delay: Duration,
+    latency_compensation: bool,
+    inter_turn_delay: Duration,
+    step_delay: Option<(u64, u64)>,
+    error_recovery: ErrorRecoveryStrategy,
+    all_screens_mode: bool,
+    model_parameters: ModelParameters,
+    vision_grounding: bool,
+    grounding_prompt: String,
+    text_mode: bool,
+    /// Longest page source the `get_page_source` tool returns, and whether it removes scripts
+    page_source: (usize, bool),
+    /// How long to outline each action's target before performing it
+    action_highlight: Option<u32>,
+    /// Save the conversation to the path every this many turns
+    checkpoint: Option<(u32, PathBuf)>,
+    output_schema: Option<Value>,
+    /// `output_schema` compiled once, in `with_structured_output_mode`
+    output_validator: Option<Arc<JSONSchema>>,
+    /// Final answer of the last run that matched `output_schema`
+    structured_output: Mutex<Option<Value>>,
+    adaptive_delay: bool,
+    /// Whether the model may call the script tool, each call still needing the safety check
+    script_execution: bool,
+    battery_pause_threshold: Option<f32>,
+    memory_alerts: Vec<(String, u64)>,
+    /// Processes currently over their memory alert threshold, already reported to the model
+    memory_alerted: Mutex<HashSet<String>>,
+    measured_latency: Mutex<Option<Duration>>,
+    last_response_id: Mutex<Option<String>>,
+    last_model_version: Mutex<Option<String>>,
+    acknowledge_safety_check: Arc<SafetyCheckCallback>,
 }
 
-impl Agent {
-    /// Create a new agent with the specified client, computer, and tools
-    pub fn new(
-        client: OpenAIClient,
-        computer: Box<dyn Computer>,
-        mut tools: Vec<Value>,
-        acknowledge_safety_check: Option<SafetyCheckCallback>,
-    ) -> Self {
-        // Add computer tool
-        tools.push(json!({
-            "type": "computer-preview",
-            "display_width": computer.dimensions().0,
-            "display_height": computer.dimensions().1,
-            "environment": computer.environment(),
-        }));
-        
-        Self {
-            client,
-            computer,
-            tools,
-            print_steps: true,
-            debug: false,
-            show_images: false,
-            acknowledge_safety_check: acknowledge_safety_check
-                .unwrap_or_else(|| Box::new(default_safety_check_callback)),
-        }
+/// Agent controlling a computer chosen at runtime
+pub type DynAgent = Agent<Box<dyn Computer>>;
+
+/// Builder for [`DynAgent`] with setters that take `&mut self`, for conditional configuration
+///
+/// Unset options keep the `Agent` defaults. `client` and `computer` are required.
+#[derive(Default)]
+pub struct AgentBuilder {
+    client: Option<OpenAIClient>,
+    computer: Option<Box<dyn Computer>>,
+    tools: Vec<Value>,
+    safety_check: Option<SafetyCheckCallback>,
+    print_steps: Option<bool>,
+    debug: Option<bool>,
+    show_images: Option<bool>,
+    validate_model: Option<bool>,
+    fallback_computer: Option<Box<dyn Computer>>,
+    input_sanitizer: Option<InputSanitizer>,
+    screenshot_compressor: Option<Box<dyn ScreenshotCompressor>>,
+    screenshot_pipeline: Option<Vec<Box<dyn ScreenshotProcessor>>>,
+    screenshot_diff_threshold: Option<f64>,
+    compression_config: Option<CompressionConfig>,
+    max_screenshot_size: Option<(u32, u32)>,
+    system_prompt: Option<String>,
+    thought_prefix: Option<String>,
+    strip_thought_prefix: Option<bool>,
+    stateless_continuation: Option<bool>,
+    max_action_rate: Option<f64>,
+    observation_filter: Option<ObservationFilter>,
+    cursor_in_screenshot: Option<bool>,
+    screenshot_format: Option<ScreenshotFormat>,
+    annotation_model: Option<String>,
+    summary_model: Option<String>,
+    before_action_hook: Option<ActionHook>,
+    after_action_hook: Option<ActionHook>,
+    watermark: Option<WatermarkConfig>,
+    cost_budget: Option<f64>,
+    settle_delay: Option<Duration>,
+    latency_compensation: Option<bool>,
+    inter_turn_delay_ms: Option<u32>,
+    step_delay: Option<(u64, u64)>,
+    error_recovery: Option<ErrorRecoveryStrategy>,
+    all_screens_mode: Option<bool>,
+    model_parameters: Option<ModelParameters>,
+    vision_grounding: Option<bool>,
+    grounding_prompt: Option<String>,
+    text_mode: Option<bool>,
+    page_source: Option<(usize, bool)>,
+    action_highlight: Option<u32>,
+    checkpoint: Option<(u32, PathBuf)>,
+    output_schema: Option<Value>,
+    adaptive_delay: Option<bool>,
+    script_execution: Option<bool>,
+    battery_pause_threshold: Option<f32>,
+    memory_alerts: Vec<(String, u64)>,
+    observers: Vec<Box<dyn AgentObserver>>,
+    event_log: Option<PathBuf>,
+}
+
+impl AgentBuilder {
+    /// Set the API client (required)
+    pub fn client(&mut self, client: OpenAIClient) -> &mut Self {
+        self.client = Some(client);
+        self
     }
     
-    /// Set whether to print steps
-    pub fn with_print_steps(mut self, print_steps: bool) -> Self {
-        self.print_steps = print_steps;
+    /// Set the computer the agent controls (required)
+    pub fn computer(&mut self, computer: Box<dyn Computer>) -> &mut Self {
+        self.computer = Some(computer);
         self
     }
     
-    /// Set whether to print debug information
-    pub fn with_debug(mut self, debug: bool) -> Self {
-        self.debug = debug;
+    /// Add a tool besides the computer tool and the built-in function tools
+    pub fn tool(&mut self, tool: Value) -> &mut Self {
+        self.tools.push(tool);
         self
     }
     
-    /// Set whether to show images
-    pub fn with_show_images(mut self, show_images: bool) -> Self {
-        self.show_images = show_images;
+    /// Set the callback that acknowledges safety checks
+    pub fn safety_check(&mut self, callback: SafetyCheckCallback) -> &mut Self {
+        self.safety_check = Some(callback);
         self
     }
     
-    /// Debug print a value
-    fn debug_print(&self, value: &impl fmt::Debug) {
-        if self.debug {
-            println!("{:?}", value);
-        }
+    /// See [`Agent::with_print_steps`]
+    pub fn print_steps(&mut self, print_steps: bool) -> &mut Self {
+        self.print_steps = Some(print_steps);
+        self
     }
     
-    /// Handle an item from the API response
-    async fn handle_item(&self, item: &Value) -> Result<Vec<Value>, CuaError> {
-        let mut new_items = Vec::new();
+    /// See [`Agent::with_debug`]
+    pub fn debug(&mut self, debug: bool) -> &mut Self {
+        self.debug = Some(debug);
+        self
+    }
+    
+    /// See [`Agent::with_show_images`]
+    pub fn show_images(&mut self, show_images: bool) -> &mut Self {
+        self.show_images = Some(show_images);
+        self
+    }
+    
+    /// See [`Agent::with_validate_model`]
+    pub fn validate_model(&mut self, validate_model: bool) -> &mut Self {
+        self.validate_model = Some(validate_model);
+        self
+    }
+    
+    /// See [`Agent::with_fallback_computer`]
+    pub fn fallback_computer(&mut self, fallback: Box<dyn Computer>) -> &mut Self {
+        self.fallback_computer = Some(fallback);
+        self
+    }
+    
+    /// See [`Agent::with_input_sanitizer`]
+    pub fn input_sanitizer(&mut self, sanitizer: InputSanitizer) -> &mut Self {
+        self.input_sanitizer = Some(sanitizer);
+        self
+    }
+    
+    /// See [`Agent::with_screenshot_compressor`]
+    pub fn screenshot_compressor(&mut self, compressor: Box<dyn ScreenshotCompressor>) -> &mut Self {
+        self.screenshot_compressor = Some(compressor);
+        self
+    }
+    
+    /// See [`Agent::with_screenshot_compression_pipeline`]
+    pub fn screenshot_compression_pipeline(&mut self, processors: Vec<Box<dyn ScreenshotProcessor>>) -> &mut Self {
+        self.screenshot_pipeline = Some(processors);
+        self
+    }
+    
+    /// See [`Agent::with_screenshot_diff_threshold`]
+    pub fn screenshot_diff_threshold(&mut self, threshold: f64) -> &mut Self {
+        self.screenshot_diff_threshold = Some(threshold);
+        self
+    }
+    
+    /// See [`Agent::with_compression_config`]
+    pub fn compression_config(&mut self, config: CompressionConfig) -> &mut Self {
+        self.compression_config = Some(config);
+        self
+    }
+    
+    /// See [`Agent::with_max_screenshot_size`]
+    pub fn max_screenshot_size(&mut self, width: u32, height: u32) -> &mut Self {
+        self.max_screenshot_size = Some((width, height));
+        self
+    }
+    
+    /// See [`Agent::with_system_prompt`]
+    pub fn system_prompt(&mut self, prompt: &str) -> &mut Self {
+        self.system_prompt = Some(prompt.to_string());
+        self
+    }
+    
+    /// See [`Agent::with_stateless_continuation`]
+    pub fn stateless_continuation(&mut self, stateless: bool) -> &mut Self {
+        self.stateless_continuation = Some(stateless);
+        self
+    }
+    
+    /// See [`Agent::with_thought_prefix`]
+    pub fn thought_prefix(&mut self, prefix: &str) -> &mut Self {
+        self.thought_prefix = Some(prefix.to_string());
+        self
+    }
+    
+    /// See [`Agent::with_strip_thought_prefix`]
+    pub fn strip_thought_prefix(&mut self, strip: bool) -> &mut Self {
+        self.strip_thought_prefix = Some(strip);
+        self
+    }
+    
+    /// See [`Agent::with_max_action_rate`]
+    pub fn max_action_rate(&mut self, actions_per_minute: f64) -> &mut Self {
+        self.max_action_rate = Some(actions_per_minute);
+        self
+    }
+    
+    /// See [`Agent::with_observation_filter`]
+    pub fn observation_filter(&mut self, filter: ObservationFilter) -> &mut Self {
+        self.observation_filter = Some(filter);
+        self
+    }
+    
+    /// See [`Agent::with_cursor_in_screenshot`]
+    pub fn cursor_in_screenshot(&mut self, cursor_in_screenshot: bool) -> &mut Self {
+        self.cursor_in_screenshot = Some(cursor_in_screenshot);
+        self
+    }
+    
+    /// See [`Agent::with_screenshot_format`]
+    pub fn screenshot_format(&mut self, format: ScreenshotFormat) -> &mut Self {
+        self.screenshot_format = Some(format);
+        self
+    }
+    
+    /// See [`Agent::with_image_annotation_model`]
+    pub fn image_annotation_model(&mut self, model: &str) -> &mut Self {
+        self.annotation_model = Some(model.to_string());
+        self
+    }
+    
+    /// See [`Agent::with_summary_model`]
+    pub fn summary_model(&mut self, model: &str) -> &mut Self {
+        self.summary_model = Some(model.to_string());
+        self
+    }
+    
+    /// See [`Agent::with_before_action_hook`]
+    pub fn before_action_hook(&mut self, hook: ActionHook) -> &mut Self {
+        self.before_action_hook = Some(hook);
+        self
+    }
+    
+    /// See [`Agent::with_after_action_hook`]
+    pub fn after_action_hook(&mut self, hook: ActionHook) -> &mut Self {
+        self.after_action_hook = Some(hook);
+        self
+    }
+    
+    /// See [`Agent::with_watermark_config`]
+    pub fn watermark(&mut self, config: WatermarkConfig) -> &mut Self {
+        self.watermark = Some(config);
+        self
+    }
+    
+    /// See [`Agent::with_cost_budget`]
+    pub fn cost_budget(&mut self, max_usd: f64) -> &mut Self {
+        self.cost_budget = Some(max_usd);
+        self
+    }
+    
+    /// See [`Agent::with_settle_delay`]
+    pub fn settle_delay(&mut self, settle_delay: Duration) -> &mut Self {
+        self.settle_delay = Some(settle_delay);
+        self
+    }
+    
+    /// See [`Agent::with_latency_compensation`]
+    pub fn latency_compensation(&mut self, latency_compensation: bool) -> &mut Self {
+        self.latency_compensation = Some(latency_compensation);
+        self
+    }
+    
+    /// See [`Agent::with_inter_turn_delay`]
+    pub fn inter_turn_delay(&mut self, ms: u32) -> &mut Self {
+        self.inter_turn_delay_ms = Some(ms);
+        self
+    }
+    
+    /// See [`Agent::with_step_delay`]
+    pub fn step_delay(&mut self, min_ms: u64, max_ms: u64) -> &mut Self {
+        self.step_delay = Some((min_ms, max_ms));
+        self
+    }
+    
+    /// See [`Agent::with_error_recovery_strategy`]
+    pub fn error_recovery_strategy(&mut self, strategy: ErrorRecoveryStrategy) -> &mut Self {
+        self.error_recovery = Some(strategy);
+        self
+    }
+    
+    /// See [`Agent::with_all_screens_mode`]
+    pub fn all_screens_mode(&mut self, enabled: bool) -> &mut Self {
+        self.all_screens_mode = Some(enabled);
+        self
+    }
+    
+    /// See [`Agent::with_model_parameters`]
+    pub fn model_parameters(&mut self, parameters: ModelParameters) -> &mut Self {
+        self.model_parameters = Some(parameters);
+        self
+    }
+    
+    /// See [`Agent::with_vision_grounding`]
+    pub fn vision_grounding(&mut self, enabled: bool) -> &mut Self {
+        self.vision_grounding = Some(enabled);
+        self
+    }
+    
+    /// See [`Agent::with_vision_grounding_prompt`]
+    pub fn vision_grounding_prompt(&mut self, prompt: &str) -> &mut Self {
+        self.grounding_prompt = Some(prompt.to_string());
+        self
+    }
+    
+    /// See [`Agent::with_text_mode`]
+    pub fn text_mode(&mut self, enabled: bool) -> &mut Self {
+        self.text_mode = Some(enabled);
+        self
+    }
+    
+    /// See [`Agent::with_page_source_options`]
+    pub fn page_source_options(&mut self, max_bytes: usize, strip_scripts: bool) -> &mut Self {
+        self.page_source = Some((max_bytes, strip_scripts));
+        self
+    }
+    
+    /// See [`Agent::with_action_highlight`]
+    pub fn action_highlight(&mut self, duration_ms: u32) -> &mut Self {
+        self.action_highlight = Some(duration_ms);
+        self
+    }
+    
+    /// See [`Agent::with_checkpoint_interval`]
+    pub fn checkpoint_interval(&mut self, turns: u32, checkpoint_path: PathBuf) -> &mut Self {
+        self.checkpoint = Some((turns, checkpoint_path));
+        self
+    }
+    
+    /// See [`Agent::with_structured_output_mode`]
+    pub fn structured_output_mode(&mut self, output_schema: Value) -> &mut Self {
+        self.output_schema = Some(output_schema);
+        self
+    }
+    
+    /// See [`Agent::with_adaptive_delay`]
+    pub fn adaptive_delay(&mut self, adaptive_delay: bool) -> &mut Self {
+        self.adaptive_delay = Some(adaptive_delay);
+        self
+    }
+    
+    /// See [`Agent::with_script_execution`]
+    pub fn script_execution(&mut self, enabled: bool) -> &mut Self {
+        self.script_execution = Some(enabled);
+        self
+    }
+    
+    /// See [`Agent::with_battery_pause_threshold`]
+    pub fn battery_pause_threshold(&mut self, threshold: f32) -> &mut Self {
+        self.battery_pause_threshold = Some(threshold);
+        self
+    }
+    
+    /// See [`Agent::with_memory_alert_threshold`]
+    pub fn memory_alert_threshold(&mut self, process: &str, bytes: u64) -> &mut Self {
+        self.memory_alerts.push((process.to_string(), bytes));
+        self
+    }
+    
+    /// See [`Agent::with_observer`]; may be called repeatedly
+    pub fn observer(&mut self, observer: Box<dyn AgentObserver>) -> &mut Self {
+        self.observers.push(observer);
+        self
+    }
+    
+    /// See [`Agent::with_event_log`]
+    pub fn event_log(&mut self, path: &Path) -> &mut Self {
+        self.event_log = Some(path.to_path_buf());
+        self
+    }
+    
+    /// Build the agent, taking the configuration out of the builder
+    ///
+    /// Fails if `client` or `computer` is missing, the client has no model
+    /// name, or the computer reports a zero dimension.
+    pub fn build(&mut self) -> Result<DynAgent, CuaError> {
+        let client = self.client.take()
+            .ok_or_else(|| CuaError::Other("AgentBuilder: client is required".to_string()))?;
+        if client.model().trim().is_empty() {
+            return Err(CuaError::Other("AgentBuilder: model name must not be empty".to_string()));
+        }
+        let computer = self.computer.take()
+            .ok_or_else(|| CuaError::Other("AgentBuilder: computer is required".to_string()))?;
+        let (width, height) = computer.dimensions();
+        if width == 0 || height == 0 {
+            return Err(CuaError::Other(format!(
+                "AgentBuilder: computer dimensions must be non-zero, got {}x{}",
+                width, height
+            )));
+        }
         
-        // Check the item type
-        let item_type = item.get("type")
-            .and_then(|t| t.as_str())
-            .unwrap_or("");
+        let mut agent = Agent::new(client, computer, std::mem::take(&mut self.tools), self.safety_check.take());
+        if let Some(fallback) = self.fallback_computer.take() {
+            agent = agent.with_fallback_computer(fallback);
+        }
+        if let Some(print_steps) = self.print_steps {
+            agent = agent.with_print_steps(print_steps);
+        }
+        if let Some(debug) = self.debug {
+            agent = agent.with_debug(debug);
+        }
+        if let Some(show_images) = self.show_images {
+            agent = agent.with_show_images(show_images);
+        }
+        if let Some(validate_model) = self.validate_model {
+            agent = agent.with_validate_model(validate_model);
+        }
+        if let Some(sanitizer) = self.input_sanitizer.take() {
+            agent = agent.with_input_sanitizer(sanitizer);
+        }
+        if let Some(compressor) = self.screenshot_compressor.take() {
+            agent = agent.with_screenshot_compressor(compressor);
+        }
+        if let Some(processors) = self.screenshot_pipeline.take() {
+            agent = agent.with_screenshot_compression_pipeline(processors);
+        }
+        if let Some(threshold) = self.screenshot_diff_threshold {
+            agent = agent.with_screenshot_diff_threshold(threshold)?;
+        }
+        if let Some(config) = self.compression_config.take() {
+            agent = agent.with_compression_config(config);
+        }
+        if let Some((width, height)) = self.max_screenshot_size {
+            agent = agent.with_max_screenshot_size(width, height);
+        }
+        if let Some(prompt) = self.system_prompt.take() {
+            agent = agent.with_system_prompt(&prompt);
+        }
+        if let Some(stateless) = self.stateless_continuation {
+            agent = agent.with_stateless_continuation(stateless);
+        }
+        if let Some(prefix) = self.thought_prefix.take() {
+            agent = agent.with_thought_prefix(&prefix);
+        }
+        if let Some(strip) = self.strip_thought_prefix {
+            agent = agent.with_strip_thought_prefix(strip);
+        }
+        if let Some(rate) = self.max_action_rate {
+            agent = agent.with_max_action_rate(rate)?;
+        }
+        if let Some(filter) = self.observation_filter.take() {
+            agent = agent.with_observation_filter(filter);
+        }
+        if let Some(cursor_in_screenshot) = self.cursor_in_screenshot {
+            agent = agent.with_cursor_in_screenshot(cursor_in_screenshot);
+        }
+        if let Some(format) = self.screenshot_format {
+            agent = agent.with_screenshot_format(format);
+        }
+        if let Some(model) = self.annotation_model.take() {
+            agent = agent.with_image_annotation_model(&model);
+        }
+        if let Some(model) = self.summary_model.take() {
+            agent = agent.with_summary_model(&model);
+        }
+        if let Some(hook) = self.before_action_hook.take() {
+            agent = agent.with_before_action_hook(hook);
+        }
+        if let Some(hook) = self.after_action_hook.take() {
+            agent = agent.with_after_action_hook(hook);
+        }
+        if let Some(config) = self.watermark.take() {
+            agent = agent.with_watermark_config(config);
+        }
+        if let Some(path) = self.event_log.take() {
+            agent = agent.with_event_log(&path)?;
+        }
+        if let Some(max_usd) = self.cost_budget {
+            agent = agent.with_cost_budget(max_usd);
+        }
+        if let Some(settle_delay) = self.settle_delay {
+            agent = agent.with_settle_delay(settle_delay);
+        }
+        if let Some(latency_compensation) = self.latency_compensation {
+            agent = agent.with_latency_compensation(latency_compensation);
+        }
+        if let Some(ms) = self.inter_turn_delay_ms {
+            agent = agent.with_inter_turn_delay(ms);
+        }
+        if let Some((min_ms, max_ms)) = self.step_delay {
+            agent = agent.with_step_delay(min_ms, max_ms);
+        }
+        if let Some(strategy) = self.error_recovery {
+            agent = agent.with_error_recovery_strategy(strategy);
+        }
+        if let Some(enabled) = self.all_screens_mode {
+            agent = agent.with_all_screens_mode(enabled);
+        }
+        if let Some(parameters) = self.model_parameters {
+            agent = agent.with_model_parameters(parameters);
+        }
+        if let Some(enabled) = self.vision_grounding {
+            agent = agent.with_vision_grounding(enabled);
+        }
+        if let Some(prompt) = self.grounding_prompt.take() {
+            agent = agent.with_vision_grounding_prompt(&prompt);
+        }
+        if let Some(enabled) = self.text_mode {
+            agent = agent.with_text_mode(enabled);
+        }
+        if let Some((max_bytes, strip_scripts)) = self.page_source {
+            agent = agent.with_page_source_options(max_bytes, strip_scripts);
+        }
+        if let Some(duration_ms) = self.action_highlight {
+            agent = agent.with_action_highlight(duration_ms);
+        }
+        if let Some((turns, checkpoint_path)) = self.checkpoint.take() {
+            agent = agent.with_checkpoint_interval(turns, checkpoint_path);
+        }
+        if let Some(output_schema) = self.output_schema.take() {
+            agent = agent.with_structured_output_mode(output_schema)?;
+        }
+        if let Some(adaptive_delay) = self.adaptive_delay {
+            agent = agent.with_adaptive_delay(adaptive_delay);
+        }
+        if let Some(enabled) = self.script_execution {
+            agent = agent.with_script_execution(enabled);
+        }
+        if let Some(threshold) = self.battery_pause_threshold {
+            agent = agent.with_battery_pause_threshold(threshold);
+        }
+        for (process, bytes) in std::mem::take(&mut self.memory_alerts) {
+            agent = agent.with_memory_alert_threshold(&process, bytes);
+        }
+        for observer in self.observers.drain(..) {
+            agent = agent.with_observer(observer);
+        }
+        Ok(agent)
+    }
+}
+
+impl DynAgent {
+    /// Start building an agent with [`AgentBuilder`]
+    pub fn builder() -> AgentBuilder {
+        AgentBuilder::default()
+    }
+    
+    /// Retry computer calls that fail with an action or screenshot error on `fallback`
+    ///
+    /// Once the fallback has handled a call, it replaces the primary computer
+    /// for the rest of the session.
+    pub fn with_fallback_computer(mut self, fallback: Box<dyn Computer>) -> Self {
+        let computer = FallbackComputer::new(Box::new(self.computer), fallback);
+        self.fallback_active = Some(computer.fallback_flag());
+        self.computer = Arc::new(Box::new(computer));
+        self
+    }
+    
+    /// Perform the actions recorded in an event log (see [`Agent::with_event_log`]) on `computer`,
+    /// at their original pace and without calling the API
+    ///
+    /// The log records screen coordinates, so the actions land where they did
+    /// in the recorded session. Logs whose typed text was changed by an input
+    /// sanitizer cannot be replayed and are rejected before any action runs.
+    pub async fn replay_from_log(path: &Path, computer: Box<dyn Computer>) -> Result<(), CuaError> {
+        Self::replay_from_log_at_speed(path, computer, 1.0).await
+    }
+    
+    /// Like [`DynAgent::replay_from_log`], with the pauses between actions divided by `speed`
+    ///
+    /// `speed` must be positive; `f64::INFINITY` replays without pausing.
+    pub async fn replay_from_log_at_speed(path: &Path, computer: Box<dyn Computer>, speed: f64) -> Result<(), CuaError> {
+        if speed.is_nan() || speed <= 0.0 {
+            return Err(CuaError::Other(format!("Replay speed must be positive, got {}", speed)));
+        }
+        let log = std::fs::read_to_string(path)?;
+        let mut actions = Vec::new();
+        for (number, line) in log.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+            let event: Value = serde_json::from_str(line)
+                .map_err(|e| CuaError::Other(format!("Invalid event on line {} of {}: {}", number + 1, path.display(), e)))?;
+            if event["type"] != "action_start" {
+                continue;
+            }
+            if event["data"]["params"]["sanitized"] == true {
+                return Err(CuaError::Other(format!(
+                    "Cannot replay line {} of {}: its typed text was sanitized",
+                    number + 1,
+                    path.display()
+                )));
+            }
+            actions.push(event);
+        }
         
-        match item_type {
-            "message" => {
-                if self.debug {
-                    println!("DEBUG: Handling 'message' item: {:?}", item);
-                }
-                if self.print_steps {
-                    if let Some(content) = item.get("content").and_then(|c| c.as_array()) {
-                        if let Some(text_obj) = content.first() {
-                            if let Some(text) = text_obj.get("text").and_then(|t| t.as_str()) {
-                                println!("{}", text);
-                            }
-                        }
-                    }
+        let mut previous_ts = None;
+        for event in actions {
+            let action_type = event["data"]["action_type"].as_str().unwrap_or_default();
+            
+            // Keep the recorded gap to the previous action
+            let ts = event["ts"].as_u64().unwrap_or(0);
+            if let Some(previous_ts) = previous_ts.replace(ts) {
+                let gap = Duration::from_millis(ts.saturating_sub(previous_ts)).div_f64(speed);
+                if !gap.is_zero() {
+                    tokio::time::sleep(gap).await;
                 }
             }
-            "function_call" => {
-                if let (Some(name), Some(arguments), Some(call_id)) = (
-                    item.get("name").and_then(|n| n.as_str()),
-                    item.get("arguments").and_then(|a| a.as_str()),
-                    item.get("call_id"),
-                ) {
-                    if self.debug {
-                        println!("DEBUG: Handling 'function_call' item with name: {}", name);
-                    }
-                    if self.print_steps {
-                        println!("Function call: {}({})", name, arguments);
-                    }
-                    
-                    // For now, we return a hardcoded success response
-                    // In a real implementation, we'd handle specific function calls here
-                    new_items.push(json!({
-                        "type": "function_call_output",
-                        "call_id": call_id,
-                        "output": "success",
-                    }));
-                }
+            
+            tracing::debug!(action = action_type, "Replaying action");
+            if !perform_screen_action(&computer, action_type, &event["data"]["params"]).await? {
+                tracing::warn!(action = action_type, "Skipping an action type that cannot be replayed");
             }
-            "computer_call" => {
-                if let (Some(action), Some(call_id)) = (
-                    item.get("action"),
-                    item.get("call_id"),
-                ) {
-                    if let Some(action_type) = action.get("type").and_then(|t| t.as_str()) {
-                        if self.print_steps {
-                            println!("Computer action: {}", action_type);
-                        }
-                        
-                        // Handle safety checks
-                        let mut acknowledged_safety_checks = Vec::new();
-                        if let Some(pending_checks) = item.get("pending_safety_checks").and_then(|c| c.as_array()) {
-                            for check in pending_checks {
-                                if let Some(message) = check.get("message").and_then(|m| m.as_str()) {
-                                    if self.print_steps {
-                                        println!("Safety check: {}", message);
-                                    }
-                                    
-                                    if !(self.acknowledge_safety_check)(message) {
-                                        return Err(CuaError::Other(format!(
-                                            "Safety check failed: {}", message
-                                        )));
-                                    }
-                                    
-                                    acknowledged_safety_checks.push(check.clone());
-                                }
-                            }
-                        }
-                        
-                        // Perform the action based on the type
-                        match action_type {
-                            // Handle explicit screenshot request
-                            "screenshot" => {
-                                // No action needed here, we'll take the screenshot below
-                                if self.print_steps {
-                                    println!("Taking screenshot as requested by the model");
-                                }
-                            },
-                            "click" => {
-                                let x = action.get("x").and_then(|x| x.as_i64()).unwrap_or(0) as i32;
-                                let y = action.get("y").and_then(|y| y.as_i64()).unwrap_or(0) as i32;
-                                let button = action.get("button").and_then(|b| b.as_str()).unwrap_or("left");
-                                if self.debug {
-                                    println!("DEBUG: Processing click command at ({}, {}) with button: {}", x, y, button);
-                                }
-                                self.computer.click(x, y, button).await?;
-                            }
-                            "double_click" => {
-                                let x = action.get("x").and_then(|x| x.as_i64()).unwrap_or(0) as i32;
-                                let y = action.get("y").and_then(|y| y.as_i64()).unwrap_or(0) as i32;
-                                
-                                self.computer.double_click(x, y).await?;
-                            }
-                            "scroll" => {
-                                let x = action.get("x").and_then(|x| x.as_i64()).unwrap_or(0) as i32;
-                                let y = action.get("y").and_then(|y| y.as_i64()).unwrap_or(0) as i32;
-                                let scroll_x = action.get("scroll_x").and_then(|sx| sx.as_i64()).unwrap_or(0) as i32;
-                                let scroll_y = action.get("scroll_y").and_then(|sy| sy.as_i64()).unwrap_or(0) as i32;
-                                
-                                self.computer.scroll(x, y, scroll_x, scroll_y).await?;
-                            }
-                            "type" => {
-                                let text = action.get("text").and_then(|t| t.as_str()).unwrap_or("");
-                                
-                                self.computer.type_text(text).await?;
-                            }
-                            "wait" => {
-                                let ms = action.get("ms").and_then(|m| m.as_u64()).unwrap_or(1000) as u32;
-                                
-                                self.computer.wait(ms).await?;
-                            }
-                            "move" => {
-                                let x = action.get("x").and_then(|x| x.as_i64()).unwrap_or(0) as i32;
-                                let y = action.get("y").and_then(|y| y.as_i64()).unwrap_or(0) as i32;
-                                
-                                self.computer.move_cursor(x, y).await?;
-                            }
-                            "keypress" => {
-                                if let Some(keys) = action.get("keys").and_then(|k| k.as_array()) {
-                                    let key_strings: Vec<String> = keys
-                                        .iter()
-                                        .filter_map(|k| k.as_str().map(|s| s.to_string()))
-                                        .collect();
-                                    
-                                    self.computer.keypress(&key_strings).await?;
-                                }
-                            }
-                            "drag" => {
-                                if let Some(path) = action.get("path").and_then(|p| p.as_array()) {
-                                    if self.debug {
-                                        println!("DEBUG: Processing drag command with {} points", path.len());
-                                    }
-                                    let path_points: Vec<HashMap<String, i32>> = path
-                                        .iter()
-                                        .filter_map(|point| {
-                                            if let Some(obj) = point.as_object() {
-                                                let mut point_map = HashMap::new();
-                                                if let (Some(x), Some(y)) = (
-                                                    obj.get("x").and_then(|x| x.as_i64()),
-                                                    obj.get("y").and_then(|y| y.as_i64()),
-                                                ) {
-                                                    point_map.insert("x".to_string(), x as i32);
-                                                    point_map.insert("y".to_string(), y as i32);
-                                                    Some(point_map)
-                                                } else {
-                                                    None
-                                                }
-                                            } else {
-                                                None
-                                            }
-                                        })
-                                        .collect();
-                                    
-                                    self.computer.drag(&path_points).await?;
-                                }
-                            }
-                            "goto" => {
-                                if let Some(url) = action.get("url").and_then(|u| u.as_str()) {
-                                    if self.print_steps {
-                                        println!("Attempted to navigate to URL: {}", url);
-                                        println!("Note: Direct URL navigation is not implemented. Please add browser navigation capabilities to your Computer implementation.");
-                                    }
-                                    // This is where you would add browser navigation functionality
-                                    // Since we don't have a browser implementation yet, we'll just acknowledge
-                                    // Instead of returning an error, we'll just continue
-                                }
-                            },
-                            _ => {
-                                if self.print_steps {
-                                    println!("Unknown action type '{}' requested by the model", action_type);
-                                    println!("This action is not implemented, but we'll continue with a screenshot");
-                                }
-                                // Instead of returning an error, we'll just continue
-                                // This makes the agent more resilient to unknown action types
-                            }
-                        }
-                        
-                        // Take a screenshot
-                        let screenshot_base64 = self.computer.screenshot().await?;
-                        
-                        // Create the response
-                        let mut call_output = json!({
-                            "type": "computer_call_output",
-                            "call_id": call_id,
-                            "acknowledged_safety_checks": acknowledged_safety_checks,
-                            "output": {
-                                "type": "input_image",
-                                "image_url": format!("data:image/png;base64,{}", screenshot_base64),
-                            },
-                        });
-                        
-                        // Add current URL for browser environments
-                        if self.computer.environment() == "browser" {
-                            let current_url = self.computer.get_current_url().await?;
-                            if let Some(output) = call_output.get_mut("output").and_then(|o| o.as_object_mut()) {
-                                output.insert("current_url".to_string(), json!(current_url));
-                            }
-                        }
-                        
-                        new_items.push(call_output);
-                    }
-                }
+        }
+        Ok(())
+    }
+}
+
+impl<C: Computer> Agent<C> {
+    /// Create a new agent with the specified client, computer, and tools
+    pub fn new(
+        client: OpenAIClient,
+        computer: C,
+        mut tools: Vec<Value>,
+        acknowledge_safety_check: Option<SafetyCheckCallback>,
+    ) -> Self {
+        // Add computer tool
+        tools.push(json!({
+            "type": "computer-preview",
+            "display_width": computer.dimensions().0,
+            "display_height": computer.dimensions().1,
+            "environment": computer.environment(),
+        }));
+        
+        // Add function tools backed by the computer
+        tools.extend(tools::computer_tools_for(&computer));
+        tools.push(tools::screenshot_diff_tool());
+        
+        Self {
+            client: Arc::new(client),
+            computer: Arc::new(computer),
+            fallback_active: None,
+            tools,
+            tool_fns: Vec::new(),
+            initial_items: Vec::new(),
+            history: Mutex::new(Vec::new()),
+            print_steps: true,
+            debug: false,
+            show_images: false,
+            validate_model: false,
+            model_validated: AtomicBool::new(false),
+            input_sanitizer: None,
+            action_history: Mutex::new(Vec::new()),
+            screenshot_compressor: None,
+            screenshot_pipeline: None,
+            screenshot_diff_threshold: None,
+            last_upload: Mutex::new(None),
+            compression_config: CompressionConfig::default(),
+            turn_records: Mutex::new(Vec::new()),
+            uploaded_screenshots: Mutex::new(HashMap::new()),
+            max_screenshot_size: None,
+            screenshot_scale: Mutex::new(1.0),
+            recording_area: OnceLock::new(),
+            watermark: None,
+            system_prompt: None,
+            thought_prefix: None,
+            strip_thought_prefix: true,
+            stateless_continuation: false,
+            rate_limiter: None,
+            observation_filter: None,
+            cursor_in_screenshot: false,
+            screenshot_format: ScreenshotFormat::default(),
+            annotation_model: None,
+            summary_model: DEFAULT_SUMMARY_MODEL.to_string(),
+            session_summary: Mutex::new(None),
+            annotation_cache: Mutex::new(VecDeque::new()),
+            before_action_hook: None,
+            after_action_hook: None,
+            cost_budget: None,
+            usage: Mutex::new(UsageStats::default()),
+            last_screenshot: Mutex::new(None),
+            observers: CompositeObserver::new(),
+            warnings_emitted: AtomicUsize::new(0),
+            action_context: Mutex::new(String::new()),
+            settle_delay: Duration::ZERO,
+            latency_compensation: false,
+            inter_turn_delay: Duration::ZERO,
+            step_delay: None,
+            error_recovery: ErrorRecoveryStrategy::default(),
+            all_screens_mode: false,
+            model_parameters: ModelParameters::default(),
+            vision_grounding: false,
+            grounding_prompt: GROUNDING_PROMPT.to_string(),
+            text_mode: false,
+            page_source: (DEFAULT_PAGE_SOURCE_LIMIT, true),
+            action_highlight: None,
+            checkpoint: None,
+            output_schema: None,
+            output_validator: None,
+            structured_output: Mutex::new(None),
+            adaptive_delay: false,
+            script_execution: false,
+            battery_pause_threshold: None,
+            memory_alerts: Vec::new(),
+            memory_alerted: Mutex::new(HashSet::new()),
+            measured_latency: Mutex::new(None),
+            last_response_id: Mutex::new(None),
+            last_model_version: Mutex::new(None),
+            acknowledge_safety_check: Arc::new(acknowledge_safety_check
+                .unwrap_or_else(|| Box::new(default_safety_check_callback))),
+        }
+    }
+    
+    /// Create an independent copy of the agent from the current conversation
+    ///
+    /// The fork shares the client, computer, tools, observers and callbacks, and
+    /// copies the conversation, usage and records so far. Checkpointing is not
+    /// carried over, so forks never overwrite the parent's checkpoint.
+    pub fn fork(&self) -> Self {
+        Self {
+            client: Arc::clone(&self.client),
+            computer: Arc::clone(&self.computer),
+            fallback_active: self.fallback_active.clone(),
+            tools: self.tools.clone(),
+            tool_fns: self.tool_fns.clone(),
+            initial_items: self.initial_items.clone(),
+            history: Mutex::new(self.history()),
+            print_steps: self.print_steps,
+            debug: self.debug,
+            show_images: self.show_images,
+            validate_model: self.validate_model,
+            model_validated: AtomicBool::new(self.model_validated.load(Ordering::SeqCst)),
+            input_sanitizer: self.input_sanitizer.clone(),
+            action_history: Mutex::new(self.action_history()),
+            screenshot_compressor: self.screenshot_compressor.clone(),
+            screenshot_pipeline: self.screenshot_pipeline.clone(),
+            screenshot_diff_threshold: self.screenshot_diff_threshold,
+            last_upload: Mutex::new(self.last_upload.lock().unwrap().clone()),
+            compression_config: self.compression_config.clone(),
+            turn_records: Mutex::new(self.turn_records()),
+            uploaded_screenshots: Mutex::new(self.uploaded_screenshots.lock().unwrap().clone()),
+            max_screenshot_size: self.max_screenshot_size,
+            screenshot_scale: Mutex::new(*self.screenshot_scale.lock().unwrap()),
+            recording_area: self.recording_area.clone(),
+            watermark: self.watermark.clone(),
+            system_prompt: self.system_prompt.clone(),
+            thought_prefix: self.thought_prefix.clone(),
+            strip_thought_prefix: self.strip_thought_prefix,
+            stateless_continuation: self.stateless_continuation,
+            rate_limiter: self.rate_limiter.clone(),
+            observation_filter: self.observation_filter.clone(),
+            cursor_in_screenshot: self.cursor_in_screenshot,
+            screenshot_format: self.screenshot_format,
+            annotation_model: self.annotation_model.clone(),
+            summary_model: self.summary_model.clone(),
+            session_summary: Mutex::new(self.session_summary.lock().unwrap().clone()),
+            annotation_cache: Mutex::new(self.annotation_cache.lock().unwrap().clone()),
+            before_action_hook: self.before_action_hook.clone(),
+            after_action_hook: self.after_action_hook.clone(),
+            cost_budget: self.cost_budget,
+            usage: Mutex::new(self.usage_stats()),
+            last_screenshot: Mutex::new(self.last_screenshot.lock().unwrap().clone()),
+            observers: self.observers.clone(),
+            warnings_emitted: AtomicUsize::new(self.warnings_emitted()),
+            action_context: Mutex::new(self.action_context.lock().unwrap().clone()),
+            settle_delay: self.settle_delay,
+            latency_compensation: self.latency_compensation,
+            inter_turn_delay: self.inter_turn_delay,
+            step_delay: self.step_delay,
+            error_recovery: self.error_recovery,
+            all_screens_mode: self.all_screens_mode,
+            model_parameters: self.model_parameters,
+            vision_grounding: self.vision_grounding,
+            grounding_prompt: self.grounding_prompt.clone(),
+            text_mode: self.text_mode,
+            page_source: self.page_source,
+            action_highlight: self.action_highlight,
+            checkpoint: None,
+            output_schema: self.output_schema.clone(),
+            output_validator: self.output_validator.clone(),
+            structured_output: Mutex::new(None),
+            adaptive_delay: self.adaptive_delay,
+            script_execution: self.script_execution,
+            battery_pause_threshold: self.battery_pause_threshold,
+            memory_alerts: self.memory_alerts.clone(),
+            memory_alerted: Mutex::new(self.memory_alerted.lock().unwrap().clone()),
+            measured_latency: Mutex::new(*self.measured_latency.lock().unwrap()),
+            last_response_id: Mutex::new(self.last_response_id()),
+            last_model_version: Mutex::new(self.last_model_version()),
+            acknowledge_safety_check: Arc::clone(&self.acknowledge_safety_check),
+        }
+    }
+    
+    /// Combine the conversation of `forked`, a fork of this agent, into this agent's
+    ///
+    /// Returns the merged conversation, which becomes this agent's history.
+    /// Only the conversation is merged; the fork's usage and records are dropped.
+    pub fn merge_fork(&self, forked: Agent<C>, strategy: MergeStrategy) -> Result<Vec<Value>, CuaError> {
+        if !Arc::ptr_eq(&self.computer, &forked.computer) {
+            return Err(CuaError::Other("Cannot merge an agent that is not a fork of this agent".to_string()));
+        }
+        
+        let ours = self.history();
+        let theirs = forked.history();
+        let merged = match strategy {
+            MergeStrategy::TakeLongest if theirs.len() > ours.len() => theirs,
+            MergeStrategy::TakeShortest if theirs.len() < ours.len() => theirs,
+            MergeStrategy::TakeLongest | MergeStrategy::TakeShortest => ours,
+            MergeStrategy::Concatenate => {
+                let shared = ours.iter().zip(&theirs).take_while(|(a, b)| a == b).count();
+                ours.into_iter().chain(theirs.into_iter().skip(shared)).collect()
+            }
+        };
+        
+        *self.history.lock().unwrap() = merged.clone();
+        Ok(merged)
+    }
+    
+    /// Get the conversation as of the last completed turn or step
+    pub fn history(&self) -> Vec<Value> {
+        self.history.lock().unwrap().clone()
+    }
+    
+    /// Register a function tool handled by the agent
+    pub fn with_tool_fn(mut self, tool: Box<dyn ToolFn>) -> Self {
+        self.tools.push(tool.definition());
+        self.tool_fns.push(Arc::from(tool));
+        self
+    }
+    
+    /// Register every tool of a schema-defined [`ToolRegistry`]
+    pub fn with_tool_registry(mut self, registry: ToolRegistry) -> Self {
+        for tool in registry.into_tool_fns() {
+            self = self.with_tool_fn(tool);
+        }
+        self
+    }
+    
+    /// Let the model search the web through the Brave Search API (see [`WebSearchTool`])
+    pub fn with_web_search_tool(self, api_key: &str) -> Self {
+        self.with_tool_fn(Box::new(WebSearchTool::new(api_key)))
+    }
+    
+    /// Set whether to print steps
+    pub fn with_print_steps(mut self, print_steps: bool) -> Self {
+        self.print_steps = print_steps;
+        self
+    }
+    
+    /// Set whether to print debug information
+    pub fn with_debug(mut self, debug: bool) -> Self {
+        self.debug = debug;
+        self
+    }
+    
+    /// Set whether to show images
+    pub fn with_show_images(mut self, show_images: bool) -> Self {
+        self.show_images = show_images;
+        self
+    }
+    
+    /// Whether the fallback computer has taken over from the primary one
+    pub fn using_fallback_computer(&self) -> bool {
+        self.fallback_active
+            .as_ref()
+            .is_some_and(|active| active.load(Ordering::SeqCst))
+    }
+    
+    /// Set whether to check the configured model against `/v1/models` before the first request
+    pub fn with_validate_model(mut self, validate_model: bool) -> Self {
+        self.validate_model = validate_model;
+        self
+    }
+    
+    /// Set a sanitizer applied to typed text before it is logged or recorded
+    ///
+    /// The sanitizer also receives the model's latest message as context. The
+    /// computer still receives the original text.
+    pub fn with_input_sanitizer(mut self, sanitizer: InputSanitizer) -> Self {
+        self.input_sanitizer = Some(Arc::new(sanitizer));
+        self
+    }
+    
+    /// Get the computer actions performed so far, with typed text sanitized
+    pub fn action_history(&self) -> Vec<ActionRecord> {
+        self.action_history.lock().unwrap().clone()
+    }
+    
+    /// Compress screenshots before they are uploaded to the API
+    pub fn with_screenshot_compressor(mut self, compressor: Box<dyn ScreenshotCompressor>) -> Self {
+        self.screenshot_compressor = Some(Arc::from(compressor));
+        self
+    }
+    
+    /// Transform screenshots with `processors`, in order, before they are uploaded
+    ///
+    /// Takes the place of the screenshot compressor and format conversion.
+    /// The output is PNG unless a stage such as [`compression::JpegQualityProcessor`]
+    /// sets another format. Coordinates in screenshots resized by a stage such
+    /// as [`compression::ResizeProcessor`] are mapped back to the screen.
+    pub fn with_screenshot_compression_pipeline(mut self, processors: Vec<Box<dyn ScreenshotProcessor>>) -> Self {
+        self.screenshot_pipeline = Some(Arc::new(processors));
+        self
+    }
+    
+    /// Reuse the previous screenshot instead of uploading a new one that barely changed
+    ///
+    /// When the fraction of pixels that differ from the last uploaded screenshot
+    /// (see [`vision::screenshot_diff_score`]) is below `threshold`, the
+    /// `computer_call_output` repeats the previous `image_url`. The threshold
+    /// is a fraction, so anything outside `[0, 1]` (or NaN) is an error.
+    pub fn with_screenshot_diff_threshold(mut self, threshold: f64) -> Result<Self, CuaError> {
+        if !(0.0..=1.0).contains(&threshold) {
+            return Err(CuaError::Other(format!(
+                "Screenshot diff threshold must be between 0 and 1, got {}",
+                threshold
+            )));
+        }
+        self.screenshot_diff_threshold = Some(threshold);
+        Ok(self)
+    }
+    
+    /// Set the settings passed to the screenshot compressor
+    pub fn with_compression_config(mut self, config: CompressionConfig) -> Self {
+        self.compression_config = config;
+        self
+    }
+    
+    /// Downscale screenshots that exceed `width`x`height` before uploading them
+    ///
+    /// The model then works in the scaled coordinate space, so its coordinates
+    /// are scaled back up before they reach the computer.
+    pub fn with_max_screenshot_size(mut self, width: u32, height: u32) -> Self {
+        self.max_screenshot_size = Some((width, height));
+        
+        // Advertise the display size the model will actually see
+        let (display_width, display_height) = self.computer.dimensions();
+        let scale = vision::fit_scale(display_width, display_height, width, height);
+        *self.screenshot_scale.lock().unwrap() = scale;
+        if let Some(tool) = self.tools.iter_mut().find(|t| t["type"] == "computer-preview") {
+            tool["display_width"] = json!((display_width as f64 * scale).round() as u32);
+            tool["display_height"] = json!((display_height as f64 * scale).round() as u32);
+        }
+        self
+    }
+    
+    /// Send a system message with this prompt at the start of each API request
+    ///
+    /// The computer's [`keyboard_shortcut_map`](Computer::keyboard_shortcut_map) is
+    /// appended, so the model uses the right shortcuts for the environment.
+    pub fn with_system_prompt(mut self, prompt: &str) -> Self {
+        let mut shortcuts: Vec<_> = self.computer.keyboard_shortcut_map().into_iter().collect();
+        shortcuts.sort();
+        let mut prompt = format!("{}\n\nKeyboard shortcuts in this environment:", prompt.trim_end());
+        for (action, keys) in shortcuts {
+            prompt.push_str(&format!("\n- {}: {}", action, keys.join("+")));
+        }
+        self.system_prompt = Some(prompt);
+        self
+    }
+    
+    /// Continue from the previous response by ID within a turn, sending only the new items
+    ///
+    /// Uses the API's `previous_response_id` instead of resending the whole
+    /// conversation on every request after the first of each turn.
+    pub fn with_stateless_continuation(mut self, stateless: bool) -> Self {
+        self.stateless_continuation = stateless;
+        self
+    }
+    
+    /// Add a user message with this prompt before each API request
+    ///
+    /// Useful for nudging the model to reason, e.g. "Think step by step before
+    /// choosing your next action."
+    pub fn with_thought_prefix(mut self, prefix: &str) -> Self {
+        self.thought_prefix = Some(prefix.to_string());
+        self
+    }
+    
+    /// Set whether to drop the thought prefix messages from the conversation after each request
+    pub fn with_strip_thought_prefix(mut self, strip: bool) -> Self {
+        self.strip_thought_prefix = strip;
+        self
+    }
+    
+    /// Limit computer actions to this many per minute, to avoid throttling
+    ///
+    /// Fails if the rate is not a positive, finite number.
+    pub fn with_max_action_rate(mut self, actions_per_minute: f64) -> Result<Self, CuaError> {
+        self.rate_limiter = Some(Arc::new(ActionRateLimiter::new(actions_per_minute)?));
+        Ok(self)
+    }
+    
+    /// Only handle response items for which `filter` returns true, e.g. [`computer_call_only`]
+    pub fn with_observation_filter(mut self, filter: ObservationFilter) -> Self {
+        self.observation_filter = Some(Arc::new(filter));
+        self
+    }
+    
+    /// Draw the mouse cursor on screenshots sent to the model
+    pub fn with_cursor_in_screenshot(mut self, cursor_in_screenshot: bool) -> Self {
+        self.cursor_in_screenshot = cursor_in_screenshot;
+        self
+    }
+    
+    /// Upload screenshots as PNG (the default) or as JPEGs
+    ///
+    /// Screenshots are still captured as PNG and only encoded for the upload,
+    /// so screenshot diffs compare the raw screen. A screenshot compressor, if
+    /// set, still has the last word on the upload format.
+    pub fn with_screenshot_format(mut self, format: ScreenshotFormat) -> Self {
+        self.screenshot_format = format;
+        self
+    }
+    
+    /// The configured screenshot format, or PNG if the computer cannot produce it
+    fn effective_screenshot_format(&self) -> ScreenshotFormat {
+        let format = self.screenshot_format;
+        if !format.is_supported_by(&self.computer.screenshot_format_support()) {
+            tracing::debug!(?format, "Screenshot format not supported, using PNG");
+            return ScreenshotFormat::Png;
+        }
+        format
+    }
+    
+    /// Have `model` describe each screenshot in one sentence, added as a user message after it
+    ///
+    /// Helps primary models that cannot see images. Screenshots and images from
+    /// function tools are described. Each distinct image costs one extra request
+    /// to `model` with the agent's client, priced at `model`'s rate in the usage
+    /// stats; descriptions of the last few images are reused.
+    pub fn with_image_annotation_model(mut self, model: &str) -> Self {
+        self.annotation_model = Some(model.to_string());
+        self
+    }
+    
+    /// Set the model [`Agent::summarize_session`] uses (default [`DEFAULT_SUMMARY_MODEL`])
+    pub fn with_summary_model(mut self, model: &str) -> Self {
+        self.summary_model = model.to_string();
+        self
+    }
+    
+    /// Run `hook` with the action parameters before each computer action; an `Err` cancels the action
+    pub fn with_before_action_hook(mut self, hook: ActionHook) -> Self {
+        self.before_action_hook = Some(Arc::new(hook));
+        self
+    }
+    
+    /// Run `hook` with the resulting screenshot after each computer action
+    pub fn with_after_action_hook(mut self, hook: ActionHook) -> Self {
+        self.after_action_hook = Some(Arc::new(hook));
+        self
+    }
+    
+    /// Stop with `CuaError::BudgetExceeded` once the estimated API cost passes `max_usd`
+    pub fn with_cost_budget(mut self, max_usd: f64) -> Self {
+        self.cost_budget = Some(max_usd);
+        self
+    }
+    
+    /// Get the estimated API cost in USD so far
+    pub fn total_cost(&self) -> f64 {
+        self.usage.lock().unwrap().estimated_cost
+    }
+    
+    /// Get the tokens used and estimated cost so far
+    pub fn usage_stats(&self) -> UsageStats {
+        *self.usage.lock().unwrap()
+    }
+    
+    /// Notify `observer` of actions, screenshots, model output, errors and completed turns
+    ///
+    /// Observers are called in the order they were added.
+    pub fn with_observer(mut self, observer: Box<dyn AgentObserver>) -> Self {
+        self.observers.add(observer);
+        self
+    }
+    
+    /// Stream API requests and responses, actions, function tool calls and screenshots to `path` as NDJSON
+    ///
+    /// Installs an [`EventLog`] observer; see it for the line format.
+    pub fn with_event_log(self, path: &Path) -> Result<Self, CuaError> {
+        Ok(self.with_observer(Box::new(EventLog::create(path)?)))
+    }
+    
+    /// Number of warnings the API has returned this session
+    pub fn warnings_emitted(&self) -> usize {
+        self.warnings_emitted.load(Ordering::SeqCst)
+    }
+    
+    /// Stamp every uploaded screenshot with `text`, the time and the turn number
+    pub fn with_screenshot_watermark(self, text: &str) -> Self {
+        self.with_watermark_config(WatermarkConfig::new(text))
+    }
+    
+    /// Stamp every uploaded screenshot with a watermark configured in detail
+    pub fn with_watermark_config(mut self, config: WatermarkConfig) -> Self {
+        self.watermark = Some(config);
+        self
+    }
+    
+    /// Wait this long after each action before taking the screenshot, so the UI can settle
+    pub fn with_settle_delay(mut self, settle_delay: Duration) -> Self {
+        self.settle_delay = settle_delay;
+        self
+    }
+    
+    /// Set whether to subtract the computer's measured latency from the settle delay
+    ///
+    /// On slow systems the action round-trip already gives the UI time to
+    /// settle, so waiting the full delay on top of it is wasted time.
+    pub fn with_latency_compensation(mut self, latency_compensation: bool) -> Self {
+        self.latency_compensation = latency_compensation;
+        self
+    }
+    
+    /// Wait `ms` milliseconds between API requests within a turn, to avoid bursts that trigger rate limiting
+    pub fn with_inter_turn_delay(mut self, ms: u32) -> Self {
+        self.inter_turn_delay = Duration::from_millis(ms as u64);
+        self
+    }
+    
+    /// Wait a random time between `min_ms` and `max_ms` milliseconds between computer actions
+    ///
+    /// Some applications miss input events that arrive in quick succession.
+    pub fn with_step_delay(mut self, min_ms: u64, max_ms: u64) -> Self {
+        self.step_delay = Some((min_ms.min(max_ms), min_ms.max(max_ms)));
+        self
+    }
+    
+    /// Choose how to handle computer actions that fail (default: [`ErrorRecoveryStrategy::Abort`])
+    pub fn with_error_recovery_strategy(mut self, strategy: ErrorRecoveryStrategy) -> Self {
+        self.error_recovery = strategy;
+        self
+    }
+    
+    /// Send a screenshot of every screen after each action
+    ///
+    /// The `computer_call_output` still carries the primary screen (see
+    /// [`Computer::primary_screen`]), since it accepts a single image; the other
+    /// screens follow as an array in a user message, each labelled with its
+    /// bounds and encoded like the primary screenshot.
+    pub fn with_all_screens_mode(mut self, enabled: bool) -> Self {
+        self.all_screens_mode = enabled;
+        self
+    }
+    
+    /// Set the sampling parameters (temperature, top_p, output token limit) of model requests
+    ///
+    /// They apply to the requests that drive the task, not to the annotation,
+    /// grounding and summary requests. Forks start with the same parameters.
+    pub fn with_model_parameters(mut self, parameters: ModelParameters) -> Self {
+        self.model_parameters = parameters;
+        self
+    }
+    
+    /// Have the model confirm the target of each click or move before it is performed
+    ///
+    /// The target is marked with a red circle on a fresh screenshot, which costs
+    /// an extra API request per action. A rejected action is not performed and
+    /// the model is told so instead.
+    pub fn with_vision_grounding(mut self, enabled: bool) -> Self {
+        self.vision_grounding = enabled;
+        self
+    }
+    
+    /// Set the question asked with the marked screenshot (see `with_vision_grounding`)
+    pub fn with_vision_grounding_prompt(mut self, prompt: &str) -> Self {
+        self.grounding_prompt = prompt.to_string();
+        self
+    }
+    
+    /// Follow the screenshot after each action with the screen's OCR text
+    ///
+    /// The computer use API requires an image in each call output, so the text
+    /// is sent as an extra user message. Helps the model read small or dense
+    /// text, especially with downscaled screenshots.
+    pub fn with_text_mode(mut self, enabled: bool) -> Self {
+        self.text_mode = enabled;
+        self
+    }
+    
+    /// Limit the page source the `get_page_source` tool returns to `max_bytes`
+    ///
+    /// By default the source is cut at [`DEFAULT_PAGE_SOURCE_LIMIT`] and its
+    /// `<script>` elements are removed; `strip_scripts` false keeps them.
+    pub fn with_page_source_options(mut self, max_bytes: usize, strip_scripts: bool) -> Self {
+        self.page_source = (max_bytes, strip_scripts);
+        self
+    }
+    
+    /// Outline the target of each pointer action in red for `duration_ms` before performing it
+    ///
+    /// Helps a person watching a live session follow along. Failures to draw
+    /// the outline are logged and otherwise ignored.
+    pub fn with_action_highlight(mut self, duration_ms: u32) -> Self {
+        self.action_highlight = Some(duration_ms);
+        self
+    }
+    
+    /// Save the conversation to `checkpoint_path` every `turns` turns, see [`Agent::save_state`]
+    ///
+    /// Lets a long task be resumed with [`Checkpoint::load`] after a crash.
+    /// The checkpoint is deleted once a run finishes. Failures to write or
+    /// delete it are logged and otherwise ignored.
+    ///
+    /// The checkpoint holds the screenshots the model saw, so keep it
+    /// somewhere private. Typed text passes through the input sanitizer first.
+    pub fn with_checkpoint_interval(mut self, turns: u32, checkpoint_path: PathBuf) -> Self {
+        self.checkpoint = Some((turns.max(1), checkpoint_path));
+        self
+    }
+    
+    /// Write `items` to `path` as a [`Checkpoint`], replacing any previous one
+    ///
+    /// Text the model typed is sanitized like in the action history, and on
+    /// Unix the file is only readable by its owner.
+    pub fn save_state(&self, path: &Path, items: &[Value]) -> Result<(), CuaError> {
+        let checkpoint = Checkpoint {
+            model: self.client.model().to_string(),
+            turns: self.turn_records.lock().unwrap().len(),
+            timestamp: SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default().as_secs(),
+            items: items.iter().map(|item| self.sanitize_typed_text(item)).collect(),
+        };
+        // Write a sibling file first so a crash mid-write keeps the old checkpoint
+        let temp_path = path.with_extension("tmp");
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        std::io::Write::write_all(&mut options.open(&temp_path)?, serde_json::to_string_pretty(&checkpoint)?.as_bytes())?;
+        std::fs::rename(&temp_path, path)?;
+        Ok(())
+    }
+    
+    /// Copy `item`, passing the text of `type` actions and typing tools through the input sanitizer
+    fn sanitize_typed_text(&self, item: &Value) -> Value {
+        let mut item = item.clone();
+        if self.input_sanitizer.is_none() {
+            return item;
+        }
+        match item.get("type").and_then(|t| t.as_str()) {
+            Some("computer_call") => {
+                if let Some(text) = item.pointer("/action/text").and_then(|t| t.as_str()) {
+                    item["action"]["text"] = json!(self.sanitize_input(text));
+                }
+            }
+            Some("function_call") => {
+                let arguments = item.get("arguments").and_then(|a| a.as_str());
+                if let Some(mut arguments) = arguments.and_then(|a| serde_json::from_str::<Value>(a).ok()) {
+                    if let Some(text) = arguments.get("text").and_then(|t| t.as_str()) {
+                        arguments["text"] = json!(self.sanitize_input(text));
+                        item["arguments"] = json!(arguments.to_string());
+                    }
+                }
+            }
+            _ => {}
+        }
+        item
+    }
+    
+    /// Ask the model for a final answer in JSON conforming to `output_schema`
+    ///
+    /// The schema is appended to the system prompt. When a run ends with an
+    /// answer that parses and validates against it, the answer is available
+    /// from [`structured_output`](Self::structured_output). Fails if
+    /// `output_schema` is not a valid JSON Schema.
+    pub fn with_structured_output_mode(mut self, output_schema: Value) -> Result<Self, CuaError> {
+        let validator = JSONSchema::compile(&output_schema)
+            .map_err(|e| CuaError::Other(format!("Invalid output schema: {}", e)))?;
+        self.output_validator = Some(Arc::new(validator));
+        self.output_schema = Some(output_schema);
+        Ok(self)
+    }
+    
+    /// Final answer of the last run, if it matched the schema of `with_structured_output_mode`
+    pub fn structured_output(&self) -> Option<Value> {
+        self.structured_output.lock().unwrap().clone()
+    }
+    
+    /// Set whether to wait out a rate-limited (429) request's `Retry-After` and retry it
+    ///
+    /// Without this, a rate-limited request ends the run. With it, the request
+    /// is retried up to [`MAX_RATE_LIMIT_RETRIES`] times, waiting
+    /// [`DEFAULT_RATE_LIMIT_DELAY`] when the API gives no `Retry-After`.
+    pub fn with_adaptive_delay(mut self, adaptive_delay: bool) -> Self {
+        self.adaptive_delay = adaptive_delay;
+        self
+    }
+    
+    /// Set whether to offer the model the `execute_script` tool (off by default)
+    ///
+    /// Every script is shown to the safety check callback before it runs and is
+    /// refused unless the callback approves it.
+    pub fn with_script_execution(mut self, enabled: bool) -> Self {
+        self.script_execution = enabled;
+        self.tools.retain(|tool| tool["name"] != tools::SCRIPT_TOOL);
+        if enabled {
+            self.tools.push(tools::script_tool());
+        }
+        self
+    }
+    
+    /// Pause before each API request while the battery charge is below `threshold` (0.0-1.0)
+    ///
+    /// The agent resumes once the battery has charged back to the threshold,
+    /// checking every [`BATTERY_POLL_INTERVAL`]. Systems without a battery never pause.
+    pub fn with_battery_pause_threshold(mut self, threshold: f32) -> Self {
+        self.battery_pause_threshold = Some(threshold);
+        self
+    }
+    
+    /// Warn the model before the next request when processes named `process` use more than `bytes` in total
+    ///
+    /// The warning is a user message, sent once until the process drops back
+    /// under the threshold. Can be called for several processes.
+    pub fn with_memory_alert_threshold(mut self, process: &str, bytes: u64) -> Self {
+        self.memory_alerts.push((process.to_string(), bytes));
+        self
+    }
+    
+    /// Warning messages for processes that newly exceeded their memory alert threshold
+    async fn memory_warnings(&self) -> Vec<Value> {
+        if self.memory_alerts.is_empty() {
+            return Vec::new();
+        }
+        let processes = match self.computer.get_running_processes_memory().await {
+            Ok(processes) => processes,
+            Err(e) => {
+                tracing::warn!(error = %e, "Failed to read process memory");
+                return Vec::new();
+            }
+        };
+        
+        let mut alerted = self.memory_alerted.lock().unwrap();
+        let mut warnings = Vec::new();
+        for (process, limit) in &self.memory_alerts {
+            let used: u64 = processes.iter().filter(|(name, _)| name == process).map(|(_, bytes)| bytes).sum();
+            if used <= *limit {
+                alerted.remove(process);
+            } else if alerted.insert(process.clone()) {
+                tracing::warn!(process = %process, used, limit, "Process memory above alert threshold");
+                warnings.push(json!({
+                    "role": "user",
+                    "content": format!(
+                        "Warning: {} is using {} MB of memory, above the {} MB alert threshold. It may be leaking memory.",
+                        process, used / (1024 * 1024), limit / (1024 * 1024)
+                    ),
+                }));
+            }
+        }
+        warnings
+    }
+    
+    /// Wait while the battery is below the pause threshold, if one is set
+    async fn wait_for_battery(&self) {
+        let Some(threshold) = self.battery_pause_threshold else { return };
+        let mut paused = false;
+        loop {
+            let level = match self.computer.get_battery_level().await {
+                Ok(Some(level)) => level,
+                Ok(None) => return,
+                Err(e) => {
+                    tracing::warn!(error = %e, "Failed to read the battery level, not pausing");
+                    return;
+                }
+            };
+            if level >= threshold {
+                if paused {
+                    tracing::info!(level, "Battery recovered, resuming");
+                }
+                return;
+            }
+            if !paused {
+                tracing::warn!(level, threshold, "Battery low, pausing until it is charged");
+                paused = true;
+            }
+            tokio::time::sleep(BATTERY_POLL_INTERVAL).await;
+        }
+    }
+    
+    /// Sleep before the next API request; `first` is the first request of the turn
+    async fn wait_before_request(&self, first: bool) {
+        let delay = if first { Duration::ZERO } else { self.inter_turn_delay };
+        if !delay.is_zero() {
+            tracing::debug!(delay_ms = delay.as_millis() as u64, "Waiting before the next API request");
+            tokio::time::sleep(delay).await;
+        }
+    }
+    
+    /// Get the settle delay to apply, measuring the latency on first use if compensating
+    async fn effective_settle_delay(&self) -> Result<Duration, CuaError> {
+        if !self.latency_compensation || self.settle_delay.is_zero() {
+            return Ok(self.settle_delay);
+        }
+        
+        let cached = *self.measured_latency.lock().unwrap();
+        let latency = match cached {
+            Some(latency) => latency,
+            None => {
+                let latency = self.computer.measure_latency().await?;
+                *self.measured_latency.lock().unwrap() = Some(latency);
+                latency
+            }
+        };
+        
+        Ok(self.settle_delay.saturating_sub(latency))
+    }
+    
+    /// Get the ID of the most recent API response
+    pub fn last_response_id(&self) -> Option<String> {
+        self.last_response_id.lock().unwrap().clone()
+    }
+    
+    /// Get the model version that produced the most recent API response
+    pub fn last_model_version(&self) -> Option<String> {
+        self.last_model_version.lock().unwrap().clone()
+    }
+    
+    /// Snapshot the agent's current state
+    ///
+    /// The state is read under the agent's own locks, so this can be called
+    /// from another task while the agent is running.
+    pub fn introspect(&self) -> AgentState {
+        let last_screenshot_hash = self.last_screenshot.lock().unwrap().as_deref().map(screenshot_hash);
+        AgentState {
+            turn_count: self.turn_records.lock().unwrap().len(),
+            total_tokens: self.usage_stats(),
+            current_items_count: self.history.lock().unwrap().len(),
+            last_action_type: self.action_history.lock().unwrap().last().map(|record| record.action_type.clone()),
+            last_screenshot_hash,
+            warnings_count: self.warnings_emitted(),
+        }
+    }
+    
+    /// Get the statistics of every turn so far
+    pub fn turn_records(&self) -> Vec<TurnRecord> {
+        self.turn_records.lock().unwrap().clone()
+    }
+    
+    /// Get the most recent screenshot sent to the model (base64)
+    pub fn get_last_screenshot(&self) -> Option<String> {
+        let hash = self.turn_records.lock().unwrap().iter().rev().find_map(|record| record.screenshot_hash.clone())?;
+        self.uploaded_screenshots.lock().unwrap().get(&hash).cloned()
+    }
+    
+    /// Get the last screenshot sent to the model during a turn (1-based, base64)
+    pub fn get_screenshot_at_turn(&self, turn: usize) -> Option<String> {
+        let hash = self.turn_records.lock().unwrap().get(turn.checked_sub(1)?)?.screenshot_hash.clone()?;
+        self.uploaded_screenshots.lock().unwrap().get(&hash).cloned()
+    }
+    
+    /// Downscale an image to the maximum screenshot size, returning it and the scale applied
+    fn fit_max_screenshot_size(&self, image_base64: String) -> Result<(String, f64), CuaError> {
+        let Some((max_width, max_height)) = self.max_screenshot_size else {
+            return Ok((image_base64, 1.0));
+        };
+        
+        let image = vision::decode_screenshot(&image_base64)?;
+        let scale = vision::fit_scale(image.width(), image.height(), max_width, max_height);
+        if scale >= 1.0 {
+            return Ok((image_base64, scale));
+        }
+        
+        let width = ((image.width() as f64 * scale).round() as u32).clamp(1, max_width);
+        let height = ((image.height() as f64 * scale).round() as u32).clamp(1, max_height);
+        let resized = image::imageops::resize(&image, width, height, FilterType::Lanczos3);
+        Ok((vision::encode_png(&resized.into())?, scale))
+    }
+    
+    /// Prepare an image returned by one of the [`tools::IMAGE_OUTPUT_TOOLS`] for upload
+    ///
+    /// Only downscaled: the image is a window or region, so it neither
+    /// changes the screenshot scale nor counts as the turn's screenshot.
+    fn prepare_tool_image(&self, image_base64: String) -> Result<(String, &'static str), CuaError> {
+        let (image, _) = self.fit_max_screenshot_size(image_base64)?;
+        let mime_type = if vision::is_jpeg(&image) { "image/jpeg" } else { "image/png" };
+        Ok((image, mime_type))
+    }
+    
+    /// Fail if any of the screen `points` of a pointer action lies outside the capturable area
+    ///
+    /// The area is looked up once. If the computer cannot report it, points are not checked.
+    async fn ensure_capturable(&self, action_type: &str, points: &[(i32, i32)]) -> Result<(), CuaError> {
+        if points.is_empty() {
+            return Ok(());
+        }
+        let area = match self.recording_area.get() {
+            Some(area) => *area,
+            None => {
+                let area = match self.computer.get_screen_recording_area().await {
+                    Ok(area) => Some(area),
+                    Err(e) => {
+                        tracing::warn!(error = %e, "Cannot get the screen recording area; not checking action coordinates");
+                        None
+                    }
+                };
+                *self.recording_area.get_or_init(|| area)
+            }
+        };
+        let Some(area) = area else { return Ok(()) };
+        
+        match points.iter().find(|&&(x, y)| !area_contains(area, x, y)) {
+            Some((x, y)) => Err(CuaError::ActionError(format!(
+                "{} at ({}, {}) is outside the capturable area {:?}",
+                action_type, x, y, area
+            ))),
+            None => Ok(()),
+        }
+    }
+    
+    /// Map a point from the screenshot the model saw back to screen coordinates
+    fn to_screen(&self, x: i32, y: i32) -> (i32, i32) {
+        let scale = *self.screenshot_scale.lock().unwrap();
+        ((x as f64 / scale).round() as i32, (y as f64 / scale).round() as i32)
+    }
+    
+    /// Run a computer tool, mapping its coordinates between screenshot and screen space
+    ///
+    /// The model gives and reads coordinates in the space of the (possibly
+    /// downscaled) screenshots it sees, like `to_screen` handles for actions.
+    async fn call_computer_tool(&self, name: &str, args: &Value) -> Option<Result<Value, CuaError>> {
+        let scale = *self.screenshot_scale.lock().unwrap();
+        let mut args = args.clone();
+        if tools::SCREEN_AREA_ARGUMENT_TOOLS.contains(&name) {
+            tools::scale_coordinates(&mut args, 1.0 / scale);
+        }
+        if name == "get_page_source" {
+            let (max_bytes, strip_scripts) = self.page_source;
+            return Some(tools::page_source(&*self.computer, max_bytes, strip_scripts).await);
+        }
+        let result = tools::call_computer_tool(&*self.computer, name, &args).await?;
+        Some(result.map(|mut output| {
+            if tools::SCREEN_POSITION_OUTPUT_TOOLS.contains(&name) {
+                tools::scale_coordinates(&mut output, scale);
+            }
+            output
+        }))
+    }
+    
+    /// Run the script tool's script once the safety check callback approves it
+    async fn execute_script(&self, args: &Value) -> Result<Value, CuaError> {
+        if !self.script_execution {
+            return Err(CuaError::SafetyError("Script execution is not enabled".to_string()));
+        }
+        let script = args.get("script").and_then(|s| s.as_str()).unwrap_or("");
+        let lang = args.get("lang").and_then(|l| l.as_str()).unwrap_or("");
+        if !(self.acknowledge_safety_check)(&format!("Run this {} script?\n{}", lang, script)) {
+            return Err(CuaError::SafetyError("Script was not approved".to_string()));
+        }
+        self.computer.execute_script(script, lang).await.map(|output| json!(output))
+    }
+    
+    /// Downscale, watermark and compress a captured screenshot as configured
+    ///
+    /// Returns the base64 image to upload and its MIME type, and records the
+    /// sizes in the current turn. The scale applied becomes the one the model's
+    /// coordinates are mapped back with.
+    fn prepare_screenshot(&self, screenshot_base64: String) -> Result<(String, &'static str), CuaError> {
+        let original_len = screenshot_base64.len();
+        let (image, mime_type, scale) = self.encode_screenshot(screenshot_base64)?;
+        *self.screenshot_scale.lock().unwrap() = scale;
+        
+        if let Some(record) = self.turn_records.lock().unwrap().last_mut() {
+            record.screenshot_bytes += original_len;
+            record.uploaded_bytes += image.len();
+            let hash = screenshot_hash(&image);
+            self.uploaded_screenshots.lock().unwrap().entry(hash.clone()).or_insert_with(|| image.clone());
+            record.screenshot_hash = Some(hash);
+        }
+        
+        Ok((image, mime_type))
+    }
+    
+    /// Downscale, watermark and compress a screenshot without recording anything
+    ///
+    /// Returns the base64 image, its MIME type and the scale from screen to image.
+    fn encode_screenshot(&self, screenshot_base64: String) -> Result<(String, &'static str, f64), CuaError> {
+        let (screenshot_base64, mut scale) = self.fit_max_screenshot_size(screenshot_base64)?;
+        let screenshot_base64 = match &self.watermark {
+            Some(config) => {
+                let label = format!(
+                    "{} | {} | turn {}",
+                    config.text,
+                    humantime::format_rfc3339_seconds(SystemTime::now()),
+                    self.turn_records.lock().unwrap().len()
+                );
+                watermark::apply_watermark(&screenshot_base64, config, &label)?
+            }
+            None => screenshot_base64,
+        };
+        let (image, mime_type) = match (self.screenshot_pipeline.as_deref(), &self.screenshot_compressor, self.effective_screenshot_format()) {
+            (Some(pipeline), _, _) => {
+                let (image, mime_type, ratio) = compression::run_pipeline_scaled(pipeline, &screenshot_base64)?;
+                scale *= ratio;
+                (image, mime_type)
+            }
+            (None, Some(compressor), _) => (
+                compressor.compress(&screenshot_base64, &self.compression_config)?,
+                self.compression_config.format.mime_type(),
+            ),
+            // Screenshots are captured as PNG and encoded for the upload here
+            (None, None, ScreenshotFormat::Jpeg(quality)) if !vision::is_jpeg(&screenshot_base64) => {
+                let image = vision::decode_screenshot(&screenshot_base64)?.to_rgba8();
+                (vision::encode_jpeg(image.as_raw(), image.width(), image.height(), quality)?, "image/jpeg")
+            }
+            #[cfg(feature = "webp")]
+            (None, None, ScreenshotFormat::WebP(quality)) => {
+                let webp = compression::encode_webp(&vision::decode_screenshot(&screenshot_base64)?, quality);
+                (base64::Engine::encode(&base64::engine::general_purpose::STANDARD, webp), "image/webp")
+            }
+            (None, None, format) => (screenshot_base64, format.mime_type()),
+        };
+        Ok((image, mime_type, scale))
+    }
+    
+    /// The previous upload, if `captured` differs from its screenshot by less than the diff threshold
+    ///
+    /// Counts the skipped upload in the current turn.
+    fn reusable_upload(&self, captured: &str) -> Option<(String, &'static str)> {
+        let threshold = self.screenshot_diff_threshold?;
+        let (previous, image, mime_type) = self.last_upload.lock().unwrap().clone()?;
+        // Screenshots that cannot be compared (e.g. after a resize) are always sent
+        let score = vision::screenshot_diff_score(&previous, captured).ok()?;
+        if score >= threshold {
+            return None;
+        }
+        if let Some(record) = self.turn_records.lock().unwrap().last_mut() {
+            record.skipped_uploads += 1;
+        }
+        Some((image, mime_type))
+    }
+    
+    /// Apply the input sanitizer, if any, to typed text
+    fn sanitize_input(&self, text: &str) -> String {
+        match &self.input_sanitizer {
+            Some(sanitizer) => sanitizer(text, &self.action_context.lock().unwrap()),
+            None => text.to_string(),
+        }
+    }
+    
+    /// Seed the conversation with a screenshot of the starting screen state
+    ///
+    /// The screenshot is added as a `computer_call_output` item ahead of the
+    /// user input, so the model has visual context before its first action.
+    pub fn inject_screenshot(&mut self, base64: &str) -> &mut Self {
+        self.initial_items.push(screenshot_item(base64));
+        self
+    }
+    
+    /// Ask the model what to do next on `base64` (a PNG screenshot) without performing anything
+    ///
+    /// Makes a single API request and returns the `computer_call` actions the
+    /// model plans, in order. The request and its output become the agent's history.
+    pub async fn continue_from_screenshot(&self, base64: &str, instruction: &str) -> Result<Vec<PlannedAction>, CuaError> {
+        let mut items = self.initial_items.clone();
+        items.push(screenshot_item(base64));
+        items.push(json!({
+            "role": "user",
+            "content": instruction,
+        }));
+        
+        let response = self
+            .client
+            .create_response_with_parameters(&items, &self.tools, None, &self.model_parameters)
+            .await?;
+        self.record_response(&response);
+        items.extend(response.output.iter().cloned());
+        *self.history.lock().unwrap() = items;
+        
+        Ok(response.output.iter().filter_map(PlannedAction::from_computer_call).collect())
+    }
+    
+    /// Remember the metadata and cost of an API response
+    fn record_response(&self, response: &ApiResponse) {
+        tracing::debug!(response_id = %response.id, model = %response.model, "API response");
+        if !response.id.is_empty() {
+            *self.last_response_id.lock().unwrap() = Some(response.id.clone());
+        }
+        if !response.model.is_empty() {
+            *self.last_model_version.lock().unwrap() = Some(response.model.clone());
+        }
+        if let Some(usage) = &response.usage {
+            self.record_usage(usage, self.client.model());
+        }
+        for warning in &response.warnings {
+            tracing::warn!(response_id = %response.id, model = %response.model, warning = %warning, "API warning");
+        }
+        self.warnings_emitted.fetch_add(response.warnings.len(), Ordering::SeqCst);
+    }
+    
+    /// Add the tokens and estimated cost of a response from `model` to the session usage
+    fn record_usage(&self, usage: &Usage, model: &str) {
+        let cost = api::estimated_cost(model, usage);
+        let mut stats = self.usage.lock().unwrap();
+        stats.input_tokens += usage.input_tokens;
+        stats.output_tokens += usage.output_tokens;
+        stats.estimated_cost += cost;
+        tracing::debug!(input_tokens = usage.input_tokens, output_tokens = usage.output_tokens, cost, "Token usage");
+    }
+    
+    /// The annotation model's description of an image as a user message, if annotations are on
+    ///
+    /// A failed annotation is logged and skipped.
+    async fn annotation_message(&self, image: &str, mime_type: &str) -> Option<Value> {
+        let model = self.annotation_model.as_deref()?;
+        match self.annotate_screenshot(model, image, mime_type).await {
+            Ok(description) => Some(json!({
+                "role": "user",
+                "content": format!("Screen description: {}", description),
+            })),
+            Err(e) => {
+                tracing::warn!(error = %e, "Failed to annotate screenshot");
+                None
+            }
+        }
+    }
+    
+    /// Ask `model` to describe a screenshot, reusing the description of an identical recent one
+    async fn annotate_screenshot(&self, model: &str, image: &str, mime_type: &str) -> Result<String, CuaError> {
+        let cached = self.annotation_cache.lock().unwrap().iter()
+            .find(|(cached_image, _)| cached_image == image)
+            .map(|(_, description)| description.clone());
+        if let Some(description) = cached {
+            return Ok(description);
+        }
+        
+        let input = [json!({
+            "role": "user",
+            "content": [
+                { "type": "input_text", "text": ANNOTATION_PROMPT },
+                { "type": "input_image", "image_url": format!("data:{};base64,{}", mime_type, image) },
+            ],
+        })];
+        let response = self.client.create_response_with_model(&input, &[], model).await?;
+        if let Some(usage) = &response.usage {
+            self.record_usage(usage, model);
+        }
+        let description = response.output.iter()
+            .filter(|item| item.get("type").and_then(|t| t.as_str()) == Some("message"))
+            .find_map(message_text)
+            .ok_or_else(|| CuaError::ApiError("Annotation response has no message".to_string()))?
+            .to_string();
+        
+        let mut cache = self.annotation_cache.lock().unwrap();
+        if cache.len() == ANNOTATION_CACHE_SIZE {
+            cache.pop_front();
+        }
+        cache.push_back((image.to_string(), description.clone()));
+        Ok(description)
+    }
+    
+    /// Ask the model whether the target of a pointer action, marked on a fresh screenshot, is right
+    ///
+    /// Returns the error to report instead of performing the action if the model rejects it.
+    async fn confirm_target(&self, action_type: &str, action: &Value) -> Result<Option<CuaError>, CuaError> {
+        if !matches!(action_type, "click" | "double_click" | "middle_click" | "move") {
+            return Ok(None);
+        }
+        let x = action.get("x").and_then(|x| x.as_i64()).unwrap_or(0) as i32;
+        let y = action.get("y").and_then(|y| y.as_i64()).unwrap_or(0) as i32;
+        let (screen_x, screen_y) = self.to_screen(x, y);
+        let marked = vision::draw_target(&self.computer.screenshot().await?, screen_x, screen_y)?;
+        
+        let input = [json!({
+            "role": "user",
+            "content": [
+                { "type": "input_text", "text": format!("{}\n\nAction: {}", self.grounding_prompt, action) },
+                { "type": "input_image", "image_url": format!("data:image/png;base64,{}", marked) },
+            ],
+        })];
+        let response = self.client.create_response(&input, &[]).await?;
+        if let Some(usage) = &response.usage {
+            self.record_usage(usage, self.client.model());
+        }
+        let answer = response.output.iter()
+            .filter(|item| item.get("type").and_then(|t| t.as_str()) == Some("message"))
+            .find_map(message_text)
+            .ok_or_else(|| CuaError::ApiError("Grounding response has no message".to_string()))?;
+        
+        if answer.trim().to_lowercase().starts_with("yes") {
+            Ok(None)
+        } else {
+            Ok(Some(CuaError::ActionError(format!(
+                "The {} at ({}, {}) was not performed because the marked target was rejected: {}",
+                action_type, x, y, answer.trim()
+            ))))
+        }
+    }
+    
+    /// Summarize the actions taken so far in one paragraph, using the summary model
+    ///
+    /// The summary is cached until the conversation grows.
+    pub async fn summarize_session(&self) -> Result<String, CuaError> {
+        let history = self.history();
+        if let Some((len, summary)) = &*self.session_summary.lock().unwrap() {
+            if *len == history.len() {
+                return Ok(summary.clone());
+            }
+        }
+        
+        let summary = self.summarize_items(&history).await?;
+        *self.session_summary.lock().unwrap() = Some((history.len(), summary.clone()));
+        Ok(summary)
+    }
+    
+    /// Summarize the actions in `items`, e.g. the conversations of batch tasks, like [`Agent::summarize_session`]
+    ///
+    /// The summary is not cached. Typed text passes through the input sanitizer first.
+    pub async fn summarize_items(&self, items: &[Value]) -> Result<String, CuaError> {
+        let transcript: Vec<String> = items.iter()
+            .filter_map(|item| transcript_line(&self.sanitize_typed_text(item)))
+            .collect();
+        let input = [json!({
+            "role": "user",
+            "content": format!("{}\n{}", SUMMARY_PROMPT, transcript.join("\n")),
+        })];
+        let response = self.client.create_response_with_model(&input, &[], &self.summary_model).await?;
+        if let Some(usage) = &response.usage {
+            self.record_usage(usage, &self.summary_model);
+        }
+        response.output.iter()
+            .filter(|item| item.get("type").and_then(|t| t.as_str()) == Some("message"))
+            .find_map(message_text)
+            .map(str::to_string)
+            .ok_or_else(|| CuaError::ApiError("Summary response has no message".to_string()))
+    }
+    
+    /// Check that the configured model is available to this API key
+    pub async fn check_model(&self) -> Result<(), CuaError> {
+        let models = self.client.list_models().await?;
+        if !models.iter().any(|m| m == self.client.model()) {
+            return Err(CuaError::Other(format!(
+                "Model '{}' is not available. Run `models` to list available models",
+                self.client.model()
+            )));
+        }
+        Ok(())
+    }
+    
+    /// Debug print a value
+    fn debug_print(&self, value: &impl fmt::Debug) {
+        if self.debug {
+            tracing::debug!("{:?}", value);
+        }
+    }
+    
+    /// Diff the screen against the last screenshot sent to the model, which it then replaces
+    ///
+    /// Returns the tool output, with the changed region, and the diff image.
+    async fn diff_against_last_screenshot(&self) -> Result<(Value, String), CuaError> {
+        let before = self.last_screenshot.lock().unwrap().clone()
+            .ok_or_else(|| CuaError::ActionError("No earlier screenshot to compare against".to_string()))?;
+        let after = self.computer.screenshot().await?;
+        let changed_region = vision::screenshot_diff(&before, &after)?
+            .map(|(x, y, width, height)| json!({ "x": x, "y": y, "width": width, "height": height }));
+        let image = vision::take_screenshot_diff(&before, &after)?;
+        *self.last_screenshot.lock().unwrap() = Some(after);
+        Ok((json!({ "changed_region": changed_region }), image))
+    }
+    
+    /// Map the coordinates of a `computer_call` action from the screenshot the model saw to the screen
+    fn to_screen_action(&self, action_type: &str, action: &Value) -> Value {
+        let mut screen_action = action.clone();
+        let field = |name: &str| action.get(name).and_then(|v| v.as_i64()).map(|v| v as i32);
+        if let (Some(x), Some(y)) = (field("x"), field("y")) {
+            let (x, y) = self.to_screen(x, y);
+            screen_action["x"] = json!(x);
+            screen_action["y"] = json!(y);
+        }
+        if let (Some(x), Some(y)) = (field("from_x"), field("from_y")) {
+            let (x, y) = self.to_screen(x, y);
+            screen_action["from_x"] = json!(x);
+            screen_action["from_y"] = json!(y);
+        }
+        if let (Some(x), Some(y)) = (field("to_x"), field("to_y")) {
+            let (x, y) = self.to_screen(x, y);
+            screen_action["to_x"] = json!(x);
+            screen_action["to_y"] = json!(y);
+        }
+        if action_type == "drag_by_delta" {
+            let (start_x, start_y) = (field("start_x").unwrap_or(0), field("start_y").unwrap_or(0));
+            let end_x = start_x.checked_add(field("delta_x").unwrap_or(0));
+            let end_y = start_y.checked_add(field("delta_y").unwrap_or(0));
+            // Scale the end point rather than the delta so rounding matches absolute drags.
+            // An end point past the coordinate range is left for `drag_by_delta` to reject.
+            if let (Some(end_x), Some(end_y)) = (end_x, end_y) {
+                let (from_x, from_y) = self.to_screen(start_x, start_y);
+                let (to_x, to_y) = self.to_screen(end_x, end_y);
+                screen_action["start_x"] = json!(from_x);
+                screen_action["start_y"] = json!(from_y);
+                screen_action["delta_x"] = json!(to_x.saturating_sub(from_x));
+                screen_action["delta_y"] = json!(to_y.saturating_sub(from_y));
+            }
+        }
+        if let Some(path) = action.get("path").and_then(|p| p.as_array()) {
+            screen_action["path"] = path
+                .iter()
+                .map(|point| {
+                    let coordinate = |name: &str| point.get(name).and_then(|v| v.as_i64()).map(|v| v as i32);
+                    match (coordinate("x"), coordinate("y")) {
+                        (Some(x), Some(y)) => {
+                            let (x, y) = self.to_screen(x, y);
+                            json!({ "x": x, "y": y })
+                        }
+                        _ => point.clone(),
+                    }
+                })
+                .collect();
+        }
+        screen_action
+    }
+    
+    /// Perform a `computer_call` action on the computer
+    async fn perform_action(&self, action_type: &str, action: &Value) -> Result<(), CuaError> {
+        let action = self.to_screen_action(action_type, action);
+        if self.debug {
+            tracing::debug!(action = action_type, params = %action, "Processing computer action");
+        }
+        if self.print_steps {
+            match action_type {
+                "screenshot" => tracing::info!("Taking screenshot as requested by the model"),
+                "type" | "type_at" => {
+                    let text = action.get("text").and_then(|t| t.as_str()).unwrap_or("");
+                    tracing::info!(text = %self.sanitize_input(text), "Typing text");
+                }
+                _ => {}
+            }
+        }
+        
+        self.ensure_capturable(action_type, &action_points(action_type, &action)).await?;
+        if !perform_screen_action(&*self.computer, action_type, &action).await? && self.print_steps {
+            // Unknown actions are skipped rather than failing the turn, so the
+            // agent stays resilient to action types it does not support yet
+            tracing::warn!(action = action_type, "Action type is not supported, continuing with a screenshot");
+        }
+        Ok(())
+    }
+    
+    /// Handle an item from the API response
+    async fn handle_item(&self, item: &Value) -> Result<Vec<Value>, CuaError> {
+        let mut new_items = Vec::new();
+        
+        // Check the item type
+        let item_type = item.get("type")
+            .and_then(|t| t.as_str())
+            .unwrap_or("");
+        
+        if let Some(filter) = &self.observation_filter {
+            if !filter(item) {
+                tracing::debug!(item_type, "Skipping item rejected by the observation filter");
+                return Ok(new_items);
+            }
+        }
+        
+        match item_type {
+            "message" => {
+                if self.debug {
+                    tracing::debug!(?item, "Handling 'message' item");
+                }
+                if let Some(text) = message_text(item) {
+                    if self.print_steps {
+                        tracing::info!("{}", text);
+                    }
+                    self.observers.on_model_output(text);
+                    *self.action_context.lock().unwrap() = text.to_string();
+                }
+            }
+            "function_call" => {
+                if let (Some(name), Some(arguments), Some(call_id)) = (
+                    item.get("name").and_then(|n| n.as_str()),
+                    item.get("arguments").and_then(|a| a.as_str()),
+                    item.get("call_id"),
+                ) {
+                    if self.debug {
+                        tracing::debug!(name, "Handling 'function_call' item");
+                    }
+                    if self.print_steps {
+                        tracing::info!(name, arguments, "Function call");
+                    }
+                    
+                    // Dispatch registered and computer tools; other functions get a generic success response
+                    let args: Value = serde_json::from_str(arguments).unwrap_or_else(|_| json!({}));
+                    let mut diff_image = None;
+                    let result = if name == tools::SCREENSHOT_DIFF_TOOL {
+                        Some(self.diff_against_last_screenshot().await.map(|(output, image)| {
+                            diff_image = Some(image);
+                            output
+                        }))
+                    } else if name == tools::SCRIPT_TOOL {
+                        Some(self.execute_script(&args).await)
+                    } else {
+                        match self.tool_fns.iter().find(|tool| tool.definition()["name"] == name) {
+                            Some(tool) => Some(tool.call(&args).await),
+                            None => self.call_computer_tool(name, &args).await,
+                        }
+                    };
+                    let mut image_message = match diff_image {
+                        Some(image) => Some((
+                            "Screenshot diff: changed pixels are red, unchanged ones gray.".to_string(),
+                            self.prepare_screenshot(image)?,
+                        )),
+                        None => None,
+                    };
+                    let output = match result {
+                        Some(Ok(Value::String(image))) if tools::IMAGE_OUTPUT_TOOLS.contains(&name) => {
+                            image_message = Some((format!("Image captured by {}.", name), self.prepare_tool_image(image)?));
+                            "Image captured; it follows as an image input.".to_string()
+                        }
+                        Some(Ok(value)) => value.to_string(),
+                        Some(Err(e)) => format!("Error: {}", e),
+                        None => "success".to_string(),
+                    };
+                    
+                    self.observers.on_function_call(name, &args, &output);
+                    new_items.push(json!({
+                        "type": "function_call_output",
+                        "call_id": call_id,
+                        "output": output,
+                    }));
+                    
+                    // Function outputs are text only, so images follow as a user message
+                    if let Some((caption, (image, mime_type))) = image_message {
+                        new_items.push(json!({
+                            "role": "user",
+                            "content": [
+                                { "type": "input_text", "text": caption },
+                                { "type": "input_image", "image_url": format!("data:{};base64,{}", mime_type, image) },
+                            ],
+                        }));
+                        if let Some(annotation) = self.annotation_message(&image, mime_type).await {
+                            new_items.push(annotation);
+                        }
+                    }
+                }
+            }
+            "computer_call" => {
+                if let (Some(action), Some(call_id)) = (
+                    item.get("action"),
+                    item.get("call_id"),
+                ) {
+                    if let Some(action_type) = action.get("type").and_then(|t| t.as_str()) {
+                        if self.print_steps {
+                            tracing::info!(action = action_type, "Computer action");
+                        }
+                        
+                        // Handle safety checks
+                        let mut acknowledged_safety_checks = Vec::new();
+                        if let Some(pending_checks) = item.get("pending_safety_checks").and_then(|c| c.as_array()) {
+                            for check in pending_checks {
+                                if let Some(message) = check.get("message").and_then(|m| m.as_str()) {
+                                    if self.print_steps {
+                                        tracing::warn!(message, "Safety check");
+                                    }
+                                    
+                                    if !(self.acknowledge_safety_check)(message) {
+                                        return Err(CuaError::Other(format!(
+                                            "Safety check failed: {}", message
+                                        )));
+                                    }
+                                    
+                                    acknowledged_safety_checks.push(check.clone());
+                                }
+                            }
+                        }
+                        
+                        if let Some(hook) = &self.before_action_hook {
+                            hook(action_type, action).await?;
+                        }
+                        
+                        // Stay within the configured action rate
+                        if let Some(rate_limiter) = &self.rate_limiter {
+                            rate_limiter.acquire().await;
+                        }
+                        
+                        // Pause like a human would after the previous action
+                        if let Some((min_ms, max_ms)) = self.step_delay {
+                            if !self.action_history.lock().unwrap().is_empty() {
+                                let delay = rand::thread_rng().gen_range(min_ms..=max_ms);
+                                tokio::time::sleep(Duration::from_millis(delay)).await;
+                            }
+                        }
+                        
+                        // Have the model confirm pointer targets if configured to
+                        let rejection = if self.vision_grounding {
+                            self.confirm_target(action_type, action).await?
+                        } else {
+                            None
+                        };
+                        
+                        // Show a person watching where the action will happen
+                        if let (Some(duration_ms), None) = (self.action_highlight, &rejection) {
+                            let x = action.get("x").and_then(|x| x.as_i64());
+                            let y = action.get("y").and_then(|y| y.as_i64());
+                            if let (Some(x), Some(y)) = (x, y) {
+                                let (x, y) = self.to_screen(x as i32, y as i32);
+                                let (left, top) = ((x - 20).max(0) as u32, (y - 20).max(0) as u32);
+                                // The outline shows in the background, so wait for it before acting
+                                match self.computer.highlight_region(left, top, 40, 40, "red", duration_ms).await {
+                                    Ok(()) => tokio::time::sleep(Duration::from_millis(duration_ms as u64)).await,
+                                    Err(e) => tracing::warn!(action = action_type, error = %e, "Could not highlight the action target"),
+                                }
+                            }
+                        }
+                        
+                        // Record the action in screen coordinates, never storing raw typed text
+                        if rejection.is_none() {
+                            let mut params = self.to_screen_action(action_type, action);
+                            if let Some(text) = action.get("text").and_then(|t| t.as_str()) {
+                                let sanitized = self.sanitize_input(text);
+                                if sanitized != text {
+                                    // Marks text a replay cannot reproduce
+                                    params["sanitized"] = json!(true);
+                                }
+                                params["text"] = json!(sanitized);
+                            }
+                            self.observers.on_action(action_type, &params);
+                            self.action_history.lock().unwrap().push(ActionRecord::new(action_type, params));
+                        }
+                        
+                        // Perform the action, letting the model handle failures if configured to
+                        let started = Instant::now();
+                        let performed = rejection.is_none();
+                        let action_error = match rejection {
+                            Some(rejection) => {
+                                tracing::warn!(action = action_type, error = %rejection, "Action target rejected");
+                                Some(rejection)
+                            }
+                            None => match self.perform_with_retries(action_type, action).await {
+                                Ok(()) => None,
+                                Err(e) if matches!(
+                                    self.error_recovery,
+                                    ErrorRecoveryStrategy::TakeScreenshotAndContinue | ErrorRecoveryStrategy::AskModel
+                                ) => {
+                                    tracing::warn!(action = action_type, error = %e, "Action failed, continuing");
+                                    self.observers.on_error(&e);
+                                    Some(e)
+                                }
+                                Err(e) => {
+                                    self.observers.on_action_complete(action_type, started.elapsed(), Some(&e));
+                                    return Err(e);
+                                }
+                            },
+                        };
+                        if performed {
+                            self.observers.on_action_complete(action_type, started.elapsed(), action_error.as_ref());
+                        }
+                        
+                        // Let the UI settle before capturing the result
+                        let settle_delay = self.effective_settle_delay().await?;
+                        if !settle_delay.is_zero() {
+                            tokio::time::sleep(settle_delay).await;
+                        }
+                        
+                        // Take a PNG screenshot; `prepare_screenshot` encodes the upload format
+                        let screenshot_base64 = if self.cursor_in_screenshot {
+                            self.computer.screenshot_with_cursor().await?
+                        } else {
+                            self.computer.screenshot().await?
+                        };
+                        *self.last_screenshot.lock().unwrap() = Some(screenshot_base64.clone());
+                        let screen_text = if self.text_mode {
+                            match self.computer.ocr_extract(&screenshot_base64).await {
+                                Ok(words) => Some(ocr::words_to_text(&words)).filter(|text| !text.trim().is_empty()),
+                                Err(e) => {
+                                    tracing::warn!(error = %e, "OCR failed, sending the screenshot alone");
+                                    None
+                                }
+                            }
+                        } else {
+                            None
+                        };
+                        let (screenshot_base64, mime_type) = match self.reusable_upload(&screenshot_base64) {
+                            Some(upload) => upload,
+                            None => {
+                                let (image, mime_type) = self.prepare_screenshot(screenshot_base64.clone())?;
+                                if self.screenshot_diff_threshold.is_some() {
+                                    *self.last_upload.lock().unwrap() = Some((screenshot_base64, image.clone(), mime_type));
+                                }
+                                (image, mime_type)
+                            }
+                        };
+                        let turn = self.turn_records.lock().unwrap().len();
+                        self.observers.on_screenshot(&screenshot_base64, turn);
+                        if let Some(hook) = &self.after_action_hook {
+                            hook(action_type, &json!(screenshot_base64)).await?;
+                        }
+                        
+                        // Create the response
+                        let output = json!({
+                            "type": "input_image",
+                            "image_url": format!("data:{};base64,{}", mime_type, screenshot_base64),
+                        });
+                        let mut call_output = json!({
+                            "type": "computer_call_output",
+                            "call_id": call_id,
+                            "acknowledged_safety_checks": acknowledged_safety_checks,
+                            "output": output,
+                        });
+                        
+                        // Add current URL for browser environments
+                        if self.computer.environment() == "browser" {
+                            let current_url = self.computer.get_current_url().await?;
+                            if let Some(output) = call_output.get_mut("output").and_then(|o| o.as_object_mut()) {
+                                output.insert("current_url".to_string(), json!(current_url));
+                            }
+                        }
+                        
+                        new_items.push(call_output);
+                        
+                        if let Some(text) = screen_text {
+                            new_items.push(json!({ "role": "user", "content": format!("Screen text:\n{}", text) }));
+                        }
+                        
+                        if self.all_screens_mode {
+                            // The call output already shows the primary screen
+                            let primary = self.computer.primary_screen();
+                            let bounds = self.computer.screen_bounds().await.unwrap_or_else(|e| {
+                                tracing::warn!(error = %e, "Cannot get the screen bounds; labelling screens by index only");
+                                Vec::new()
+                            });
+                            let mut content = Vec::new();
+                            for (index, screen) in self.computer.capture_all_screens().await? {
+                                if index == primary {
+                                    continue;
+                                }
+                                let label = match bounds.get(index) {
+                                    Some((x, y, width, height)) => format!("Screen {} at ({}, {}), {}x{}", index, x, y, width, height),
+                                    None => format!("Screen {}", index),
+                                };
+                                let (image, mime_type, _) = self.encode_screenshot(screen)?;
+                                content.push(json!({ "type": "input_text", "text": label }));
+                                content.push(json!({ "type": "input_image", "image_url": format!("data:{};base64,{}", mime_type, image) }));
+                            }
+                            if !content.is_empty() {
+                                new_items.push(json!({ "role": "user", "content": content }));
+                            }
+                        }
+                        
+                        if let Some(e) = action_error {
+                            // The call output above already carries the screenshot
+                            let text = match self.error_recovery {
+                                ErrorRecoveryStrategy::TakeScreenshotAndContinue => {
+                                    format!("An error occurred: {}. The screenshot above is the current screen state.", e)
+                                }
+                                _ => format!("The last action failed: {}. Decide how to continue.", e),
+                            };
+                            new_items.push(json!({ "role": "user", "content": text }));
+                        }
+                        
+                        if let Some(annotation) = self.annotation_message(&screenshot_base64, mime_type).await {
+                            new_items.push(annotation);
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+        
+        Ok(new_items)
+    }
+    
+    /// Perform an action, retrying failures as the recovery strategy allows
+    ///
+    /// When debugging, a screenshot of every failure is kept.
+    async fn perform_with_retries(&self, action_type: &str, action: &Value) -> Result<(), CuaError> {
+        let retries = match self.error_recovery {
+            ErrorRecoveryStrategy::RetryN(retries) if IDEMPOTENT_ACTIONS.contains(&action_type) => retries,
+            _ => 0,
+        };
+        let mut attempt = 0;
+        loop {
+            let perform = self.perform_action(action_type, action);
+            let result = if self.debug {
+                self.computer.take_screenshot_on_error(Box::pin(perform)).await
+            } else {
+                perform.await
+            };
+            match result {
+                Err(e) if attempt < retries => {
+                    attempt += 1;
+                    tracing::warn!(action = action_type, error = %e, attempt, retries, "Action failed, retrying");
+                }
+                result => return result,
+            }
+        }
+    }
+    
+    /// Run the agent for a single turn
+    pub async fn run_full_turn(&self, input_items: &[Value]) -> Result<Vec<Value>, CuaError> {
+        // A failed run must not leave the previous run's answer behind
+        *self.structured_output.lock().unwrap() = None;
+        let result = self.run_turns(input_items).await;
+        match &result {
+            Ok(items) => {
+                *self.history.lock().unwrap() = items.clone();
+                // Earlier conversation passed back in, e.g. in interactive mode, holds older answers
+                let new_items = items.get(input_items.len()..).unwrap_or_default();
+                *self.structured_output.lock().unwrap() = self.parse_structured_output(new_items);
+            }
+            Err(e) => self.observers.on_error(e),
+        }
+        result
+    }
+    
+    /// Parse the final assistant message in `items`, the items a run added, and validate it against the output schema
+    fn parse_structured_output(&self, items: &[Value]) -> Option<Value> {
+        let validator = self.output_validator.as_ref()?;
+        let text = items.iter()
+            .rev()
+            .find(|item| item.get("role").and_then(|r| r.as_str()) == Some("assistant"))
+            .and_then(message_text)?;
+        
+        // Models often wrap JSON in a Markdown code block
+        let text = text.trim();
+        let text = text.strip_prefix("```json").or_else(|| text.strip_prefix("```")).unwrap_or(text);
+        let text = text.strip_suffix("```").unwrap_or(text);
+        let value: Value = match serde_json::from_str(text.trim()) {
+            Ok(value) => value,
+            Err(e) => {
+                tracing::warn!(error = %e, "Final answer is not valid JSON");
+                return None;
+            }
+        };
+        if !validator.is_valid(&value) {
+            tracing::warn!("Final answer does not match the output schema");
+            return None;
+        }
+        Some(value)
+    }
+    
+    /// System prompt of each request, including the output schema in structured output mode
+    fn full_system_prompt(&self) -> Option<String> {
+        let structured = self.output_schema.as_ref()
+            .map(|schema| format!("{}\n{}", STRUCTURED_OUTPUT_PROMPT, schema));
+        match (&self.system_prompt, structured) {
+            (Some(prompt), Some(structured)) => Some(format!("{}\n\n{}", prompt, structured)),
+            (prompt, structured) => structured.or_else(|| prompt.clone()),
+        }
+    }
+    
+    /// Make a single API request for `items` and handle the response
+    ///
+    /// Computer actions the model requests are performed before returning.
+    /// The caller decides whether to step again with `items` followed by the
+    /// returned `new_items`.
+    pub async fn step(&self, items: Vec<Value>) -> Result<StepResult, CuaError> {
+        let result = self.step_once(items).await;
+        if let Err(e) = &result {
+            self.observers.on_error(e);
+        }
+        result
+    }
+    
+    async fn step_once(&self, items: Vec<Value>) -> Result<StepResult, CuaError> {
+        self.ensure_model_validated().await?;
+        let start = items.len();
+        let mut all_items = items;
+        let mut state = TurnState::new(&all_items);
+        let is_complete = self.request_turn(&mut all_items, &mut state, true).await?;
+        *self.history.lock().unwrap() = all_items.clone();
+        
+        let new_items = all_items.split_off(start);
+        let last_action = new_items.iter().rev().find_map(PlannedAction::from_computer_call);
+        Ok(StepResult { new_items, is_complete, last_action })
+    }
+    
+    /// Validate the model once per agent, if enabled
+    async fn ensure_model_validated(&self) -> Result<(), CuaError> {
+        if self.validate_model && !self.model_validated.load(Ordering::SeqCst) {
+            self.check_model().await?;
+            self.model_validated.store(true, Ordering::SeqCst);
+        }
+        Ok(())
+    }
+    
+    /// Request responses and handle their items until the model gives a final answer
+    async fn run_turns(&self, input_items: &[Value]) -> Result<Vec<Value>, CuaError> {
+        self.ensure_model_validated().await?;
+        
+        // Create a copy of input items, remembering their IDs to avoid duplicates
+        let mut all_items = input_items.to_vec();
+        let mut state = TurnState::new(&all_items);
+        
+        // Keep looping until we get a final assistant response
+        let mut first_request = true;
+        loop {
+            let done = self.request_turn(&mut all_items, &mut state, first_request).await?;
+            if let Some((interval, path)) = &self.checkpoint {
+                let turns = self.turn_records.lock().unwrap().len();
+                if turns.is_multiple_of(*interval as usize) {
+                    if let Err(e) = self.save_state(path, &all_items) {
+                        tracing::warn!(error = %e, path = %path.display(), "Failed to save the checkpoint");
+                    }
+                }
+            }
+            if done {
+                break;
+            }
+            first_request = false;
+        }
+        
+        // A finished run has nothing to resume
+        if let Some((_, path)) = &self.checkpoint {
+            match std::fs::remove_file(path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                    tracing::warn!(error = %e, path = %path.display(), "Failed to delete the checkpoint");
+                }
+                _ => {}
+            }
+        }
+        Ok(all_items)
+    }
+    
+    /// Request one response for `all_items`, handle its items and append them
+    ///
+    /// Returns whether the model gave a final answer.
+    async fn request_turn(
+        &self,
+        all_items: &mut Vec<Value>,
+        state: &mut TurnState,
+        first_request: bool,
+    ) -> Result<bool, CuaError> {
+        // Space out requests to avoid bursts
+        self.wait_before_request(first_request).await;
+        self.wait_for_battery().await;
+        
+        for warning in self.memory_warnings().await {
+            all_items.push(warning.clone());
+            state.unsent.push(warning);
+        }
+        
+        // Debug print current state
+        if self.debug {
+            self.debug_print(&all_items);
+        }
+        
+        // When continuing from the previous response, send only what the API hasn't seen
+        let previous_id = state.previous_response_id.take().filter(|_| self.stateless_continuation);
+        let mut input = match previous_id {
+            Some(_) => std::mem::take(&mut state.unsent),
+            None => {
+                let system = self.full_system_prompt().into_iter().map(|prompt| json!({ "role": "system", "content": prompt }));
+                system.chain(all_items.iter().cloned()).collect()
+            }
+        };
+        if let Some(prefix) = &self.thought_prefix {
+            let message = json!({
+                "role": "user",
+                "content": prefix,
+            });
+            if !self.strip_thought_prefix {
+                all_items.push(message.clone());
+            }
+            input.push(message);
+        }
+        
+        // Create a request to the API
+        self.observers.on_api_request(input.len(), previous_id.as_deref());
+        let mut rate_limit_retries = 0;
+        let response = loop {
+            let response = self
+                .client
+                .create_response_with_parameters(&input, &self.tools, previous_id.as_deref(), &self.model_parameters)
+                .await;
+            match response {
+                Err(CuaError::RateLimited { retry_after, .. }) if self.adaptive_delay && rate_limit_retries < MAX_RATE_LIMIT_RETRIES => {
+                    rate_limit_retries += 1;
+                    let delay = retry_after.unwrap_or(DEFAULT_RATE_LIMIT_DELAY);
+                    tracing::warn!(delay_ms = delay.as_millis() as u64, attempt = rate_limit_retries, "Rate limited, waiting before retrying");
+                    tokio::time::sleep(delay).await;
+                }
+                response => break response,
+            }
+        };
+        self.observers.on_api_response(response.as_ref());
+        let response = response?;
+        
+        if self.debug {
+            self.debug_print(&response);
+        }
+        
+        self.record_response(&response);
+        
+        // Start a new turn record
+        let turn = {
+            let mut records = self.turn_records.lock().unwrap();
+            let turn = records.len() + 1;
+            records.push(TurnRecord { turn, ..Default::default() });
+            turn
+        };
+        
+        if !response.id.is_empty() {
+            state.previous_response_id = Some(response.id.clone());
+        }
+        state.unsent.clear();
+        let mut new_items = Vec::new();
+        
+        // Add the output to new items, checking for duplicates
+        for item in response.output {
+            // Skip items we've already processed
+            if let Some(id) = item.get("id").and_then(|id| id.as_str()) {
+                if state.processed_ids.contains(id) {
+                    continue;
+                }
+                state.processed_ids.insert(id.to_string());
+            }
+            
+            new_items.push(item.clone());
+            
+            // Handle each item
+            let handled_items = self.handle_item(&item).await?;
+            
+            // Also check for duplicates in handled items
+            for handled_item in handled_items {
+                if let Some(id) = handled_item.get("id").and_then(|id| id.as_str()) {
+                    if state.processed_ids.contains(id) {
+                        continue;
+                    }
+                    state.processed_ids.insert(id.to_string());
+                }
+                state.unsent.push(handled_item.clone());
+                new_items.push(handled_item);
+            }
+        }
+        
+        self.observers.on_turn_complete(turn, &self.usage_stats());
+        
+        // Check if we got a final response
+        let is_complete = new_items
+            .last()
+            .is_some_and(|item| item.get("role").and_then(|r| r.as_str()) == Some("assistant"));
+        all_items.extend(new_items);
+        
+        // Stop before the next request once the budget is spent
+        if let Some(limit) = self.cost_budget.filter(|_| !is_complete) {
+            let spent = self.total_cost();
+            if spent > limit {
+                return Err(CuaError::BudgetExceeded { spent, limit, items: all_items.clone() });
+            }
+        }
+        
+        Ok(is_complete)
+    }
+    
+    /// Run the agent with the specified input
+    pub async fn run(&self, input: &str) -> Result<Vec<Value>, CuaError> {
+        // Create initial items
+        let mut items = self.initial_items.clone();
+        items.push(json!({
+            "role": "user",
+            "content": input,
+        }));
+        
+        // Run a turn with the input
+        self.run_full_turn(&items).await
+    }
+    
+    /// Run each task on its own fork of the agent, at most `concurrency` at a time
+    ///
+    /// The forks share this agent's client and observers, and their token usage
+    /// is added to this agent's. Tasks running at once would fight over one
+    /// screen, so with a `concurrency` above 1 each task runs on a computer from
+    /// `computers`; without a factory the tasks run one at a time on this
+    /// agent's computer. `rate_limiter` replaces the forks' action rate limiter
+    /// so that one limit covers all tasks; without it they share this agent's
+    /// limiter, if any. A failed task is reported in its result. Results are in
+    /// the order of `tasks`.
+    pub async fn run_batch_tasks(
+        &self,
+        tasks: Vec<String>,
+        concurrency: usize,
+        rate_limiter: Option<Arc<ActionRateLimiter>>,
+        computers: Option<&ComputerFactory<C>>,
+    ) -> Result<Vec<AgentResult>, CuaError> {
+        if concurrency == 0 {
+            return Err(CuaError::Other("Batch concurrency must be at least 1".to_string()));
+        }
+        if concurrency > 1 && computers.is_none() {
+            return Err(CuaError::Other(format!(
+                "Running {} tasks at once needs a computer per task; pass a computer factory or use a concurrency of 1",
+                concurrency
+            )));
+        }
+        
+        let mut results: Vec<(usize, AgentResult)> = stream::iter(tasks.into_iter().enumerate())
+            .map(|(index, input)| {
+                let rate_limiter = rate_limiter.clone();
+                async move {
+                    let started = Instant::now();
+                    let mut agent = self.fork();
+                    if let Some(rate_limiter) = rate_limiter {
+                        agent.rate_limiter = Some(rate_limiter);
+                    }
+                    let usage_before = agent.usage_stats();
+                    let result = match computers {
+                        Some(computers) => computers().map(|computer| agent.replace_computer(computer)),
+                        None => Ok(()),
+                    };
+                    let (items, error) = match result {
+                        Ok(()) => match agent.run(&input).await {
+                            Ok(items) => (items, None),
+                            Err(e) => (Vec::new(), Some(e)),
+                        },
+                        Err(e) => (Vec::new(), Some(e)),
+                    };
+                    self.add_usage_since(&agent, usage_before);
+                    let structured = agent.structured_output();
+                    (index, AgentResult { input, items, duration: started.elapsed(), error, structured })
+                }
+            })
+            .buffer_unordered(concurrency)
+            .collect()
+            .await;
+        results.sort_by_key(|(index, _)| *index);
+        Ok(results.into_iter().map(|(_, result)| result).collect())
+    }
+    
+    /// Run a fork on `computer` instead of the computer it shares with its parent
+    fn replace_computer(&mut self, computer: C) {
+        self.computer = Arc::new(computer);
+        self.fallback_active = None;
+        self.recording_area = OnceLock::new();
+    }
+    
+    /// Add the tokens and cost `fork` used since its usage was `before` to this agent's usage
+    fn add_usage_since(&self, fork: &Agent<C>, before: UsageStats) {
+        let after = fork.usage_stats();
+        let mut usage = self.usage.lock().unwrap();
+        usage.input_tokens += after.input_tokens - before.input_tokens;
+        usage.output_tokens += after.output_tokens - before.output_tokens;
+        usage.estimated_cost += after.estimated_cost - before.estimated_cost;
+    }
+    
+    /// Run the agent with `observer` registered for this run only
+    pub async fn run_with_observer(&mut self, input: &str, observer: Box<dyn AgentObserver>) -> Result<Vec<Value>, CuaError> {
+        self.observers.add(observer);
+        let result = self.run(input).await;
+        self.observers.pop();
+        result
+    }
+    
+    /// Run the steps of a script in order, carrying the conversation forward
+    ///
+    /// A failed step ends the script with its error unless the step sets
+    /// `continue_on_error`, in which case the failure is recorded and the
+    /// next step continues from the conversation before the failed step.
+    pub async fn run_script(&self, script: &AgentScript) -> Result<Vec<script::StepResult>, CuaError> {
+        let mut items = self.initial_items.clone();
+        let mut results = Vec::new();
+        
+        for step in &script.steps {
+            if self.print_steps {
+                tracing::info!(input = %step.input, "Script step");
+            }
+            
+            let mut step_items = items.clone();
+            step_items.push(json!({
+                "role": "user",
+                "content": step.input,
+            }));
+            
+            let outcome = match step.timeout {
+                Some(timeout) => tokio::time::timeout(timeout, self.run_full_turn(&step_items))
+                    .await
+                    .unwrap_or_else(|_| Err(CuaError::Other(format!(
+                        "Step '{}' timed out after {:?}", step.input, timeout
+                    )))),
+                None => self.run_full_turn(&step_items).await,
+            };
+            
+            match outcome {
+                Ok(all_items) => {
+                    let new_items = all_items[items.len()..].to_vec();
+                    items = all_items;
+                    results.push(script::StepResult { input: step.input.clone(), outcome: Ok(new_items) });
+                }
+                Err(e) if step.continue_on_error => {
+                    results.push(script::StepResult { input: step.input.clone(), outcome: Err(e) });
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        
+        Ok(results)
+    }
+    
+    /// Run the agent interactively
+    pub async fn run_interactive(&self) -> Result<(), CuaError> {
+        let mut items = self.initial_items.clone();
+        
+        println!("OpenAI CUA Agent");
+        println!("Type 'exit' to quit");
+        
+        loop {
+            // Get input from user
+            print!("> ");
+            std::io::Write::flush(&mut std::io::stdout())?;
+            
+            let mut input = String::new();
+            std::io::stdin().read_line(&mut input)?;
+            
+            let input = input.trim();
+            if input.eq_ignore_ascii_case("exit") {
+                break;
+            }
+            
+            // Add input to items
+            items.push(json!({
+                "role": "user",
+                "content": input,
+            }));
+            
+            // Run a turn with the input
+            items = self.run_full_turn(&items).await?;
+        }
+        
+        Ok(())
+    }
+}
+
+/// Runs an agent one API request at a time, keeping the conversation between steps
+pub struct SteppingAgent<'a, C: Computer> {
+    agent: &'a Agent<C>,
+    all_items: Vec<Value>,
+}
+
+impl<'a, C: Computer> SteppingAgent<'a, C> {
+    /// Start a conversation with `input`, after the agent's initial items
+    pub fn new(agent: &'a Agent<C>, input: &str) -> Self {
+        let mut all_items = agent.initial_items.clone();
+        all_items.push(json!({
+            "role": "user",
+            "content": input,
+        }));
+        Self { agent, all_items }
+    }
+    
+    /// Make the next request, adding its items to the conversation
+    pub async fn next(&mut self) -> Result<StepResult, CuaError> {
+        let result = self.agent.step(self.all_items.clone()).await?;
+        self.all_items.extend(result.new_items.iter().cloned());
+        Ok(result)
+    }
+    
+    /// The conversation so far
+    pub fn items(&self) -> &[Value] {
+        &self.all_items
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockComputer;
+    use crate::mock_api::{assistant_message, computer_call, response_body, MockApi};
+    use crate::ocr::OcrWord;
+    use crate::sanitizer::PiiSanitizer;
+    
+    // To run these tests, you need to have an OpenAI API key
+    // and the API must support the CUA model
+    #[tokio::test]
+    #[ignore] // Ignore by default since it requires API credentials
+    async fn test_agent_run() {
+        // This is a simple test of the agent
+        // It requires a valid API key and org ID
+        
+        // Set up API key and org ID
+        std::env::set_var("OPENAI_API_KEY", "YOUR_API_KEY");
+        std::env::set_var("OPENAI_ORG", "YOUR_ORG_ID");
+        
+        // Create client
+        let client = OpenAIClient::from_env(None).unwrap();
+        
+        // Create computer
+        let computer = MockComputer::new("linux", 1920, 1080);
+        
+        // Create agent
+        let agent = Agent::new(
+            client,
+            Box::new(computer),
+            Vec::new(),
+            None,
+        );
+        
+        // Run the agent
+        let result = agent.run("Hello, world!").await;
+        
+        // Check if the agent ran successfully
+        assert!(result.is_ok());
+    }
+    
+    #[tokio::test]
+    async fn test_validate_model_rejects_unknown_model() {
+        let server = MockApi::start().await;
+        server.push_response(200, json!({ "data": [{ "id": "computer-use-preview" }] }));
+        
+        let agent = Agent::new(
+            server.client(),
+            Box::new(MockComputer::new("linux", 1920, 1080)),
+            Vec::new(),
+            None,
+        )
+        .with_validate_model(true);
+        
+        let result = agent.run("Hello").await;
+        
+        assert!(matches!(result, Err(CuaError::Other(msg)) if msg.contains("test_model")));
+        assert_eq!(server.requests().len(), 1);
+    }
+    
+    #[tokio::test]
+    async fn test_tool_fn_handles_function_call() {
+        let server = MockApi::start().await;
+        server.push_response(200, response_body(vec![json!({
+            "type": "function_call",
+            "id": "fc_1",
+            "call_id": "call_1",
+            "name": "web_search",
+            "arguments": "{\"query\": \"rust release\"}",
+        })]));
+        server.push_response(200, json!({ "web": { "results": [
+            { "title": "Rust 1.80", "url": "https://blog.rust-lang.org", "description": "Released today" },
+        ] } }));
+        server.push_response(200, response_body(vec![assistant_message("Done")]));
+        
+        let search = WebSearchTool::new("brave_key").with_base_url(&server.base_url());
+        let agent = Agent::new(
+            server.client(),
+            Box::new(MockComputer::new("linux", 1920, 1080)),
+            Vec::new(),
+            None,
+        )
+        .with_tool_fn(Box::new(search));
+        agent.run("What is the latest Rust release?").await.unwrap();
+        
+        let requests = server.requests();
+        assert!(requests[0].body["tools"].as_array().unwrap().iter().any(|t| t["name"] == "web_search"));
+        assert!(requests[1].path.starts_with("/v1/web/search?q=rust+release"));
+        let output = requests[2].body["input"].as_array().unwrap().iter()
+            .find(|item| item["type"] == "function_call_output")
+            .unwrap()["output"]
+            .clone();
+        let results: Value = serde_json::from_str(output.as_str().unwrap()).unwrap();
+        assert_eq!(results[0]["snippet"], "Released today");
+    }
+    
+    #[tokio::test]
+    async fn test_tool_registry_from_schema_file() {
+        let path = std::env::temp_dir().join(format!("cua_tools_{}.json", uuid::Uuid::new_v4()));
+        std::fs::write(&path, json!({ "tools": [
+            { "name": "lookup_ticket", "description": "Find a support ticket", "parameters": { "type": "object", "properties": {} } },
+            { "name": "close_ticket", "description": "Close a support ticket", "parameters": { "type": "object", "properties": {} } },
+        ] }).to_string()).unwrap();
+        let registry = ToolRegistry::from_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        
+        let server = MockApi::start().await;
+        server.push_response(200, response_body(vec![json!({
+            "type": "function_call",
+            "id": "fc_1",
+            "call_id": "call_1",
+            "name": "close_ticket",
+            "arguments": "{}",
+        })]));
+        server.push_response(200, response_body(vec![assistant_message("Done")]));
+        
+        let agent = Agent::new(server.client(), MockComputer::new("linux", 1920, 1080), Vec::new(), None)
+            .with_tool_registry(registry);
+        agent.run("Close ticket 42").await.unwrap();
+        
+        let requests = server.requests();
+        let tools = requests[0].body["tools"].as_array().unwrap();
+        assert!(["lookup_ticket", "close_ticket"].iter().all(|name| tools.iter().any(|t| t["name"] == *name)));
+        let output = requests[1].body["input"].as_array().unwrap().iter()
+            .find(|item| item["type"] == "function_call_output")
+            .unwrap()["output"]
+            .clone();
+        assert_eq!(serde_json::from_str::<Value>(output.as_str().unwrap()).unwrap(), json!({ "status": "not_implemented" }));
+    }
+    
+    #[tokio::test]
+    async fn test_inject_screenshot_is_first_item() {
+        let server = MockApi::start().await;
+        server.push_response(200, response_body(vec![assistant_message("Done")]));
+        
+        let mut agent = Agent::new(
+            server.client(),
+            Box::new(MockComputer::new("linux", 1920, 1080)),
+            Vec::new(),
+            None,
+        );
+        agent.inject_screenshot("c2NyZWVu");
+        agent.run("What is on the screen?").await.unwrap();
+        
+        let input = &server.requests()[0].body["input"];
+        assert_eq!(input[0]["type"], "computer_call_output");
+        assert_eq!(input[0]["output"]["image_url"], "data:image/png;base64,c2NyZWVu");
+        assert!(input[0]["call_id"].as_str().unwrap().starts_with("call_"));
+        assert_eq!(input[1]["role"], "user");
+    }
+    
+    #[tokio::test]
+    async fn test_input_sanitizer_hides_typed_email() {
+        let server = MockApi::start().await;
+        server.push_response(200, response_body(vec![computer_call(
+            "call_1",
+            json!({ "type": "type", "text": "Contact alice@example.com" }),
+        )]));
+        server.push_response(200, response_body(vec![assistant_message("Done")]));
+        
+        let computer = MockComputer::new("linux", 1920, 1080);
+        let agent = Agent::new(server.client(), Box::new(computer.clone()), Vec::new(), None)
+            .with_input_sanitizer(PiiSanitizer::boxed());
+        agent.run("Type the contact").await.unwrap();
+        
+        assert_eq!(agent.action_history()[0].params["text"], "Contact <EMAIL>");
+        assert_eq!(computer.action_history()[0].params["text"], "Contact alice@example.com");
+        
+        // The model's message before the action gives the sanitizer its context
+        server.push_response(200, response_body(vec![
+            assistant_message("Entering the password"),
+            computer_call("call_2", json!({ "type": "type", "text": "hunter2" })),
+        ]));
+        server.push_response(200, response_body(vec![assistant_message("Done")]));
+        agent.run("Log in").await.unwrap();
+        
+        assert_eq!(agent.action_history()[1].params["text"], "*****");
+        assert_eq!(computer.action_history()[1].params["text"], "hunter2");
+    }
+    
+    /// Compressor that replaces every screenshot with a fixed short payload
+    struct FixedCompressor;
+    
+    impl ScreenshotCompressor for FixedCompressor {
+        fn compress(&self, _base64_png: &str, _config: &CompressionConfig) -> Result<String, CuaError> {
+            Ok("anBn".to_string())
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_screenshot_compressor_tracks_ratio() {
+        let server = MockApi::start().await;
+        server.push_response(200, response_body(vec![computer_call("call_1", json!({ "type": "screenshot" }))]));
+        server.push_response(200, response_body(vec![assistant_message("Done")]));
+        
+        let agent = Agent::new(
+            server.client(),
+            Box::new(MockComputer::new("linux", 1920, 1080)),
+            Vec::new(),
+            None,
+        )
+        .with_screenshot_compressor(Box::new(FixedCompressor));
+        agent.run("Look at the screen").await.unwrap();
+        
+        let output = &server.requests()[1].body["input"][2]["output"];
+        assert_eq!(output["image_url"], "data:image/jpeg;base64,anBn");
+        let records = agent.turn_records();
+        assert_eq!(records[0].compression_ratio(), Some(4.0 / 20.0));
+        assert_eq!(records[1].compression_ratio(), None);
+    }
+    
+    #[tokio::test]
+    async fn test_jpeg_screenshot_format() {
+        let server = MockApi::start().await;
+        server.push_response(200, response_body(vec![computer_call("call_1", json!({ "type": "screenshot" }))]));
+        server.push_response(200, response_body(vec![assistant_message("Done")]));
+        let computer = MockComputer::new("linux", 64, 48);
+        let screen = image::RgbaImage::from_pixel(64, 48, image::Rgba([30, 60, 90, 255]));
+        let png = vision::encode_png(&screen.into()).unwrap();
+        computer.set_screenshot(&png);
+        
+        let agent = Agent::new(server.client(), computer, Vec::new(), None)
+            .with_screenshot_format(ScreenshotFormat::Jpeg(60));
+        agent.run("Look at the screen").await.unwrap();
+        
+        let image_url = server.requests()[1].body["input"][2]["output"]["image_url"].as_str().unwrap().to_string();
+        let base64 = image_url.strip_prefix("data:image/jpeg;base64,").unwrap();
+        assert!(vision::is_jpeg(base64));
+        // The PNG capture is kept for screenshot diffs
+        assert_eq!(*agent.last_screenshot.lock().unwrap(), Some(png));
+    }
+    
+    #[cfg(feature = "webp")]
+    #[tokio::test]
+    async fn test_webp_screenshot_format() {
+        let server = MockApi::start().await;
+        let computer = MockComputer::new("linux", 64, 48);
+        let screen = image::RgbaImage::from_pixel(64, 48, image::Rgba([30, 60, 90, 255]));
+        computer.set_screenshot(&vision::encode_png(&screen.into()).unwrap());
+        let agent = Agent::new(server.client(), computer, Vec::new(), None)
+            .with_screenshot_format(ScreenshotFormat::WebP(80));
+        
+        let items = agent.handle_item(&computer_call("call_1", json!({ "type": "screenshot" }))).await.unwrap();
+        let image_url = items[0]["output"]["image_url"].as_str().unwrap();
+        let base64 = image_url.strip_prefix("data:image/webp;base64,").unwrap();
+        let bytes = base64::Engine::decode(&base64::engine::general_purpose::STANDARD, base64).unwrap();
+        assert_eq!((&bytes[..4], &bytes[8..12]), (&b"RIFF"[..], &b"WEBP"[..]));
+    }
+    
+    #[tokio::test]
+    async fn test_last_screenshot_by_turn() {
+        let server = MockApi::start().await;
+        server.push_response(200, response_body(vec![computer_call("call_1", json!({ "type": "screenshot" }))]));
+        server.push_response(200, response_body(vec![assistant_message("First")]));
+        server.push_response(200, response_body(vec![computer_call("call_2", json!({ "type": "screenshot" }))]));
+        server.push_response(200, response_body(vec![assistant_message("Second")]));
+        let computer = MockComputer::new("linux", 8, 8);
+        let agent = Agent::new(server.client(), computer.clone(), Vec::new(), None);
+        assert_eq!(agent.get_last_screenshot(), None);
+        
+        let first = vision::encode_png(&image::RgbaImage::from_pixel(8, 8, image::Rgba([255, 0, 0, 255])).into()).unwrap();
+        let second = vision::encode_png(&image::RgbaImage::from_pixel(8, 8, image::Rgba([0, 0, 255, 255])).into()).unwrap();
+        computer.set_screenshot(&first);
+        agent.run("Look").await.unwrap();
+        computer.set_screenshot(&second);
+        agent.run("Look again").await.unwrap();
+        
+        assert_eq!(agent.get_last_screenshot(), Some(second.clone()));
+        assert_eq!(agent.turn_records()[0].screenshot_hash, Some(screenshot_hash(&first)));
+        assert_eq!(agent.get_screenshot_at_turn(1), Some(first));
+        assert_eq!(agent.get_screenshot_at_turn(2), None);
+        assert_eq!(agent.get_screenshot_at_turn(3), Some(second));
+        assert_eq!(agent.get_screenshot_at_turn(0), None);
+    }
+    
+    #[tokio::test]
+    async fn test_screenshot_diff_threshold_reuses_unchanged_screenshot() {
+        let server = MockApi::start().await;
+        server.push_response(200, response_body(vec![
+            computer_call("call_1", json!({ "type": "click", "x": 1, "y": 1, "button": "left" })),
+            computer_call("call_2", json!({ "type": "click", "x": 2, "y": 2, "button": "left" })),
+            computer_call("call_3", json!({ "type": "click", "x": 3, "y": 3, "button": "left" })),
+        ]));
+        server.push_response(200, response_body(vec![assistant_message("Done")]));
+        let computer = MockComputer::new("linux", 8, 8);
+        computer.set_screenshot(&vision::encode_png(&image::RgbaImage::from_pixel(8, 8, image::Rgba([0, 128, 0, 255])).into()).unwrap());
+        let agent = Agent::new(server.client(), computer, Vec::new(), None).with_screenshot_diff_threshold(0.01).unwrap();
+        
+        agent.run("Click three times").await.unwrap();
+        
+        let input = server.requests()[1].body["input"].as_array().unwrap().clone();
+        let urls: Vec<&str> = input.iter()
+            .filter(|item| item["type"] == "computer_call_output")
+            .map(|item| item["output"]["image_url"].as_str().unwrap())
+            .collect();
+        assert_eq!(urls.len(), 3);
+        assert_eq!(urls[1], urls[0]);
+        assert_eq!(urls[2], urls[0]);
+        assert_eq!(agent.turn_records()[0].skipped_uploads, 2);
+    }
+    
+    #[test]
+    fn test_screenshot_diff_threshold_rejects_non_fractions() {
+        let agent = || Agent::new(OpenAIClient::new(String::new(), None, None), MockComputer::new("linux", 1024, 768), Vec::new(), None);
+        assert!(agent().with_screenshot_diff_threshold(f64::NAN).is_err());
+        assert!(agent().with_screenshot_diff_threshold(-0.1).is_err());
+        assert!(agent().with_screenshot_diff_threshold(1.5).is_err());
+        assert!(agent().with_screenshot_diff_threshold(1.0).is_ok());
+    }
+    
+    #[tokio::test]
+    async fn test_introspect_tracks_steps() {
+        let server = MockApi::start().await;
+        server.push_response(200, response_body(vec![computer_call("call_1", json!({ "type": "click", "x": 10, "y": 20, "button": "left" }))]));
+        server.push_response(200, response_body(vec![assistant_message("Done")]));
+        let agent = Agent::new(server.client(), MockComputer::new("linux", 1024, 768), Vec::new(), None);
+        let initial = agent.introspect();
+        assert_eq!(initial.turn_count, 0);
+        assert_eq!(initial.last_action_type, None);
+        
+        let items = vec![json!({ "role": "user", "content": "Click" })];
+        let first = agent.step(items.clone()).await.unwrap();
+        let state = agent.introspect();
+        assert_eq!(state.turn_count, 1);
+        assert_eq!(state.last_action_type.as_deref(), Some("click"));
+        assert!(state.last_screenshot_hash.is_some());
+        assert_eq!(state.current_items_count, items.len() + first.new_items.len());
+        
+        agent.step([items, first.new_items].concat()).await.unwrap();
+        assert_eq!(agent.introspect().turn_count, 2);
+    }
+    
+    #[tokio::test]
+    async fn test_event_log_records_session_in_order() {
+        let server = MockApi::start().await;
+        server.push_response(200, response_body(vec![computer_call("call_1", json!({ "type": "click", "x": 10, "y": 20, "button": "left" }))]));
+        server.push_response(200, response_body(vec![json!({
+            "type": "function_call",
+            "call_id": "call_2",
+            "name": "get_clipboard",
+            "arguments": "{}",
+        })]));
+        server.push_response(200, response_body(vec![computer_call("call_3", json!({ "type": "double_click", "x": 10, "y": 20 }))]));
+        server.push_response(200, response_body(vec![assistant_message("Done")]));
+        let path = std::env::temp_dir().join(format!("cua_events_{}.ndjson", uuid::Uuid::new_v4()));
+        let computer = MockComputer::new("linux", 1024, 768);
+        computer.fail_action("double_click");
+        
+        let agent = Agent::new(server.client(), computer, Vec::new(), None)
+            .with_error_recovery_strategy(ErrorRecoveryStrategy::AskModel)
+            .with_event_log(&path)
+            .unwrap();
+        agent.run("Click the button").await.unwrap();
+        // Dropping the agent waits for the log to be written
+        drop(agent);
+        
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let events: Vec<Value> = contents.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        let types: Vec<&str> = events.iter().map(|event| event["type"].as_str().unwrap()).collect();
+        assert_eq!(types, vec![
+            "api_request", "api_response", "action_start", "action_complete", "screenshot",
+            "api_request", "api_response", "function_call",
+            "api_request", "api_response", "action_start", "action_failed", "screenshot",
+            "api_request", "api_response",
+        ]);
+        assert!(events.windows(2).all(|pair| pair[0]["ts"].as_u64() <= pair[1]["ts"].as_u64()));
+        assert_eq!(events[2]["data"]["params"]["x"], 10);
+        assert_eq!(events[7]["data"]["name"], "get_clipboard");
+        assert_eq!(events[11]["data"]["error"]["type"], "ActionError");
+    }
+    
+    #[tokio::test]
+    async fn test_summarize_session_cached() {
+        let server = MockApi::start().await;
+        server.push_response(200, response_body(vec![computer_call("call_1", json!({ "type": "type", "text": "alice@example.com" }))]));
+        server.push_response(200, response_body(vec![assistant_message("Done")]));
+        let mut summary_body = response_body(vec![assistant_message("Typed the recipient's address.")]);
+        summary_body["usage"] = json!({ "input_tokens": 1_000_000, "output_tokens": 0, "total_tokens": 1_000_000 });
+        server.push_response(200, summary_body);
+        
+        let agent = Agent::new(server.client(), MockComputer::new("linux", 1024, 768), Vec::new(), None)
+            .with_summary_model("gpt-4o-mini")
+            .with_input_sanitizer(PiiSanitizer::boxed());
+        agent.run("Address the email to Alice").await.unwrap();
+        let summary = agent.summarize_session().await.unwrap();
+        
+        assert_eq!(summary, "Typed the recipient's address.");
+        assert_eq!(agent.summarize_session().await.unwrap(), summary);
+        // Priced at the summary model's rate
+        assert!((agent.usage_stats().estimated_cost - 0.15).abs() < 1e-9);
+        let requests = server.requests();
+        assert_eq!(requests.len(), 3);
+        assert_eq!(requests[2].body["model"], "gpt-4o-mini");
+        let prompt = requests[2].body["input"][0]["content"].as_str().unwrap();
+        assert!(prompt.starts_with(SUMMARY_PROMPT));
+        assert!(prompt.contains("user: Address the email to Alice"));
+        // Typed text is sanitized like in the logs
+        assert!(prompt.contains("Action: {\"text\":\"<EMAIL>\",\"type\":\"type\"}"));
+        assert!(!requests[2].body.to_string().contains("alice@example.com"));
+    }
+    
+    #[tokio::test]
+    async fn test_model_parameters_only_on_task_requests() {
+        let server = MockApi::start().await;
+        server.push_response(200, response_body(vec![assistant_message("Done")]));
+        server.push_response(200, response_body(vec![assistant_message("Nothing was done.")]));
+        let parameters = ModelParameters { temperature: Some(0.2), top_p: None, max_output_tokens: Some(256) };
+        let agent = Agent::new(server.client(), MockComputer::new("linux", 1024, 768), Vec::new(), None)
+            .with_model_parameters(parameters);
+        
+        agent.run("Say hello").await.unwrap();
+        agent.summarize_session().await.unwrap();
+        
+        let requests = server.requests();
+        assert_eq!(requests[0].body["temperature"], json!(0.2f32));
+        assert_eq!(requests[0].body["max_output_tokens"], 256);
+        assert!(requests[1].body.get("temperature").is_none());
+        assert!(requests[1].body.get("max_output_tokens").is_none());
+    }
+    
+    #[tokio::test]
+    async fn test_replay_from_log_matches_recorded_actions() {
+        let server = MockApi::start().await;
+        server.push_response(200, response_body(vec![computer_call("call_1", json!({ "type": "click", "x": 10, "y": 20, "button": "left" }))]));
+        server.push_response(200, response_body(vec![computer_call("call_2", json!({ "type": "type", "text": "hello" }))]));
+        server.push_response(200, response_body(vec![computer_call("call_3", json!({ "type": "keypress", "keys": ["ENTER"] }))]));
+        server.push_response(200, response_body(vec![assistant_message("Done")]));
+        let path = std::env::temp_dir().join(format!("cua_replay_{}.ndjson", uuid::Uuid::new_v4()));
+        // The model sees half-size screenshots, so the log must hold screen coordinates
+        let recorded = MockComputer::new("linux", 2048, 1536);
+        let screen = image::RgbaImage::from_pixel(2048, 1536, image::Rgba([30, 60, 90, 255]));
+        recorded.set_screenshot(&vision::encode_png(&screen.into()).unwrap());
+        Agent::new(server.client(), recorded.clone(), Vec::new(), None)
+            .with_max_screenshot_size(1024, 768)
+            .with_event_log(&path)
+            .unwrap()
+            .run("Say hello")
+            .await
+            .unwrap();
+        
+        let replayed = MockComputer::new("linux", 2048, 1536);
+        DynAgent::replay_from_log_at_speed(&path, Box::new(replayed.clone()), f64::INFINITY).await.unwrap();
+        std::fs::remove_file(&path).unwrap();
+        
+        let actions = |computer: &MockComputer| -> Vec<(String, Value)> {
+            computer.action_history().into_iter().map(|record| (record.action_type, record.params)).collect()
+        };
+        assert_eq!(actions(&replayed).len(), 3);
+        assert_eq!(actions(&replayed), actions(&recorded));
+        assert_eq!(actions(&replayed)[0].1, json!({ "x": 20, "y": 40, "button": "left" }));
+        assert_eq!(server.requests().len(), 4);
+    }
+    
+    #[tokio::test]
+    async fn test_replay_from_log_refuses_sanitized_text() {
+        let server = MockApi::start().await;
+        server.push_response(200, response_body(vec![computer_call("call_1", json!({ "type": "click", "x": 10, "y": 20, "button": "left" }))]));
+        server.push_response(200, response_body(vec![computer_call("call_2", json!({ "type": "type", "text": "alice@example.com" }))]));
+        server.push_response(200, response_body(vec![assistant_message("Done")]));
+        let path = std::env::temp_dir().join(format!("cua_replay_{}.ndjson", uuid::Uuid::new_v4()));
+        Agent::new(server.client(), MockComputer::new("linux", 1024, 768), Vec::new(), None)
+            .with_input_sanitizer(PiiSanitizer::boxed())
+            .with_event_log(&path)
+            .unwrap()
+            .run("Enter the email")
+            .await
+            .unwrap();
+        
+        let replayed = MockComputer::new("linux", 1024, 768);
+        let result = DynAgent::replay_from_log_at_speed(&path, Box::new(replayed.clone()), f64::INFINITY).await;
+        std::fs::remove_file(&path).unwrap();
+        
+        assert!(result.unwrap_err().to_string().contains("sanitized"));
+        assert!(replayed.action_history().is_empty());
+    }
+    
+    #[tokio::test]
+    async fn test_image_annotation_injected_and_cached() {
+        let server = MockApi::start().await;
+        server.push_response(200, response_body(vec![computer_call("call_1", json!({ "type": "screenshot" }))]));
+        let mut annotation = response_body(vec![assistant_message("A login form with two fields")]);
+        annotation["usage"] = json!({ "input_tokens": 1_000_000, "output_tokens": 0, "total_tokens": 1_000_000 });
+        server.push_response(200, annotation);
+        server.push_response(200, response_body(vec![computer_call("call_2", json!({ "type": "screenshot" }))]));
+        server.push_response(200, response_body(vec![assistant_message("Done")]));
+        
+        let agent = Agent::new(server.client(), MockComputer::new("linux", 1920, 1080), Vec::new(), None)
+            .with_image_annotation_model("gpt-4o-mini");
+        agent.run("What is on screen?").await.unwrap();
+        // Priced at the annotation model's rate, not the agent's
+        assert!((agent.usage_stats().estimated_cost - 0.15).abs() < 1e-9);
+        
+        let requests = server.requests();
+        assert_eq!(requests.len(), 4);
+        assert_eq!(requests[1].body["model"], "gpt-4o-mini");
+        assert_eq!(requests[1].body["input"][0]["content"][0]["text"], ANNOTATION_PROMPT);
+        let input = requests[2].body["input"].as_array().unwrap();
+        assert_eq!(input[2]["type"], "computer_call_output");
+        assert_eq!(input[3]["role"], "user");
+        assert_eq!(input[3]["content"], "Screen description: A login form with two fields");
+        // The identical second screenshot reuses the cached description
+        assert_eq!(requests[3].body["model"], requests[0].body["model"]);
+        assert_eq!(requests[3].body["input"][6]["content"], "Screen description: A login form with two fields");
+    }
+    
+    #[tokio::test]
+    async fn test_last_response_metadata() {
+        let server = MockApi::start().await;
+        let mut body = response_body(vec![assistant_message("Done")]);
+        body["id"] = json!("resp_abc");
+        body["model"] = json!("computer-use-preview-2025-03-11");
+        server.push_response(200, body);
+        
+        let agent = Agent::new(server.client(), Box::new(MockComputer::new("linux", 1920, 1080)), Vec::new(), None);
+        assert_eq!(agent.last_response_id(), None);
+        agent.run("Hello").await.unwrap();
+        
+        assert_eq!(agent.last_response_id().as_deref(), Some("resp_abc"));
+        assert_eq!(agent.last_model_version().as_deref(), Some("computer-use-preview-2025-03-11"));
+    }
+    
+    #[tokio::test]
+    async fn test_latency_compensation_shortens_settle_delay() {
+        let computer = MockComputer::new("linux", 1920, 1080);
+        computer.set_latency(Duration::from_millis(50));
+        let agent = Agent::new(OpenAIClient::new("key".to_string(), None, None), Box::new(computer), Vec::new(), None)
+            .with_settle_delay(Duration::from_millis(80))
+            .with_latency_compensation(true);
+        
+        let delay = agent.effective_settle_delay().await.unwrap();
+        
+        assert!(delay <= Duration::from_millis(30));
+    }
+    
+    #[tokio::test]
+    async fn test_max_screenshot_size_scales_coordinates() {
+        let screen = image::RgbaImage::from_pixel(3840, 2160, image::Rgba([30, 60, 90, 255]));
+        let computer = MockComputer::new("linux", 3840, 2160);
+        computer.set_screenshot(&vision::encode_png(&screen.into()).unwrap());
+        
+        let server = MockApi::start().await;
+        server.push_response(200, response_body(vec![computer_call(
+            "call_1",
+            json!({ "type": "click", "x": 100, "y": 50, "button": "left" }),
+        )]));
+        server.push_response(200, response_body(vec![assistant_message("Done")]));
+        
+        let agent = Agent::new(server.client(), Box::new(computer.clone()), Vec::new(), None)
+            .with_max_screenshot_size(1920, 1080);
+        agent.run("Click the icon").await.unwrap();
+        
+        let requests = server.requests();
+        assert_eq!(requests[0].body["tools"][0]["display_width"], 1920);
+        let image_url = requests[1].body["input"][2]["output"]["image_url"].as_str().unwrap();
+        let uploaded = vision::decode_screenshot(image_url.trim_start_matches("data:image/png;base64,")).unwrap();
+        assert!(uploaded.width() <= 1920 && uploaded.height() <= 1080);
+        
+        assert_eq!(computer.action_history()[0].params, json!({ "x": 200, "y": 100, "button": "left" }));
+    }
+    
+    #[tokio::test]
+    async fn test_pipeline_resize_scales_coordinates() {
+        let screen = image::RgbaImage::from_pixel(1920, 1080, image::Rgba([30, 60, 90, 255]));
+        let computer = MockComputer::new("linux", 1920, 1080);
+        computer.set_screenshot(&vision::encode_png(&screen.into()).unwrap());
+        
+        let server = MockApi::start().await;
+        server.push_response(200, response_body(vec![computer_call("call_1", json!({ "type": "screenshot" }))]));
+        server.push_response(200, response_body(vec![computer_call(
+            "call_2",
+            json!({ "type": "click", "x": 100, "y": 50, "button": "left" }),
+        )]));
+        server.push_response(200, response_body(vec![assistant_message("Done")]));
+        
+        let agent = Agent::new(server.client(), computer.clone(), Vec::new(), None)
+            .with_screenshot_compression_pipeline(vec![Box::new(compression::ResizeProcessor { max_width: 960, max_height: 960 })]);
+        agent.run("Click the icon").await.unwrap();
+        
+        let click = computer.action_history().into_iter().find(|record| record.action_type == "click").unwrap();
+        assert_eq!(click.params, json!({ "x": 200, "y": 100, "button": "left" }));
+    }
+    
+    #[tokio::test]
+    async fn test_max_screenshot_size_scales_tool_coordinates() {
+        let computer = MockComputer::new("linux", 3840, 2160);
+        computer.set_region_text((200, 100, 400, 200), "Total due");
+        computer.set_text_cursor_position((400, 300));
+        let server = MockApi::start().await;
+        let agent = Agent::new(server.client(), computer, Vec::new(), None).with_max_screenshot_size(1920, 1080);
+        let function_call = |name: &str, arguments: Value| json!({
+            "type": "function_call",
+            "call_id": "call_1",
+            "name": name,
+            "arguments": arguments.to_string(),
+        });
+        
+        let read = function_call("read_screen_text", json!({ "x": 100, "y": 50, "width": 200, "height": 100 }));
+        let items = agent.handle_item(&read).await.unwrap();
+        assert_eq!(items[0]["output"], "\"Total due\"");
+        
+        let items = agent.handle_item(&function_call("get_text_cursor_position", json!({}))).await.unwrap();
+        assert_eq!(serde_json::from_str::<Value>(items[0]["output"].as_str().unwrap()).unwrap(), json!({ "x": 200, "y": 150 }));
+    }
+    
+    #[tokio::test]
+    async fn test_page_source_options_limit_the_tool() {
+        let computer = MockComputer::new("browser", 1280, 800);
+        computer.set_page_source("<body><script>track()</script></body>");
+        let server = MockApi::start().await;
+        let agent = Agent::new(server.client(), computer, Vec::new(), None).with_page_source_options(14, false);
+        let get_source = json!({
+            "type": "function_call",
+            "call_id": "call_1",
+            "name": "get_page_source",
+            "arguments": json!({ "max_bytes": 1000 }).to_string(),
+        });
+        
+        let items = agent.handle_item(&get_source).await.unwrap();
+        assert_eq!(items[0]["output"], json!("<body><script>\n<!-- truncated at 14 bytes -->").to_string());
+    }
+    
+    #[tokio::test]
+    async fn test_thought_prefix_sent_but_stripped() {
+        let prefix = "Think step by step before choosing your next action.";
+        let server = MockApi::start().await;
+        server.push_response(200, response_body(vec![assistant_message("Done")]));
+        
+        let agent = Agent::new(
+            server.client(),
+            Box::new(MockComputer::new("linux", 1920, 1080)),
+            Vec::new(),
+            None,
+        )
+        .with_thought_prefix(prefix);
+        let items = agent.run("Open the settings").await.unwrap();
+        
+        let input = server.requests()[0].body["input"].clone();
+        assert_eq!(input[1], json!({ "role": "user", "content": prefix }));
+        assert!(!items.iter().any(|item| item["content"] == prefix));
+        assert_eq!(items.len(), 2);
+    }
+    
+    #[tokio::test]
+    async fn test_system_prompt_includes_shortcuts() {
+        let server = MockApi::start().await;
+        server.push_response(200, response_body(vec![assistant_message("Done")]));
+        
+        let agent = Agent::new(server.client(), MockComputer::new("mac", 1440, 900), Vec::new(), None)
+            .with_system_prompt("You operate a Mac.");
+        let items = agent.run("Undo the last edit").await.unwrap();
+        
+        let input = server.requests()[0].body["input"].clone();
+        assert_eq!(input[0]["role"], "system");
+        let prompt = input[0]["content"].as_str().unwrap();
+        assert!(prompt.starts_with("You operate a Mac.\n\nKeyboard shortcuts"));
+        assert!(prompt.contains("\n- undo: super+z"));
+        assert!(!items.iter().any(|item| item["role"] == "system"));
+    }
+    
+    #[tokio::test]
+    async fn test_run_script_records_step_outcomes() {
+        let server = MockApi::start().await;
+        server.push_response(200, response_body(vec![assistant_message("Settings opened")]));
+        // No second response queued, so the mock server fails step 2
+        
+        let agent = Agent::new(
+            server.client(),
+            Box::new(MockComputer::new("linux", 1920, 1080)),
+            Vec::new(),
+            None,
+        );
+        let mut script = AgentScript::new(&[
+            ("Open the settings app", Some(Duration::from_secs(5))),
+            ("Enable dark mode", None),
+        ]);
+        script.steps[1].continue_on_error = true;
+        let results = agent.run_script(&script).await.unwrap();
+        
+        assert_eq!(results.len(), 2);
+        assert!(results[0].succeeded());
+        let step_items = results[0].outcome.as_ref().unwrap();
+        assert_eq!(step_items[0]["content"], "Open the settings app");
+        assert_eq!(step_items[1]["content"][0]["text"], "Settings opened");
+        assert!(matches!(results[1].outcome, Err(CuaError::ApiError(_))));
+        
+        // Step 2 was sent with the conversation from step 1
+        let input = server.requests()[1].body["input"].as_array().unwrap().clone();
+        assert_eq!(input.len(), 3);
+        assert_eq!(input[2]["content"], "Enable dark mode");
+    }
+    
+    #[tokio::test]
+    async fn test_fallback_computer_takes_screenshot() {
+        let server = MockApi::start().await;
+        server.push_response(200, response_body(vec![computer_call("call_1", json!({ "type": "screenshot" }))]));
+        server.push_response(200, response_body(vec![assistant_message("Done")]));
+        
+        let primary = MockComputer::new("linux", 1920, 1080);
+        primary.fail_action("screenshot");
+        let fallback = MockComputer::new("linux", 1920, 1080);
+        fallback.set_screenshot("ZmFsbGJhY2s=");
+        
+        let agent = DynAgent::new(server.client(), Box::new(primary), Vec::new(), None)
+            .with_fallback_computer(Box::new(fallback));
+        assert!(!agent.using_fallback_computer());
+        agent.run("Look at the screen").await.unwrap();
+        
+        let output = &server.requests()[1].body["input"][2]["output"];
+        assert_eq!(output["image_url"], "data:image/png;base64,ZmFsbGJhY2s=");
+        assert!(agent.using_fallback_computer());
+    }
+    
+    #[tokio::test]
+    async fn test_fallback_computer_under_simulated_failures() {
+        let server = MockApi::start().await;
+        server.push_response(200, response_body(vec![computer_call(
+            "call_1",
+            json!({ "type": "click", "x": 10, "y": 20, "button": "left" }),
+        )]));
+        server.push_response(200, response_body(vec![assistant_message("Done")]));
+        
+        let primary = MockComputer::new("linux", 1920, 1080);
+        primary.simulate_latency(1, 5);
+        primary.simulate_failure_rate(1.0);
+        let fallback = MockComputer::new("linux", 1920, 1080);
+        fallback.simulate_latency(1, 5);
+        
+        let agent = DynAgent::new(server.client(), Box::new(primary.clone()), Vec::new(), None)
+            .with_fallback_computer(Box::new(fallback.clone()));
+        agent.run("Click the button").await.unwrap();
+        
+        assert!(agent.using_fallback_computer());
+        assert!(primary.action_history().is_empty());
+        assert_eq!(fallback.action_history()[0].action_type, "click");
+    }
+    
+    #[tokio::test]
+    async fn test_memory_alert_injects_warning() {
+        let server = MockApi::start().await;
+        server.push_response(200, response_body(vec![computer_call("call_1", json!({ "type": "click", "x": 10, "y": 20, "button": "left" }))]));
+        server.push_response(200, response_body(vec![assistant_message("Done")]));
+        let computer = MockComputer::new("linux", 1920, 1080);
+        let agent = Agent::new(server.client(), computer.clone(), Vec::new(), None)
+            .with_memory_alert_threshold("firefox", 1024 * 1024 * 1024);
+        computer.set_process_memory(vec![
+            ("firefox".to_string(), 900 * 1024 * 1024),
+            ("firefox".to_string(), 300 * 1024 * 1024),
+            ("Xorg".to_string(), 200 * 1024 * 1024),
+        ]);
+        
+        let items = agent.run("Browse").await.unwrap();
+        
+        let warning = "Warning: firefox is using 1200 MB of memory, above the 1024 MB alert threshold. It may be leaking memory.";
+        assert_eq!(items.iter().filter(|item| item["content"] == warning).count(), 1);
+        let requests = server.requests();
+        assert!(requests[0].body["input"].as_array().unwrap().iter().any(|item| item["content"] == warning));
+        // Reported once while the process stays above the threshold
+        let second_input = requests[1].body["input"].as_array().unwrap();
+        assert_eq!(second_input.iter().filter(|item| item["content"] == warning).count(), 1);
+    }
+    
+    #[tokio::test]
+    async fn test_error_recovery_strategies() {
+        let server = MockApi::start().await;
+        let computer = MockComputer::new("linux", 1920, 1080);
+        let click = computer_call("call_1", json!({ "type": "click", "x": 10, "y": 20, "button": "left" }));
+        let move_cursor = computer_call("call_1", json!({ "type": "move", "x": 10, "y": 20 }));
+        
+        // Two retries make three attempts, then the error is returned
+        let agent = Agent::new(server.client(), computer.clone(), Vec::new(), None)
+            .with_error_recovery_strategy(ErrorRecoveryStrategy::RetryN(2));
+        computer.fail_action_times("move", 3);
+        assert!(matches!(agent.handle_item(&move_cursor).await, Err(CuaError::ActionError(_))));
+        assert!(computer.action_history().is_empty());
+        computer.fail_action_times("move", 2);
+        agent.handle_item(&move_cursor).await.unwrap();
+        assert_eq!(computer.action_history().len(), 1);
+        
+        // A click is not idempotent, so it is never retried
+        computer.fail_action_times("click", 1);
+        assert!(matches!(agent.handle_item(&click).await, Err(CuaError::ActionError(_))));
+        agent.handle_item(&click).await.unwrap();
+        assert_eq!(computer.action_history().len(), 2);
+        
+        let agent = Agent::new(server.client(), computer.clone(), Vec::new(), None)
+            .with_error_recovery_strategy(ErrorRecoveryStrategy::TakeScreenshotAndContinue);
+        computer.fail_action_times("click", 1);
+        let items = agent.handle_item(&click).await.unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0]["output"]["type"], "input_image");
+        assert_eq!(
+            items[1]["content"],
+            "An error occurred: Action error: Mock click failure. The screenshot above is the current screen state."
+        );
+    }
+    
+    #[tokio::test]
+    async fn test_vision_grounding_confirms_target_first() {
+        let server = MockApi::start().await;
+        server.push_response(200, response_body(vec![assistant_message("Yes, that is the Save button.")]));
+        server.push_response(200, response_body(vec![assistant_message("No, that is the Cancel button.")]));
+        let computer = MockComputer::new("linux", 1920, 1080);
+        let screen = image::RgbaImage::from_pixel(200, 100, image::Rgba([255, 255, 255, 255]));
+        computer.set_screenshot(&vision::encode_png(&screen.into()).unwrap());
+        let agent = Agent::new(server.client(), computer.clone(), Vec::new(), None).with_vision_grounding(true);
+        let click = computer_call("call_1", json!({ "type": "click", "x": 50, "y": 40, "button": "left" }));
+        
+        // The marked screenshot is sent before the confirmed click
+        agent.handle_item(&click).await.unwrap();
+        let requests = server.requests();
+        let image_url = requests[0].body["input"][0]["content"][1]["image_url"].as_str().unwrap();
+        let marked = vision::decode_screenshot(image_url.trim_start_matches("data:image/png;base64,")).unwrap().to_rgba8();
+        assert_eq!(*marked.get_pixel(50 + 12, 40), image::Rgba([255, 0, 0, 255]));
+        assert_eq!(computer.action_history().len(), 1);
+        
+        // A rejected target is not clicked
+        let items = agent.handle_item(&click).await.unwrap();
+        assert_eq!(computer.action_history().len(), 1);
+        assert_eq!(items[0]["type"], "computer_call_output");
+        assert!(items[1]["content"].as_str().unwrap().contains("the marked target was rejected: No, that is the Cancel button."));
+    }
+    
+    #[tokio::test]
+    async fn test_run_batch_tasks_limits_concurrency() {
+        let server = MockApi::start().await;
+        for n in 1..=4 {
+            // Each task clicks once and finishes in a single response
+            let mut body = response_body(vec![
+                computer_call(&format!("call_{}", n), json!({ "type": "click", "x": 10, "y": 20, "button": "left" })),
+                assistant_message("Done"),
+            ]);
+            body["usage"] = json!({ "input_tokens": 100, "output_tokens": 10, "total_tokens": 110 });
+            server.push_response(200, body);
+        }
+        struct ActionCounter(Arc<AtomicUsize>);
+        impl AgentObserver for ActionCounter {
+            fn on_action(&self, _action_type: &str, _params: &Value) {
+                self.0.fetch_add(1, Ordering::SeqCst);
             }
-            _ => {}
         }
+        let observed = Arc::new(AtomicUsize::new(0));
+        let running = Arc::new(AtomicUsize::new(0));
+        let max_running = Arc::new(AtomicUsize::new(0));
+        let (hook_running, hook_max) = (running.clone(), max_running.clone());
+        let agent = Agent::new(server.client(), MockComputer::new("linux", 1920, 1080), Vec::new(), None)
+            .with_observer(Box::new(ActionCounter(observed.clone())))
+            .with_before_action_hook(Box::new(move |_, _| {
+                let (running, max_running) = (hook_running.clone(), hook_max.clone());
+                Box::pin(async move {
+                    let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                    max_running.fetch_max(now, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                    running.fetch_sub(1, Ordering::SeqCst);
+                    Ok(())
+                })
+            }));
         
-        Ok(new_items)
+        let tasks: Vec<String> = (1..=4).map(|n| format!("Task {}", n)).collect();
+        let computers: Arc<Mutex<Vec<MockComputer>>> = Arc::default();
+        let created = computers.clone();
+        let factory = move || {
+            let computer = MockComputer::new("linux", 1920, 1080);
+            created.lock().unwrap().push(computer.clone());
+            Ok(computer)
+        };
+        let results = agent.run_batch_tasks(tasks.clone(), 2, None, Some(&factory)).await.unwrap();
+        
+        assert_eq!(max_running.load(Ordering::SeqCst), 2);
+        assert_eq!(results.iter().map(|r| r.input.clone()).collect::<Vec<_>>(), tasks);
+        assert!(results.iter().all(|r| r.error.is_none() && r.duration >= Duration::from_millis(100)));
+        // Each task clicked on a computer of its own
+        let computers = computers.lock().unwrap().clone();
+        assert_eq!(computers.len(), 4);
+        assert!(computers.iter().all(|computer| computer.action_history().len() == 1));
+        // The parent sees the forks' actions and pays for their tokens
+        assert_eq!(observed.load(Ordering::SeqCst), 4);
+        assert_eq!(agent.usage_stats().input_tokens, 400);
+        assert_eq!(agent.usage_stats().output_tokens, 40);
+        assert!(matches!(agent.run_batch_tasks(Vec::new(), 0, None, None).await, Err(CuaError::Other(_))));
+        assert!(matches!(agent.run_batch_tasks(tasks, 2, None, None).await, Err(CuaError::Other(_))));
     }
     
-    /// Run the agent for a single turn
-    pub async fn run_full_turn(&self, input_items: &[Value]) -> Result<Vec<Value>, CuaError> {
-        // Create a copy of input items
-        let mut all_items = input_items.to_vec();
+    #[tokio::test]
+    async fn test_structured_output_mode_parses_final_answer() {
+        let server = MockApi::start().await;
+        server.push_response(200, response_body(vec![assistant_message("```json\n{\"name\": \"ACME\", \"amount\": 12.5}\n```")]));
+        server.push_response(200, response_body(vec![assistant_message("{\"name\": \"ACME\"}")]));
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "name": { "type": "string" },
+                "amount": { "type": "number" },
+            },
+            "required": ["name", "amount"],
+        });
+        let agent = Agent::new(server.client(), MockComputer::new("linux", 1920, 1080), Vec::new(), None)
+            .with_structured_output_mode(schema)
+            .unwrap();
         
-        // Use a set of processed IDs to avoid duplicates
-        let mut processed_ids = std::collections::HashSet::new();
+        agent.run("Read the invoice").await.unwrap();
+        assert_eq!(agent.structured_output(), Some(json!({ "name": "ACME", "amount": 12.5 })));
+        let requests = server.requests();
+        assert_eq!(requests[0].body["input"][0]["role"], "system");
+        assert!(requests[0].body["input"][0]["content"].as_str().unwrap().contains("\"required\":[\"name\",\"amount\"]"));
         
-        // Save original input IDs
-        for item in &all_items {
-            if let Some(id) = item.get("id").and_then(|id| id.as_str()) {
-                processed_ids.insert(id.to_string());
+        // Forks start without an answer
+        assert_eq!(agent.fork().structured_output(), None);
+        
+        // An answer that does not match the schema is not returned
+        agent.run("Read the invoice").await.unwrap();
+        assert_eq!(agent.structured_output(), None);
+        
+        // A failed run clears the previous answer
+        server.push_response(200, response_body(vec![assistant_message("{\"name\": \"ACME\", \"amount\": 1}")]));
+        agent.run("Read the invoice").await.unwrap();
+        assert!(agent.structured_output().is_some());
+        server.push_response(500, json!({ "error": { "message": "down" } }));
+        assert!(agent.run("Read the invoice").await.is_err());
+        assert_eq!(agent.structured_output(), None);
+        
+        // Continuing the conversation, an earlier answer is not this run's answer
+        server.push_response(200, response_body(vec![assistant_message("{\"name\": \"ACME\", \"amount\": 2}")]));
+        let mut items = agent.run("Read the invoice").await.unwrap();
+        assert!(agent.structured_output().is_some());
+        server.push_response(200, response_body(vec![assistant_message("The invoice is no longer open.")]));
+        items.push(json!({ "role": "user", "content": "Read it again" }));
+        agent.run_full_turn(&items).await.unwrap();
+        assert_eq!(agent.structured_output(), None);
+        
+        let invalid = Agent::new(server.client(), MockComputer::new("linux", 1920, 1080), Vec::new(), None)
+            .with_structured_output_mode(json!({ "type": "no-such-type" }));
+        assert!(matches!(invalid, Err(CuaError::Other(_))));
+    }
+    
+    #[tokio::test]
+    async fn test_text_mode_sends_ocr_text() {
+        let server = MockApi::start().await;
+        let computer = MockComputer::new("linux", 1920, 1080);
+        computer.set_ocr_result("c2NyZWVu", vec![
+            OcrWord::new("Invoice", (10, 10, 80, 16), 1),
+            OcrWord::new("1042", (100, 10, 40, 16), 1),
+            OcrWord::new("Total", (10, 40, 50, 16), 2),
+        ]);
+        computer.set_screenshot("c2NyZWVu");
+        assert_eq!(computer.screenshot_as_text().await.unwrap(), "Invoice 1042\nTotal");
+        let agent = Agent::new(server.client(), computer.clone(), Vec::new(), None).with_text_mode(true);
+        let click = computer_call("call_1", json!({ "type": "click", "x": 10, "y": 20, "button": "left" }));
+        
+        let items = agent.handle_item(&click).await.unwrap();
+        assert_eq!(items[0]["output"]["image_url"], "data:image/png;base64,c2NyZWVu");
+        assert_eq!(items[1]["content"], "Screen text:\nInvoice 1042\nTotal");
+        
+        // Without any recognized text only the screenshot is sent
+        computer.set_screenshot("Ymxhbmsgc2NyZWVu");
+        let items = agent.handle_item(&click).await.unwrap();
+        assert_eq!(items[0]["output"]["image_url"], "data:image/png;base64,Ymxhbmsgc2NyZWVu");
+        assert_eq!(items.len(), 1);
+    }
+    
+    #[tokio::test]
+    async fn test_all_screens_mode_sends_every_screen() {
+        let server = MockApi::start().await;
+        let computer = MockComputer::new("linux", 1920, 1080);
+        computer.set_screens(&[(0, 0, 1920, 1080), (1920, 0, 1280, 1024), (-1280, 0, 1280, 1024)]);
+        let screen = image::RgbaImage::from_pixel(64, 48, image::Rgba([30, 60, 90, 255]));
+        computer.set_screenshot(&vision::encode_png(&screen.into()).unwrap());
+        let agent = Agent::new(server.client(), computer, Vec::new(), None)
+            .with_all_screens_mode(true)
+            .with_screenshot_format(ScreenshotFormat::Jpeg(60));
+        
+        let click = computer_call("call_1", json!({ "type": "click", "x": 10, "y": 20, "button": "left" }));
+        let items = agent.handle_item(&click).await.unwrap();
+        assert_eq!(items[0]["type"], "computer_call_output");
+        // Screen 0 is only in the call output
+        let content = items[1]["content"].as_array().unwrap();
+        let images: Vec<_> = content.iter().filter(|part| part["type"] == "input_image").collect();
+        assert_eq!(images.len(), 2);
+        assert!(images.iter().all(|image| image["image_url"].as_str().unwrap().starts_with("data:image/jpeg;base64,")));
+        assert_eq!(content[0]["text"], "Screen 1 at (1920, 0), 1280x1024");
+        assert_eq!(content[2]["text"], "Screen 2 at (-1280, 0), 1280x1024");
+    }
+    
+    #[tokio::test]
+    async fn test_checkpoint_interval_saves_every_other_turn() {
+        let server = MockApi::start().await;
+        for n in 1..=5 {
+            let action = match n {
+                3 => json!({ "type": "type", "text": "hunter2" }),
+                _ => json!({ "type": "click", "x": 10, "y": 20, "button": "left" }),
+            };
+            server.push_response(200, response_body(vec![computer_call(&format!("call_{}", n), action)]));
+        }
+        server.push_response(200, response_body(vec![assistant_message("Done")]));
+        let path = std::env::temp_dir().join(format!("cua_checkpoint_{}.json", uuid::Uuid::new_v4()));
+        
+        // Note the saved checkpoint as each turn's action runs
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let (hook_seen, hook_path) = (seen.clone(), path.clone());
+        let agent = Agent::new(server.client(), MockComputer::new("linux", 1920, 1080), Vec::new(), None)
+            .with_checkpoint_interval(2, path.clone())
+            .with_input_sanitizer(Box::new(|text, _| "*".repeat(text.len())))
+            .with_before_action_hook(Box::new(move |_, _| {
+                hook_seen.lock().unwrap().push(Checkpoint::load(&hook_path).ok());
+                Box::pin(async { Ok(()) })
+            }));
+        
+        agent.run_full_turn(&[json!({ "role": "user", "content": "Click five times" })]).await.unwrap();
+        let seen = seen.lock().unwrap().clone();
+        let turns: Vec<_> = seen.iter().map(|checkpoint| checkpoint.as_ref().map(|c| c.turns)).collect();
+        assert_eq!(turns, vec![None, None, Some(2), Some(2), Some(4)]);
+        
+        let checkpoint = seen[4].clone().unwrap();
+        assert_eq!(checkpoint.model, agent.client.model());
+        assert!(checkpoint.timestamp > 0);
+        assert_eq!(checkpoint.items.last().unwrap()["call_id"], "call_4");
+        // Typed text is sanitized
+        let typed = checkpoint.items.iter().find(|item| item["call_id"] == "call_3" && item["type"] == "computer_call").unwrap();
+        assert_eq!(typed["action"]["text"], "*******");
+        // The finished run deleted its checkpoint
+        assert!(!path.exists());
+        
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            agent.save_state(&path, &[]).unwrap();
+            assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+            let _ = std::fs::remove_file(&path);
+        }
+    }
+    
+    #[tokio::test(start_paused = true)]
+    async fn test_step_delay_between_actions() {
+        let server = MockApi::start().await;
+        let agent = Agent::new(
+            server.client(),
+            Box::new(MockComputer::new("linux", 1920, 1080)),
+            Vec::new(),
+            None,
+        )
+        .with_step_delay(100, 200);
+        
+        let start = tokio::time::Instant::now();
+        for n in 1..=5 {
+            let call = computer_call(&format!("call_{}", n), json!({ "type": "click", "x": 10, "y": 20, "button": "left" }));
+            agent.handle_item(&call).await.unwrap();
+        }
+        
+        // Four pauses between five actions
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(400), "{:?}", elapsed);
+        assert!(elapsed <= Duration::from_millis(800), "{:?}", elapsed);
+        assert_eq!(agent.action_history().len(), 5);
+    }
+    
+    #[tokio::test(start_paused = true)]
+    async fn test_battery_pause_waits_for_charge() {
+        let server = MockApi::start().await;
+        let computer = MockComputer::new("linux", 1920, 1080);
+        let agent = Agent::new(server.client(), computer.clone(), Vec::new(), None)
+            .with_battery_pause_threshold(0.1);
+        
+        // Above the threshold the agent does not pause
+        computer.set_battery_level(0.5);
+        let start = tokio::time::Instant::now();
+        agent.wait_for_battery().await;
+        assert_eq!(start.elapsed(), Duration::ZERO);
+        
+        // Below it, the agent waits until the battery is charged again
+        computer.set_battery_level(0.05);
+        let charger = computer.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_secs(75)).await;
+            charger.set_battery_level(0.12);
+        });
+        let start = tokio::time::Instant::now();
+        agent.wait_for_battery().await;
+        assert_eq!(start.elapsed(), BATTERY_POLL_INTERVAL * 3);
+    }
+    
+    #[tokio::test(start_paused = true)]
+    async fn test_wait_before_request() {
+        let server = MockApi::start().await;
+        let agent = Agent::new(
+            server.client(),
+            Box::new(MockComputer::new("linux", 1920, 1080)),
+            Vec::new(),
+            None,
+        )
+        .with_inter_turn_delay(1500);
+        let waited = |first: bool| {
+            let agent = &agent;
+            async move {
+                let start = tokio::time::Instant::now();
+                agent.wait_before_request(first).await;
+                start.elapsed()
+            }
+        };
+        
+        assert_eq!(waited(true).await, Duration::ZERO);
+        assert_eq!(waited(false).await, Duration::from_millis(1500));
+    }
+    
+    #[tokio::test]
+    async fn test_inter_turn_delay_between_requests() {
+        let server = MockApi::start().await;
+        for n in 1..=2 {
+            server.push_response(200, response_body(vec![computer_call(
+                &format!("call_{}", n),
+                json!({ "type": "click", "x": 10, "y": 20, "button": "left" }),
+            )]));
+        }
+        server.push_response(200, response_body(vec![assistant_message("Done")]));
+        
+        let agent = Agent::new(
+            server.client(),
+            Box::new(MockComputer::new("linux", 1920, 1080)),
+            Vec::new(),
+            None,
+        )
+        .with_inter_turn_delay(100);
+        let start = std::time::Instant::now();
+        agent.run("Click twice").await.unwrap();
+        
+        assert_eq!(server.requests().len(), 3);
+        assert!(start.elapsed() >= Duration::from_millis(200));
+    }
+    
+    #[tokio::test]
+    async fn test_adaptive_delay_uses_retry_after() {
+        let server = MockApi::start().await;
+        server.push_response_with_headers(429, &[("Retry-After", "1")], json!({ "error": "rate limited" }));
+        server.push_response_with_headers(429, &[("Retry-After", "1")], json!({ "error": "rate limited" }));
+        server.push_response(200, response_body(vec![assistant_message("Done")]));
+        let agent = |server: &MockApi| Agent::new(
+            server.client(),
+            Box::new(MockComputer::new("linux", 1920, 1080)),
+            Vec::new(),
+            None,
+        );
+        
+        // Without adaptive delay, a rate-limited request ends the run
+        assert!(matches!(agent(&server).run("Hello").await, Err(CuaError::RateLimited { .. })));
+        
+        // With it, the request is retried after the Retry-After wait
+        let start = std::time::Instant::now();
+        agent(&server).with_adaptive_delay(true).run("Hello again").await.unwrap();
+        assert!(start.elapsed() >= Duration::from_secs(1));
+        assert_eq!(server.requests().len(), 3);
+    }
+    
+    #[tokio::test]
+    async fn test_screenshot_diff_tool() {
+        let server = MockApi::start().await;
+        server.push_response(200, response_body(vec![computer_call(
+            "call_1",
+            json!({ "type": "click", "x": 10, "y": 20, "button": "left" }),
+        )]));
+        server.push_response(200, response_body(vec![json!({
+            "type": "function_call",
+            "id": "fc_2",
+            "call_id": "call_2",
+            "name": "screenshot_diff",
+            "arguments": "{}",
+        })]));
+        server.push_response(200, response_body(vec![assistant_message("Done")]));
+        
+        let mut after = image::RgbaImage::from_pixel(100, 80, image::Rgba([255, 255, 255, 255]));
+        for x in 20..30 {
+            for y in 40..45 {
+                after.put_pixel(x, y, image::Rgba([0, 0, 0, 255]));
             }
         }
+        let before = image::RgbaImage::from_pixel(100, 80, image::Rgba([255, 255, 255, 255]));
+        let computer = MockComputer::new("linux", 100, 80);
+        computer.queue_screenshot(&vision::encode_png(&before.into()).unwrap());
+        computer.queue_screenshot(&vision::encode_png(&after.into()).unwrap());
         
-        // Keep looping until we get a final assistant response
-        loop {
-            // Debug print current state
-            if self.debug {
-                self.debug_print(&all_items);
+        let agent = Agent::new(server.client(), Box::new(computer), Vec::new(), None);
+        agent.run("Did the click change anything?").await.unwrap();
+        
+        let input = server.requests()[2].body["input"].clone();
+        let items = input.as_array().unwrap();
+        let output = items.iter().find(|item| item["type"] == "function_call_output").unwrap();
+        let output: Value = serde_json::from_str(output["output"].as_str().unwrap()).unwrap();
+        assert_eq!(output["changed_region"], json!({ "x": 20, "y": 40, "width": 10, "height": 5 }));
+        let image_message = items.last().unwrap();
+        assert_eq!(image_message["role"], "user");
+        assert!(image_message["content"][1]["image_url"].as_str().unwrap().starts_with("data:image/png;base64,"));
+    }
+    
+    #[tokio::test]
+    async fn test_pointer_actions_outside_capturable_area_rejected() {
+        let server = MockApi::start().await;
+        server.push_response(200, response_body(vec![computer_call(
+            "call_1",
+            json!({ "type": "drag", "path": [{ "x": 10, "y": 10 }, { "x": 500, "y": 10 }] }),
+        )]));
+        let computer = MockComputer::new("linux", 100, 80);
+        let agent = Agent::new(server.client(), Box::new(computer.clone()), Vec::new(), None);
+        
+        let err = agent.run("Drag it off screen").await.unwrap_err();
+        assert!(err.to_string().contains("drag at (500, 10) is outside the capturable area"), "{}", err);
+        assert!(computer.action_history().iter().all(|a| a.action_type != "drag"));
+        
+        // Without a known area, actions are not checked
+        let server = MockApi::start().await;
+        server.push_response(200, response_body(vec![computer_call("call_1", json!({ "type": "move", "x": 500, "y": 10 }))]));
+        server.push_response(200, response_body(vec![assistant_message("Done")]));
+        let computer = MockComputer::new("linux", 100, 80);
+        computer.fail_action("get_screen_recording_area");
+        let agent = Agent::new(server.client(), Box::new(computer.clone()), Vec::new(), None);
+        
+        agent.run("Move off screen").await.unwrap();
+        assert!(computer.action_history().iter().any(|a| a.action_type == "move"));
+    }
+    
+    #[tokio::test]
+    async fn test_capture_element_image_sent_as_input_image() {
+        let server = MockApi::start().await;
+        server.push_response(200, response_body(vec![json!({
+            "type": "function_call",
+            "id": "fc_1",
+            "call_id": "call_1",
+            "name": "capture_element",
+            "arguments": r#"{"x": 10, "y": 10, "width": 20, "height": 10, "label": "button"}"#,
+        })]));
+        server.push_response(200, response_body(vec![assistant_message("Done")]));
+        
+        let screen = image::RgbaImage::from_pixel(100, 80, image::Rgba([0, 128, 255, 255]));
+        let computer = MockComputer::new("linux", 100, 80);
+        computer.queue_screenshot(&vision::encode_png(&screen.into()).unwrap());
+        
+        let agent = Agent::new(server.client(), Box::new(computer), Vec::new(), None);
+        agent.run("What does the button look like?").await.unwrap();
+        
+        let input = server.requests()[1].body["input"].clone();
+        let items = input.as_array().unwrap();
+        let output = items.iter().find(|item| item["type"] == "function_call_output").unwrap();
+        assert!(output["output"].as_str().unwrap().len() < 100);
+        let image_message = items.last().unwrap();
+        assert_eq!(image_message["role"], "user");
+        let image_url = image_message["content"][1]["image_url"].as_str().unwrap();
+        let image = vision::decode_screenshot(image_url.strip_prefix("data:image/png;base64,").unwrap()).unwrap();
+        assert_eq!((image.width(), image.height()), (20, 10));
+        assert!(agent.get_last_screenshot().is_none());
+    }
+    
+    #[tokio::test]
+    async fn test_execute_script_needs_opt_in_and_approval() {
+        let script_call = || json!({
+            "type": "function_call",
+            "id": "fc_1",
+            "call_id": "call_1",
+            "name": "execute_script",
+            "arguments": r#"{"script": "echo hi", "lang": "bash"}"#,
+        });
+        let server = MockApi::start().await;
+        let computer = MockComputer::new("linux", 100, 80);
+        let prompts = Arc::new(Mutex::new(Vec::new()));
+        let approve = Arc::new(AtomicBool::new(false));
+        let (recorded, approved) = (Arc::clone(&prompts), Arc::clone(&approve));
+        let callback: SafetyCheckCallback = Box::new(move |message| {
+            recorded.lock().unwrap().push(message.to_string());
+            approved.load(Ordering::SeqCst)
+        });
+        let agent = Agent::new(server.client(), computer.clone(), Vec::new(), Some(callback));
+        
+        // Off by default: not offered, and refused if called anyway
+        server.push_response(200, response_body(vec![script_call()]));
+        server.push_response(200, response_body(vec![assistant_message("Done")]));
+        agent.run("Run it").await.unwrap();
+        assert!(server.requests()[0].body["tools"].as_array().unwrap().iter().all(|tool| tool["name"] != "execute_script"));
+        assert!(prompts.lock().unwrap().is_empty());
+        
+        let agent = agent.with_script_execution(true);
+        server.push_response(200, response_body(vec![script_call()]));
+        server.push_response(200, response_body(vec![assistant_message("Done")]));
+        agent.run("Run it").await.unwrap();
+        assert!(server.requests()[2].body["tools"].as_array().unwrap().iter().any(|tool| tool["name"] == "execute_script"));
+        let output = server.requests()[3].body["input"].as_array().unwrap().iter()
+            .find(|item| item["type"] == "function_call_output").unwrap()["output"].clone();
+        assert!(output.as_str().unwrap().contains("not approved"));
+        
+        approve.store(true, Ordering::SeqCst);
+        server.push_response(200, response_body(vec![script_call()]));
+        server.push_response(200, response_body(vec![assistant_message("Done")]));
+        agent.run("Run it").await.unwrap();
+        
+        assert_eq!(prompts.lock().unwrap().len(), 2);
+        assert!(prompts.lock().unwrap()[0].contains("echo hi"));
+        let scripts: Vec<_> = computer.action_history().into_iter().filter(|a| a.action_type == "execute_script").collect();
+        assert_eq!(scripts.len(), 1);
+        assert_eq!(scripts[0].params, json!({ "script": "echo hi", "lang": "bash" }));
+    }
+    
+    #[tokio::test]
+    async fn test_observer_events_fire_in_order() {
+        struct Recorder(Arc<Mutex<Vec<String>>>);
+        impl AgentObserver for Recorder {
+            fn on_action(&self, action_type: &str, _params: &Value) {
+                self.0.lock().unwrap().push(format!("action:{}", action_type));
             }
-            
-            // Create a request to the API
-            let response = self.client.create_response(&all_items, &self.tools).await?;
-            
-            if self.debug {
-                self.debug_print(&response);
+            fn on_screenshot(&self, _base64: &str, turn: usize) {
+                self.0.lock().unwrap().push(format!("screenshot:{}", turn));
             }
-            
-            let mut new_items = Vec::new();
-            
-            // Add the output to new items, checking for duplicates
-            for item in response.output {
-                // Skip items we've already processed
-                if let Some(id) = item.get("id").and_then(|id| id.as_str()) {
-                    if processed_ids.contains(id) {
-                        continue;
-                    }
-                    processed_ids.insert(id.to_string());
-                }
-                
-                new_items.push(item.clone());
-                
-                // Handle each item
-                let handled_items = self.handle_item(&item).await?;
-                
-                // Also check for duplicates in handled items
-                for handled_item in handled_items {
-                    if let Some(id) = handled_item.get("id").and_then(|id| id.as_str()) {
-                        if processed_ids.contains(id) {
-                            continue;
-                        }
-                        processed_ids.insert(id.to_string());
-                    }
-                    new_items.push(handled_item);
-                }
+            fn on_model_output(&self, text: &str) {
+                self.0.lock().unwrap().push(format!("output:{}", text));
             }
-            
-            // Check if we got a final response
-            if let Some(last_item) = new_items.last() {
-                if last_item.get("role").and_then(|r| r.as_str()) == Some("assistant") {
-                    // Append new items to all_items for the result
-                    all_items.extend(new_items);
-                    break;
-                }
+            fn on_error(&self, err: &CuaError) {
+                self.0.lock().unwrap().push(format!("error:{}", err));
+            }
+            fn on_turn_complete(&self, turn: usize, _usage: &UsageStats) {
+                self.0.lock().unwrap().push(format!("turn:{}", turn));
             }
-            
-            // Update all items for the next iteration
-            all_items.extend(new_items);
         }
         
-        Ok(all_items)
+        let server = MockApi::start().await;
+        server.push_response(200, response_body(vec![computer_call(
+            "call_1",
+            json!({ "type": "click", "x": 10, "y": 20, "button": "left" }),
+        )]));
+        server.push_response(200, response_body(vec![assistant_message("Clicked it")]));
+        
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let mut agent = Agent::new(
+            server.client(),
+            Box::new(MockComputer::new("linux", 1920, 1080)),
+            Vec::new(),
+            None,
+        );
+        agent.run_with_observer("Click the button", Box::new(Recorder(events.clone()))).await.unwrap();
+        
+        assert_eq!(*events.lock().unwrap(), vec![
+            "action:click", "screenshot:1", "turn:1", "output:Clicked it", "turn:2",
+        ]);
+        
+        // The observer was only registered for that run
+        server.push_response(500, json!({ "error": "boom" }));
+        assert!(agent.run("Again").await.is_err());
+        assert_eq!(events.lock().unwrap().len(), 5);
     }
     
-    /// Run the agent with the specified input
-    pub async fn run(&self, input: &str) -> Result<Vec<Value>, CuaError> {
-        // Create initial items
-        let items = vec![json!({
-            "role": "user",
-            "content": input,
-        })];
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn test_api_warnings_are_logged() {
+        let server = MockApi::start().await;
+        server.push_response(200, json!({
+            "id": "resp_1",
+            "output": [assistant_message("Done")],
+            "warnings": ["Model computer-use-preview-2025-01 is deprecated", "Content flagged for review"],
+        }));
         
-        // Run a turn with the input
-        self.run_full_turn(&items).await
+        let agent = Agent::new(
+            server.client(),
+            Box::new(MockComputer::new("linux", 1920, 1080)),
+            Vec::new(),
+            None,
+        );
+        agent.run("Hello").await.unwrap();
+        
+        assert_eq!(agent.warnings_emitted(), 2);
+        assert!(logs_contain("API warning"));
+        assert!(logs_contain("is deprecated"));
     }
     
-    /// Run the agent interactively
-    pub async fn run_interactive(&self) -> Result<(), CuaError> {
-        let mut items = Vec::new();
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn test_agent_emits_tracing_events() {
+        let server = MockApi::start().await;
+        server.push_response(200, response_body(vec![computer_call(
+            "call_1",
+            json!({ "type": "click", "x": 10, "y": 20, "button": "left" }),
+        )]));
+        server.push_response(200, response_body(vec![assistant_message("Clicked it")]));
         
-        println!("OpenAI CUA Agent");
-        println!("Type 'exit' to quit");
+        let agent = Agent::new(
+            server.client(),
+            Box::new(MockComputer::new("linux", 1920, 1080)),
+            Vec::new(),
+            None,
+        );
+        agent.run("Click the button").await.unwrap();
         
-        loop {
-            // Get input from user
-            print!("> ");
-            std::io::Write::flush(&mut std::io::stdout())?;
-            
-            let mut input = String::new();
-            std::io::stdin().read_line(&mut input)?;
-            
-            let input = input.trim();
-            if input.eq_ignore_ascii_case("exit") {
-                break;
-            }
-            
-            // Add input to items
-            items.push(json!({
-                "role": "user",
-                "content": input,
-            }));
-            
-            // Run a turn with the input
-            items = self.run_full_turn(&items).await?;
+        assert!(logs_contain("Computer action action=\"click\""));
+        assert!(logs_contain("MockComputer: Clicking x=10 y=20"));
+        assert!(logs_contain("Clicked it"));
+    }
+    
+    #[tokio::test]
+    async fn test_stateless_continuation_sends_previous_response_id() {
+        let server = MockApi::start().await;
+        let mut first = response_body(vec![computer_call("call_1", json!({ "type": "screenshot" }))]);
+        first["id"] = json!("resp_1");
+        let mut last = response_body(vec![assistant_message("Done")]);
+        last["id"] = json!("resp_2");
+        server.push_response(200, first);
+        server.push_response(200, last);
+        
+        let agent = Agent::new(server.client(), MockComputer::new("linux", 1920, 1080), Vec::new(), None)
+            .with_stateless_continuation(true);
+        let items = agent.run("Take a look").await.unwrap();
+        
+        let requests = server.requests();
+        assert!(requests[0].body.get("previous_response_id").is_none());
+        assert_eq!(requests[0].body["input"].as_array().unwrap().len(), 1);
+        assert_eq!(requests[1].body["previous_response_id"], "resp_1");
+        let input = requests[1].body["input"].as_array().unwrap();
+        assert_eq!(input.len(), 1);
+        assert_eq!(input[0]["type"], "computer_call_output");
+        assert_eq!(items.len(), 4);
+    }
+    
+    #[tokio::test]
+    async fn test_stepping_agent_one_request_per_step() {
+        let server = MockApi::start().await;
+        server.push_response(200, response_body(vec![computer_call(
+            "call_1",
+            json!({ "type": "click", "x": 10, "y": 20, "button": "left" }),
+        )]));
+        server.push_response(200, response_body(vec![assistant_message("Done")]));
+        let computer = MockComputer::new("linux", 1920, 1080);
+        let agent = Agent::new(server.client(), computer.clone(), Vec::new(), None);
+        let mut stepper = SteppingAgent::new(&agent, "Click the button");
+        
+        let first = stepper.next().await.unwrap();
+        assert!(!first.is_complete);
+        assert_eq!(first.last_action.unwrap().action_type, "click");
+        assert_eq!(first.new_items[1]["type"], "computer_call_output");
+        assert_eq!(computer.action_history().len(), 1);
+        assert_eq!(server.requests().len(), 1);
+        
+        let last = stepper.next().await.unwrap();
+        assert!(last.is_complete);
+        assert_eq!(last.last_action, None);
+        assert_eq!(server.requests().len(), 2);
+        assert_eq!(server.requests()[1].body["input"].as_array().unwrap().len(), 3);
+        assert_eq!(stepper.items().len(), 4);
+    }
+    
+    #[tokio::test]
+    async fn test_fork_branches_diverge_from_fork_point() {
+        let server = MockApi::start().await;
+        for text in ["First done", "Second done", "Took branch A", "Took branch B"] {
+            server.push_response(200, response_body(vec![assistant_message(text)]));
         }
+        let agent = Agent::new(server.client(), MockComputer::new("linux", 1920, 1080), Vec::new(), None);
+        let continue_with = |agent: &Agent<MockComputer>, input: &str| {
+            let mut items = agent.history();
+            items.push(json!({ "role": "user", "content": input }));
+            items
+        };
         
-        Ok(())
+        agent.run("First step").await.unwrap();
+        agent.run_full_turn(&continue_with(&agent, "Second step")).await.unwrap();
+        let fork = agent.fork();
+        assert_eq!(fork.history(), agent.history());
+        
+        agent.run_full_turn(&continue_with(&agent, "Try A")).await.unwrap();
+        fork.run_full_turn(&continue_with(&fork, "Try B")).await.unwrap();
+        let (ours, theirs) = (agent.history(), fork.history());
+        assert_eq!(ours[..4], theirs[..4]);
+        assert_eq!(ours[5]["content"][0]["text"], "Took branch A");
+        assert_eq!(theirs[5]["content"][0]["text"], "Took branch B");
+        
+        let unrelated = Agent::new(server.client(), MockComputer::new("linux", 1920, 1080), Vec::new(), None);
+        assert!(agent.merge_fork(unrelated, MergeStrategy::Concatenate).is_err());
+        let merged = agent.merge_fork(fork, MergeStrategy::Concatenate).unwrap();
+        assert_eq!(merged.len(), 8);
+        assert_eq!(merged[7]["content"][0]["text"], "Took branch B");
+        assert_eq!(agent.history(), merged);
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::mock::MockComputer;
     
-    // To run these tests, you need to have an OpenAI API key
-    // and the API must support the CUA model
     #[tokio::test]
-    #[ignore] // Ignore by default since it requires API credentials
-    async fn test_agent_run() {
-        // This is a simple test of the agent
-        // It requires a valid API key and org ID
+    #[tracing_test::traced_test]
+    async fn test_computer_call_only_filter_skips_messages() {
+        let server = MockApi::start().await;
+        server.push_response(200, response_body(vec![
+            assistant_message("Let me find the submit button first"),
+            computer_call("call_1", json!({ "type": "click", "x": 10, "y": 20, "button": "left" })),
+        ]));
+        server.push_response(200, response_body(vec![assistant_message("Submitted")]));
         
-        // Set up API key and org ID
-        std::env::set_var("OPENAI_API_KEY", "YOUR_API_KEY");
-        std::env::set_var("OPENAI_ORG", "YOUR_ORG_ID");
+        let computer = MockComputer::new("linux", 1920, 1080);
+        let agent = Agent::new(server.client(), Box::new(computer.clone()), Vec::new(), None)
+            .with_observation_filter(Box::new(computer_call_only));
+        let items = agent.run("Submit the form").await.unwrap();
         
-        // Create client
-        let client = OpenAIClient::from_env(None).unwrap();
+        let texts: Vec<&str> = items.iter()
+            .filter_map(|item| item["content"][0]["text"].as_str())
+            .collect();
+        assert_eq!(texts, vec!["Let me find the submit button first", "Submitted"]);
+        assert!(items.iter().any(|item| item["type"] == "computer_call_output"));
+        assert_eq!(computer.action_history()[0].action_type, "click");
+        assert!(logs_contain("Skipping item rejected by the observation filter"));
+        assert!(!logs_contain("Let me find the submit button first"));
+    }
+    
+    #[tokio::test]
+    async fn test_before_action_hook_vetoes_action() {
+        let server = MockApi::start().await;
+        server.push_response(200, response_body(vec![computer_call(
+            "call_1",
+            json!({ "type": "click", "x": 10, "y": 20, "button": "left" }),
+        )]));
         
-        // Create computer
         let computer = MockComputer::new("linux", 1920, 1080);
+        let agent = Agent::new(server.client(), Box::new(computer.clone()), Vec::new(), None)
+            .with_before_action_hook(Box::new(|action_type, _params| {
+                let action_type = action_type.to_string();
+                Box::pin(async move { Err(CuaError::ActionError(format!("{} blocked", action_type))) })
+            }));
+        let result = agent.run("Click the button").await;
         
-        // Create agent
+        assert!(matches!(result, Err(CuaError::ActionError(msg)) if msg == "click blocked"));
+        assert!(computer.action_history().is_empty());
+    }
+    
+    #[tokio::test]
+    async fn test_after_action_hook_receives_screenshot() {
+        let server = MockApi::start().await;
+        server.push_response(200, response_body(vec![computer_call(
+            "call_1",
+            json!({ "type": "click", "x": 10, "y": 20, "button": "left" }),
+        )]));
+        server.push_response(200, response_body(vec![assistant_message("Done")]));
+        
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let hook_seen = seen.clone();
         let agent = Agent::new(
-            client,
-            Box::new(computer),
+            server.client(),
+            Box::new(MockComputer::new("linux", 1920, 1080)),
             Vec::new(),
             None,
-        );
+        )
+        .with_after_action_hook(Box::new(move |action_type, screenshot| {
+            hook_seen.lock().unwrap().push((action_type.to_string(), screenshot.clone()));
+            Box::pin(async { Ok(()) })
+        }));
+        agent.run("Click the button").await.unwrap();
         
-        // Run the agent
-        let result = agent.run("Hello, world!").await;
+        let seen = seen.lock().unwrap();
+        assert_eq!(*seen, vec![("click".to_string(), json!("bW9ja3NjcmVlbnNob3Q="))]);
+    }
+    
+    #[tokio::test]
+    async fn test_builder_requires_client() {
+        let mut builder = Agent::builder();
+        builder.computer(Box::new(MockComputer::new("linux", 1920, 1080)));
         
-        // Check if the agent ran successfully
-        assert!(result.is_ok());
+        let result = builder.build();
+        
+        assert!(matches!(result, Err(CuaError::Other(msg)) if msg.contains("client is required")));
+    }
+    
+    #[tokio::test]
+    async fn test_builder_validates_and_builds() {
+        let server = MockApi::start().await;
+        
+        let mut builder = Agent::builder();
+        builder.client(server.client()).computer(Box::new(MockComputer::new("linux", 0, 1080)));
+        assert!(matches!(builder.build(), Err(CuaError::Other(msg)) if msg.contains("dimensions")));
+        
+        let mut builder = Agent::builder();
+        builder
+            .client(server.client())
+            .computer(Box::new(MockComputer::new("linux", 1920, 1080)))
+            .print_steps(false)
+            .max_screenshot_size(1280, 720);
+        let agent = builder.build().unwrap();
+        
+        assert!(!agent.print_steps);
+        assert_eq!(agent.max_screenshot_size, Some((1280, 720)));
+        let computer_tool = agent.tools.iter().find(|t| t["type"] == "computer-preview").unwrap();
+        assert_eq!(computer_tool["display_width"], 1280);
+    }
+    
+    #[tokio::test]
+    async fn test_cost_budget_stops_agent() {
+        // computer-use-preview input costs $3 per million tokens, so each turn costs $0.03
+        let turn = |n: usize| {
+            let mut body = response_body(vec![computer_call(
+                &format!("call_{}", n),
+                json!({ "type": "click", "x": 10, "y": 20, "button": "left" }),
+            )]);
+            body["usage"] = json!({ "input_tokens": 10_000, "output_tokens": 0, "total_tokens": 10_000 });
+            body
+        };
+        
+        for (budget, expected_turns) in [(0.01, 1), (0.05, 2)] {
+            let server = MockApi::start().await;
+            for n in 0..3 {
+                server.push_response(200, turn(n));
+            }
+            
+            let agent = Agent::new(
+                server.client(),
+                Box::new(MockComputer::new("linux", 1920, 1080)),
+                Vec::new(),
+                None,
+            )
+            .with_cost_budget(budget);
+            let result = agent.run("Keep clicking").await;
+            
+            match result {
+                Err(CuaError::BudgetExceeded { spent, limit, items }) => {
+                    assert!((spent - 0.03 * expected_turns as f64).abs() < 1e-9);
+                    assert_eq!(limit, budget);
+                    let outputs = items.iter().filter(|i| i["type"] == "computer_call_output").count();
+                    assert_eq!(outputs, expected_turns);
+                }
+                other => panic!("expected BudgetExceeded, got {:?}", other),
+            }
+            assert_eq!(agent.turn_records().len(), expected_turns);
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_continue_from_screenshot_returns_planned_actions() {
+        let server = MockApi::start().await;
+        server.push_response(200, response_body(vec![
+            assistant_message("I'll open the file menu"),
+            computer_call("call_1", json!({ "type": "click", "x": 12, "y": 8, "button": "left" })),
+            computer_call("call_2", json!({ "type": "keypress", "keys": ["ctrl", "o"] })),
+        ]));
+        
+        let computer = MockComputer::new("linux", 1920, 1080);
+        let agent = Agent::new(server.client(), Box::new(computer.clone()), Vec::new(), None);
+        let actions = agent.continue_from_screenshot("c2NyZWVu", "Open a file").await.unwrap();
+        
+        assert_eq!(actions, vec![
+            PlannedAction {
+                action_type: "click".to_string(),
+                params: json!({ "type": "click", "x": 12, "y": 8, "button": "left" }),
+            },
+            PlannedAction {
+                action_type: "keypress".to_string(),
+                params: json!({ "type": "keypress", "keys": ["ctrl", "o"] }),
+            },
+        ]);
+        assert!(computer.action_history().is_empty());
+        
+        let request = &server.requests()[0].body;
+        assert_eq!(request["input"][0]["output"]["image_url"], "data:image/png;base64,c2NyZWVu");
+        assert_eq!(request["input"][1]["content"], "Open a file");
     }
 }
@@ -1,38 +1,143 @@
 // src/api.rs - Updated to match OpenAI CUA requirements
 
 use crate::error::CuaError;
-use serde::{Deserialize, Serialize};
+use serde::Deserialize;
 use serde_json::{json, Value};
 use std::env;
 use hyper::{body::to_bytes, Client, Request, Body, Method};
+use hyper::client::HttpConnector;
 use hyper_tls::HttpsConnector;
-use http::header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE};
-use std::time::Instant;
+use http::header::{AUTHORIZATION, CONTENT_TYPE, RETRY_AFTER};
+use std::time::{Duration, Instant};
 
 /// Response from the OpenAI API
 #[derive(Debug, Deserialize)]
 pub struct ApiResponse {
+    /// Response ID, quoted in OpenAI support tickets
+    #[serde(default)]
+    pub id: String,
+    /// Exact model version that produced the response
+    #[serde(default)]
+    pub model: String,
+    /// Unix timestamp of the response creation
+    #[serde(default)]
+    pub created_at: u64,
     pub output: Vec<Value>,
+    /// Tokens used by the request, if reported
+    #[serde(default)]
+    pub usage: Option<Usage>,
+    /// Notices such as model deprecations, from `warnings` or `usage_notes`
+    #[serde(default, alias = "usage_notes")]
+    pub warnings: Vec<String>,
     #[serde(flatten)]
     pub extra: serde_json::Map<String, Value>,
 }
 
+/// Token counts reported for a response
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+pub struct Usage {
+    #[serde(default)]
+    pub input_tokens: u64,
+    #[serde(default)]
+    pub output_tokens: u64,
+    #[serde(default)]
+    pub total_tokens: u64,
+}
+
+/// Response from the models endpoint
+#[derive(Debug, Deserialize)]
+struct ModelList {
+    data: Vec<ModelEntry>,
+}
+
+/// A single entry in the models list
+#[derive(Debug, Deserialize)]
+struct ModelEntry {
+    id: String,
+}
+
+/// Default base URL for the OpenAI API
+const DEFAULT_BASE_URL: &str = "https://api.openai.com/v1";
+
+/// USD per million (input, output) tokens, by model name prefix
+///
+/// Models not listed are priced like `computer-use-preview`.
+const MODEL_PRICES: &[(&str, f64, f64)] = &[
+    ("computer-use-preview", 3.0, 12.0),
+    ("gpt-4o-mini", 0.15, 0.6),
+    ("gpt-4o", 2.5, 10.0),
+];
+
+/// Estimate the cost in USD of a response's token usage with `model`
+pub fn estimated_cost(model: &str, usage: &Usage) -> f64 {
+    let (_, input_price, output_price) = MODEL_PRICES
+        .iter()
+        .find(|(prefix, _, _)| model.starts_with(prefix))
+        .unwrap_or(&MODEL_PRICES[0]);
+    (usage.input_tokens as f64 * input_price + usage.output_tokens as f64 * output_price) / 1_000_000.0
+}
+
+/// Default time limit for a whole API request
+const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Connection settings of the underlying HTTP client
+#[derive(Debug, Clone, Copy)]
+struct HttpConfig {
+    connect_timeout: Option<Duration>,
+    pool_max_idle_per_host: usize,
+    keep_alive: bool,
+    tcp_nodelay: bool,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            connect_timeout: None,
+            pool_max_idle_per_host: usize::MAX,
+            keep_alive: true,
+            tcp_nodelay: false,
+        }
+    }
+}
+
+/// Build the HTTPS client with the given connection settings
+fn build_client(config: HttpConfig) -> Client<HttpsConnector<HttpConnector>> {
+    let mut http = HttpConnector::new();
+    http.enforce_http(false);
+    http.set_connect_timeout(config.connect_timeout);
+    http.set_nodelay(config.tcp_nodelay);
+    // Without keep-alive no connection is kept for reuse
+    let max_idle = if config.keep_alive { config.pool_max_idle_per_host } else { 0 };
+    Client::builder()
+        .pool_max_idle_per_host(max_idle)
+        .build(HttpsConnector::new_with_connector(http))
+}
+
+/// Sampling parameters sent with each response request; unset ones use the API defaults
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ModelParameters {
+    pub temperature: Option<f32>,
+    pub top_p: Option<f32>,
+    pub max_output_tokens: Option<u32>,
+}
+
 /// Client for communicating with the OpenAI API
 pub struct OpenAIClient {
     api_key: String,
     org_id: Option<String>,
-    client: Client<HttpsConnector<hyper::client::HttpConnector>>,
+    client: Client<HttpsConnector<HttpConnector>>,
+    http_config: HttpConfig,
     model: String,
+    base_url: String,
+    request_timeout: Duration,
 }
 
 impl OpenAIClient {
     /// Create a new OpenAI client with the specified API key and org ID
     pub fn new(api_key: String, org_id: Option<String>, model: Option<String>) -> Self {
-        // Create HTTPS connector
-        let https = HttpsConnector::new();
-        
         // Create client
-        let client = Client::builder().build(https);
+        let http_config = HttpConfig::default();
+        let client = build_client(http_config);
         
         // Default model for CUA
         let model = model.unwrap_or_else(|| "computer-use-preview".to_string());
@@ -41,7 +146,10 @@ impl OpenAIClient {
             api_key,
             org_id,
             client,
+            http_config,
             model,
+            base_url: DEFAULT_BASE_URL.to_string(),
+            request_timeout: DEFAULT_REQUEST_TIMEOUT,
         }
     }
     
@@ -55,24 +163,61 @@ impl OpenAIClient {
         Ok(Self::new(api_key, org_id, model))
     }
     
-    /// Create a response using the Responses API
-    pub async fn create_response(&self, input: &[Value], tools: &[Value]) -> Result<ApiResponse, CuaError> {
-        let url = "https://api.openai.com/v1/responses";
-        let start_time = Instant::now();
-        println!("DEBUG: Sending API request to {}", url);
-        
-        // Create the request body
-        let body = json!({
-            "model": self.model,
-            "input": input,
-            "tools": tools,
-            "truncation": "auto"
-        });
-        
-        // Create the request with individual headers
+    /// Set the base URL of the API (e.g. for a proxy or a local test server)
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+    
+    /// Set the time limit for a whole request, including reading the response (default 120s)
+    pub fn with_request_timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = timeout;
+        self
+    }
+    
+    /// Set the time limit for establishing a connection to the API
+    pub fn with_connect_timeout(mut self, timeout: Duration) -> Self {
+        self.http_config.connect_timeout = Some(timeout);
+        self.client = build_client(self.http_config);
+        self
+    }
+    
+    /// Set how many idle connections to the API are kept open for reuse (default unlimited)
+    pub fn with_connection_pool_size(mut self, size: usize) -> Self {
+        self.http_config.pool_max_idle_per_host = size;
+        self.client = build_client(self.http_config);
+        self
+    }
+    
+    /// Reuse connections across requests (default true)
+    pub fn with_keep_alive(mut self, keep_alive: bool) -> Self {
+        self.http_config.keep_alive = keep_alive;
+        self.client = build_client(self.http_config);
+        self
+    }
+    
+    /// Disable Nagle's algorithm on API connections (default false)
+    pub fn with_tcp_nodelay(mut self, nodelay: bool) -> Self {
+        self.http_config.tcp_nodelay = nodelay;
+        self.client = build_client(self.http_config);
+        self
+    }
+    
+    /// Get the model used for requests
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+    
+    /// Estimate the cost in USD of a response's token usage with this client's model
+    pub fn estimated_cost(&self, usage: &Usage) -> f64 {
+        estimated_cost(&self.model, usage)
+    }
+    
+    /// Create a request builder with the authentication headers set
+    fn request_builder(&self, method: Method, path: &str) -> http::request::Builder {
         let mut request_builder = Request::builder()
-            .method(Method::POST)
-            .uri(url)
+            .method(method)
+            .uri(format!("{}{}", self.base_url, path))
             .header(AUTHORIZATION, format!("Bearer {}", self.api_key))
             .header(CONTENT_TYPE, "application/json")
             .header("Openai-Beta", "responses=v1");
@@ -82,25 +227,45 @@ impl OpenAIClient {
             request_builder = request_builder.header("OpenAI-Organization", org_id);
         }
         
-        // Build the request with the body
-        let request = request_builder
-            .body(Body::from(body.to_string()))
-            .map_err(|e| CuaError::Other(format!("Failed to create request: {}", e)))?;
-        
+        request_builder
+    }
+    
+    /// Send a request and return the response body, mapping error statuses to `CuaError::ApiError`
+    async fn send(&self, request: Request<Body>) -> Result<hyper::body::Bytes, CuaError> {
+        tokio::time::timeout(self.request_timeout, self.send_inner(request))
+            .await
+            .unwrap_or_else(|_| Err(CuaError::ApiError(format!(
+                "request timed out after {}s",
+                self.request_timeout.as_secs_f64()
+            ))))
+    }
+    
+    /// Send a request without the time limit
+    async fn send_inner(&self, request: Request<Body>) -> Result<hyper::body::Bytes, CuaError> {
         // Send the request
         let response = self.client.request(request)
             .await
             .map_err(|e| CuaError::Other(format!("Failed to send request: {}", e)))?;
-        println!("DEBUG: API request sent. Status: {}", response.status());
+        tracing::debug!(status = %response.status(), "API request sent");
         
         // Check for errors
         if !response.status().is_success() {
             let status = response.status();
+            let retry_after = response.headers().get(RETRY_AFTER)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.trim().parse::<u64>().ok())
+                .map(Duration::from_secs);
             let body_bytes = to_bytes(response.into_body())
                 .await
                 .map_err(|e| CuaError::Other(format!("Failed to read error response: {}", e)))?;
             
             let error_text = String::from_utf8_lossy(&body_bytes);
+            if status.as_u16() == 429 {
+                return Err(CuaError::RateLimited {
+                    message: format!("API returned error {}: {}", status, error_text),
+                    retry_after,
+                });
+            }
             if status.as_u16() == 502 {
                 return Err(CuaError::ApiError(format!(
                     "Received 502 Bad Gateway from API. Please check your network connectivity or try again later. Error details: {}",
@@ -114,14 +279,97 @@ impl OpenAIClient {
             )));
         }
         
-        // Parse the response
-        let body_bytes = to_bytes(response.into_body())
+        to_bytes(response.into_body())
             .await
-            .map_err(|e| CuaError::Other(format!("Failed to read response: {}", e)))?;
+            .map_err(|e| CuaError::Other(format!("Failed to read response: {}", e)))
+    }
+    
+    /// List the IDs of the models available to this API key
+    pub async fn list_models(&self) -> Result<Vec<String>, CuaError> {
+        let request = self.request_builder(Method::GET, "/models")
+            .body(Body::empty())
+            .map_err(|e| CuaError::Other(format!("Failed to create request: {}", e)))?;
+        
+        let body_bytes = self.send(request).await?;
+        let models = serde_json::from_slice::<ModelList>(&body_bytes)
+            .map_err(|e| CuaError::Other(format!("Failed to parse models response: {}", e)))?;
         
+        Ok(models.data.into_iter().map(|m| m.id).collect())
+    }
+    
+    /// Create a response using the Responses API
+    pub async fn create_response(&self, input: &[Value], tools: &[Value]) -> Result<ApiResponse, CuaError> {
+        self.post_response(input, tools, &self.model, None, &ModelParameters::default()).await
+    }
+    
+    /// Create a response with `model` instead of the client's model
+    pub async fn create_response_with_model(&self, input: &[Value], tools: &[Value], model: &str) -> Result<ApiResponse, CuaError> {
+        self.post_response(input, tools, model, None, &ModelParameters::default()).await
+    }
+    
+    /// Create a response with sampling parameters, continuing from `prev_id` if given
+    pub async fn create_response_with_parameters(
+        &self,
+        input: &[Value],
+        tools: &[Value],
+        prev_id: Option<&str>,
+        parameters: &ModelParameters,
+    ) -> Result<ApiResponse, CuaError> {
+        self.post_response(input, tools, &self.model, prev_id, parameters).await
+    }
+    
+    /// Continue from the response `prev_id`, sending only the items added since
+    ///
+    /// The API keeps the earlier conversation, so `input` holds just the new
+    /// items, such as the outputs of the previous response's calls.
+    pub async fn create_response_with_previous_id(&self, input: &[Value], tools: &[Value], prev_id: &str) -> Result<ApiResponse, CuaError> {
+        self.post_response(input, tools, &self.model, Some(prev_id), &ModelParameters::default()).await
+    }
+    
+    async fn post_response(
+        &self,
+        input: &[Value],
+        tools: &[Value],
+        model: &str,
+        prev_id: Option<&str>,
+        parameters: &ModelParameters,
+    ) -> Result<ApiResponse, CuaError> {
+        let url = format!("{}/responses", self.base_url);
+        let start_time = Instant::now();
+        tracing::debug!(%url, "Sending API request");
+        
+        // Create the request body
+        let mut body = json!({
+            "model": model,
+            "input": input,
+            "tools": tools,
+            "truncation": "auto"
+        });
+        if let Some(prev_id) = prev_id {
+            body["previous_response_id"] = json!(prev_id);
+        }
+        if let Some(temperature) = parameters.temperature {
+            body["temperature"] = json!(temperature);
+        }
+        if let Some(top_p) = parameters.top_p {
+            body["top_p"] = json!(top_p);
+        }
+        if let Some(max_output_tokens) = parameters.max_output_tokens {
+            body["max_output_tokens"] = json!(max_output_tokens);
+        }
+        
+        // Build the request with the body
+        let request = self.request_builder(Method::POST, "/responses")
+            .body(Body::from(body.to_string()))
+            .map_err(|e| CuaError::Other(format!("Failed to create request: {}", e)))?;
+        
+        // Send the request
+        let body_bytes = self.send(request).await?;
+        
+        // Parse the response
         let api_response = serde_json::from_slice::<ApiResponse>(&body_bytes)
             .map_err(|e| CuaError::Other(format!("Failed to parse response: {}", e)))?;
-        println!("DEBUG: API response processed in {} ms", start_time.elapsed().as_millis());
+        tracing::debug!(elapsed_ms = start_time.elapsed().as_millis() as u64, "API response processed");
         Ok(api_response)
     }
 }
@@ -129,6 +377,10 @@ impl OpenAIClient {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::mock_api::MockApi;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
     
     #[test]
     fn test_openai_client_creation() {
@@ -142,4 +394,174 @@ mod tests {
         assert_eq!(client.org_id, Some("test_org".to_string()));
         assert_eq!(client.model, "test_model");
     }
+    
+    #[test]
+    fn test_api_response_metadata() {
+        let response: ApiResponse = serde_json::from_value(json!({
+            "id": "resp_123",
+            "object": "response",
+            "created_at": 1741476542,
+            "model": "computer-use-preview-2025-03-11",
+            "output": [],
+        })).unwrap();
+        
+        assert_eq!(response.id, "resp_123");
+        assert_eq!(response.model, "computer-use-preview-2025-03-11");
+        assert_eq!(response.created_at, 1741476542);
+        assert_eq!(response.extra["object"], "response");
+        assert!(response.warnings.is_empty());
+    }
+    
+    #[test]
+    fn test_api_response_warnings() {
+        let response: ApiResponse = serde_json::from_value(json!({
+            "output": [],
+            "warnings": ["Model is deprecated"],
+        })).unwrap();
+        assert_eq!(response.warnings, vec!["Model is deprecated"]);
+        
+        let response: ApiResponse = serde_json::from_value(json!({
+            "output": [],
+            "usage_notes": ["Near the rate limit"],
+        })).unwrap();
+        assert_eq!(response.warnings, vec!["Near the rate limit"]);
+    }
+    
+    #[tokio::test]
+    async fn test_list_models() {
+        let server = MockApi::start().await;
+        server.push_response(200, json!({
+            "object": "list",
+            "data": [
+                { "id": "computer-use-preview", "object": "model" },
+                { "id": "gpt-4o", "object": "model" },
+            ],
+        }));
+        
+        let models = server.client().list_models().await.unwrap();
+        
+        assert_eq!(models, vec!["computer-use-preview", "gpt-4o"]);
+        let requests = server.requests();
+        assert_eq!(requests[0].method, "GET");
+        assert_eq!(requests[0].path, "/v1/models");
+        assert!(requests[0].body.is_null());
+    }
+    
+    #[tokio::test]
+    async fn test_model_parameters_in_request_body() {
+        let server = MockApi::start().await;
+        server.push_response(200, json!({ "output": [] }));
+        server.push_response(200, json!({ "output": [] }));
+        let client = server.client();
+        
+        client.create_response(&[], &[]).await.unwrap();
+        let parameters = ModelParameters {
+            temperature: Some(0.0),
+            top_p: Some(0.5),
+            max_output_tokens: Some(512),
+        };
+        client.create_response_with_parameters(&[], &[], None, &parameters).await.unwrap();
+        
+        let requests = server.requests();
+        let body = requests[0].body.as_object().unwrap();
+        assert!(!body.contains_key("temperature"));
+        assert!(!body.contains_key("top_p"));
+        assert!(!body.contains_key("max_output_tokens"));
+        assert_eq!(requests[1].body["temperature"], 0.0);
+        assert_eq!(requests[1].body["top_p"], 0.5);
+        assert_eq!(requests[1].body["max_output_tokens"], 512);
+    }
+    
+    #[tokio::test]
+    async fn test_rate_limited_error_carries_retry_after() {
+        let server = MockApi::start().await;
+        server.push_response_with_headers(429, &[("Retry-After", "12")], json!({ "error": "rate limited" }));
+        let client = server.client();
+        
+        let error = client.list_models().await.unwrap_err();
+        assert!(error.is_retriable());
+        assert!(matches!(error, CuaError::RateLimited { retry_after: Some(wait), .. } if wait == Duration::from_secs(12)));
+    }
+    
+    #[tokio::test]
+    async fn test_request_timeout() {
+        // A server that accepts connections but never responds
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut connections = Vec::new();
+            while let Ok((stream, _)) = listener.accept().await {
+                connections.push(stream);
+            }
+        });
+        
+        let client = OpenAIClient::new("test_key".to_string(), None, None)
+            .with_base_url(&format!("http://{}/v1", addr))
+            .with_request_timeout(Duration::from_millis(200));
+        let start = Instant::now();
+        let result = client.list_models().await;
+        
+        assert!(start.elapsed() < Duration::from_secs(2));
+        let error = result.unwrap_err();
+        assert!(error.is_retriable());
+        assert!(matches!(error, CuaError::ApiError(msg) if msg == "request timed out after 0.2s"));
+    }
+    
+    /// Serve `{"data": []}` over keep-alive connections after a short delay, counting accepted connections
+    async fn start_keep_alive_server() -> (String, Arc<AtomicUsize>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let connections = Arc::new(AtomicUsize::new(0));
+        let accepted = connections.clone();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                accepted.fetch_add(1, Ordering::SeqCst);
+                tokio::spawn(async move {
+                    let mut buffer = Vec::new();
+                    let mut chunk = [0u8; 1024];
+                    while let Ok(n) = stream.read(&mut chunk).await {
+                        if n == 0 {
+                            break;
+                        }
+                        buffer.extend_from_slice(&chunk[..n]);
+                        if buffer.windows(4).any(|w| w == b"\r\n\r\n") {
+                            buffer.clear();
+                            tokio::time::sleep(Duration::from_millis(100)).await;
+                            let body = r#"{"data":[]}"#;
+                            let response = format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{}", body.len(), body);
+                            if stream.write_all(response.as_bytes()).await.is_err() {
+                                break;
+                            }
+                        }
+                    }
+                });
+            }
+        });
+        (format!("http://{}/v1", addr), connections)
+    }
+    
+    /// Connections opened for two rounds of two concurrent requests
+    async fn connections_for_two_rounds(client: OpenAIClient, connections: &AtomicUsize) -> usize {
+        for _ in 0..2 {
+            let (first, second) = tokio::join!(client.list_models(), client.list_models());
+            first.unwrap();
+            second.unwrap();
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        connections.load(Ordering::SeqCst)
+    }
+    
+    #[tokio::test]
+    async fn test_connection_pool_size_limits_idle_connections() {
+        let (base_url, connections) = start_keep_alive_server().await;
+        let client = OpenAIClient::new("test_key".to_string(), None, None).with_base_url(&base_url);
+        assert_eq!(connections_for_two_rounds(client, &connections).await, 2);
+        
+        // Only one of the two connections is kept, so the second round opens another
+        let (base_url, connections) = start_keep_alive_server().await;
+        let client = OpenAIClient::new("test_key".to_string(), None, None)
+            .with_base_url(&base_url)
+            .with_connection_pool_size(1);
+        assert_eq!(connections_for_two_rounds(client, &connections).await, 3);
+    }
 }
@@ -1,14 +1,58 @@
 // src/cli.rs - Updated to match OpenAI CUA approach
 
-use crate::api::OpenAIClient;
-use crate::agent::{Agent, SafetyCheckCallback};
+use crate::api::{ModelParameters, OpenAIClient};
+use crate::agent::{Checkpoint, DynAgent, SafetyCheckCallback, SteppingAgent};
 use crate::computer::Computer;
 use crate::mock::MockComputer;
+use crate::observer::JsonObserver;
+use crate::rate_limiter::ActionRateLimiter;
 use crate::error::CuaError;
+use crate::script::AgentScript;
 use crate::thread_computer::ThreadComputer;
-use std::io::{self, Write};
+use crate::tools::registry::ToolRegistry;
+use crate::tools::search::WebSearchTool;
+use std::io::{self, IsTerminal, Write};
 use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use base64::{engine::general_purpose, Engine};
 use dotenv::dotenv;
+use tracing_subscriber::EnvFilter;
+
+/// Where `--checkpoint-every` saves the conversation without `--checkpoint-path`
+const DEFAULT_CHECKPOINT_PATH: &str = "checkpoint.json";
+
+/// Install the tracing subscriber
+///
+/// `RUST_LOG` selects what is shown (default `info`, or `debug` with
+/// `--debug`); `format` is "text" or "json". Log levels are colored, with
+/// warnings such as API notices tagged in yellow, when stdout is a terminal and
+/// `NO_COLOR` is unset.
+fn init_logging(format: &str, debug: bool) {
+    let default_level = if debug { "debug" } else { "info" };
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(default_level));
+    let color = env::var_os("NO_COLOR").is_none() && io::stdout().is_terminal();
+    let builder = tracing_subscriber::fmt().with_env_filter(filter).with_ansi(color);
+    
+    // Another subscriber may already be installed, e.g. by an embedding application
+    let _ = match format {
+        "json" => builder.json().try_init(),
+        _ => builder.try_init(),
+    };
+}
+
+/// Point `DISPLAY` at the `--virtual-display` argument, if given
+///
+/// Call this before starting the async runtime: changing the environment while
+/// other threads may read it is unsound.
+pub fn set_virtual_display_env() {
+    let args: Vec<String> = env::args().collect();
+    if let Some(pair) = args.windows(2).find(|pair| pair[0] == "--virtual-display") {
+        env::set_var("DISPLAY", &pair[1]);
+    }
+}
 
 /// Run the CLI
 pub async fn run() -> Result<(), CuaError> {
@@ -20,8 +64,42 @@ pub async fn run() -> Result<(), CuaError> {
     let mut use_mock = false;
     let mut debug = false;
     let mut show_images = false;
+    let mut validate_model = false;
+    let mut web_search = false;
+    let mut step_mode = false;
+    let mut stateless_continuation = false;
+    let mut tools_schema: Option<String> = None;
+    let mut virtual_display: Option<String> = None;
+    let mut list_models = false;
     let mut input: Option<String> = None;
     let mut model: Option<String> = None;
+    let mut inject_screenshot: Option<String> = None;
+    let mut system_prompt: Option<String> = None;
+    let mut thought_prefix: Option<String> = None;
+    let mut script_file: Option<String> = None;
+    let mut task_file: Option<String> = None;
+    let mut concurrency: Option<String> = None;
+    let mut output_schema: Option<String> = None;
+    let mut checkpoint_every: Option<String> = None;
+    let mut checkpoint_path: Option<String> = None;
+    let mut request_timeout_secs: Option<String> = None;
+    let mut connection_pool_size: Option<String> = None;
+    let mut summarize_on_exit = false;
+    let mut log_format = String::from("text");
+    let mut actions_per_minute: Option<String> = None;
+    let mut cost_budget: Option<String> = None;
+    let mut from_screenshot: Option<String> = None;
+    let mut instruction: Option<String> = None;
+    let mut events_file: Option<String> = None;
+    let mut inter_turn_delay_ms: Option<String> = None;
+    let mut step_delay_min: Option<String> = None;
+    let mut step_delay_max: Option<String> = None;
+    let mut adaptive_delay = false;
+    let mut allow_scripts = false;
+    let mut vision_grounding = false;
+    let mut temperature: Option<String> = None;
+    let mut top_p: Option<String> = None;
+    let mut max_output_tokens: Option<String> = None;
     
     // Parse arguments
     let mut i = 1;
@@ -36,53 +114,225 @@ pub async fn run() -> Result<(), CuaError> {
             "--show" => {
                 show_images = true;
             }
-            "--input" => {
-                if i + 1 < args.len() {
-                    input = Some(args[i + 1].clone());
-                    i += 1;
-                }
+            "--validate-model" => {
+                validate_model = true;
+            }
+            "--adaptive-delay" => {
+                adaptive_delay = true;
+            }
+            "--allow-scripts" => {
+                allow_scripts = true;
+            }
+            "--vision-grounding" => {
+                vision_grounding = true;
+            }
+            "--web-search" => {
+                web_search = true;
+            }
+            "--step-mode" => {
+                step_mode = true;
+            }
+            "--stateless-continuation" => {
+                stateless_continuation = true;
+            }
+            "--virtual-display" if i + 1 < args.len() => {
+                virtual_display = Some(args[i + 1].clone());
+                i += 1;
+            }
+            "--tools-schema" if i + 1 < args.len() => {
+                tools_schema = Some(args[i + 1].clone());
+                i += 1;
+            }
+            "--inject-screenshot" if i + 1 < args.len() => {
+                inject_screenshot = Some(args[i + 1].clone());
+                i += 1;
+            }
+            "--system-prompt" if i + 1 < args.len() => {
+                system_prompt = Some(args[i + 1].clone());
+                i += 1;
+            }
+            "--thought-prefix" if i + 1 < args.len() => {
+                thought_prefix = Some(args[i + 1].clone());
+                i += 1;
+            }
+            "--script-file" if i + 1 < args.len() => {
+                script_file = Some(args[i + 1].clone());
+                i += 1;
+            }
+            "--task-file" if i + 1 < args.len() => {
+                task_file = Some(args[i + 1].clone());
+                i += 1;
+            }
+            "--output-schema" if i + 1 < args.len() => {
+                output_schema = Some(args[i + 1].clone());
+                i += 1;
+            }
+            "--checkpoint-every" if i + 1 < args.len() => {
+                checkpoint_every = Some(args[i + 1].clone());
+                i += 1;
+            }
+            "--checkpoint-path" if i + 1 < args.len() => {
+                checkpoint_path = Some(args[i + 1].clone());
+                i += 1;
+            }
+            "--concurrency" if i + 1 < args.len() => {
+                concurrency = Some(args[i + 1].clone());
+                i += 1;
+            }
+            "--request-timeout-secs" if i + 1 < args.len() => {
+                request_timeout_secs = Some(args[i + 1].clone());
+                i += 1;
+            }
+            "--summarize-on-exit" => {
+                summarize_on_exit = true;
+            }
+            "--connection-pool-size" if i + 1 < args.len() => {
+                connection_pool_size = Some(args[i + 1].clone());
+                i += 1;
+            }
+            "--actions-per-minute" if i + 1 < args.len() => {
+                actions_per_minute = Some(args[i + 1].clone());
+                i += 1;
+            }
+            "--from-screenshot" if i + 1 < args.len() => {
+                from_screenshot = Some(args[i + 1].clone());
+                i += 1;
             }
-            "--model" => {
-                if i + 1 < args.len() {
-                    model = Some(args[i + 1].clone());
-                    i += 1;
-                }
+            "--instruction" if i + 1 < args.len() => {
+                instruction = Some(args[i + 1].clone());
+                i += 1;
+            }
+            "--inter-turn-delay-ms" if i + 1 < args.len() => {
+                inter_turn_delay_ms = Some(args[i + 1].clone());
+                i += 1;
+            }
+            "--step-delay-min" if i + 1 < args.len() => {
+                step_delay_min = Some(args[i + 1].clone());
+                i += 1;
+            }
+            "--step-delay-max" if i + 1 < args.len() => {
+                step_delay_max = Some(args[i + 1].clone());
+                i += 1;
+            }
+            "--temperature" if i + 1 < args.len() => {
+                temperature = Some(args[i + 1].clone());
+                i += 1;
+            }
+            "--top-p" if i + 1 < args.len() => {
+                top_p = Some(args[i + 1].clone());
+                i += 1;
+            }
+            "--max-output-tokens" if i + 1 < args.len() => {
+                max_output_tokens = Some(args[i + 1].clone());
+                i += 1;
+            }
+            "--events-file" if i + 1 < args.len() => {
+                events_file = Some(args[i + 1].clone());
+                i += 1;
+            }
+            "--cost-budget" if i + 1 < args.len() => {
+                cost_budget = Some(args[i + 1].clone());
+                i += 1;
+            }
+            "--log-format" if i + 1 < args.len() => {
+                log_format = args[i + 1].clone();
+                i += 1;
+            }
+            "models" if i == 1 => {
+                list_models = true;
+            }
+            "--input" if i + 1 < args.len() => {
+                input = Some(args[i + 1].clone());
+                i += 1;
+            }
+            "--model" if i + 1 < args.len() => {
+                model = Some(args[i + 1].clone());
+                i += 1;
             }
             _ => {}
         }
         i += 1;
     }
     
-    println!("OpenAI CUA Desktop CLI");
+    init_logging(&log_format, debug);
+    tracing::info!("OpenAI CUA Desktop CLI");
     
     // Check for API key
     if env::var("OPENAI_API_KEY").is_err() {
-        println!("Error: OPENAI_API_KEY environment variable not set");
-        println!("Please set it in your environment or in a .env file");
+        tracing::error!("OPENAI_API_KEY environment variable not set. Please set it in your environment or in a .env file");
         return Err(CuaError::Other("OPENAI_API_KEY not set".to_string()));
     }
     
     // Create API client with the specified model
-    let client = OpenAIClient::from_env(model)?;
+    let mut client = OpenAIClient::from_env(model)?;
+    if let Some(secs) = request_timeout_secs {
+        match secs.parse::<u64>() {
+            Ok(secs) => client = client.with_request_timeout(Duration::from_secs(secs)),
+            Err(_) => tracing::warn!(value = %secs, "Ignoring invalid --request-timeout-secs"),
+        }
+    }
+    if let Some(size) = connection_pool_size {
+        match size.parse::<usize>() {
+            Ok(size) => client = client.with_connection_pool_size(size),
+            Err(_) => tracing::warn!(value = %size, "Ignoring invalid --connection-pool-size"),
+        }
+    }
+    let mut model_parameters = ModelParameters::default();
+    if let Some(value) = temperature {
+        match value.parse::<f32>() {
+            Ok(value) => model_parameters.temperature = Some(value),
+            Err(_) => tracing::warn!(%value, "Ignoring invalid --temperature"),
+        }
+    }
+    if let Some(value) = top_p {
+        match value.parse::<f32>() {
+            Ok(value) => model_parameters.top_p = Some(value),
+            Err(_) => tracing::warn!(%value, "Ignoring invalid --top-p"),
+        }
+    }
+    if let Some(value) = max_output_tokens {
+        match value.parse::<u32>() {
+            Ok(value) => model_parameters.max_output_tokens = Some(value),
+            Err(_) => tracing::warn!(%value, "Ignoring invalid --max-output-tokens"),
+        }
+    }
+    
+    // List the available models and exit
+    if list_models {
+        for model in client.list_models().await? {
+            println!("{}", model);
+        }
+        return Ok(());
+    }
     
     // Create computer
     let computer: Box<dyn Computer> = if use_mock {
-        println!("Using mock computer implementation");
+        tracing::info!("Using mock computer implementation");
         Box::new(MockComputer::new("linux", 1920, 1080))
     } else {
-        println!("Using thread-based desktop implementation");
-        match ThreadComputer::new() {
+        tracing::info!("Using thread-based desktop implementation");
+        let computer = match &virtual_display {
+            Some(display) => ThreadComputer::with_virtual_display(display).await,
+            None => ThreadComputer::new(),
+        };
+        match computer {
             Ok(computer) => Box::new(computer),
             Err(e) => {
-                println!("Error creating thread-based computer: {}", e);
-                println!("Falling back to mock implementation");
+                tracing::error!(error = %e, "Error creating thread-based computer, falling back to mock implementation");
                 Box::new(MockComputer::new("linux", 1920, 1080))
             }
         }
     };
     
-    println!("Computer environment: {}", computer.environment());
-    println!("Screen dimensions: {:?}", computer.dimensions());
+    tracing::info!(
+        environment = computer.environment(),
+        dimensions = ?computer.dimensions(),
+        "Computer ready"
+    );
+    match computer.measure_latency().await {
+        Ok(latency) => tracing::info!(latency_ms = latency.as_millis() as u64, "Action latency"),
+        Err(e) => tracing::warn!(error = %e, "Could not measure action latency"),
+    }
     
     // Create safety check callback
     let safety_check: SafetyCheckCallback = Box::new(|message| {
@@ -97,23 +347,261 @@ pub async fn run() -> Result<(), CuaError> {
     });
     
     // Create agent
-    let agent = Agent::new(
-        client,
-        computer,
-        Vec::new(),
-        Some(safety_check),
-    )
-    .with_debug(debug)
-    .with_show_images(show_images);
+    let mut builder = DynAgent::builder();
+    builder
+        .client(client)
+        .computer(computer)
+        .safety_check(safety_check)
+        .debug(debug)
+        .show_images(show_images)
+        .validate_model(validate_model);
+    if let Some(prompt) = system_prompt {
+        builder.system_prompt(&prompt);
+    }
+    if let Some(prefix) = thought_prefix {
+        builder.thought_prefix(&prefix);
+    }
+    let actions_per_minute = actions_per_minute.and_then(|rate| match rate.parse::<f64>() {
+        Ok(rate) if rate.is_finite() && rate > 0.0 => Some(rate),
+        _ => {
+            tracing::warn!(value = %rate, "Ignoring invalid --actions-per-minute");
+            None
+        }
+    });
+    if let Some(rate) = actions_per_minute {
+        builder.max_action_rate(rate);
+    }
+    if let Some(budget) = cost_budget {
+        match budget.parse::<f64>() {
+            Ok(budget) if budget > 0.0 => {
+                builder.cost_budget(budget);
+            }
+            _ => tracing::warn!(value = %budget, "Ignoring invalid --cost-budget"),
+        }
+    }
+    if let Some(ms) = inter_turn_delay_ms {
+        match ms.parse::<u32>() {
+            Ok(ms) => {
+                builder.inter_turn_delay(ms);
+            }
+            Err(_) => tracing::warn!(value = %ms, "Ignoring invalid --inter-turn-delay-ms"),
+        }
+    }
+    if step_delay_min.is_some() || step_delay_max.is_some() {
+        // A single bound gives a fixed delay
+        let min = step_delay_min.as_deref().or(step_delay_max.as_deref()).unwrap_or_default();
+        let max = step_delay_max.as_deref().unwrap_or(min);
+        match (min.parse::<u64>(), max.parse::<u64>()) {
+            (Ok(min_ms), Ok(max_ms)) => {
+                builder.step_delay(min_ms, max_ms);
+            }
+            _ => tracing::warn!(min, max, "Ignoring invalid --step-delay-min/--step-delay-max"),
+        }
+    }
+    builder.adaptive_delay(adaptive_delay);
+    builder.script_execution(allow_scripts);
+    builder.vision_grounding(vision_grounding);
+    builder.model_parameters(model_parameters);
+    if let Some(path) = output_schema {
+        // The schema is compiled, and rejected if invalid, when the agent is built
+        let schema = serde_json::from_str(&fs::read_to_string(&path)?)
+            .map_err(|e| CuaError::Other(format!("Invalid --output-schema {}: {}", path, e)))?;
+        builder.structured_output_mode(schema);
+    }
+    let checkpoint_path = match (&checkpoint_every, checkpoint_path) {
+        (_, Some(path)) => Some(PathBuf::from(path)),
+        (Some(_), None) => Some(PathBuf::from(DEFAULT_CHECKPOINT_PATH)),
+        (None, None) => None,
+    };
+    if let (Some(turns), Some(path)) = (checkpoint_every, &checkpoint_path) {
+        match turns.parse::<u32>() {
+            Ok(turns) if turns > 0 => {
+                builder.checkpoint_interval(turns, path.clone());
+            }
+            _ => tracing::warn!(value = %turns, "Ignoring invalid --checkpoint-every"),
+        }
+    }
+    builder.stateless_continuation(stateless_continuation);
+    if let Some(path) = events_file {
+        builder.observer(Box::new(JsonObserver::new(fs::File::create(&path)?)));
+    }
+    let mut agent = builder.build()?;
+    if web_search {
+        agent = agent.with_tool_fn(Box::new(WebSearchTool::from_env()?));
+    }
+    if let Some(path) = tools_schema {
+        let registry = ToolRegistry::from_file(&path)?;
+        tracing::info!(%path, tools = ?registry.names().collect::<Vec<_>>(), "Loaded tool schema");
+        agent = agent.with_tool_registry(registry);
+    }
+    
+    // Seed the conversation with a starting screenshot
+    if let Some(path) = inject_screenshot {
+        let png = fs::read(&path)?;
+        tracing::info!(%path, "Injecting screenshot");
+        agent.inject_screenshot(&general_purpose::STANDARD.encode(png));
+    }
+    
+    // Plan the actions for a saved screenshot, print them as JSON and exit
+    if let Some(path) = from_screenshot {
+        let instruction = instruction
+            .ok_or_else(|| CuaError::Other("--from-screenshot requires --instruction".to_string()))?;
+        let png = fs::read(&path)?;
+        tracing::info!(%path, %instruction, "Planning actions from screenshot");
+        let actions = agent.continue_from_screenshot(&general_purpose::STANDARD.encode(png), &instruction).await?;
+        println!("{}", serde_json::to_string_pretty(&actions)?);
+        if summarize_on_exit {
+            print_session_summary(&agent).await?;
+        }
+        return Ok(());
+    }
+    
+    // Run a script and exit
+    if let Some(path) = script_file {
+        let script = AgentScript::from_json(&fs::read_to_string(&path)?)?;
+        tracing::info!(%path, steps = script.steps.len(), "Running script");
+        for result in agent.run_script(&script).await? {
+            match &result.outcome {
+                Ok(_) => tracing::info!(input = %result.input, "Step succeeded"),
+                Err(e) => tracing::error!(input = %result.input, error = %e, "Step failed"),
+            }
+        }
+        if summarize_on_exit {
+            print_session_summary(&agent).await?;
+        }
+        return Ok(());
+    }
+    
+    // Run a batch of tasks and exit
+    if let Some(path) = task_file {
+        let tasks: Vec<String> = fs::read_to_string(&path)?
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(str::to_string)
+            .collect();
+        let concurrency = match concurrency.as_deref().map(str::parse::<usize>) {
+            None => 1,
+            Some(Ok(1)) => 1,
+            Some(Ok(n)) if n > 1 => {
+                // Every task would drive the same mouse and keyboard
+                return Err(CuaError::Other(format!(
+                    "--concurrency {} needs a computer per task, but this desktop has one; use --concurrency 1",
+                    n
+                )));
+            }
+            Some(_) => {
+                tracing::warn!(value = ?concurrency, "Ignoring invalid --concurrency");
+                1
+            }
+        };
+        // One limiter covers all tasks together
+        let rate_limiter = actions_per_minute
+            .map(|rate| ActionRateLimiter::new(rate).map(Arc::new))
+            .transpose()?;
+        tracing::info!(%path, tasks = tasks.len(), concurrency, "Running tasks");
+        let results = agent.run_batch_tasks(tasks, concurrency, rate_limiter, None).await?;
+        for result in &results {
+            let duration_ms = result.duration.as_millis() as u64;
+            match &result.error {
+                None => tracing::info!(input = %result.input, duration_ms, "Task succeeded"),
+                Some(e) => tracing::error!(input = %result.input, duration_ms, error = %e, "Task failed"),
+            }
+        }
+        if summarize_on_exit {
+            // The tasks ran on forks, so their conversations are summarized together
+            let items: Vec<_> = results.into_iter().flat_map(|result| result.items).collect();
+            println!("Session summary: {}", agent.summarize_items(&items).await?);
+        }
+        return Ok(());
+    }
+    
+    // Offer to resume the session saved in an earlier checkpoint
+    let resumed = match &checkpoint_path {
+        Some(path) if path.exists() => offer_resume(&agent, path).await?,
+        _ => false,
+    };
     
     // Run the agent
-    if let Some(initial_input) = input {
-        println!("Running with initial input: {}", initial_input);
-        let _ = agent.run(&initial_input).await?;
+    if let Some(initial_input) = input.filter(|_| !resumed) {
+        tracing::info!(input = %initial_input, "Running with initial input");
+        if step_mode {
+            run_stepping(&agent, &initial_input).await?;
+        } else {
+            let _ = agent.run(&initial_input).await?;
+            if let Some(output) = agent.structured_output() {
+                println!("{}", serde_json::to_string_pretty(&output)?);
+            }
+        }
+    } else if step_mode {
+        return Err(CuaError::Other("--step-mode requires --input".to_string()));
     }
     
     // Run interactively
     agent.run_interactive().await?;
     
+    if summarize_on_exit {
+        print_session_summary(&agent).await?;
+    }
+    
+    Ok(())
+}
+
+/// Print the summary of the agent's session for `--summarize-on-exit`
+async fn print_session_summary(agent: &DynAgent) -> Result<(), CuaError> {
+    println!("Session summary: {}", agent.summarize_session().await?);
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Ask whether to continue the conversation saved at `path`, and run it if so
+///
+/// Returns whether the session was resumed.
+async fn offer_resume(agent: &DynAgent, path: &Path) -> Result<bool, CuaError> {
+    let checkpoint = match Checkpoint::load(path) {
+        Ok(checkpoint) => checkpoint,
+        Err(e) => {
+            tracing::warn!(error = %e, path = %path.display(), "Ignoring unreadable checkpoint");
+            return Ok(false);
+        }
+    };
+    let saved_at = std::time::UNIX_EPOCH + Duration::from_secs(checkpoint.timestamp);
+    println!(
+        "Found a checkpoint from {} ({} turns with {})",
+        humantime::format_rfc3339_seconds(saved_at),
+        checkpoint.turns,
+        checkpoint.model
+    );
+    print!("Resume it? (y/n): ");
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    if answer.trim().to_lowercase() != "y" {
+        return Ok(false);
+    }
+    
+    tracing::info!(path = %path.display(), turns = checkpoint.turns, "Resuming from checkpoint");
+    agent.run_full_turn(&checkpoint.items).await?;
+    Ok(true)
+}
+
+/// Run `input` one API request at a time, asking before each further request
+async fn run_stepping(agent: &DynAgent, input: &str) -> Result<(), CuaError> {
+    let mut stepper = SteppingAgent::new(agent, input);
+    loop {
+        let step = stepper.next().await?;
+        if let Some(action) = &step.last_action {
+            println!("Last action: {} {}", action.action_type, action.params);
+        }
+        if step.is_complete {
+            return Ok(());
+        }
+        
+        print!("Press Enter for the next step, or type 'stop': ");
+        std::io::Write::flush(&mut std::io::stdout())?;
+        let mut answer = String::new();
+        std::io::stdin().read_line(&mut answer)?;
+        if answer.trim().eq_ignore_ascii_case("stop") {
+            return Ok(());
+        }
+    }
+}
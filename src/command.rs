@@ -0,0 +1,73 @@
+//! Helper for running external desktop utilities (xclip, copyq, ...)
+
+use crate::error::CuaError;
+use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+/// Run a program, optionally feeding `stdin`, and return its stdout
+///
+/// A missing program or a non-zero exit status is reported as an `ActionError`
+/// so callers can fall back to another utility.
+pub async fn run_command(program: &str, args: &[&str], stdin: Option<&str>) -> Result<String, CuaError> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(if stdin.is_some() { Stdio::piped() } else { Stdio::null() })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        // Stop the program if the caller gives up waiting, e.g. on a timeout
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| CuaError::ActionError(format!("Failed to run {}: {}", program, e)))?;
+    
+    if let (Some(input), Some(mut pipe)) = (stdin, child.stdin.take()) {
+        pipe.write_all(input.as_bytes()).await?;
+        // Dropping the pipe closes stdin so the program sees EOF
+    }
+    
+    let output = child.wait_with_output().await?;
+    if !output.status.success() {
+        return Err(CuaError::ActionError(format!(
+            "{} exited with {}: {}",
+            program,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Start a program in the background without waiting for it to finish
+///
+/// Only a missing program is reported; the exit status is not checked. The
+/// child is reaped by a background task once it exits.
+pub fn spawn_command(program: &str, args: &[&str]) -> Result<(), CuaError> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| CuaError::ActionError(format!("Failed to run {}: {}", program, e)))?;
+    
+    let program = program.to_string();
+    tokio::spawn(async move {
+        match child.wait().await {
+            Ok(status) if !status.success() => tracing::warn!(program, %status, "Background command failed"),
+            Err(e) => tracing::warn!(program, error = %e, "Failed to wait for background command"),
+            Ok(_) => {}
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[tokio::test]
+    async fn test_spawn_command_reports_missing_program() {
+        assert!(matches!(spawn_command("no-such-program-for-cua-tests", &[]), Err(CuaError::ActionError(_))));
+    }
+}
@@ -0,0 +1,375 @@
+//! Screenshot compression applied before screenshots are uploaded to the API
+//!
+//! Raw PNG screenshots of a 1080p desktop are typically 2-4 MB. Re-encoding
+//! them as JPEG cuts the request payload by well over half while keeping UI
+//! text legible for the model.
+
+use crate::error::CuaError;
+use crate::vision::{self, decode_screenshot};
+use crate::watermark::{self, Corner, WatermarkConfig};
+use base64::{engine::general_purpose, Engine};
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageOutputFormat};
+use std::io::Cursor;
+
+/// Output format for compressed screenshots
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompressionFormat {
+    Jpeg,
+    /// Lossy WebP, available with the `webp` feature
+    #[cfg(feature = "webp")]
+    WebP,
+}
+
+impl CompressionFormat {
+    /// MIME type used in the `data:` URL sent to the API
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            CompressionFormat::Jpeg => "image/jpeg",
+            #[cfg(feature = "webp")]
+            CompressionFormat::WebP => "image/webp",
+        }
+    }
+}
+
+/// Format the agent captures screenshots in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ScreenshotFormat {
+    /// Lossless PNG, as returned by `Computer::screenshot`
+    #[default]
+    Png,
+    /// JPEG at the given quality (clamped to 1-95), from `Computer::screenshot_jpeg`
+    Jpeg(u8),
+    /// Lossy WebP at the given quality (clamped to 1-100), available with the `webp` feature
+    #[cfg(feature = "webp")]
+    WebP(u8),
+}
+
+impl ScreenshotFormat {
+    /// MIME type used in the `data:` URL sent to the API
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            ScreenshotFormat::Png => "image/png",
+            ScreenshotFormat::Jpeg(_) => "image/jpeg",
+            #[cfg(feature = "webp")]
+            ScreenshotFormat::WebP(_) => "image/webp",
+        }
+    }
+    
+    /// Whether `formats` (see `Computer::screenshot_format_support`) includes this format, at any quality
+    pub fn is_supported_by(&self, formats: &[ScreenshotFormat]) -> bool {
+        formats.iter().any(|format| std::mem::discriminant(format) == std::mem::discriminant(self))
+    }
+}
+
+/// Settings for screenshot compression
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompressionConfig {
+    /// Downscale so that neither side exceeds this many pixels.
+    /// The model then sees scaled coordinates, so leave unset unless the
+    /// caller maps coordinates back.
+    pub max_dimension: Option<u32>,
+    /// Output format
+    pub format: CompressionFormat,
+    /// Encoder quality (1-100)
+    pub quality: u8,
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        Self {
+            max_dimension: None,
+            format: CompressionFormat::Jpeg,
+            quality: 75,
+        }
+    }
+}
+
+/// Compresses base64-encoded PNG screenshots
+pub trait ScreenshotCompressor: Send + Sync {
+    /// Compress a base64 PNG and return the base64 of the compressed image
+    fn compress(&self, base64_png: &str, config: &CompressionConfig) -> Result<String, CuaError>;
+}
+
+/// Default compressor built on the `image` crate
+pub struct ImageCompressor;
+
+impl ScreenshotCompressor for ImageCompressor {
+    fn compress(&self, base64_png: &str, config: &CompressionConfig) -> Result<String, CuaError> {
+        let mut image = decode_screenshot(base64_png)?;
+        
+        // Downscale proportionally if the image is too large
+        if let Some(max) = config.max_dimension {
+            if image.width() > max || image.height() > max {
+                image = image.resize(max, max, FilterType::Triangle);
+            }
+        }
+        
+        let mut buffer = Vec::new();
+        match config.format {
+            CompressionFormat::Jpeg => {
+                // JPEG has no alpha channel
+                let rgb = DynamicImage::ImageRgb8(image.to_rgb8());
+                JpegEncoder::new_with_quality(&mut buffer, config.quality.clamp(1, 100))
+                    .encode_image(&rgb)
+                    .map_err(|e| CuaError::ScreenshotError(format!("Failed to encode JPEG: {}", e)))?;
+            }
+            #[cfg(feature = "webp")]
+            CompressionFormat::WebP => buffer = encode_webp(&image, config.quality),
+        }
+        
+        Ok(general_purpose::STANDARD.encode(&buffer))
+    }
+}
+
+/// Encode `image` as lossy WebP at `quality` (clamped to 1-100)
+#[cfg(feature = "webp")]
+pub fn encode_webp(image: &DynamicImage, quality: u8) -> Vec<u8> {
+    let rgba = image.to_rgba8();
+    webp::Encoder::from_rgba(&rgba, rgba.width(), rgba.height())
+        .encode(quality.clamp(1, 100) as f32)
+        .to_vec()
+}
+
+/// One stage of a screenshot pipeline set with `Agent::with_screenshot_compression_pipeline`
+pub trait ScreenshotProcessor: Send + Sync {
+    /// Transform the image
+    fn process(&self, image: DynamicImage) -> Result<DynamicImage, CuaError>;
+    
+    /// Format the pipeline output is encoded in if this is the last stage to set one
+    fn output_format(&self) -> Option<ScreenshotFormat> {
+        None
+    }
+}
+
+/// Downscale proportionally to fit within the given size
+///
+/// The agent maps the model's coordinates in the smaller image back to the screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResizeProcessor {
+    pub max_width: u32,
+    pub max_height: u32,
+}
+
+impl ScreenshotProcessor for ResizeProcessor {
+    fn process(&self, image: DynamicImage) -> Result<DynamicImage, CuaError> {
+        if image.width() <= self.max_width && image.height() <= self.max_height {
+            return Ok(image);
+        }
+        Ok(image.resize(self.max_width, self.max_height, FilterType::Triangle))
+    }
+}
+
+/// Encode the pipeline output as JPEG at the given quality (clamped to 1-95)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JpegQualityProcessor {
+    pub quality: u8,
+}
+
+impl ScreenshotProcessor for JpegQualityProcessor {
+    fn process(&self, image: DynamicImage) -> Result<DynamicImage, CuaError> {
+        // JPEG has no alpha channel
+        Ok(match image {
+            DynamicImage::ImageLuma8(_) | DynamicImage::ImageRgb8(_) => image,
+            DynamicImage::ImageLumaA8(_) => DynamicImage::ImageLuma8(image.to_luma8()),
+            _ => DynamicImage::ImageRgb8(image.to_rgb8()),
+        })
+    }
+    
+    fn output_format(&self) -> Option<ScreenshotFormat> {
+        Some(ScreenshotFormat::Jpeg(self.quality))
+    }
+}
+
+/// Draw `text` in a corner, as `Agent::with_watermark_config` does
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatermarkProcessor {
+    pub text: String,
+    pub position: Corner,
+}
+
+impl ScreenshotProcessor for WatermarkProcessor {
+    fn process(&self, image: DynamicImage) -> Result<DynamicImage, CuaError> {
+        let config = WatermarkConfig { position: self.position, ..WatermarkConfig::new(&self.text) };
+        let mut image = image.into_rgba8();
+        watermark::draw_watermark(&mut image, &config, &self.text);
+        Ok(image.into())
+    }
+}
+
+/// Convert to 8-bit grayscale
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GrayscaleProcessor;
+
+impl ScreenshotProcessor for GrayscaleProcessor {
+    fn process(&self, image: DynamicImage) -> Result<DynamicImage, CuaError> {
+        Ok(DynamicImage::ImageLuma8(image.to_luma8()))
+    }
+}
+
+/// Run `processors` in order on a base64 screenshot, stopping at the first error
+///
+/// Returns the base64 result and its MIME type. The output is PNG unless a
+/// stage sets another format.
+pub fn run_pipeline(processors: &[Box<dyn ScreenshotProcessor>], screenshot_base64: &str) -> Result<(String, &'static str), CuaError> {
+    run_pipeline_scaled(processors, screenshot_base64).map(|(output, mime_type, _)| (output, mime_type))
+}
+
+/// Like [`run_pipeline`], also returning the output width over the input width
+///
+/// The ratio is how much resizing stages scaled the screenshot.
+pub fn run_pipeline_scaled(
+    processors: &[Box<dyn ScreenshotProcessor>],
+    screenshot_base64: &str,
+) -> Result<(String, &'static str, f64), CuaError> {
+    let mut image = decode_screenshot(screenshot_base64)?;
+    let input_width = image.width();
+    let mut format = ScreenshotFormat::Png;
+    for processor in processors {
+        image = processor.process(image)?;
+        format = processor.output_format().unwrap_or(format);
+    }
+    
+    let output = match format {
+        ScreenshotFormat::Png => ImageOutputFormat::Png,
+        ScreenshotFormat::Jpeg(quality) => {
+            if image.color().has_alpha() {
+                image = DynamicImage::ImageRgb8(image.to_rgb8());
+            }
+            ImageOutputFormat::Jpeg(quality.clamp(1, vision::MAX_JPEG_QUALITY))
+        }
+        #[cfg(feature = "webp")]
+        ScreenshotFormat::WebP(quality) => {
+            let scale = image.width() as f64 / input_width as f64;
+            return Ok((general_purpose::STANDARD.encode(encode_webp(&image, quality)), format.mime_type(), scale));
+        }
+    };
+    let mut buffer = Vec::new();
+    image
+        .write_to(&mut Cursor::new(&mut buffer), output)
+        .map_err(|e| CuaError::ScreenshotError(format!("Failed to encode screenshot: {}", e)))?;
+    let scale = image.width() as f64 / input_width as f64;
+    Ok((general_purpose::STANDARD.encode(&buffer), format.mime_type(), scale))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::codecs::png::PngEncoder;
+    use image::{ImageEncoder, Rgba, RgbaImage};
+    use std::sync::OnceLock;
+    
+    /// Build a 1080p screenshot-like PNG: window chrome, gradients and text-like noise
+    fn desktop_png() -> String {
+        static PNG: OnceLock<String> = OnceLock::new();
+        PNG.get_or_init(|| {
+            let image = RgbaImage::from_fn(1920, 1080, |x, y| {
+                if y < 40 {
+                    Rgba([40, 44, 52, 255])
+                } else if (x / 8 + y / 16) % 7 == 0 && (x * 31 + y * 17) % 5 < 2 {
+                    Rgba([20, 20, 20, 255])
+                } else {
+                    Rgba([(x % 256) as u8, (y % 256) as u8, ((x + y) % 256) as u8, 255])
+                }
+            });
+            let mut buffer = Vec::new();
+            PngEncoder::new(&mut buffer)
+                .write_image(image.as_raw(), 1920, 1080, image::ColorType::Rgba8)
+                .unwrap();
+            general_purpose::STANDARD.encode(&buffer)
+        }).clone()
+    }
+    
+    #[test]
+    fn test_compress_produces_valid_jpeg() {
+        let png = desktop_png();
+        let jpeg = ImageCompressor.compress(&png, &CompressionConfig::default()).unwrap();
+        
+        let bytes = general_purpose::STANDARD.decode(&jpeg).unwrap();
+        assert_eq!(image::guess_format(&bytes).unwrap(), image::ImageFormat::Jpeg);
+        let decoded = image::load_from_memory(&bytes).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (1920, 1080));
+    }
+    
+    #[test]
+    fn test_compression_payload_size() {
+        let png = desktop_png();
+        let jpeg = ImageCompressor.compress(&png, &CompressionConfig::default()).unwrap();
+        
+        assert!(jpeg.len() * 2 <= png.len());
+    }
+    
+    #[cfg(feature = "webp")]
+    #[test]
+    fn test_compress_produces_valid_webp() {
+        let config = CompressionConfig { format: CompressionFormat::WebP, ..Default::default() };
+        let webp = ImageCompressor.compress(&desktop_png(), &config).unwrap();
+        
+        let bytes = general_purpose::STANDARD.decode(&webp).unwrap();
+        assert_eq!(&bytes[..4], b"RIFF");
+        assert_eq!(&bytes[8..12], b"WEBP");
+    }
+    
+    #[test]
+    fn test_compress_resizes_to_max_dimension() {
+        let config = CompressionConfig { max_dimension: Some(960), ..Default::default() };
+        let jpeg = ImageCompressor.compress(&desktop_png(), &config).unwrap();
+        
+        let decoded = image::load_from_memory(&general_purpose::STANDARD.decode(&jpeg).unwrap()).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (960, 540));
+    }
+    
+    #[test]
+    fn test_pipeline_resize_then_grayscale() {
+        let pipeline: Vec<Box<dyn ScreenshotProcessor>> = vec![
+            Box::new(ResizeProcessor { max_width: 640, max_height: 640 }),
+            Box::new(GrayscaleProcessor),
+        ];
+        
+        let (output, mime_type) = run_pipeline(&pipeline, &desktop_png()).unwrap();
+        
+        assert_eq!(mime_type, "image/png");
+        let decoded = decode_screenshot(&output).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (640, 360));
+        assert_eq!(decoded.color().channel_count(), 1);
+    }
+    
+    #[cfg(feature = "webp")]
+    #[test]
+    fn test_pipeline_encodes_webp() {
+        struct WebP;
+        impl ScreenshotProcessor for WebP {
+            fn process(&self, image: DynamicImage) -> Result<DynamicImage, CuaError> {
+                Ok(image)
+            }
+            fn output_format(&self) -> Option<ScreenshotFormat> {
+                Some(ScreenshotFormat::WebP(80))
+            }
+        }
+        let pipeline: Vec<Box<dyn ScreenshotProcessor>> = vec![Box::new(WebP)];
+        
+        let (output, mime_type) = run_pipeline(&pipeline, &desktop_png()).unwrap();
+        
+        assert_eq!(mime_type, "image/webp");
+        let bytes = general_purpose::STANDARD.decode(&output).unwrap();
+        assert_eq!(&bytes[8..12], b"WEBP");
+    }
+    
+    #[test]
+    fn test_pipeline_stops_at_failing_stage() {
+        struct Failing;
+        impl ScreenshotProcessor for Failing {
+            fn process(&self, _image: DynamicImage) -> Result<DynamicImage, CuaError> {
+                Err(CuaError::ScreenshotError("stage failed".to_string()))
+            }
+        }
+        let pipeline: Vec<Box<dyn ScreenshotProcessor>> = vec![
+            Box::new(Failing),
+            Box::new(JpegQualityProcessor { quality: 60 }),
+        ];
+        
+        let error = run_pipeline(&pipeline, &desktop_png()).unwrap_err();
+        assert!(matches!(error, CuaError::ScreenshotError(msg) if msg == "stage failed"));
+    }
+}
@@ -1,8 +1,494 @@
 //! Computer trait defining the interface for desktop control
 
+use crate::compression::ScreenshotFormat;
 use crate::error::CuaError;
+use crate::ocr::{self, OcrWord};
+use crate::vision::{self, decode_screenshot};
 use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
 use async_trait::async_trait;
+use base64::{engine::general_purpose, Engine};
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// A node in the accessibility tree of the desktop
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AccessibilityNode {
+    /// Role of the element (e.g. "push button", "text")
+    pub role: String,
+    /// Accessible name of the element
+    pub name: String,
+    /// Current value, for elements that have one (sliders, text fields)
+    pub value: Option<String>,
+    /// Screen bounds as (x, y, width, height)
+    pub bounds: (i32, i32, u32, u32),
+    /// Child elements
+    pub children: Vec<AccessibilityNode>,
+}
+
+/// Record of a single action performed on a computer
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ActionRecord {
+    /// Action type as used by the CUA model (e.g. "click", "type")
+    pub action_type: String,
+    /// Parameters of the action
+    pub params: Value,
+}
+
+impl ActionRecord {
+    /// Create a new action record
+    pub fn new(action_type: &str, params: Value) -> Self {
+        Self {
+            action_type: action_type.to_string(),
+            params,
+        }
+    }
+}
+
+/// Modifier key used by the OS-native clipboard shortcuts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PasteKey {
+    /// Ctrl, on Windows and Linux
+    Control,
+    /// Cmd, on macOS
+    Meta,
+}
+
+impl PasteKey {
+    /// Modifier for a CUA environment string such as "mac" or "linux"
+    pub fn for_environment(environment: &str) -> Self {
+        match environment {
+            "mac" | "macos" => PasteKey::Meta,
+            _ => PasteKey::Control,
+        }
+    }
+    
+    /// Modifier for the operating system this binary runs on
+    pub fn host() -> Self {
+        if cfg!(target_os = "macos") {
+            PasteKey::Meta
+        } else {
+            PasteKey::Control
+        }
+    }
+    
+    /// Keys to pass to `Computer::keypress` for the shortcut modifier + `key`
+    pub fn shortcut(self, key: &str) -> Vec<String> {
+        let modifier = match self {
+            PasteKey::Control => "ctrl",
+            PasteKey::Meta => "super",
+        };
+        vec![modifier.to_string(), key.to_string()]
+    }
+}
+
+/// Common action names mapped to their key combinations in a CUA environment
+///
+/// Keys use the names accepted by `Computer::keypress`. Environments other
+/// than "mac"/"macos" and "windows" get the Linux shortcuts.
+pub fn keyboard_shortcuts_for_environment(environment: &str) -> HashMap<String, Vec<String>> {
+    let shortcuts: &[(&str, &[&str])] = match environment {
+        "mac" | "macos" => &[
+            ("undo", &["super", "z"]),
+            ("redo", &["super", "shift", "z"]),
+            ("copy", &["super", "c"]),
+            ("cut", &["super", "x"]),
+            ("paste", &["super", "v"]),
+            ("select_all", &["super", "a"]),
+            ("find", &["super", "f"]),
+            ("save", &["super", "s"]),
+            ("new_tab", &["super", "t"]),
+            ("close_tab", &["super", "w"]),
+            ("close_window", &["super", "shift", "w"]),
+            ("quit_application", &["super", "q"]),
+            ("switch_application", &["super", "tab"]),
+            ("zoom_in", &["super", "="]),
+            ("zoom_out", &["super", "-"]),
+            ("reset_zoom", &["super", "0"]),
+        ],
+        "windows" => &[
+            ("undo", &["ctrl", "z"]),
+            ("redo", &["ctrl", "y"]),
+            ("copy", &["ctrl", "c"]),
+            ("cut", &["ctrl", "x"]),
+            ("paste", &["ctrl", "v"]),
+            ("select_all", &["ctrl", "a"]),
+            ("find", &["ctrl", "f"]),
+            ("save", &["ctrl", "s"]),
+            ("new_tab", &["ctrl", "t"]),
+            ("close_tab", &["ctrl", "w"]),
+            ("close_window", &["alt", "f4"]),
+            ("quit_application", &["alt", "f4"]),
+            ("switch_application", &["alt", "tab"]),
+            ("zoom_in", &["ctrl", "="]),
+            ("zoom_out", &["ctrl", "-"]),
+            ("reset_zoom", &["ctrl", "0"]),
+        ],
+        _ => &[
+            ("undo", &["ctrl", "z"]),
+            ("redo", &["ctrl", "shift", "z"]),
+            ("copy", &["ctrl", "c"]),
+            ("cut", &["ctrl", "x"]),
+            ("paste", &["ctrl", "v"]),
+            ("select_all", &["ctrl", "a"]),
+            ("find", &["ctrl", "f"]),
+            ("save", &["ctrl", "s"]),
+            ("new_tab", &["ctrl", "t"]),
+            ("close_tab", &["ctrl", "w"]),
+            ("close_window", &["alt", "f4"]),
+            ("quit_application", &["ctrl", "q"]),
+            ("switch_application", &["alt", "tab"]),
+            ("zoom_in", &["ctrl", "="]),
+            ("zoom_out", &["ctrl", "-"]),
+            ("reset_zoom", &["ctrl", "0"]),
+        ],
+    };
+    shortcuts
+        .iter()
+        .map(|(action, keys)| (action.to_string(), keys.iter().map(|key| key.to_string()).collect()))
+        .collect()
+}
+
+/// Zoom applied to the screen, as a scale factor around a center point
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ZoomState {
+    /// Scale factor, 1.0 when not zoomed
+    pub factor: f32,
+    /// Center of the zoom
+    pub cx: i32,
+    pub cy: i32,
+}
+
+impl Default for ZoomState {
+    fn default() -> Self {
+        Self { factor: 1.0, cx: 0, cy: 0 }
+    }
+}
+
+impl ZoomState {
+    /// Map an unzoomed point to where it appears on the zoomed screen
+    pub fn apply(&self, x: i32, y: i32) -> (i32, i32) {
+        (
+            ((x - self.cx) as f32 * self.factor).round() as i32 + self.cx,
+            ((y - self.cy) as f32 * self.factor).round() as i32 + self.cy,
+        )
+    }
+    
+    /// Map a point on the zoomed screen back to unzoomed coordinates
+    pub fn invert(&self, x: i32, y: i32) -> (i32, i32) {
+        (
+            ((x - self.cx) as f32 / self.factor).round() as i32 + self.cx,
+            ((y - self.cy) as f32 / self.factor).round() as i32 + self.cy,
+        )
+    }
+    
+    /// State after zooming the current screen to `factor` overall around screen point (cx, cy)
+    ///
+    /// Combines both zooms into one around a new center. Zooming back to a
+    /// factor of 1.0 resets the state.
+    pub fn zoom_to(&self, factor: f32, cx: i32, cy: i32) -> ZoomState {
+        if (factor - 1.0).abs() < f32::EPSILON {
+            return ZoomState::default();
+        }
+        // p -> c + r * (apply(p) - c) is p -> factor * p + offset, fixed at offset / (1 - factor)
+        let ratio = factor / self.factor;
+        let center = |c: i32, current: i32| {
+            let offset = c as f32 * (1.0 - ratio) + ratio * current as f32 * (1.0 - self.factor);
+            (offset / (1.0 - factor)).round() as i32
+        };
+        ZoomState { factor, cx: center(cx, self.cx), cy: center(cy, self.cy) }
+    }
+}
+
+/// Special key names understood by `Computer::keypress`, besides single characters
+///
+/// `ThreadComputer` maps these to Enigo keys by position.
+pub(crate) const SPECIAL_KEYS: [&str; 17] = [
+    "alt", "backspace", "capslock", "ctrl", "delete", "end", "enter", "esc", "home",
+    "shift", "space", "super", "tab", "arrowdown", "arrowleft", "arrowright", "arrowup",
+];
+
+/// Lowercase a key name and resolve aliases ("cmd", "control", "escape", ...) to `SPECIAL_KEYS` names
+pub(crate) fn canonical_key_name(key: &str) -> String {
+    let key = key.trim().to_lowercase();
+    let canonical = match key.as_str() {
+        "control" => "ctrl",
+        "cmd" | "command" | "win" | "meta" => "super",
+        "option" => "alt",
+        "return" => "enter",
+        "escape" => "esc",
+        "del" => "delete",
+        "up" => "arrowup",
+        "down" => "arrowdown",
+        "left" => "arrowleft",
+        "right" => "arrowright",
+        _ => return key,
+    };
+    canonical.to_string()
+}
+
+/// Parse a shortcut such as "Ctrl+Shift+T" into key names for `Computer::keypress`
+///
+/// Key names are case-insensitive and common aliases ("cmd", "control",
+/// "escape", ...) are accepted.
+pub fn parse_hotkey(hotkey: &str) -> Result<Vec<String>, CuaError> {
+    hotkey
+        .split('+')
+        .map(|token| {
+            let key = canonical_key_name(token);
+            if SPECIAL_KEYS.contains(&key.as_str()) || key.chars().count() == 1 {
+                Ok(key)
+            } else if key.is_empty() {
+                Err(CuaError::ActionError(format!("Empty key in hotkey '{}'", hotkey)))
+            } else {
+                Err(CuaError::ActionError(format!("Unknown key '{}' in hotkey '{}'", key, hotkey)))
+            }
+        })
+        .collect()
+}
+
+/// How often `Computer::monitor_clipboard_changes` reads the clipboard
+pub const CLIPBOARD_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The application that has keyboard focus
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApplicationInfo {
+    /// Display or process name
+    pub name: String,
+    /// Bundle identifier (macOS only)
+    pub bundle_id: Option<String>,
+    pub pid: u32,
+    pub executable_path: String,
+}
+
+/// How often `Computer::detect_dialog` checks for a dialog
+pub const DIALOG_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Condition on a base64 screenshot, checked by `Computer::wait_until`
+///
+/// The lifetime is spelled out because `async_trait` would otherwise tie the
+/// elided `&str` lifetime to the method call.
+pub type ScreenCondition = Box<dyn for<'a> Fn(&'a str) -> bool + Send>;
+
+/// How often `Computer::wait_until` takes a screenshot
+pub const WAIT_UNTIL_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// A modal dialog found on screen
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DialogInfo {
+    pub title: String,
+    /// Main text of the dialog, empty if the platform does not expose it
+    pub message: String,
+    /// Button labels, empty if the platform does not expose them
+    ///
+    /// On Linux they come from the accessibility tree, so they need the
+    /// `accessibility` feature and a running AT-SPI bus.
+    pub buttons: Vec<String>,
+    /// Bounding box as (x, y, width, height)
+    pub bounds: (i32, i32, u32, u32),
+}
+
+/// Whether `inner` lies entirely inside `outer`, both as (x, y, width, height)
+pub(crate) fn bounds_contain(outer: (i32, i32, u32, u32), inner: (i32, i32, u32, u32)) -> bool {
+    let (ox, oy) = (i64::from(outer.0), i64::from(outer.1));
+    let (ix, iy) = (i64::from(inner.0), i64::from(inner.1));
+    ix >= ox && iy >= oy
+        && ix + i64::from(inner.2) <= ox + i64::from(outer.2)
+        && iy + i64::from(inner.3) <= oy + i64::from(outer.3)
+}
+
+/// Buttons in an accessibility tree that lie inside `bounds`, as (name, bounds) in tree order
+pub(crate) fn buttons_within(node: &AccessibilityNode, bounds: (i32, i32, u32, u32)) -> Vec<(String, (i32, i32, u32, u32))> {
+    let mut buttons = Vec::new();
+    if node.role.to_lowercase().contains("button") && !node.name.trim().is_empty() && bounds_contain(bounds, node.bounds) {
+        buttons.push((node.name.trim().to_string(), node.bounds));
+    }
+    for child in &node.children {
+        buttons.extend(buttons_within(child, bounds));
+    }
+    buttons
+}
+
+/// Clicks `Computer::focus_input_field` tries before falling back to a double click
+pub const FOCUS_ATTEMPTS: u32 = 3;
+
+/// Whether a changed region looks like a blinking text cursor: 1-2 pixels wide and taller than wide
+fn is_cursor_blink(changed: (u32, u32, u32, u32)) -> bool {
+    let (_, _, width, height) = changed;
+    (1..=2).contains(&width) && height >= 4 * width
+}
+
+/// Intermediate points `Computer::drag_and_drop` inserts between the start and end
+pub const DRAG_AND_DROP_STEPS: usize = 10;
+
+/// Longest `Computer::press_and_hold` the agent and `ThreadComputer` perform, in milliseconds
+///
+/// Holding a key blocks the input thread, so longer holds are cut short.
+pub const MAX_KEY_HOLD_MS: u32 = 10_000;
+
+/// Longest outline the `highlight_region` tool asks for, in milliseconds
+pub const MAX_HIGHLIGHT_MS: u32 = 5_000;
+
+/// Build a straight drag path from `from` to `to` with `steps` evenly spaced points in between
+pub fn interpolate_path(from: (i32, i32), to: (i32, i32), steps: usize) -> Vec<HashMap<String, i32>> {
+    let segments = steps + 1;
+    (0..=segments)
+        .map(|i| {
+            let t = i as f64 / segments as f64;
+            let x = from.0 as f64 + (to.0 - from.0) as f64 * t;
+            let y = from.1 as f64 + (to.1 - from.1) as f64 * t;
+            HashMap::from([("x".to_string(), x.round() as i32), ("y".to_string(), y.round() as i32)])
+        })
+        .collect()
+}
+
+/// Environment variables `Computer::read_env_var` may read unless the computer is given its own allowlist
+pub const DEFAULT_ENV_ALLOWLIST: &[&str] = &["HOME", "USER", "USERNAME", "LANG", "SHELL", "PATH", "DISPLAY"];
+
+/// Read the environment variable `name` of this process, refusing names not in `allowlist`
+pub fn read_allowed_env_var<S: AsRef<str>>(name: &str, allowlist: &[S]) -> Result<String, CuaError> {
+    if !allowlist.iter().any(|allowed| allowed.as_ref() == name) {
+        return Err(CuaError::SafetyError(format!("Environment variable {} is not in the allowlist", name)));
+    }
+    std::env::var(name).map_err(|e| CuaError::ActionError(format!("Failed to read {}: {}", name, e)))
+}
+
+/// Bytes of page source the `get_page_source` tool returns unless the agent sets another limit
+pub const DEFAULT_PAGE_SOURCE_LIMIT: usize = 100 * 1024;
+
+/// Remove `<script>` elements from HTML
+///
+/// An unclosed script element is removed to the end of the document.
+pub fn strip_script_tags(html: &str) -> String {
+    let lower = html.to_ascii_lowercase();
+    let mut sanitized = String::with_capacity(html.len());
+    let mut position = 0;
+    while let Some(offset) = lower[position..].find("<script") {
+        let start = position + offset;
+        let after_name = lower[start + "<script".len()..].chars().next();
+        if !matches!(after_name, Some('>' | '/') | Some(' ' | '\t' | '\n' | '\r')) {
+            // Another tag whose name starts with "script"
+            sanitized.push_str(&html[position..start + 1]);
+            position = start + 1;
+            continue;
+        }
+        sanitized.push_str(&html[position..start]);
+        position = match lower[start..].find("</script>") {
+            Some(end) => start + end + "</script>".len(),
+            None => html.len(),
+        };
+    }
+    sanitized.push_str(&html[position..]);
+    sanitized
+}
+
+/// Cut page source to at most `max_bytes`, marking the cut with an HTML comment
+pub fn limit_page_source(mut html: String, max_bytes: usize) -> String {
+    if html.len() > max_bytes {
+        let mut cut = max_bytes;
+        while !html.is_char_boundary(cut) {
+            cut -= 1;
+        }
+        html.truncate(cut);
+        html.push_str(&format!("\n<!-- truncated at {} bytes -->", max_bytes));
+    }
+    html
+}
+
+/// Logical DPI of a screen at 100% scaling
+pub const BASE_DPI: f32 = 96.0;
+
+/// Convert logical coordinates to physical pixels on a screen with the given DPI
+pub fn logical_to_physical_at(dpi: f32, x: f32, y: f32) -> (i32, i32) {
+    let scale = dpi / BASE_DPI;
+    ((x * scale).round() as i32, (y * scale).round() as i32)
+}
+
+/// Convert physical pixels to logical coordinates on a screen with the given DPI
+pub fn physical_to_logical_at(dpi: f32, x: i32, y: i32) -> (f32, f32) {
+    let scale = dpi / BASE_DPI;
+    (x as f32 / scale, y as f32 / scale)
+}
+
+/// Screen bounds as (x, y, width, height), in global desktop coordinates
+pub type ScreenArea = (i32, i32, u32, u32);
+
+/// Smallest area containing all of `screens`, or `None` if there are none
+pub fn union_bounds(screens: &[ScreenArea]) -> Option<ScreenArea> {
+    let (first, rest) = screens.split_first()?;
+    let (mut left, mut top) = (first.0, first.1);
+    let (mut right, mut bottom) = (first.0 + first.2 as i32, first.1 + first.3 as i32);
+    for &(x, y, width, height) in rest {
+        left = left.min(x);
+        top = top.min(y);
+        right = right.max(x + width as i32);
+        bottom = bottom.max(y + height as i32);
+    }
+    Some((left, top, (right - left) as u32, (bottom - top) as u32))
+}
+
+/// Whether (x, y) lies inside `area`
+pub fn area_contains(area: ScreenArea, x: i32, y: i32) -> bool {
+    let (left, top, width, height) = area;
+    x >= left && y >= top && x < left + width as i32 && y < top + height as i32
+}
+
+/// A top-level window on the desktop
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WindowInfo {
+    /// Platform window handle
+    pub id: String,
+    pub title: String,
+    /// Window class (the owning application on macOS)
+    pub class: String,
+    /// ID of the owning process, 0 if unknown
+    pub pid: u32,
+    pub bounds: ScreenArea,
+    /// Stacking position, 0 for the topmost window
+    pub z_order: usize,
+}
+
+/// Kind of content stored in a clipboard entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ClipboardContentType {
+    Text,
+    Image,
+    Other,
+}
+
+/// A single item from the clipboard history
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClipboardEntry {
+    pub content: String,
+    pub content_type: ClipboardContentType,
+    pub timestamp: SystemTime,
+}
+
+/// Kinds of content the clipboard currently offers
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClipboardFormat {
+    pub text: bool,
+    pub image: bool,
+    pub html: bool,
+    pub files: bool,
+}
+
+impl ClipboardFormat {
+    /// Name of the function tool to read this content with, or `None` if the clipboard is empty
+    ///
+    /// Images and files have no text form, so they can only be pasted.
+    pub fn recommended_reader(&self) -> Option<&'static str> {
+        if self.text || self.html {
+            Some("get_clipboard")
+        } else if self.image || self.files {
+            Some("paste_from_clipboard")
+        } else {
+            None
+        }
+    }
+}
 
 /// Trait defining the interface for controlling a computer
 #[async_trait]
@@ -13,6 +499,20 @@ pub trait Computer: Send + Sync {
     /// Get the dimensions of the screen
     fn dimensions(&self) -> (u32, u32);
     
+    /// Get the last known cursor position
+    ///
+    /// Computers that do not track the cursor report the origin.
+    fn cursor_position(&self) -> (i32, i32) {
+        (0, 0)
+    }
+    
+    /// Common action names ("undo", "copy", "close_window", ...) mapped to their key combinations
+    ///
+    /// The default infers the platform from [`Computer::environment`].
+    fn keyboard_shortcut_map(&self) -> HashMap<String, Vec<String>> {
+        keyboard_shortcuts_for_environment(self.environment())
+    }
+    
     /// Take a screenshot and return it as a base64-encoded string
     async fn screenshot(&self) -> Result<String, CuaError>;
     
@@ -40,9 +540,584 @@ pub trait Computer: Send + Sync {
     /// Drag from one point to another
     async fn drag(&self, path: &[HashMap<String, i32>]) -> Result<(), CuaError>;
     
+    /// Press and hold a mouse button ("left", "right" or "middle") at the cursor
+    async fn mouse_button_down(&self, _button: &str) -> Result<(), CuaError> {
+        Err(CuaError::ActionError("Mouse button control not supported by this computer".to_string()))
+    }
+    
+    /// Release a mouse button held with `mouse_button_down`
+    async fn mouse_button_up(&self, _button: &str) -> Result<(), CuaError> {
+        Err(CuaError::ActionError("Mouse button control not supported by this computer".to_string()))
+    }
+    
+    /// Scroll horizontally at (x, y); positive `amount` scrolls right
+    async fn scroll_horizontal(&self, x: i32, y: i32, amount: i32) -> Result<(), CuaError> {
+        self.scroll(x, y, amount, 0).await
+    }
+    
+    /// Scroll vertically at (x, y); positive `amount` scrolls down
+    async fn scroll_vertical(&self, x: i32, y: i32, amount: i32) -> Result<(), CuaError> {
+        self.scroll(x, y, 0, amount).await
+    }
+    
+    /// Drag from one point to another along a straight line
+    async fn drag_and_drop(&self, from_x: i32, from_y: i32, to_x: i32, to_y: i32) -> Result<(), CuaError> {
+        self.drag(&interpolate_path((from_x, from_y), (to_x, to_y), DRAG_AND_DROP_STEPS)).await
+    }
+    
+    /// Drag from (start_x, start_y) by (delta_x, delta_y) in `steps` equal moves along a straight line
+    async fn drag_by_delta(&self, start_x: i32, start_y: i32, delta_x: i32, delta_y: i32, steps: u32) -> Result<(), CuaError> {
+        let end = start_x.checked_add(delta_x).zip(start_y.checked_add(delta_y)).ok_or_else(|| {
+            CuaError::ActionError(format!(
+                "Dragging from ({}, {}) by ({}, {}) goes past the coordinate range",
+                start_x, start_y, delta_x, delta_y
+            ))
+        })?;
+        let intermediate = steps.max(1) as usize - 1;
+        self.drag(&interpolate_path((start_x, start_y), end, intermediate)).await
+    }
+    
+    /// Triple-click at (x, y), e.g. to select a whole line or field
+    ///
+    /// The default implementation double-clicks and then clicks again.
+    async fn triple_click(&self, x: i32, y: i32) -> Result<(), CuaError> {
+        self.double_click(x, y).await?;
+        self.click(x, y, "left").await
+    }
+    
+    /// Middle-click at (x, y), e.g. to open a link in a new tab or paste the X11 primary selection
+    async fn mouse_wheel_click(&self, x: i32, y: i32) -> Result<(), CuaError> {
+        self.click(x, y, "middle").await
+    }
+    
+    /// Click the field at (x, y) and type `text` into it
+    async fn type_text_at(&self, x: i32, y: i32, text: &str) -> Result<(), CuaError> {
+        self.click(x, y, "left").await?;
+        self.type_text(text).await
+    }
+    
+    /// Triple-click the field at (x, y) to select its contents, then type `text` over them
+    async fn clear_and_type(&self, x: i32, y: i32, text: &str) -> Result<(), CuaError> {
+        self.triple_click(x, y).await?;
+        self.type_text(text).await
+    }
+    
+    /// Press a shortcut given as a human-readable string such as "Ctrl+Shift+T"
+    async fn send_hotkey(&self, hotkey: &str) -> Result<(), CuaError> {
+        self.keypress(&parse_hotkey(hotkey)?).await
+    }
+    
+    /// Move the cursor to (x, y) and keep it there, e.g. to bring up a tooltip
+    async fn hover(&self, x: i32, y: i32, duration_ms: u32) -> Result<(), CuaError> {
+        self.move_cursor(x, y).await?;
+        self.wait(duration_ms).await
+    }
+    
+    /// Hold `key` down for `duration_ms` milliseconds, e.g. for push-to-talk or continuous scrolling
+    ///
+    /// The default implementation can only tap the key, then waits out the duration.
+    async fn press_and_hold(&self, key: &str, duration_ms: u32) -> Result<(), CuaError> {
+        self.keypress(&[key.to_string()]).await?;
+        self.wait(duration_ms).await
+    }
+    
+    /// Zoom the screen to `factor` (1.0 = no zoom) around (cx, cy)
+    async fn zoom(&self, _factor: f32, _cx: i32, _cy: i32) -> Result<(), CuaError> {
+        Err(CuaError::ActionError("Zoom not supported by this computer".to_string()))
+    }
+    
+    /// Get the current zoom, for translating coordinates between zoomed and unzoomed space
+    fn zoom_state(&self) -> ZoomState {
+        ZoomState::default()
+    }
+    
+    /// Measure the round-trip time of a trivial action plus a screenshot
+    ///
+    /// Moves the cursor to where it already is and takes a screenshot, giving
+    /// a baseline for how long actions take on this system.
+    async fn measure_latency(&self) -> Result<Duration, CuaError> {
+        let start = Instant::now();
+        let (x, y) = self.cursor_position();
+        self.move_cursor(x, y).await?;
+        self.screenshot().await?;
+        Ok(start.elapsed())
+    }
+    
+    /// On-screen position of the text insertion caret in the focused element
+    async fn get_text_cursor_position(&self) -> Result<(i32, i32), CuaError> {
+        Err(CuaError::ActionError("Text cursor lookup not supported by this computer".to_string()))
+    }
+    
+    /// Region that screen capture can actually reach, as (x, y, width, height)
+    ///
+    /// Unlike `dimensions()`, this spans every monitor. Defaults to the configured display size.
+    async fn get_screen_recording_area(&self) -> Result<ScreenArea, CuaError> {
+        let (width, height) = self.dimensions();
+        Ok((0, 0, width, height))
+    }
+    
+    /// Capture the `w`x`h` region at (x, y) as a base64 PNG, naming it `label`
+    ///
+    /// Implementations may cache captures by label; the default crops a fresh screenshot.
+    async fn capture_element_screenshot(&self, x: u32, y: u32, w: u32, h: u32, _label: &str) -> Result<String, CuaError> {
+        vision::crop_screenshot(&self.screenshot().await?, x, y, w, h)
+    }
+    
+    /// Take a screenshot of only the first window whose title contains `title_pattern` (case-insensitive)
+    async fn window_screenshot(&self, _title_pattern: &str) -> Result<String, CuaError> {
+        Err(CuaError::ScreenshotError("Window capture not supported by this computer".to_string()))
+    }
+    
+    /// List the visible top-level windows, topmost first
+    async fn get_window_list(&self) -> Result<Vec<WindowInfo>, CuaError> {
+        Err(CuaError::ActionError("Window listing not supported by this computer".to_string()))
+    }
+    
+    /// Outline the `w`x`h` region at (x, y) in `color` for `duration_ms`, for a human watching the session
+    ///
+    /// Returns once the outline is drawn, which then disappears by itself.
+    /// Purely cosmetic: callers should log failures rather than stop.
+    async fn highlight_region(&self, _x: u32, _y: u32, _w: u32, _h: u32, _color: &str, _duration_ms: u32) -> Result<(), CuaError> {
+        Err(CuaError::ActionError("Region highlighting not supported by this computer".to_string()))
+    }
+    
+    /// Read an environment variable, failing with `CuaError::SafetyError` unless it is allowlisted
+    async fn read_env_var(&self, _name: &str) -> Result<String, CuaError> {
+        Err(CuaError::ActionError("Reading environment variables not supported by this computer".to_string()))
+    }
+    
+    /// Await `action`, and if it fails, save a screenshot to a temp file and report its path
+    ///
+    /// The error keeps its variant, with the path added as context (see
+    /// [`CuaError::with_context`]). Takes a boxed future so the method stays callable
+    /// on `dyn Computer`. If the screenshot cannot be taken or saved, the original
+    /// error is returned unchanged.
+    async fn take_screenshot_on_error(&self, action: BoxFuture<'_, Result<(), CuaError>>) -> Result<(), CuaError> {
+        let err = match action.await {
+            Ok(()) => return Ok(()),
+            Err(err) => err,
+        };
+        
+        let Ok(screenshot) = self.screenshot().await else { return Err(err) };
+        let Ok(bytes) = general_purpose::STANDARD.decode(screenshot) else { return Err(err) };
+        let screenshot_path = std::env::temp_dir().join(format!("cua_error_{}.png", uuid::Uuid::new_v4()));
+        if let Err(write_err) = tokio::fs::write(&screenshot_path, bytes).await {
+            tracing::warn!("Failed to save error screenshot to {}: {}", screenshot_path.display(), write_err);
+            return Err(err);
+        }
+        
+        Err(err.with_context(&format!("screenshot: {}", screenshot_path.display())))
+    }
+    
+    /// Change the display resolution, e.g. of a virtual display to match the model's expectations
+    async fn set_display_resolution(&self, _width: u32, _height: u32) -> Result<(), CuaError> {
+        Err(CuaError::ActionError("Setting the display resolution not supported by this computer".to_string()))
+    }
+    
+    /// Take a screenshot as a base64 JPEG, with `quality` clamped to 1-95
+    ///
+    /// Measured on a 1920x1080 crop of a real browser screenshot of a
+    /// documentation page (256 KB of base64 PNG), quality 95 gives 121% of the
+    /// PNG size, 90 gives 98%, 75 gives 74% and 50 gives 60%. Flat UI and text
+    /// compress well as PNG, so JPEG mainly pays off below quality 90 or on
+    /// photos and gradients, at the cost of legibility of small text.
+    /// The default implementation re-encodes `screenshot()`.
+    async fn screenshot_jpeg(&self, quality: u8) -> Result<String, CuaError> {
+        let (mut width, mut height) = (0, 0);
+        let data = self.screenshot_as_array(&mut width, &mut height).await?;
+        vision::encode_jpeg(&data, width, height, quality)
+    }
+    
+    /// Capture every screen as (screen index, base64 PNG)
+    ///
+    /// The default implementation returns `screenshot()` as the only screen.
+    async fn capture_all_screens(&self) -> Result<Vec<(usize, String)>, CuaError> {
+        Ok(vec![(0, self.screenshot().await?)])
+    }
+    
+    /// Bounds of every screen as (x, y, width, height), indexed like `capture_all_screens`
+    ///
+    /// The default reports the configured display size as the only screen.
+    async fn screen_bounds(&self) -> Result<Vec<ScreenArea>, CuaError> {
+        let (width, height) = self.dimensions();
+        Ok(vec![(0, 0, width, height)])
+    }
+    
+    /// Index in `capture_all_screens` of the screen `screenshot` captures
+    fn primary_screen(&self) -> usize {
+        0
+    }
+    
+    /// Screenshot formats this computer can capture, with the highest quality of lossy ones
+    fn screenshot_format_support(&self) -> Vec<ScreenshotFormat> {
+        vec![ScreenshotFormat::Png, ScreenshotFormat::Jpeg(vision::MAX_JPEG_QUALITY)]
+    }
+    
+    /// Take a screenshot as raw RGBA bytes, row by row, storing its size in `width` and `height`
+    ///
+    /// The default implementation decodes `screenshot()`; use
+    /// `vision::raw_to_base64_png` to turn the bytes back into a PNG.
+    async fn screenshot_as_array(&self, width: &mut u32, height: &mut u32) -> Result<Vec<u8>, CuaError> {
+        let image = decode_screenshot(&self.screenshot().await?)?.to_rgba8();
+        (*width, *height) = image.dimensions();
+        Ok(image.into_raw())
+    }
+    
+    /// Take a screenshot with the mouse cursor drawn at `cursor_position()`
+    ///
+    /// System screenshots usually leave the cursor out.
+    async fn screenshot_with_cursor(&self) -> Result<String, CuaError> {
+        let screenshot = self.screenshot().await?;
+        let (x, y) = self.cursor_position();
+        vision::draw_cursor(&screenshot, x, y)
+    }
+    
+    /// Get the RGB color of the screen pixel at (x, y)
+    ///
+    /// The default implementation captures a full screenshot.
+    async fn screen_color_at(&self, x: u32, y: u32) -> Result<(u8, u8, u8), CuaError> {
+        let (mut width, mut height) = (0, 0);
+        let data = self.screenshot_as_array(&mut width, &mut height).await?;
+        if x >= width || y >= height {
+            return Err(CuaError::ActionError(format!(
+                "Pixel ({}, {}) is outside the {}x{} screen",
+                x, y, width, height
+            )));
+        }
+        let offset = (y as usize * width as usize + x as usize) * 4;
+        Ok((data[offset], data[offset + 1], data[offset + 2]))
+    }
+    
+    /// Paste the clipboard with the OS-native shortcut (Ctrl+V or Cmd+V)
+    async fn paste_from_clipboard(&self) -> Result<(), CuaError> {
+        self.keypress(&PasteKey::for_environment(self.environment()).shortcut("v")).await
+    }
+    
+    /// Copy the current selection with the OS-native shortcut (Ctrl+C or Cmd+C)
+    async fn copy_selection(&self) -> Result<(), CuaError> {
+        self.keypress(&PasteKey::for_environment(self.environment()).shortcut("c")).await
+    }
+    
+    /// Select all text in the focused element with the OS-native shortcut (Ctrl+A or Cmd+A)
+    async fn select_all_text(&self) -> Result<(), CuaError> {
+        self.keypress(&PasteKey::for_environment(self.environment()).shortcut("a")).await
+    }
+    
+    /// Copy the current selection and return it as read back from the clipboard
+    async fn copy_selected_text(&self) -> Result<String, CuaError> {
+        self.copy_selection().await?;
+        self.get_clipboard().await
+    }
+    
+    /// Get all text in the focused element by selecting and copying it
+    ///
+    /// Overwrites the clipboard.
+    async fn get_focused_text(&self) -> Result<String, CuaError> {
+        self.select_all_text().await?;
+        self.copy_selected_text().await
+    }
+    
+    /// Recognize the words on a screenshot
+    ///
+    /// The default implementation runs the `tesseract` command line tool.
+    async fn ocr_extract(&self, screenshot_base64: &str) -> Result<Vec<OcrWord>, CuaError> {
+        ocr::ocr_extract(screenshot_base64).await
+    }
+    
+    /// Read the text in the `w`x`h` screen region at (x, y)
+    ///
+    /// Only the region is passed to OCR, which is much faster than the full screen.
+    async fn read_screen_text(&self, x: u32, y: u32, w: u32, h: u32) -> Result<String, CuaError> {
+        let region = vision::crop_screenshot(&self.screenshot().await?, x, y, w, h)?;
+        Ok(ocr::words_to_text(&self.ocr_extract(&region).await?))
+    }
+    
+    /// Recognize all text on the screen, one line per OCR line in reading order
+    async fn screenshot_as_text(&self) -> Result<String, CuaError> {
+        Ok(ocr::words_to_text(&self.ocr_extract(&self.screenshot().await?).await?))
+    }
+    
+    /// Whether `text` is currently visible anywhere on screen (case-insensitive)
+    ///
+    /// Empty text is never reported as visible.
+    async fn assert_text_visible(&self, text: &str) -> Result<bool, CuaError> {
+        let words = self.ocr_extract(&self.screenshot().await?).await?;
+        Ok(ocr::find_text(&words, text).is_some())
+    }
+    
+    /// Whether `text` is currently visible inside the `w`x`h` screen region at (x, y) (case-insensitive)
+    ///
+    /// Only words lying entirely inside the region count. Empty text is never
+    /// reported as visible.
+    async fn assert_text_visible_within(&self, text: &str, x: u32, y: u32, w: u32, h: u32) -> Result<bool, CuaError> {
+        let words = self.ocr_extract(&self.screenshot().await?).await?;
+        let words = ocr::words_within(words, x.into(), y.into(), w.into(), h.into());
+        Ok(ocr::find_text(&words, text).is_some())
+    }
+    
+    /// Get the modal dialog currently on screen, if any
+    async fn current_dialog(&self) -> Result<Option<DialogInfo>, CuaError> {
+        Err(CuaError::ActionError("Dialog detection not supported by this computer".to_string()))
+    }
+    
+    /// Wait up to `timeout_ms` for a modal dialog to appear
+    ///
+    /// Checks immediately, then every [`DIALOG_POLL_INTERVAL`]; returns `None` on timeout.
+    async fn detect_dialog(&self, timeout_ms: u32) -> Result<Option<DialogInfo>, CuaError> {
+        let polls = timeout_ms as u128 / DIALOG_POLL_INTERVAL.as_millis();
+        for poll in 0..=polls {
+            if poll > 0 {
+                tokio::time::sleep(DIALOG_POLL_INTERVAL).await;
+            }
+            if let Some(dialog) = self.current_dialog().await? {
+                return Ok(Some(dialog));
+            }
+        }
+        Ok(None)
+    }
+    
+    /// Wait up to `timeout_ms` for a screenshot that satisfies `condition`, and return it
+    ///
+    /// `condition` receives the base64 screenshot. It is checked immediately, then every
+    /// [`WAIT_UNTIL_POLL_INTERVAL`], on a blocking thread so it may do slow work such as OCR.
+    async fn wait_until(&self, condition: ScreenCondition, timeout_ms: u32) -> Result<String, CuaError> {
+        let mut condition = condition;
+        let polls = timeout_ms as u128 / WAIT_UNTIL_POLL_INTERVAL.as_millis();
+        for poll in 0..=polls {
+            if poll > 0 {
+                tokio::time::sleep(WAIT_UNTIL_POLL_INTERVAL).await;
+            }
+            let screenshot = self.screenshot().await?;
+            let (returned, screenshot, met) = tokio::task::spawn_blocking(move || {
+                let met = condition(&screenshot);
+                (condition, screenshot, met)
+            })
+            .await
+            .map_err(|e| CuaError::Other(format!("wait_until condition failed: {}", e)))?;
+            if met {
+                return Ok(screenshot);
+            }
+            condition = returned;
+        }
+        Err(CuaError::ActionError(format!("Condition not met within {}ms", timeout_ms)))
+    }
+    
+    /// Click the button labeled `button_text` (case-insensitive) on the current modal dialog
+    ///
+    /// The button is located through the accessibility tree when the computer
+    /// supports it, otherwise by OCR inside the dialog's bounds.
+    async fn dismiss_dialog(&self, button_text: &str) -> Result<(), CuaError> {
+        let dialog = self.detect_dialog(500).await?
+            .ok_or_else(|| CuaError::ActionError("No dialog to dismiss".to_string()))?;
+        
+        let from_tree = match self.accessibility_tree().await {
+            Ok(tree) => buttons_within(&tree, dialog.bounds).into_iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(button_text.trim()))
+                .map(|(_, (x, y, width, height))| (x + (width / 2) as i32, y + (height / 2) as i32)),
+            Err(_) => None,
+        };
+        let center = match from_tree {
+            Some(center) => Some(center),
+            None => {
+                let words = self.ocr_extract(&self.screenshot().await?).await?;
+                let (x, y, width, height) = dialog.bounds;
+                let words = ocr::words_within(words, x.into(), y.into(), width.into(), height.into());
+                ocr::find_text(&words, button_text)
+            }
+        };
+        
+        let (x, y) = center.ok_or_else(|| CuaError::ActionError(format!(
+            "Dialog '{}' has no '{}' button (buttons: {})",
+            dialog.title, button_text, dialog.buttons.join(", ")
+        )))?;
+        self.click(x, y, "left").await
+    }
+    
+    /// Whether a text cursor is blinking, judged from two screenshots 100 ms apart
+    async fn text_cursor_blinking(&self) -> Result<bool, CuaError> {
+        let before = self.screenshot().await?;
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let after = self.screenshot().await?;
+        Ok(vision::screenshot_diff(&before, &after)?.is_some_and(is_cursor_blink))
+    }
+    
+    /// Click the text field at (x, y) until a blinking text cursor shows it has focus
+    ///
+    /// Tries [`FOCUS_ATTEMPTS`] single clicks, then a double click.
+    async fn focus_input_field(&self, x: i32, y: i32) -> Result<(), CuaError> {
+        for _ in 0..FOCUS_ATTEMPTS {
+            self.click(x, y, "left").await?;
+            if self.text_cursor_blinking().await? {
+                return Ok(());
+            }
+        }
+        
+        self.double_click(x, y).await?;
+        if self.text_cursor_blinking().await? {
+            return Ok(());
+        }
+        Err(CuaError::ActionError("could not focus field".to_string()))
+    }
+    
+    /// Scroll down until text matching `element_description` is visible and return its center
+    ///
+    /// Scrolls half a screen at a time, at most `max_scrolls` times.
+    async fn scroll_to_element(&self, element_description: &str, max_scrolls: u32) -> Result<(i32, i32), CuaError> {
+        let (width, height) = self.dimensions();
+        let (center_x, center_y) = ((width / 2) as i32, (height / 2) as i32);
+        
+        for scrolls in 0..=max_scrolls {
+            let screenshot = self.screenshot().await?;
+            let words = self.ocr_extract(&screenshot).await?;
+            if let Some(position) = ocr::find_text(&words, element_description) {
+                return Ok(position);
+            }
+            if scrolls < max_scrolls {
+                self.scroll(center_x, center_y, 0, center_y).await?;
+            }
+        }
+        
+        Err(CuaError::ActionError(format!("element not found: {}", element_description)))
+    }
+    
     /// Get the current URL (for browser environments)
     async fn get_current_url(&self) -> Result<String, CuaError>;
     
+    /// Get the HTML source of the current page (for browser environments)
+    async fn get_page_source(&self) -> Result<String, CuaError> {
+        Err(CuaError::ActionError("Page source not supported by this computer".to_string()))
+    }
+    
+    /// Read the current text content of the clipboard
+    async fn get_clipboard(&self) -> Result<String, CuaError> {
+        Err(CuaError::ActionError("Clipboard access not supported by this computer".to_string()))
+    }
+    
+    /// Replace the clipboard content with the given text
+    async fn set_clipboard(&self, _text: &str) -> Result<(), CuaError> {
+        Err(CuaError::ActionError("Clipboard access not supported by this computer".to_string()))
+    }
+    
+    /// Replace the clipboard content with HTML, for pasting into rich-text editors
+    async fn set_clipboard_html(&self, _html: &str) -> Result<(), CuaError> {
+        Err(CuaError::ActionError("HTML clipboard content not supported by this computer".to_string()))
+    }
+    
+    /// Get the kinds of content on the clipboard, to pick how to read it
+    async fn get_clipboard_format(&self) -> Result<ClipboardFormat, CuaError> {
+        Err(CuaError::ActionError("Clipboard format detection not supported by this computer".to_string()))
+    }
+    
+    /// Get up to `max_entries` clipboard history entries, most recent first
+    async fn clipboard_history(&self, _max_entries: usize) -> Result<Vec<ClipboardEntry>, CuaError> {
+        Err(CuaError::ActionError("Clipboard history not supported by this computer".to_string()))
+    }
+    
+    /// Watch the clipboard for `duration_ms` and return each distinct non-empty value seen, oldest first
+    ///
+    /// Polls `get_clipboard` every [`CLIPBOARD_POLL_INTERVAL`].
+    async fn monitor_clipboard_changes(&self, duration_ms: u32) -> Result<Vec<String>, CuaError> {
+        let polls = duration_ms as u128 / CLIPBOARD_POLL_INTERVAL.as_millis();
+        let mut values = Vec::new();
+        for poll in 0..=polls {
+            if poll > 0 {
+                tokio::time::sleep(CLIPBOARD_POLL_INTERVAL).await;
+            }
+            let value = self.get_clipboard().await?;
+            if !value.is_empty() && !values.contains(&value) {
+                values.push(value);
+            }
+        }
+        Ok(values)
+    }
+    
+    /// Whether the clipboard holds no text
+    async fn clipboard_is_empty(&self) -> Result<bool, CuaError> {
+        Ok(self.get_clipboard().await?.is_empty())
+    }
+    
+    /// Wait up to `timeout_ms` for the clipboard to change and return its new content
+    ///
+    /// Polls `get_clipboard` every [`CLIPBOARD_POLL_INTERVAL`].
+    async fn wait_for_clipboard_change(&self, timeout_ms: u32) -> Result<String, CuaError> {
+        let initial = self.get_clipboard().await?;
+        let deadline = tokio::time::Instant::now() + Duration::from_millis(timeout_ms as u64);
+        loop {
+            let value = self.get_clipboard().await?;
+            if value != initial {
+                return Ok(value);
+            }
+            if tokio::time::Instant::now() >= deadline {
+                return Err(CuaError::ActionError(format!("timeout: clipboard did not change within {}ms", timeout_ms)));
+            }
+            tokio::time::sleep(CLIPBOARD_POLL_INTERVAL).await;
+        }
+    }
+    
+    /// Get the logical DPI of the screen, [`BASE_DPI`] times the display scale factor
+    async fn get_screen_dpi(&self) -> Result<f32, CuaError> {
+        Ok(BASE_DPI)
+    }
+    
+    /// Convert logical coordinates (CSS pixels) to physical screen pixels using the screen DPI
+    async fn logical_to_physical(&self, x: f32, y: f32) -> Result<(i32, i32), CuaError> {
+        Ok(logical_to_physical_at(self.get_screen_dpi().await?, x, y))
+    }
+    
+    /// Convert physical screen pixels to logical coordinates (CSS pixels) using the screen DPI
+    async fn physical_to_logical(&self, x: i32, y: i32) -> Result<(f32, f32), CuaError> {
+        Ok(physical_to_logical_at(self.get_screen_dpi().await?, x, y))
+    }
+    
+    /// Get the application that currently has keyboard focus
+    async fn get_focused_application(&self) -> Result<ApplicationInfo, CuaError> {
+        Err(CuaError::ActionError("Focused application lookup not supported by this computer".to_string()))
+    }
+    
+    /// Get (process name, memory in bytes) for every running process, using the most memory first
+    async fn get_running_processes_memory(&self) -> Result<Vec<(String, u64)>, CuaError> {
+        Err(CuaError::ActionError("Process memory listing not supported by this computer".to_string()))
+    }
+    
+    /// Get the accessibility tree of the desktop
+    async fn accessibility_tree(&self) -> Result<AccessibilityNode, CuaError> {
+        Err(CuaError::ActionError("Accessibility tree not supported by this computer".to_string()))
+    }
+    
+    /// Run `script` as "applescript", "powershell" or "bash" and return its stdout
+    async fn execute_script(&self, _script: &str, _lang: &str) -> Result<String, CuaError> {
+        Err(CuaError::ActionError("Script execution not supported by this computer".to_string()))
+    }
+    
+    /// Show `message` to the user as a desktop notification for about `duration_ms`
+    async fn notify(&self, _message: &str, _duration_ms: u32) -> Result<(), CuaError> {
+        Err(CuaError::ActionError("Notifications not supported by this computer".to_string()))
+    }
+    
+    /// Battery charge as a fraction from 0.0 to 1.0, or `None` on systems without a battery
+    async fn get_battery_level(&self) -> Result<Option<f32>, CuaError> {
+        Ok(None)
+    }
+    
+    /// Whether `simulate_network_condition` and `clear_network_conditions` work on this computer
+    ///
+    /// The agent only offers the network tools to the model when they do.
+    fn supports_network_emulation(&self) -> bool {
+        false
+    }
+    
+    /// Emulate a slow, lossy network (for browser environments)
+    ///
+    /// `packet_loss` is the fraction of requests dropped, from 0.0 to 1.0.
+    async fn simulate_network_condition(&self, _latency_ms: u32, _packet_loss: f32) -> Result<(), CuaError> {
+        Err(CuaError::ActionError("Network emulation not supported by this computer".to_string()))
+    }
+    
+    /// Restore normal network conditions after `simulate_network_condition`
+    async fn clear_network_conditions(&self) -> Result<(), CuaError> {
+        Err(CuaError::ActionError("Network emulation not supported by this computer".to_string()))
+    }
+    
     /// Navigate to a URL (for browser environments)
     /// Default implementation returns an error for non-browser environments
     async fn goto(&self, _url: &str) -> Result<(), CuaError> {
@@ -52,4 +1127,643 @@ pub trait Computer: Send + Sync {
             Err(CuaError::ActionError("Cannot navigate to URL in non-browser environment".to_string()))
         }
     }
-}
\ No newline at end of file
+}
+
+/// Forward to the pointee, so `Box<dyn Computer>` and `Arc<dyn Computer>` can be used where a `Computer` type is expected
+macro_rules! forward_computer {
+    ($pointer:ident) => {
+        #[async_trait]
+        impl<T: Computer + ?Sized> Computer for $pointer<T> {
+            fn environment(&self) -> &str {
+                (**self).environment()
+            }
+            
+            fn dimensions(&self) -> (u32, u32) {
+                (**self).dimensions()
+            }
+            
+            fn cursor_position(&self) -> (i32, i32) {
+                (**self).cursor_position()
+            }
+            
+            fn keyboard_shortcut_map(&self) -> HashMap<String, Vec<String>> {
+                (**self).keyboard_shortcut_map()
+            }
+            
+            async fn screenshot(&self) -> Result<String, CuaError> {
+                (**self).screenshot().await
+            }
+            
+            async fn click(&self, x: i32, y: i32, button: &str) -> Result<(), CuaError> {
+                (**self).click(x, y, button).await
+            }
+            
+            async fn double_click(&self, x: i32, y: i32) -> Result<(), CuaError> {
+                (**self).double_click(x, y).await
+            }
+            
+            async fn scroll(&self, x: i32, y: i32, scroll_x: i32, scroll_y: i32) -> Result<(), CuaError> {
+                (**self).scroll(x, y, scroll_x, scroll_y).await
+            }
+            
+            async fn type_text(&self, text: &str) -> Result<(), CuaError> {
+                (**self).type_text(text).await
+            }
+            
+            async fn wait(&self, ms: u32) -> Result<(), CuaError> {
+                (**self).wait(ms).await
+            }
+            
+            async fn move_cursor(&self, x: i32, y: i32) -> Result<(), CuaError> {
+                (**self).move_cursor(x, y).await
+            }
+            
+            async fn keypress(&self, keys: &[String]) -> Result<(), CuaError> {
+                (**self).keypress(keys).await
+            }
+            
+            async fn drag(&self, path: &[HashMap<String, i32>]) -> Result<(), CuaError> {
+                (**self).drag(path).await
+            }
+            
+            async fn mouse_button_down(&self, button: &str) -> Result<(), CuaError> {
+                (**self).mouse_button_down(button).await
+            }
+            
+            async fn mouse_button_up(&self, button: &str) -> Result<(), CuaError> {
+                (**self).mouse_button_up(button).await
+            }
+            
+            async fn scroll_horizontal(&self, x: i32, y: i32, amount: i32) -> Result<(), CuaError> {
+                (**self).scroll_horizontal(x, y, amount).await
+            }
+            
+            async fn scroll_vertical(&self, x: i32, y: i32, amount: i32) -> Result<(), CuaError> {
+                (**self).scroll_vertical(x, y, amount).await
+            }
+            
+            async fn drag_and_drop(&self, from_x: i32, from_y: i32, to_x: i32, to_y: i32) -> Result<(), CuaError> {
+                (**self).drag_and_drop(from_x, from_y, to_x, to_y).await
+            }
+            
+            async fn drag_by_delta(&self, start_x: i32, start_y: i32, delta_x: i32, delta_y: i32, steps: u32) -> Result<(), CuaError> {
+                (**self).drag_by_delta(start_x, start_y, delta_x, delta_y, steps).await
+            }
+            
+            async fn triple_click(&self, x: i32, y: i32) -> Result<(), CuaError> {
+                (**self).triple_click(x, y).await
+            }
+            
+            async fn mouse_wheel_click(&self, x: i32, y: i32) -> Result<(), CuaError> {
+                (**self).mouse_wheel_click(x, y).await
+            }
+            
+            async fn type_text_at(&self, x: i32, y: i32, text: &str) -> Result<(), CuaError> {
+                (**self).type_text_at(x, y, text).await
+            }
+            
+            async fn clear_and_type(&self, x: i32, y: i32, text: &str) -> Result<(), CuaError> {
+                (**self).clear_and_type(x, y, text).await
+            }
+            
+            async fn send_hotkey(&self, hotkey: &str) -> Result<(), CuaError> {
+                (**self).send_hotkey(hotkey).await
+            }
+            
+            async fn hover(&self, x: i32, y: i32, duration_ms: u32) -> Result<(), CuaError> {
+                (**self).hover(x, y, duration_ms).await
+            }
+            
+            async fn press_and_hold(&self, key: &str, duration_ms: u32) -> Result<(), CuaError> {
+                (**self).press_and_hold(key, duration_ms).await
+            }
+            
+            async fn zoom(&self, factor: f32, cx: i32, cy: i32) -> Result<(), CuaError> {
+                (**self).zoom(factor, cx, cy).await
+            }
+            
+            fn zoom_state(&self) -> ZoomState {
+                (**self).zoom_state()
+            }
+            
+            async fn measure_latency(&self) -> Result<Duration, CuaError> {
+                (**self).measure_latency().await
+            }
+            
+            async fn get_text_cursor_position(&self) -> Result<(i32, i32), CuaError> {
+                (**self).get_text_cursor_position().await
+            }
+            
+            async fn get_screen_recording_area(&self) -> Result<ScreenArea, CuaError> {
+                (**self).get_screen_recording_area().await
+            }
+            
+            async fn capture_element_screenshot(&self, x: u32, y: u32, w: u32, h: u32, label: &str) -> Result<String, CuaError> {
+                (**self).capture_element_screenshot(x, y, w, h, label).await
+            }
+            
+            async fn window_screenshot(&self, title_pattern: &str) -> Result<String, CuaError> {
+                (**self).window_screenshot(title_pattern).await
+            }
+            
+            async fn get_window_list(&self) -> Result<Vec<WindowInfo>, CuaError> {
+                (**self).get_window_list().await
+            }
+            
+            async fn read_env_var(&self, name: &str) -> Result<String, CuaError> {
+                (**self).read_env_var(name).await
+            }
+            
+            async fn highlight_region(&self, x: u32, y: u32, w: u32, h: u32, color: &str, duration_ms: u32) -> Result<(), CuaError> {
+                (**self).highlight_region(x, y, w, h, color, duration_ms).await
+            }
+            
+            async fn take_screenshot_on_error(&self, action: BoxFuture<'_, Result<(), CuaError>>) -> Result<(), CuaError> {
+                (**self).take_screenshot_on_error(action).await
+            }
+            
+            async fn set_display_resolution(&self, width: u32, height: u32) -> Result<(), CuaError> {
+                (**self).set_display_resolution(width, height).await
+            }
+            
+            async fn screenshot_jpeg(&self, quality: u8) -> Result<String, CuaError> {
+                (**self).screenshot_jpeg(quality).await
+            }
+            
+            async fn capture_all_screens(&self) -> Result<Vec<(usize, String)>, CuaError> {
+                (**self).capture_all_screens().await
+            }
+            
+            async fn screen_bounds(&self) -> Result<Vec<ScreenArea>, CuaError> {
+                (**self).screen_bounds().await
+            }
+            
+            fn primary_screen(&self) -> usize {
+                (**self).primary_screen()
+            }
+            
+            fn screenshot_format_support(&self) -> Vec<ScreenshotFormat> {
+                (**self).screenshot_format_support()
+            }
+            
+            fn supports_network_emulation(&self) -> bool {
+                (**self).supports_network_emulation()
+            }
+            
+            async fn screenshot_as_array(&self, width: &mut u32, height: &mut u32) -> Result<Vec<u8>, CuaError> {
+                (**self).screenshot_as_array(width, height).await
+            }
+            
+            async fn screenshot_with_cursor(&self) -> Result<String, CuaError> {
+                (**self).screenshot_with_cursor().await
+            }
+            
+            async fn screen_color_at(&self, x: u32, y: u32) -> Result<(u8, u8, u8), CuaError> {
+                (**self).screen_color_at(x, y).await
+            }
+            
+            async fn paste_from_clipboard(&self) -> Result<(), CuaError> {
+                (**self).paste_from_clipboard().await
+            }
+            
+            async fn copy_selection(&self) -> Result<(), CuaError> {
+                (**self).copy_selection().await
+            }
+            
+            async fn select_all_text(&self) -> Result<(), CuaError> {
+                (**self).select_all_text().await
+            }
+            
+            async fn copy_selected_text(&self) -> Result<String, CuaError> {
+                (**self).copy_selected_text().await
+            }
+            
+            async fn get_focused_text(&self) -> Result<String, CuaError> {
+                (**self).get_focused_text().await
+            }
+            
+            async fn ocr_extract(&self, screenshot_base64: &str) -> Result<Vec<OcrWord>, CuaError> {
+                (**self).ocr_extract(screenshot_base64).await
+            }
+            
+            async fn read_screen_text(&self, x: u32, y: u32, w: u32, h: u32) -> Result<String, CuaError> {
+                (**self).read_screen_text(x, y, w, h).await
+            }
+            
+            async fn screenshot_as_text(&self) -> Result<String, CuaError> {
+                (**self).screenshot_as_text().await
+            }
+            
+            async fn assert_text_visible(&self, text: &str) -> Result<bool, CuaError> {
+                (**self).assert_text_visible(text).await
+            }
+            
+            async fn assert_text_visible_within(&self, text: &str, x: u32, y: u32, w: u32, h: u32) -> Result<bool, CuaError> {
+                (**self).assert_text_visible_within(text, x, y, w, h).await
+            }
+            
+            async fn current_dialog(&self) -> Result<Option<DialogInfo>, CuaError> {
+                (**self).current_dialog().await
+            }
+            
+            async fn detect_dialog(&self, timeout_ms: u32) -> Result<Option<DialogInfo>, CuaError> {
+                (**self).detect_dialog(timeout_ms).await
+            }
+            
+            async fn wait_until(&self, condition: ScreenCondition, timeout_ms: u32) -> Result<String, CuaError> {
+                (**self).wait_until(condition, timeout_ms).await
+            }
+            
+            async fn dismiss_dialog(&self, button_text: &str) -> Result<(), CuaError> {
+                (**self).dismiss_dialog(button_text).await
+            }
+            
+            async fn text_cursor_blinking(&self) -> Result<bool, CuaError> {
+                (**self).text_cursor_blinking().await
+            }
+            
+            async fn focus_input_field(&self, x: i32, y: i32) -> Result<(), CuaError> {
+                (**self).focus_input_field(x, y).await
+            }
+            
+            async fn scroll_to_element(&self, element_description: &str, max_scrolls: u32) -> Result<(i32, i32), CuaError> {
+                (**self).scroll_to_element(element_description, max_scrolls).await
+            }
+            
+            async fn get_current_url(&self) -> Result<String, CuaError> {
+                (**self).get_current_url().await
+            }
+            
+            async fn get_page_source(&self) -> Result<String, CuaError> {
+                (**self).get_page_source().await
+            }
+            
+            async fn get_clipboard(&self) -> Result<String, CuaError> {
+                (**self).get_clipboard().await
+            }
+            
+            async fn set_clipboard(&self, text: &str) -> Result<(), CuaError> {
+                (**self).set_clipboard(text).await
+            }
+            
+            async fn clipboard_history(&self, max_entries: usize) -> Result<Vec<ClipboardEntry>, CuaError> {
+                (**self).clipboard_history(max_entries).await
+            }
+            
+            async fn get_clipboard_format(&self) -> Result<ClipboardFormat, CuaError> {
+                (**self).get_clipboard_format().await
+            }
+            
+            async fn set_clipboard_html(&self, html: &str) -> Result<(), CuaError> {
+                (**self).set_clipboard_html(html).await
+            }
+            
+            async fn get_screen_dpi(&self) -> Result<f32, CuaError> {
+                (**self).get_screen_dpi().await
+            }
+            
+            async fn logical_to_physical(&self, x: f32, y: f32) -> Result<(i32, i32), CuaError> {
+                (**self).logical_to_physical(x, y).await
+            }
+            
+            async fn physical_to_logical(&self, x: i32, y: i32) -> Result<(f32, f32), CuaError> {
+                (**self).physical_to_logical(x, y).await
+            }
+            
+            async fn monitor_clipboard_changes(&self, duration_ms: u32) -> Result<Vec<String>, CuaError> {
+                (**self).monitor_clipboard_changes(duration_ms).await
+            }
+            
+            async fn clipboard_is_empty(&self) -> Result<bool, CuaError> {
+                (**self).clipboard_is_empty().await
+            }
+            
+            async fn wait_for_clipboard_change(&self, timeout_ms: u32) -> Result<String, CuaError> {
+                (**self).wait_for_clipboard_change(timeout_ms).await
+            }
+            
+            async fn get_focused_application(&self) -> Result<ApplicationInfo, CuaError> {
+                (**self).get_focused_application().await
+            }
+            
+            async fn get_running_processes_memory(&self) -> Result<Vec<(String, u64)>, CuaError> {
+                (**self).get_running_processes_memory().await
+            }
+            
+            async fn accessibility_tree(&self) -> Result<AccessibilityNode, CuaError> {
+                (**self).accessibility_tree().await
+            }
+            
+            async fn execute_script(&self, script: &str, lang: &str) -> Result<String, CuaError> {
+                (**self).execute_script(script, lang).await
+            }
+            
+            async fn notify(&self, message: &str, duration_ms: u32) -> Result<(), CuaError> {
+                (**self).notify(message, duration_ms).await
+            }
+            
+            async fn get_battery_level(&self) -> Result<Option<f32>, CuaError> {
+                (**self).get_battery_level().await
+            }
+            
+            async fn simulate_network_condition(&self, latency_ms: u32, packet_loss: f32) -> Result<(), CuaError> {
+                (**self).simulate_network_condition(latency_ms, packet_loss).await
+            }
+            
+            async fn clear_network_conditions(&self) -> Result<(), CuaError> {
+                (**self).clear_network_conditions().await
+            }
+            
+            async fn goto(&self, url: &str) -> Result<(), CuaError> {
+                (**self).goto(url).await
+            }
+        }
+    };
+}
+
+forward_computer!(Box);
+forward_computer!(Arc);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockComputer;
+    
+    #[test]
+    fn test_strip_script_tags_and_limit_page_source() {
+        let html = "<html><head><SCRIPT type=\"text/javascript\">alert('<b>')</Script><scripts>kept</scripts></head>\
+                    <body><input id=\"email\"><script src=\"app.js\"></script></body></html>";
+        
+        assert_eq!(
+            strip_script_tags(html),
+            "<html><head><scripts>kept</scripts></head><body><input id=\"email\"></body></html>"
+        );
+        assert_eq!(strip_script_tags("<p>a</p><script>unclosed"), "<p>a</p>");
+        assert_eq!(limit_page_source("<p>héllo</p>".to_string(), 5), "<p>h\n<!-- truncated at 5 bytes -->");
+        assert_eq!(limit_page_source("<p></p>".to_string(), DEFAULT_PAGE_SOURCE_LIMIT), "<p></p>");
+    }
+    
+    #[test]
+    fn test_parse_hotkey() {
+        assert_eq!(parse_hotkey("Ctrl+C").unwrap(), vec!["ctrl", "c"]);
+        assert_eq!(parse_hotkey("Ctrl+Shift+T").unwrap(), vec!["ctrl", "shift", "t"]);
+        assert_eq!(parse_hotkey("Super+L").unwrap(), vec!["super", "l"]);
+        assert_eq!(parse_hotkey("cmd + Escape").unwrap(), vec!["super", "esc"]);
+    }
+    
+    #[test]
+    fn test_parse_hotkey_rejects_invalid_input() {
+        assert!(matches!(parse_hotkey("Ctrl+Banana"), Err(CuaError::ActionError(msg)) if msg.contains("banana")));
+        assert!(parse_hotkey("Ctrl+").is_err());
+        assert!(parse_hotkey("").is_err());
+    }
+    
+    #[test]
+    fn test_keyboard_shortcut_map_follows_environment() {
+        let linux = MockComputer::new("linux", 1024, 768).keyboard_shortcut_map();
+        let mac = MockComputer::new("mac", 1024, 768).keyboard_shortcut_map();
+        
+        assert_eq!(linux["undo"], vec!["ctrl", "z"]);
+        assert_eq!(mac["undo"], vec!["super", "z"]);
+        assert_ne!(linux["close_window"], mac["close_window"]);
+        assert_eq!(linux.len(), mac.len());
+        assert!(["copy", "paste", "select_all"].iter().all(|action| linux[*action] != mac[*action]));
+    }
+    
+    #[tokio::test]
+    async fn test_scroll_to_element_scrolls_until_found() {
+        let computer = MockComputer::new("linux", 1024, 768);
+        computer.queue_screenshot("YmVmb3Jl");
+        computer.queue_screenshot("YWZ0ZXI=");
+        computer.set_ocr_result("YmVmb3Jl", vec![OcrWord::new("Cancel", (10, 700, 60, 16), 1)]);
+        computer.set_ocr_result("YWZ0ZXI=", vec![
+            OcrWord::new("Save", (100, 200, 40, 20), 1),
+            OcrWord::new("settings", (150, 200, 80, 20), 1),
+        ]);
+        
+        let position = computer.scroll_to_element("save settings", 5).await.unwrap();
+        
+        assert_eq!(position, (165, 210));
+        let scrolls = computer.action_history().iter().filter(|a| a.action_type == "scroll").count();
+        assert_eq!(scrolls, 1);
+    }
+    
+    #[tokio::test]
+    async fn test_scroll_to_element_not_found() {
+        let computer = MockComputer::new("linux", 1024, 768);
+        
+        let result = computer.scroll_to_element("Submit", 2).await;
+        
+        assert!(matches!(result, Err(CuaError::ActionError(msg)) if msg.starts_with("element not found")));
+        assert_eq!(computer.action_history().len(), 2);
+    }
+    
+    #[tokio::test]
+    async fn test_drag_and_drop_path() {
+        let computer = MockComputer::new("linux", 1024, 768);
+        
+        computer.drag_and_drop(100, 200, 400, 50).await.unwrap();
+        
+        let history = computer.action_history();
+        assert_eq!(history.len(), 1);
+        let path = history[0].params["path"].as_array().unwrap();
+        assert!(path.len() >= 7, "only {} points", path.len());
+        assert_eq!((path[0]["x"].as_i64(), path[0]["y"].as_i64()), (Some(100), Some(200)));
+        let last = path.last().unwrap();
+        assert_eq!((last["x"].as_i64(), last["y"].as_i64()), (Some(400), Some(50)));
+        assert_eq!(computer.cursor_position(), (400, 50));
+    }
+    
+    #[tokio::test]
+    async fn test_drag_by_delta_path() {
+        let computer = MockComputer::new("linux", 1024, 768);
+        
+        computer.drag_by_delta(100, 100, 50, 0, 5).await.unwrap();
+        
+        let path = computer.action_history()[0].params["path"].clone();
+        let points: Vec<(i64, i64)> = path
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|point| (point["x"].as_i64().unwrap(), point["y"].as_i64().unwrap()))
+            .collect();
+        assert_eq!(points, vec![(100, 100), (110, 100), (120, 100), (130, 100), (140, 100), (150, 100)]);
+        
+        assert!(computer.drag_by_delta(i32::MAX - 10, 100, 50, 0, 5).await.is_err());
+        assert_eq!(computer.action_history().len(), 1);
+    }
+    
+    #[tokio::test]
+    async fn test_type_text_at_and_clear_and_type() {
+        let computer = MockComputer::new("linux", 1024, 768);
+        
+        computer.type_text_at(100, 200, "hello").await.unwrap();
+        computer.clear_and_type(100, 200, "world").await.unwrap();
+        
+        let history = computer.action_history();
+        let types: Vec<&str> = history.iter().map(|record| record.action_type.as_str()).collect();
+        // The mock has no triple-click of its own, so this covers the default
+        assert_eq!(types, vec!["click", "type", "double_click", "click", "type"]);
+        assert_eq!(history[0].params["x"], 100);
+        assert_eq!(history[3].params, serde_json::json!({ "x": 100, "y": 200, "button": "left" }));
+        assert_eq!(history[4].params["text"], "world");
+    }
+    
+    #[tokio::test]
+    async fn test_logical_physical_round_trip() {
+        let computer = MockComputer::new("linux", 3840, 2160);
+        for scale in [1.0, 1.5, 2.0] {
+            computer.set_screen_dpi(BASE_DPI * scale);
+            assert_eq!(computer.get_screen_dpi().await.unwrap(), BASE_DPI * scale);
+            
+            assert_eq!(computer.logical_to_physical(100.0, 40.0).await.unwrap(), ((100.0 * scale) as i32, (40.0 * scale) as i32));
+            for (x, y) in [(0, 0), (150, 75), (1919, 1079), (3839, 2159)] {
+                let (logical_x, logical_y) = computer.physical_to_logical(x, y).await.unwrap();
+                assert_eq!(computer.logical_to_physical(logical_x, logical_y).await.unwrap(), (x, y), "scale {}", scale);
+            }
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_screenshot_with_cursor() {
+        let background = image::Rgba([40, 120, 200, 255]);
+        let screen = image::RgbaImage::from_pixel(200, 100, background);
+        let computer = MockComputer::new("linux", 200, 100);
+        computer.set_screenshot(&vision::encode_png(&screen.into()).unwrap());
+        computer.move_cursor(50, 30).await.unwrap();
+        
+        let screenshot = computer.screenshot_with_cursor().await.unwrap();
+        let image = decode_screenshot(&screenshot).unwrap().to_rgba8();
+        
+        assert_ne!(*image.get_pixel(50, 30), background);
+        assert_ne!(*image.get_pixel(52, 38), background);
+        assert_eq!(*image.get_pixel(10, 10), background);
+        assert_eq!(*image.get_pixel(150, 80), background);
+    }
+    
+    fn blank_screen() -> image::RgbaImage {
+        image::RgbaImage::from_pixel(80, 40, image::Rgba([255, 255, 255, 255]))
+    }
+    
+    #[tokio::test(start_paused = true)]
+    async fn test_focus_input_field_detects_cursor_blink() {
+        let mut with_cursor = blank_screen();
+        for y in 10..26 {
+            with_cursor.put_pixel(30, y, image::Rgba([0, 0, 0, 255]));
+        }
+        let computer = MockComputer::new("linux", 80, 40);
+        computer.queue_screenshot(&vision::encode_png(&blank_screen().into()).unwrap());
+        computer.queue_screenshot(&vision::encode_png(&with_cursor.into()).unwrap());
+        
+        computer.focus_input_field(30, 18).await.unwrap();
+        
+        let actions: Vec<String> = computer.action_history().into_iter().map(|a| a.action_type).collect();
+        assert_eq!(actions, vec!["click"]);
+    }
+    
+    #[tokio::test(start_paused = true)]
+    async fn test_focus_input_field_gives_up() {
+        let computer = MockComputer::new("linux", 80, 40);
+        computer.set_screenshot(&vision::encode_png(&blank_screen().into()).unwrap());
+        
+        let result = computer.focus_input_field(30, 18).await;
+        
+        assert!(matches!(result, Err(CuaError::ActionError(msg)) if msg == "could not focus field"));
+        let actions: Vec<String> = computer.action_history().into_iter().map(|a| a.action_type).collect();
+        assert_eq!(actions, vec!["click", "click", "click", "double_click"]);
+    }
+    
+    #[tokio::test]
+    async fn test_directional_scroll() {
+        let computer = MockComputer::new("linux", 1024, 768);
+        
+        computer.scroll_horizontal(100, 200, 300).await.unwrap();
+        computer.scroll_vertical(100, 200, -150).await.unwrap();
+        
+        let history = computer.action_history();
+        assert_eq!((history[0].params["scroll_x"].as_i64(), history[0].params["scroll_y"].as_i64()), (Some(300), Some(0)));
+        assert_eq!((history[1].params["scroll_x"].as_i64(), history[1].params["scroll_y"].as_i64()), (Some(0), Some(-150)));
+    }
+    
+    #[tokio::test]
+    async fn test_take_screenshot_on_error() {
+        let computer = MockComputer::new("linux", 1024, 768);
+        computer.fail_action("click");
+        
+        computer.take_screenshot_on_error(Box::pin(computer.type_text("ok"))).await.unwrap();
+        
+        match computer.take_screenshot_on_error(Box::pin(computer.click(10, 20, "left"))).await {
+            Err(CuaError::ActionError(message)) => {
+                let (original, screenshot_path) = message.split_once(" (screenshot: ").unwrap();
+                assert_eq!(original, "Mock click failure");
+                let screenshot_path = screenshot_path.trim_end_matches(')');
+                assert!(!std::fs::read(screenshot_path).unwrap().is_empty());
+                std::fs::remove_file(screenshot_path).unwrap();
+            }
+            other => panic!("expected ActionError, got {:?}", other),
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_get_text_cursor_position() {
+        struct NoCaret;
+        #[async_trait]
+        impl Computer for NoCaret {
+            fn environment(&self) -> &str { "linux" }
+            fn dimensions(&self) -> (u32, u32) { (800, 600) }
+            async fn screenshot(&self) -> Result<String, CuaError> { Ok(String::new()) }
+            async fn click(&self, _: i32, _: i32, _: &str) -> Result<(), CuaError> { Ok(()) }
+            async fn double_click(&self, _: i32, _: i32) -> Result<(), CuaError> { Ok(()) }
+            async fn scroll(&self, _: i32, _: i32, _: i32, _: i32) -> Result<(), CuaError> { Ok(()) }
+            async fn type_text(&self, _: &str) -> Result<(), CuaError> { Ok(()) }
+            async fn wait(&self, _: u32) -> Result<(), CuaError> { Ok(()) }
+            async fn move_cursor(&self, _: i32, _: i32) -> Result<(), CuaError> { Ok(()) }
+            async fn keypress(&self, _: &[String]) -> Result<(), CuaError> { Ok(()) }
+            async fn drag(&self, _: &[HashMap<String, i32>]) -> Result<(), CuaError> { Ok(()) }
+            async fn get_current_url(&self) -> Result<String, CuaError> { Ok(String::new()) }
+        }
+        assert!(matches!(NoCaret.get_text_cursor_position().await, Err(CuaError::ActionError(_))));
+        assert_eq!(NoCaret.cursor_position(), (0, 0));
+        
+        let computer = MockComputer::new("linux", 1024, 768);
+        computer.set_text_cursor_position((312, 148));
+        assert_eq!(computer.get_text_cursor_position().await.unwrap(), (312, 148));
+    }
+    
+    #[tokio::test]
+    async fn test_screen_recording_area_spans_screens() {
+        let computer = MockComputer::new("linux", 1920, 1080);
+        assert_eq!(computer.get_screen_recording_area().await.unwrap(), (0, 0, 1920, 1080));
+        
+        // A second monitor to the left of the primary one, aligned at the bottom
+        computer.set_screens(&[(0, 0, 1920, 1080), (-1280, 56, 1280, 1024)]);
+        let area = computer.get_screen_recording_area().await.unwrap();
+        
+        assert_eq!(area, (-1280, 0, 3200, 1080));
+        assert!(area_contains(area, -1280, 500));
+        assert!(area_contains(area, 1919, 1079));
+        assert!(!area_contains(area, 1920, 500));
+        assert_eq!(union_bounds(&[]), None);
+    }
+    
+    #[test]
+    fn test_zoom_state_round_trip() {
+        let zoom = ZoomState { factor: 2.0, cx: 100, cy: 100 };
+        
+        assert_eq!(zoom.apply(150, 80), (200, 60));
+        assert_eq!(zoom.invert(200, 60), (150, 80));
+        assert_eq!(ZoomState::default().apply(150, 80), (150, 80));
+    }
+    
+    #[test]
+    fn test_zoom_to_combines_zooms() {
+        let zoom = ZoomState::default().zoom_to(2.0, 100, 100);
+        assert_eq!(zoom, ZoomState { factor: 2.0, cx: 100, cy: 100 });
+        
+        // Zooming in further around (400, 100) on the zoomed screen
+        let combined = zoom.zoom_to(4.0, 400, 100);
+        let (x, y) = zoom.apply(150, 80);
+        assert_eq!(combined.apply(150, 80), (400 + 2 * (x - 400), 100 + 2 * (y - 100)));
+        assert_eq!(combined.zoom_to(1.0, 0, 0), ZoomState::default());
+    }
+}
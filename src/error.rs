@@ -2,6 +2,10 @@
 
 use std::fmt;
 use std::error::Error;
+use std::time::Duration;
+use http::StatusCode;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
 
 /// Custom error type for CUA operations
 #[derive(Debug)]
@@ -15,9 +19,35 @@ pub enum CuaError {
     /// Error related to API calls
     ApiError(String),
     
+    /// The API rejected a request with 429 Too Many Requests
+    RateLimited {
+        /// Error returned by the API
+        message: String,
+        /// Wait the API asked for in its `Retry-After` header, if any
+        retry_after: Option<Duration>,
+    },
+    
     /// Error related to safety checks
     SafetyError(String),
     
+    /// Error communicating with a worker thread over a channel
+    ChannelError {
+        /// Name of the command that could not be delivered (e.g. "Click")
+        command: String,
+        /// Why the channel operation failed
+        reason: String,
+    },
+    
+    /// The agent's cost budget ran out before the task finished
+    BudgetExceeded {
+        /// Estimated USD spent so far
+        spent: f64,
+        /// Configured USD limit
+        limit: f64,
+        /// Conversation items up to the point the agent stopped
+        items: Vec<serde_json::Value>,
+    },
+    
     /// IO error from standard library
     IoError(std::io::Error),
     
@@ -31,15 +61,157 @@ impl fmt::Display for CuaError {
             CuaError::ActionError(msg) => write!(f, "Action error: {}", msg),
             CuaError::ScreenshotError(msg) => write!(f, "Screenshot error: {}", msg),
             CuaError::ApiError(msg) => write!(f, "API error: {}", msg),
+            CuaError::RateLimited { message, .. } => write!(f, "Rate limited: {}", message),
             CuaError::SafetyError(msg) => write!(f, "Safety error: {}", msg),
+            CuaError::ChannelError { command, reason } => {
+                write!(f, "Channel error: failed to send {} command: {}", command, reason)
+            }
+            CuaError::BudgetExceeded { spent, limit, .. } => {
+                write!(f, "cost budget exceeded: ${:.4} spent of ${:.4} limit", spent, limit)
+            }
             CuaError::IoError(err) => write!(f, "IO error: {}", err),
             CuaError::Other(msg) => write!(f, "Error: {}", msg),
         }
     }
 }
 
+impl CuaError {
+    /// Append `context` to the error's message in parentheses, keeping its variant
+    ///
+    /// A budget error has no free-form message and is returned unchanged.
+    pub fn with_context(self, context: &str) -> Self {
+        let add = |msg: String| format!("{} ({})", msg, context);
+        match self {
+            CuaError::ActionError(msg) => CuaError::ActionError(add(msg)),
+            CuaError::ScreenshotError(msg) => CuaError::ScreenshotError(add(msg)),
+            CuaError::ApiError(msg) => CuaError::ApiError(add(msg)),
+            CuaError::RateLimited { message, retry_after } => CuaError::RateLimited { message: add(message), retry_after },
+            CuaError::SafetyError(msg) => CuaError::SafetyError(add(msg)),
+            CuaError::ChannelError { command, reason } => CuaError::ChannelError { command, reason: add(reason) },
+            CuaError::IoError(err) => CuaError::IoError(std::io::Error::new(err.kind(), add(err.to_string()))),
+            CuaError::Other(msg) => CuaError::Other(add(msg)),
+            budget @ CuaError::BudgetExceeded { .. } => budget,
+        }
+    }
+    
+    /// Serialize the error as `{ "type": "<variant>", "message": "...", ... }`
+    pub fn into_json(&self) -> Value {
+        serde_json::to_value(self).unwrap_or_else(|_| serde_json::json!({ "type": "Other", "message": self.to_string() }))
+    }
+    
+    /// HTTP status to report this error with when serving the agent over HTTP
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            CuaError::SafetyError(_) => StatusCode::FORBIDDEN,
+            CuaError::ApiError(_) => StatusCode::BAD_GATEWAY,
+            CuaError::RateLimited { .. } => StatusCode::TOO_MANY_REQUESTS,
+            CuaError::ActionError(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+    
+    /// Whether retrying the failed operation may succeed
+    ///
+    /// True for API timeouts, rate limiting and server errors.
+    pub fn is_retriable(&self) -> bool {
+        match self {
+            CuaError::ApiError(msg) => {
+                msg.starts_with("request timed out")
+                    || msg.starts_with("Received 502")
+                    || msg.starts_with("API returned error 5")
+            }
+            CuaError::RateLimited { .. } => true,
+            _ => false,
+        }
+    }
+}
+
 impl Error for CuaError {}
 
+/// Wire format of `CuaError`; fields beyond `type` and `message` are set only by the variants that have them
+#[derive(Serialize, Deserialize)]
+struct ErrorRepr {
+    #[serde(rename = "type")]
+    kind: String,
+    message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    command: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    spent: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    limit: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    items: Option<Vec<Value>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    retry_after_ms: Option<u64>,
+}
+
+impl ErrorRepr {
+    fn new(kind: &str, message: String) -> Self {
+        Self { kind: kind.to_string(), message, command: None, spent: None, limit: None, items: None, retry_after_ms: None }
+    }
+}
+
+impl Serialize for CuaError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let repr = match self {
+            CuaError::ActionError(msg) => ErrorRepr::new("ActionError", msg.clone()),
+            CuaError::ScreenshotError(msg) => ErrorRepr::new("ScreenshotError", msg.clone()),
+            CuaError::ApiError(msg) => ErrorRepr::new("ApiError", msg.clone()),
+            CuaError::RateLimited { message, retry_after } => ErrorRepr {
+                retry_after_ms: retry_after.map(|wait| wait.as_millis() as u64),
+                ..ErrorRepr::new("RateLimited", message.clone())
+            },
+            CuaError::SafetyError(msg) => ErrorRepr::new("SafetyError", msg.clone()),
+            CuaError::ChannelError { command, reason } => ErrorRepr {
+                command: Some(command.clone()),
+                ..ErrorRepr::new("ChannelError", reason.clone())
+            },
+            CuaError::BudgetExceeded { spent, limit, items } => ErrorRepr {
+                spent: Some(*spent),
+                limit: Some(*limit),
+                items: Some(items.clone()),
+                ..ErrorRepr::new("BudgetExceeded", self.to_string())
+            },
+            CuaError::IoError(err) => ErrorRepr::new("IoError", err.to_string()),
+            CuaError::Other(msg) => ErrorRepr::new("Other", msg.clone()),
+        };
+        repr.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for CuaError {
+    /// IO errors come back with `ErrorKind::Other`, as the kind is not transmitted
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = ErrorRepr::deserialize(deserializer)?;
+        Ok(match repr.kind.as_str() {
+            "ActionError" => CuaError::ActionError(repr.message),
+            "ScreenshotError" => CuaError::ScreenshotError(repr.message),
+            "ApiError" => CuaError::ApiError(repr.message),
+            "RateLimited" => CuaError::RateLimited {
+                message: repr.message,
+                retry_after: repr.retry_after_ms.map(Duration::from_millis),
+            },
+            "SafetyError" => CuaError::SafetyError(repr.message),
+            "ChannelError" => CuaError::ChannelError {
+                command: repr.command.unwrap_or_default(),
+                reason: repr.message,
+            },
+            "BudgetExceeded" => CuaError::BudgetExceeded {
+                spent: repr.spent.unwrap_or_default(),
+                limit: repr.limit.unwrap_or_default(),
+                items: repr.items.unwrap_or_default(),
+            },
+            "IoError" => CuaError::IoError(std::io::Error::other(repr.message)),
+            "Other" => CuaError::Other(repr.message),
+            other => return Err(serde::de::Error::unknown_variant(other, &[
+                "ActionError", "ScreenshotError", "ApiError", "RateLimited", "SafetyError",
+                "ChannelError", "BudgetExceeded", "IoError", "Other",
+            ])),
+        })
+    }
+}
+
 impl From<std::io::Error> for CuaError {
     fn from(err: std::io::Error) -> Self {
         CuaError::IoError(err)
@@ -65,4 +237,55 @@ impl From<serde_json::Error> for CuaError {
     fn from(err: serde_json::Error) -> Self {
         CuaError::ApiError(format!("JSON error: {}", err))
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    
+    #[test]
+    fn test_serde_round_trip() {
+        let errors = vec![
+            CuaError::ActionError("click failed".to_string()),
+            CuaError::ScreenshotError("no display".to_string()),
+            CuaError::ApiError("Received 502".to_string()),
+            CuaError::RateLimited { message: "slow down".to_string(), retry_after: Some(Duration::from_secs(12)) },
+            CuaError::SafetyError("blocked".to_string()),
+            CuaError::ChannelError { command: "Click".to_string(), reason: "closed".to_string() },
+            CuaError::BudgetExceeded { spent: 0.03, limit: 0.01, items: vec![json!({ "role": "user" })] },
+            CuaError::IoError(std::io::Error::other("disk full")),
+            CuaError::Other("oops".to_string()),
+        ];
+        
+        for error in errors {
+            let value = error.into_json();
+            let restored: CuaError = serde_json::from_value(value.clone()).unwrap();
+            assert_eq!(restored.to_string(), error.to_string());
+            assert_eq!(restored.into_json(), value);
+        }
+        
+        assert_eq!(
+            CuaError::ActionError("click failed".to_string()).into_json(),
+            json!({ "type": "ActionError", "message": "click failed" })
+        );
+        assert!(serde_json::from_value::<CuaError>(json!({ "type": "Nope", "message": "" })).is_err());
+    }
+    
+    #[test]
+    fn test_with_context_keeps_variant() {
+        let error = CuaError::ActionError("click failed".to_string()).with_context("screenshot: /tmp/shot.png");
+        assert_eq!(error.to_string(), "Action error: click failed (screenshot: /tmp/shot.png)");
+        
+        let error = CuaError::ChannelError { command: "Click".to_string(), reason: "closed".to_string() }.with_context("retried");
+        assert!(matches!(error, CuaError::ChannelError { ref reason, .. } if reason == "closed (retried)"));
+    }
+    
+    #[test]
+    fn test_status_code() {
+        assert_eq!(CuaError::SafetyError(String::new()).status_code(), StatusCode::FORBIDDEN);
+        assert_eq!(CuaError::ApiError(String::new()).status_code(), StatusCode::BAD_GATEWAY);
+        assert_eq!(CuaError::ActionError(String::new()).status_code(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(CuaError::Other(String::new()).status_code(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+}
@@ -0,0 +1,472 @@
+//! Computer wrapper that falls back to a second computer when the first fails
+//!
+//! Useful in CI, where the desktop computer cannot reach a display: the
+//! agent keeps running against a mock instead of aborting.
+
+use crate::compression::ScreenshotFormat;
+use crate::computer::{AccessibilityNode, ApplicationInfo, ClipboardEntry, ClipboardFormat, Computer, DialogInfo, ScreenArea, WindowInfo, ZoomState};
+use crate::error::CuaError;
+use crate::ocr::OcrWord;
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Whether an error means the computer itself is unusable, so the fallback should be tried
+///
+/// A `ChannelError` means the input thread died, the main failure mode of `ThreadComputer`.
+fn should_fall_back(error: &CuaError) -> bool {
+    matches!(
+        error,
+        CuaError::ActionError(_) | CuaError::ScreenshotError(_) | CuaError::ChannelError { .. }
+    )
+}
+
+/// Run a `Computer` call on the active computer, switching to the fallback on failure
+///
+/// If the fallback fails as well, the primary's error is returned.
+macro_rules! with_fallback {
+    ($self:ident, $method:literal, |$computer:ident| $call:expr) => {{
+        if $self.is_using_fallback() {
+            let $computer = &$self.fallback;
+            $call.await
+        } else {
+            let $computer = &$self.primary;
+            match $call.await {
+                Err(error) if should_fall_back(&error) => {
+                    let $computer = &$self.fallback;
+                    match $call.await {
+                        Ok(value) => {
+                            tracing::warn!(method = $method, %error, "Primary computer failed, switching to fallback");
+                            $self.using_fallback.store(true, Ordering::SeqCst);
+                            Ok(value)
+                        }
+                        Err(_) => Err(error),
+                    }
+                }
+                result => result,
+            }
+        }
+    }};
+}
+
+/// A computer that retries failed calls on a fallback computer
+///
+/// After the first call the fallback handles successfully, every later call
+/// goes straight to the fallback.
+pub struct FallbackComputer {
+    primary: Box<dyn Computer>,
+    fallback: Box<dyn Computer>,
+    using_fallback: Arc<AtomicBool>,
+}
+
+impl FallbackComputer {
+    /// Create a computer that uses `primary` until it fails, then `fallback`
+    pub fn new(primary: Box<dyn Computer>, fallback: Box<dyn Computer>) -> Self {
+        Self {
+            primary,
+            fallback,
+            using_fallback: Arc::new(AtomicBool::new(false)),
+        }
+    }
+    
+    /// Whether calls currently go to the fallback computer
+    pub fn is_using_fallback(&self) -> bool {
+        self.using_fallback.load(Ordering::SeqCst)
+    }
+    
+    /// Shared flag that is set once the fallback computer takes over
+    pub fn fallback_flag(&self) -> Arc<AtomicBool> {
+        self.using_fallback.clone()
+    }
+    
+    /// The computer currently handling calls
+    fn active(&self) -> &dyn Computer {
+        if self.is_using_fallback() {
+            self.fallback.as_ref()
+        } else {
+            self.primary.as_ref()
+        }
+    }
+}
+
+#[async_trait]
+impl Computer for FallbackComputer {
+    fn environment(&self) -> &str {
+        self.active().environment()
+    }
+    
+    fn dimensions(&self) -> (u32, u32) {
+        self.active().dimensions()
+    }
+    
+    fn screenshot_format_support(&self) -> Vec<ScreenshotFormat> {
+        self.active().screenshot_format_support()
+    }
+    
+    fn cursor_position(&self) -> (i32, i32) {
+        self.active().cursor_position()
+    }
+    
+    fn supports_network_emulation(&self) -> bool {
+        self.primary.supports_network_emulation() || self.fallback.supports_network_emulation()
+    }
+    
+    fn keyboard_shortcut_map(&self) -> HashMap<String, Vec<String>> {
+        self.active().keyboard_shortcut_map()
+    }
+    
+    async fn screenshot(&self) -> Result<String, CuaError> {
+        with_fallback!(self, "screenshot", |computer| computer.screenshot())
+    }
+    
+    async fn get_text_cursor_position(&self) -> Result<(i32, i32), CuaError> {
+        with_fallback!(self, "get_text_cursor_position", |computer| computer.get_text_cursor_position())
+    }
+    
+    async fn get_screen_recording_area(&self) -> Result<ScreenArea, CuaError> {
+        with_fallback!(self, "get_screen_recording_area", |computer| computer.get_screen_recording_area())
+    }
+    
+    async fn capture_element_screenshot(&self, x: u32, y: u32, w: u32, h: u32, label: &str) -> Result<String, CuaError> {
+        with_fallback!(self, "capture_element_screenshot", |computer| computer.capture_element_screenshot(x, y, w, h, label))
+    }
+    
+    async fn window_screenshot(&self, title_pattern: &str) -> Result<String, CuaError> {
+        with_fallback!(self, "window_screenshot", |computer| computer.window_screenshot(title_pattern))
+    }
+    
+    async fn capture_all_screens(&self) -> Result<Vec<(usize, String)>, CuaError> {
+        with_fallback!(self, "capture_all_screens", |computer| computer.capture_all_screens())
+    }
+    
+    async fn screen_bounds(&self) -> Result<Vec<ScreenArea>, CuaError> {
+        with_fallback!(self, "screen_bounds", |computer| computer.screen_bounds())
+    }
+    
+    fn primary_screen(&self) -> usize {
+        self.active().primary_screen()
+    }
+    
+    async fn highlight_region(&self, x: u32, y: u32, w: u32, h: u32, color: &str, duration_ms: u32) -> Result<(), CuaError> {
+        with_fallback!(self, "highlight_region", |computer| computer.highlight_region(x, y, w, h, color, duration_ms))
+    }
+    
+    async fn read_env_var(&self, name: &str) -> Result<String, CuaError> {
+        with_fallback!(self, "read_env_var", |computer| computer.read_env_var(name))
+    }
+    
+    async fn get_window_list(&self) -> Result<Vec<WindowInfo>, CuaError> {
+        with_fallback!(self, "get_window_list", |computer| computer.get_window_list())
+    }
+    
+    async fn set_display_resolution(&self, width: u32, height: u32) -> Result<(), CuaError> {
+        with_fallback!(self, "set_display_resolution", |computer| computer.set_display_resolution(width, height))
+    }
+    
+    async fn screenshot_jpeg(&self, quality: u8) -> Result<String, CuaError> {
+        with_fallback!(self, "screenshot_jpeg", |computer| computer.screenshot_jpeg(quality))
+    }
+    
+    async fn screenshot_as_array(&self, width: &mut u32, height: &mut u32) -> Result<Vec<u8>, CuaError> {
+        with_fallback!(self, "screenshot_as_array", |computer| computer.screenshot_as_array(width, height))
+    }
+    
+    async fn screenshot_with_cursor(&self) -> Result<String, CuaError> {
+        with_fallback!(self, "screenshot_with_cursor", |computer| computer.screenshot_with_cursor())
+    }
+    
+    async fn click(&self, x: i32, y: i32, button: &str) -> Result<(), CuaError> {
+        with_fallback!(self, "click", |computer| computer.click(x, y, button))
+    }
+    
+    async fn double_click(&self, x: i32, y: i32) -> Result<(), CuaError> {
+        with_fallback!(self, "double_click", |computer| computer.double_click(x, y))
+    }
+    
+    async fn scroll(&self, x: i32, y: i32, scroll_x: i32, scroll_y: i32) -> Result<(), CuaError> {
+        with_fallback!(self, "scroll", |computer| computer.scroll(x, y, scroll_x, scroll_y))
+    }
+    
+    async fn type_text(&self, text: &str) -> Result<(), CuaError> {
+        with_fallback!(self, "type_text", |computer| computer.type_text(text))
+    }
+    
+    async fn wait(&self, ms: u32) -> Result<(), CuaError> {
+        with_fallback!(self, "wait", |computer| computer.wait(ms))
+    }
+    
+    async fn move_cursor(&self, x: i32, y: i32) -> Result<(), CuaError> {
+        with_fallback!(self, "move_cursor", |computer| computer.move_cursor(x, y))
+    }
+    
+    async fn keypress(&self, keys: &[String]) -> Result<(), CuaError> {
+        with_fallback!(self, "keypress", |computer| computer.keypress(keys))
+    }
+    
+    async fn drag(&self, path: &[HashMap<String, i32>]) -> Result<(), CuaError> {
+        with_fallback!(self, "drag", |computer| computer.drag(path))
+    }
+    
+    async fn mouse_button_down(&self, button: &str) -> Result<(), CuaError> {
+        with_fallback!(self, "mouse_button_down", |computer| computer.mouse_button_down(button))
+    }
+    
+    async fn mouse_button_up(&self, button: &str) -> Result<(), CuaError> {
+        with_fallback!(self, "mouse_button_up", |computer| computer.mouse_button_up(button))
+    }
+    
+    async fn scroll_horizontal(&self, x: i32, y: i32, amount: i32) -> Result<(), CuaError> {
+        with_fallback!(self, "scroll_horizontal", |computer| computer.scroll_horizontal(x, y, amount))
+    }
+    
+    async fn scroll_vertical(&self, x: i32, y: i32, amount: i32) -> Result<(), CuaError> {
+        with_fallback!(self, "scroll_vertical", |computer| computer.scroll_vertical(x, y, amount))
+    }
+    
+    async fn drag_and_drop(&self, from_x: i32, from_y: i32, to_x: i32, to_y: i32) -> Result<(), CuaError> {
+        with_fallback!(self, "drag_and_drop", |computer| computer.drag_and_drop(from_x, from_y, to_x, to_y))
+    }
+    
+    async fn drag_by_delta(&self, start_x: i32, start_y: i32, delta_x: i32, delta_y: i32, steps: u32) -> Result<(), CuaError> {
+        with_fallback!(self, "drag_by_delta", |computer| computer.drag_by_delta(start_x, start_y, delta_x, delta_y, steps))
+    }
+    
+    async fn triple_click(&self, x: i32, y: i32) -> Result<(), CuaError> {
+        with_fallback!(self, "triple_click", |computer| computer.triple_click(x, y))
+    }
+    
+    async fn mouse_wheel_click(&self, x: i32, y: i32) -> Result<(), CuaError> {
+        with_fallback!(self, "mouse_wheel_click", |computer| computer.mouse_wheel_click(x, y))
+    }
+    
+    async fn type_text_at(&self, x: i32, y: i32, text: &str) -> Result<(), CuaError> {
+        with_fallback!(self, "type_text_at", |computer| computer.type_text_at(x, y, text))
+    }
+    
+    async fn clear_and_type(&self, x: i32, y: i32, text: &str) -> Result<(), CuaError> {
+        with_fallback!(self, "clear_and_type", |computer| computer.clear_and_type(x, y, text))
+    }
+    
+    async fn send_hotkey(&self, hotkey: &str) -> Result<(), CuaError> {
+        with_fallback!(self, "send_hotkey", |computer| computer.send_hotkey(hotkey))
+    }
+    
+    async fn hover(&self, x: i32, y: i32, duration_ms: u32) -> Result<(), CuaError> {
+        with_fallback!(self, "hover", |computer| computer.hover(x, y, duration_ms))
+    }
+    
+    async fn press_and_hold(&self, key: &str, duration_ms: u32) -> Result<(), CuaError> {
+        with_fallback!(self, "press_and_hold", |computer| computer.press_and_hold(key, duration_ms))
+    }
+    
+    async fn zoom(&self, factor: f32, cx: i32, cy: i32) -> Result<(), CuaError> {
+        with_fallback!(self, "zoom", |computer| computer.zoom(factor, cx, cy))
+    }
+    
+    fn zoom_state(&self) -> ZoomState {
+        self.active().zoom_state()
+    }
+    
+    async fn measure_latency(&self) -> Result<Duration, CuaError> {
+        with_fallback!(self, "measure_latency", |computer| computer.measure_latency())
+    }
+    
+    async fn screen_color_at(&self, x: u32, y: u32) -> Result<(u8, u8, u8), CuaError> {
+        with_fallback!(self, "screen_color_at", |computer| computer.screen_color_at(x, y))
+    }
+    
+    async fn paste_from_clipboard(&self) -> Result<(), CuaError> {
+        with_fallback!(self, "paste_from_clipboard", |computer| computer.paste_from_clipboard())
+    }
+    
+    async fn copy_selection(&self) -> Result<(), CuaError> {
+        with_fallback!(self, "copy_selection", |computer| computer.copy_selection())
+    }
+    
+    async fn select_all_text(&self) -> Result<(), CuaError> {
+        with_fallback!(self, "select_all_text", |computer| computer.select_all_text())
+    }
+    
+    async fn copy_selected_text(&self) -> Result<String, CuaError> {
+        with_fallback!(self, "copy_selected_text", |computer| computer.copy_selected_text())
+    }
+    
+    async fn get_focused_text(&self) -> Result<String, CuaError> {
+        with_fallback!(self, "get_focused_text", |computer| computer.get_focused_text())
+    }
+    
+    async fn ocr_extract(&self, screenshot_base64: &str) -> Result<Vec<OcrWord>, CuaError> {
+        with_fallback!(self, "ocr_extract", |computer| computer.ocr_extract(screenshot_base64))
+    }
+    
+    async fn read_screen_text(&self, x: u32, y: u32, w: u32, h: u32) -> Result<String, CuaError> {
+        with_fallback!(self, "read_screen_text", |computer| computer.read_screen_text(x, y, w, h))
+    }
+    
+    async fn screenshot_as_text(&self) -> Result<String, CuaError> {
+        with_fallback!(self, "screenshot_as_text", |computer| computer.screenshot_as_text())
+    }
+    
+    async fn assert_text_visible(&self, text: &str) -> Result<bool, CuaError> {
+        with_fallback!(self, "assert_text_visible", |computer| computer.assert_text_visible(text))
+    }
+    
+    async fn assert_text_visible_within(&self, text: &str, x: u32, y: u32, w: u32, h: u32) -> Result<bool, CuaError> {
+        with_fallback!(self, "assert_text_visible_within", |computer| computer.assert_text_visible_within(text, x, y, w, h))
+    }
+    
+    async fn current_dialog(&self) -> Result<Option<DialogInfo>, CuaError> {
+        with_fallback!(self, "current_dialog", |computer| computer.current_dialog())
+    }
+    
+    async fn detect_dialog(&self, timeout_ms: u32) -> Result<Option<DialogInfo>, CuaError> {
+        with_fallback!(self, "detect_dialog", |computer| computer.detect_dialog(timeout_ms))
+    }
+    
+    async fn dismiss_dialog(&self, button_text: &str) -> Result<(), CuaError> {
+        with_fallback!(self, "dismiss_dialog", |computer| computer.dismiss_dialog(button_text))
+    }
+    
+    async fn text_cursor_blinking(&self) -> Result<bool, CuaError> {
+        with_fallback!(self, "text_cursor_blinking", |computer| computer.text_cursor_blinking())
+    }
+    
+    async fn focus_input_field(&self, x: i32, y: i32) -> Result<(), CuaError> {
+        with_fallback!(self, "focus_input_field", |computer| computer.focus_input_field(x, y))
+    }
+    
+    async fn get_current_url(&self) -> Result<String, CuaError> {
+        with_fallback!(self, "get_current_url", |computer| computer.get_current_url())
+    }
+    
+    async fn get_page_source(&self) -> Result<String, CuaError> {
+        with_fallback!(self, "get_page_source", |computer| computer.get_page_source())
+    }
+    
+    async fn get_clipboard(&self) -> Result<String, CuaError> {
+        with_fallback!(self, "get_clipboard", |computer| computer.get_clipboard())
+    }
+    
+    async fn set_clipboard(&self, text: &str) -> Result<(), CuaError> {
+        with_fallback!(self, "set_clipboard", |computer| computer.set_clipboard(text))
+    }
+    
+    async fn clipboard_history(&self, max_entries: usize) -> Result<Vec<ClipboardEntry>, CuaError> {
+        with_fallback!(self, "clipboard_history", |computer| computer.clipboard_history(max_entries))
+    }
+    
+    async fn get_clipboard_format(&self) -> Result<ClipboardFormat, CuaError> {
+        with_fallback!(self, "get_clipboard_format", |computer| computer.get_clipboard_format())
+    }
+    
+    async fn set_clipboard_html(&self, html: &str) -> Result<(), CuaError> {
+        with_fallback!(self, "set_clipboard_html", |computer| computer.set_clipboard_html(html))
+    }
+    
+    async fn get_screen_dpi(&self) -> Result<f32, CuaError> {
+        with_fallback!(self, "get_screen_dpi", |computer| computer.get_screen_dpi())
+    }
+    
+    async fn logical_to_physical(&self, x: f32, y: f32) -> Result<(i32, i32), CuaError> {
+        with_fallback!(self, "logical_to_physical", |computer| computer.logical_to_physical(x, y))
+    }
+    
+    async fn physical_to_logical(&self, x: i32, y: i32) -> Result<(f32, f32), CuaError> {
+        with_fallback!(self, "physical_to_logical", |computer| computer.physical_to_logical(x, y))
+    }
+    
+    async fn monitor_clipboard_changes(&self, duration_ms: u32) -> Result<Vec<String>, CuaError> {
+        with_fallback!(self, "monitor_clipboard_changes", |computer| computer.monitor_clipboard_changes(duration_ms))
+    }
+    
+    async fn clipboard_is_empty(&self) -> Result<bool, CuaError> {
+        with_fallback!(self, "clipboard_is_empty", |computer| computer.clipboard_is_empty())
+    }
+    
+    async fn wait_for_clipboard_change(&self, timeout_ms: u32) -> Result<String, CuaError> {
+        with_fallback!(self, "wait_for_clipboard_change", |computer| computer.wait_for_clipboard_change(timeout_ms))
+    }
+    
+    async fn get_focused_application(&self) -> Result<ApplicationInfo, CuaError> {
+        with_fallback!(self, "get_focused_application", |computer| computer.get_focused_application())
+    }
+    
+    async fn get_running_processes_memory(&self) -> Result<Vec<(String, u64)>, CuaError> {
+        with_fallback!(self, "get_running_processes_memory", |computer| computer.get_running_processes_memory())
+    }
+    
+    async fn accessibility_tree(&self) -> Result<AccessibilityNode, CuaError> {
+        with_fallback!(self, "accessibility_tree", |computer| computer.accessibility_tree())
+    }
+    
+    async fn execute_script(&self, script: &str, lang: &str) -> Result<String, CuaError> {
+        with_fallback!(self, "execute_script", |computer| computer.execute_script(script, lang))
+    }
+    
+    async fn notify(&self, message: &str, duration_ms: u32) -> Result<(), CuaError> {
+        with_fallback!(self, "notify", |computer| computer.notify(message, duration_ms))
+    }
+    
+    async fn get_battery_level(&self) -> Result<Option<f32>, CuaError> {
+        with_fallback!(self, "get_battery_level", |computer| computer.get_battery_level())
+    }
+    
+    async fn simulate_network_condition(&self, latency_ms: u32, packet_loss: f32) -> Result<(), CuaError> {
+        with_fallback!(self, "simulate_network_condition", |computer| computer.simulate_network_condition(latency_ms, packet_loss))
+    }
+    
+    async fn clear_network_conditions(&self) -> Result<(), CuaError> {
+        with_fallback!(self, "clear_network_conditions", |computer| computer.clear_network_conditions())
+    }
+    
+    async fn goto(&self, url: &str) -> Result<(), CuaError> {
+        with_fallback!(self, "goto", |computer| computer.goto(url))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockComputer;
+    
+    #[tokio::test]
+    async fn test_screenshot_falls_back() {
+        let primary = MockComputer::new("linux", 1920, 1080);
+        primary.fail_action("screenshot");
+        let fallback = MockComputer::new("linux", 1024, 768);
+        fallback.set_screenshot("ZmFsbGJhY2s=");
+        
+        let computer = FallbackComputer::new(Box::new(primary), Box::new(fallback.clone()));
+        assert_eq!(computer.dimensions(), (1920, 1080));
+        
+        assert_eq!(computer.screenshot().await.unwrap(), "ZmFsbGJhY2s=");
+        assert!(computer.is_using_fallback());
+        assert_eq!(computer.dimensions(), (1024, 768));
+        
+        computer.click(10, 20, "left").await.unwrap();
+        assert_eq!(fallback.action_history()[0].action_type, "click");
+    }
+    
+    #[tokio::test]
+    async fn test_both_failing_returns_primary_error() {
+        let primary = MockComputer::new("linux", 1920, 1080);
+        primary.fail_action("click");
+        let fallback = MockComputer::new("linux", 1920, 1080);
+        fallback.fail_action("click");
+        
+        let computer = FallbackComputer::new(Box::new(primary), Box::new(fallback));
+        let result = computer.click(10, 20, "left").await;
+        
+        assert!(matches!(result, Err(CuaError::ActionError(_))));
+        assert!(!computer.is_using_fallback());
+    }
+    
+    #[test]
+    fn test_should_fall_back() {
+        assert!(should_fall_back(&CuaError::ChannelError { command: "Click".to_string(), reason: "closed".to_string() }));
+        assert!(should_fall_back(&CuaError::ScreenshotError("no display".to_string())));
+        assert!(!should_fall_back(&CuaError::SafetyError("blocked".to_string())));
+    }
+}
@@ -0,0 +1,29 @@
+//! OpenAI Computer-Use Agent desktop environment
+//!
+//! The library exposes the `Computer` trait, its implementations, the agent
+//! loop and the OpenAI API client. The binary in `main.rs` is a thin wrapper
+//! around [`cli::run`].
+
+pub mod error;
+pub mod computer;
+pub mod mock;
+pub mod ocr;
+pub mod thread_computer;
+pub mod api;
+pub mod agent;
+pub mod cli;
+pub mod compression;
+pub mod fallback;
+pub mod observer;
+pub mod rate_limiter;
+pub mod sanitizer;
+pub mod script;
+pub mod telemetry;
+pub mod tools;
+pub mod vision;
+pub mod watermark;
+
+mod command;
+
+#[cfg(test)]
+mod mock_api;
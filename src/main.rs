@@ -1,29 +1,25 @@
 // src/main.rs - Updated with proper error handling and CLI integration
 
-mod error;
-mod computer;
-mod mock;
-mod thread_computer;
-mod api;
-mod agent;
-mod cli;
-
-use tokio;
-use computer::Computer;
-use mock::MockComputer;
-use thread_computer::ThreadComputer;
+use openai_cua_desktop::cli;
+use openai_cua_desktop::computer::Computer;
+use openai_cua_desktop::mock::MockComputer;
+use openai_cua_desktop::thread_computer::ThreadComputer;
 use std::io::{self, Write};
 use std::env;
 use std::collections::HashMap;
 use dotenv::dotenv;
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Initialize logger if needed
-    env_logger::init();
-    
+fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Load environment variables from .env file if it exists
     dotenv().ok();
+    // Set before the runtime starts its worker threads
+    cli::set_virtual_display_env();
+    
+    tokio::runtime::Runtime::new()?.block_on(run())
+}
+
+/// Run the CLI on the async runtime
+async fn run() -> Result<(), Box<dyn std::error::Error>> {
     
     println!("OpenAI CUA Desktop Environment");
     
@@ -44,6 +40,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 }
 
 /// Run a simple test mode when the CLI fails to start
+#[allow(dead_code)]
 async fn run_test_mode() -> Result<(), Box<dyn std::error::Error>> {
     println!("Testing desktop environment functionality");
     
@@ -78,6 +75,7 @@ async fn run_test_mode() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+#[allow(dead_code)]
 async fn run_with_mock() -> Result<(), Box<dyn std::error::Error>> {
     println!("Using MockComputer for testing");
     
@@ -109,10 +107,11 @@ async fn run_with_mock() -> Result<(), Box<dyn std::error::Error>> {
     println!("  Starting at (200, 200)");
     println!("  Dragging to (300, 300)");
     
-    let mut path = Vec::new();
-    path.push(HashMap::from([("x".to_string(), 200), ("y".to_string(), 200)]));
-    path.push(HashMap::from([("x".to_string(), 250), ("y".to_string(), 250)]));
-    path.push(HashMap::from([("x".to_string(), 300), ("y".to_string(), 300)]));
+    let path = vec![
+        HashMap::from([("x".to_string(), 200), ("y".to_string(), 200)]),
+        HashMap::from([("x".to_string(), 250), ("y".to_string(), 250)]),
+        HashMap::from([("x".to_string(), 300), ("y".to_string(), 300)]),
+    ];
     
     computer.drag(&path).await?;
     
@@ -124,6 +123,7 @@ async fn run_with_mock() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+#[allow(dead_code)]
 async fn run_with_thread() -> Result<(), Box<dyn std::error::Error>> {
     println!("Using thread-based Enigo implementation");
     
@@ -153,10 +153,11 @@ async fn run_with_thread() -> Result<(), Box<dyn std::error::Error>> {
     println!("  Starting at (200, 200)");
     println!("  Dragging to (300, 300)");
     
-    let mut path = Vec::new();
-    path.push(HashMap::from([("x".to_string(), 200), ("y".to_string(), 200)]));
-    path.push(HashMap::from([("x".to_string(), 250), ("y".to_string(), 250)]));
-    path.push(HashMap::from([("x".to_string(), 300), ("y".to_string(), 300)]));
+    let path = vec![
+        HashMap::from([("x".to_string(), 200), ("y".to_string(), 200)]),
+        HashMap::from([("x".to_string(), 250), ("y".to_string(), 250)]),
+        HashMap::from([("x".to_string(), 300), ("y".to_string(), 300)]),
+    ];
     
     computer.drag(&path).await?;
     
@@ -1,19 +1,98 @@
 //! Mock implementation of the Computer trait for testing purposes
 
-use crate::computer::Computer;
+use crate::compression::ScreenshotFormat;
+use crate::computer::{AccessibilityNode, ActionRecord, ApplicationInfo, ClipboardContentType, ClipboardEntry, ClipboardFormat, Computer, DialogInfo, PasteKey, ScreenArea, WindowInfo, ZoomState, bounds_contain, read_allowed_env_var, union_bounds, BASE_DPI, DEFAULT_ENV_ALLOWLIST};
 use crate::error::CuaError;
-use std::collections::HashMap;
-use std::sync::RwLock;
+use crate::ocr::OcrWord;
+use crate::vision::{self, decode_screenshot};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, RwLock};
 use tokio::time::sleep;
-use std::time::Duration;
+use std::time::{Duration, Instant, SystemTime};
 use async_trait::async_trait;
+use serde_json::{json, Value};
+use rand::Rng;
+
+/// RGB pixel color
+type Rgb = (u8, u8, u8);
+
+/// Screen region as (x, y, width, height)
+type Region = (u32, u32, u32, u32);
+
+/// Raw RGBA pixels with their width and height
+type RawImage = (Vec<u8>, u32, u32);
+
+/// Matcher for the parameters of an expected action
+pub type ParamsMatcher = Box<dyn Fn(&Value) -> bool + Send + Sync>;
+
+/// An action `MockComputer::expect_sequence` expects next
+pub struct ExpectedAction {
+    /// Action type as recorded in the action history (e.g. "click", "type"), or "screenshot"
+    pub action_type: String,
+    /// Whether the parameters of the call are the expected ones
+    pub params_matcher: ParamsMatcher,
+}
+
+impl ExpectedAction {
+    /// Expect an action of `action_type` with any parameters
+    pub fn new(action_type: &str) -> Self {
+        Self::matching(action_type, Box::new(|_| true))
+    }
+    
+    /// Expect an action of `action_type` whose parameters satisfy `params_matcher`
+    pub fn matching(action_type: &str, params_matcher: ParamsMatcher) -> Self {
+        Self {
+            action_type: action_type.to_string(),
+            params_matcher,
+        }
+    }
+}
 
 /// A mock implementation of the Computer trait for testing
+///
+/// Clones share their state, so a test can keep a handle to a mock that has
+/// been moved into an `Agent` and inspect it afterwards.
+#[derive(Clone)]
 pub struct MockComputer {
     environment: String,
     dimensions: (u32, u32),
-    cursor_position: RwLock<(i32, i32)>,
-    current_url: RwLock<String>,
+    cursor_position: Arc<RwLock<(i32, i32)>>,
+    current_url: Arc<RwLock<String>>,
+    page_source: Arc<RwLock<String>>,
+    clipboard: Arc<RwLock<Vec<ClipboardEntry>>>,
+    action_history: Arc<RwLock<Vec<ActionRecord>>>,
+    accessibility_tree: Arc<RwLock<Option<AccessibilityNode>>>,
+    latency: Arc<RwLock<Duration>>,
+    pixel_colors: Arc<RwLock<HashMap<(u32, u32), Rgb>>>,
+    screenshot: Arc<RwLock<String>>,
+    screenshot_queue: Arc<RwLock<VecDeque<String>>>,
+    raw_screenshot: Arc<RwLock<Option<RawImage>>>,
+    ocr_results: Arc<RwLock<HashMap<String, Vec<OcrWord>>>>,
+    focused_text: Arc<RwLock<String>>,
+    clipboard_queue: Arc<RwLock<VecDeque<String>>>,
+    scheduled_clipboard: Arc<RwLock<Vec<(tokio::time::Instant, String)>>>,
+    clipboard_format: Arc<RwLock<Option<ClipboardFormat>>>,
+    clipboard_html: Arc<RwLock<Option<String>>>,
+    screen_dpi: Arc<RwLock<f32>>,
+    network_emulation: Arc<RwLock<bool>>,
+    window_list: Arc<RwLock<Vec<WindowInfo>>>,
+    region_texts: Arc<RwLock<HashMap<Region, String>>>,
+    visible_texts: Arc<RwLock<Vec<OcrWord>>>,
+    dialog: Arc<RwLock<Option<DialogInfo>>>,
+    focused_application: Arc<RwLock<Option<ApplicationInfo>>>,
+    process_memory: Arc<RwLock<Vec<(String, u64)>>>,
+    screens: Arc<RwLock<Vec<ScreenArea>>>,
+    text_cursor_position: Arc<RwLock<Option<(i32, i32)>>>,
+    battery_level: Arc<RwLock<Option<f32>>>,
+    env_allowlist: Arc<RwLock<Vec<String>>>,
+    failing_actions: Arc<RwLock<HashSet<String>>>,
+    remaining_failures: Arc<RwLock<HashMap<String, u32>>>,
+    simulated_latency: Arc<RwLock<Option<(u64, u64)>>>,
+    failure_rate: Arc<RwLock<f32>>,
+    zoom: Arc<RwLock<ZoomState>>,
+    held_buttons: Arc<RwLock<HashSet<String>>>,
+    pressed_path: Arc<RwLock<Vec<(i32, i32)>>>,
+    expected_actions: Arc<RwLock<Option<VecDeque<ExpectedAction>>>>,
 }
 
 impl MockComputer {
@@ -28,14 +107,293 @@ impl MockComputer {
         Self {
             environment: environment.to_string(),
             dimensions: (width, height),
-            cursor_position: RwLock::new((0, 0)),
-            current_url: RwLock::new(current_url),
+            cursor_position: Arc::new(RwLock::new((0, 0))),
+            current_url: Arc::new(RwLock::new(current_url)),
+            page_source: Arc::new(RwLock::new("<html><head></head><body></body></html>".to_string())),
+            clipboard: Arc::new(RwLock::new(Vec::new())),
+            action_history: Arc::new(RwLock::new(Vec::new())),
+            accessibility_tree: Arc::new(RwLock::new(None)),
+            latency: Arc::new(RwLock::new(Duration::ZERO)),
+            pixel_colors: Arc::new(RwLock::new(HashMap::new())),
+            screenshot: Arc::new(RwLock::new("bW9ja3NjcmVlbnNob3Q=".to_string())), // "mockscreenshot" in base64
+            screenshot_queue: Arc::new(RwLock::new(VecDeque::new())),
+            raw_screenshot: Arc::new(RwLock::new(None)),
+            ocr_results: Arc::new(RwLock::new(HashMap::new())),
+            focused_text: Arc::new(RwLock::new(String::new())),
+            clipboard_queue: Arc::new(RwLock::new(VecDeque::new())),
+            scheduled_clipboard: Arc::new(RwLock::new(Vec::new())),
+            clipboard_format: Arc::new(RwLock::new(None)),
+            clipboard_html: Arc::new(RwLock::new(None)),
+            screen_dpi: Arc::new(RwLock::new(BASE_DPI)),
+            network_emulation: Arc::new(RwLock::new(true)),
+            window_list: Arc::new(RwLock::new(Vec::new())),
+            region_texts: Arc::new(RwLock::new(HashMap::new())),
+            visible_texts: Arc::new(RwLock::new(Vec::new())),
+            dialog: Arc::new(RwLock::new(None)),
+            focused_application: Arc::new(RwLock::new(None)),
+            process_memory: Arc::new(RwLock::new(Vec::new())),
+            screens: Arc::new(RwLock::new(Vec::new())),
+            text_cursor_position: Arc::new(RwLock::new(None)),
+            battery_level: Arc::new(RwLock::new(None)),
+            env_allowlist: Arc::new(RwLock::new(DEFAULT_ENV_ALLOWLIST.iter().map(|name| name.to_string()).collect())),
+            failing_actions: Arc::new(RwLock::new(HashSet::new())),
+            remaining_failures: Arc::new(RwLock::new(HashMap::new())),
+            simulated_latency: Arc::new(RwLock::new(None)),
+            failure_rate: Arc::new(RwLock::new(0.0)),
+            zoom: Arc::new(RwLock::new(ZoomState::default())),
+            held_buttons: Arc::new(RwLock::new(HashSet::new())),
+            pressed_path: Arc::new(RwLock::new(Vec::new())),
+            expected_actions: Arc::new(RwLock::new(None)),
         }
     }
     
-    /// Get the current cursor position
-    pub fn cursor_position(&self) -> (i32, i32) {
-        *self.cursor_position.read().unwrap()
+    /// Set the fake latency reported by `measure_latency`
+    pub fn set_latency(&self, latency: Duration) {
+        *self.latency.write().unwrap() = latency;
+    }
+    
+    /// Get the actions performed on this computer, in order
+    pub fn action_history(&self) -> Vec<ActionRecord> {
+        self.action_history.read().unwrap().clone()
+    }
+    
+    /// Set the tree returned by `accessibility_tree`
+    pub fn set_accessibility_tree(&self, tree: AccessibilityNode) {
+        *self.accessibility_tree.write().unwrap() = Some(tree);
+    }
+    
+    /// Set the color returned by `screen_color_at` for a pixel (unset pixels are white)
+    pub fn set_pixel_color(&self, x: u32, y: u32, color: (u8, u8, u8)) {
+        self.pixel_colors.write().unwrap().insert((x, y), color);
+    }
+    
+    /// Set the base64 image returned by `screenshot`
+    pub fn set_screenshot(&self, base64: &str) {
+        *self.screenshot.write().unwrap() = base64.to_string();
+    }
+    
+    /// Set the RGBA bytes returned by `screenshot_as_array`, instead of decoding `screenshot`
+    pub fn set_raw_screenshot(&self, data: Vec<u8>, width: u32, height: u32) {
+        *self.raw_screenshot.write().unwrap() = Some((data, width, height));
+    }
+    
+    /// Queue a base64 image to be returned by one `screenshot` call, before the default one
+    pub fn queue_screenshot(&self, base64: &str) {
+        self.screenshot_queue.write().unwrap().push_back(base64.to_string());
+    }
+    
+    /// Queue base64 images to be returned by successive `screenshot` calls, in order
+    pub fn inject_sequence(&self, screenshots: Vec<String>) {
+        self.screenshot_queue.write().unwrap().extend(screenshots);
+    }
+    
+    /// Set the words `ocr_extract` recognizes on a screenshot (none by default)
+    pub fn set_ocr_result(&self, screenshot: &str, words: Vec<OcrWord>) {
+        self.ocr_results.write().unwrap().insert(screenshot.to_string(), words);
+    }
+    
+    /// Set the text of the focused element, which a copy shortcut puts on the clipboard
+    pub fn set_focused_text(&self, text: &str) {
+        *self.focused_text.write().unwrap() = text.to_string();
+    }
+    
+    /// Queue values that successive `get_clipboard` calls copy onto the clipboard, one per call
+    pub fn queue_clipboard_values(&self, values: &[&str]) {
+        self.clipboard_queue.write().unwrap().extend(values.iter().map(|v| v.to_string()));
+    }
+    
+    /// Set the format `get_clipboard_format` reports instead of plain text for a non-empty clipboard
+    pub fn set_clipboard_format(&self, format: ClipboardFormat) {
+        *self.clipboard_format.write().unwrap() = Some(format);
+    }
+    
+    /// Set whether network emulation is supported (default true)
+    pub fn set_network_emulation_supported(&self, supported: bool) {
+        *self.network_emulation.write().unwrap() = supported;
+    }
+    
+    /// Set the DPI `get_screen_dpi` reports (default 96)
+    pub fn set_screen_dpi(&self, dpi: f32) {
+        *self.screen_dpi.write().unwrap() = dpi;
+    }
+    
+    /// Set the windows `get_window_list` reports
+    pub fn set_window_list(&self, windows: Vec<WindowInfo>) {
+        *self.window_list.write().unwrap() = windows;
+    }
+    
+    /// HTML last put on the clipboard with `set_clipboard_html`, kept apart from the plain text
+    pub fn clipboard_html(&self) -> Option<String> {
+        self.clipboard_html.read().unwrap().clone()
+    }
+    
+    /// Put `content` on the clipboard once `delay_ms` has passed, as seen by the next `get_clipboard`
+    pub fn schedule_clipboard_change(&self, delay_ms: u64, content: &str) {
+        let at = tokio::time::Instant::now() + Duration::from_millis(delay_ms);
+        let mut scheduled = self.scheduled_clipboard.write().unwrap();
+        scheduled.push((at, content.to_string()));
+        scheduled.sort_by_key(|(at, _)| *at);
+    }
+    
+    /// Set the text `read_screen_text` returns for the exact region (x, y, w, h)
+    pub fn set_region_text(&self, region: Region, text: &str) {
+        self.region_texts.write().unwrap().insert(region, text.to_string());
+    }
+    
+    /// Set the texts currently on screen, each with its bounds as (x, y, width, height)
+    ///
+    /// `ocr_extract` recognizes each text as one line on any screenshot without
+    /// its own OCR result (see [`MockComputer::set_ocr_result`]).
+    pub fn set_visible_texts(&self, texts: &[(&str, Region)]) {
+        *self.visible_texts.write().unwrap() = texts.iter().enumerate()
+            .map(|(line, (text, (x, y, w, h)))| OcrWord::new(text, (*x as i32, *y as i32, *w, *h), line as u32))
+            .collect();
+    }
+    
+    /// Show a modal dialog, returned by `current_dialog` until a click lands inside it
+    pub fn inject_dialog(&self, dialog: DialogInfo) {
+        *self.dialog.write().unwrap() = Some(dialog);
+    }
+    
+    /// Set the application `get_focused_application` returns
+    pub fn set_focused_application(&self, application: ApplicationInfo) {
+        *self.focused_application.write().unwrap() = Some(application);
+    }
+    
+    /// Set the (process name, memory in bytes) pairs `get_running_processes_memory` returns
+    pub fn set_process_memory(&self, processes: Vec<(String, u64)>) {
+        *self.process_memory.write().unwrap() = processes;
+    }
+    
+    /// Set the monitors' bounds, whose union `get_screen_recording_area` returns
+    ///
+    /// Without screens, the area is the mock's dimensions.
+    pub fn set_screens(&self, screens: &[ScreenArea]) {
+        *self.screens.write().unwrap() = screens.to_vec();
+    }
+    
+    /// Set the caret position `get_text_cursor_position` returns
+    pub fn set_text_cursor_position(&self, position: (i32, i32)) {
+        *self.text_cursor_position.write().unwrap() = Some(position);
+    }
+    
+    /// Set the charge `get_battery_level` reports (default: no battery)
+    pub fn set_battery_level(&self, level: f32) {
+        *self.battery_level.write().unwrap() = Some(level);
+    }
+    
+    /// Set the environment variables `read_env_var` may read (default `DEFAULT_ENV_ALLOWLIST`)
+    pub fn set_env_allowlist(&self, vars: Vec<String>) {
+        *self.env_allowlist.write().unwrap() = vars;
+    }
+    
+    /// Make an action (e.g. "click" or "screenshot") fail from now on
+    pub fn fail_action(&self, action_type: &str) {
+        self.failing_actions.write().unwrap().insert(action_type.to_string());
+    }
+    
+    /// Make the next `times` calls of an action fail, and later ones succeed
+    pub fn fail_action_times(&self, action_type: &str, times: u32) {
+        self.remaining_failures.write().unwrap().insert(action_type.to_string(), times);
+    }
+    
+    /// Whether an action has been set to fail, using up one of its counted failures
+    fn is_failing(&self, action_type: &str) -> bool {
+        if let Some(remaining) = self.remaining_failures.write().unwrap().get_mut(action_type).filter(|n| **n > 0) {
+            *remaining -= 1;
+            return true;
+        }
+        self.failing_actions.read().unwrap().contains(action_type)
+    }
+    
+    /// Delay every action and screenshot by a random time between `min_ms` and `max_ms`
+    pub fn simulate_latency(&self, min_ms: u64, max_ms: u64) {
+        *self.simulated_latency.write().unwrap() = Some((min_ms, max_ms.max(min_ms)));
+    }
+    
+    /// Make the given fraction (0.0 to 1.0) of actions and screenshots fail at random
+    pub fn simulate_failure_rate(&self, rate: f32) {
+        *self.failure_rate.write().unwrap() = rate.clamp(0.0, 1.0);
+    }
+    
+    /// Get the mouse buttons currently held down
+    pub fn held_buttons(&self) -> HashSet<String> {
+        self.held_buttons.read().unwrap().clone()
+    }
+    
+    /// Get the cursor path of the last press-move-release gesture or drag
+    pub fn pressed_path(&self) -> Vec<(i32, i32)> {
+        self.pressed_path.read().unwrap().clone()
+    }
+    
+    /// Expect exactly `actions`, in order, from now on
+    ///
+    /// Every action and screenshot is checked against the next expected action
+    /// and panics on a mismatch. Call `verify` at the end of the test.
+    pub fn expect_sequence(&self, actions: Vec<ExpectedAction>) {
+        *self.expected_actions.write().unwrap() = Some(actions.into());
+    }
+    
+    /// Assert that every action passed to `expect_sequence` was performed
+    pub fn verify(&self) {
+        let remaining: Vec<String> = self.expected_actions.read().unwrap()
+            .iter()
+            .flatten()
+            .map(|expected| expected.action_type.clone())
+            .collect();
+        assert!(remaining.is_empty(), "MockComputer: expected actions were not performed: {:?}", remaining);
+    }
+    
+    /// Check a call against the next expected action, if a sequence is expected
+    fn check_expected(&self, action_type: &str, params: &Value) {
+        let next = match self.expected_actions.write().unwrap().as_mut() {
+            Some(expected) => expected.pop_front(),
+            None => return,
+        };
+        // Panic after releasing the lock so `verify` still works after a caught panic
+        match next {
+            None => panic!("MockComputer: unexpected {} {}, no more actions were expected", action_type, params),
+            Some(expected) if expected.action_type != action_type => panic!(
+                "MockComputer: expected {} but got {} {}",
+                expected.action_type, action_type, params
+            ),
+            Some(expected) if !(expected.params_matcher)(params) => panic!(
+                "MockComputer: {} called with unexpected parameters {}",
+                action_type, params
+            ),
+            Some(_) => {}
+        }
+    }
+    
+    /// Sleep for the simulated latency, then fail at the simulated failure rate
+    async fn simulate_conditions(&self) -> Result<(), CuaError> {
+        let latency = *self.simulated_latency.read().unwrap();
+        if let Some((min_ms, max_ms)) = latency {
+            let delay_ms = rand::thread_rng().gen_range(min_ms..=max_ms);
+            sleep(Duration::from_millis(delay_ms)).await;
+        }
+        
+        let failure_rate = *self.failure_rate.read().unwrap();
+        if failure_rate > 0.0 && rand::random::<f32>() < failure_rate {
+            return Err(CuaError::ActionError("simulated failure".to_string()));
+        }
+        Ok(())
+    }
+    
+    /// Record an action in the history, or fail if the action is set to fail
+    async fn record(&self, action_type: &str, params: serde_json::Value) -> Result<(), CuaError> {
+        self.check_expected(action_type, &params);
+        self.simulate_conditions().await?;
+        if self.is_failing(action_type) {
+            return Err(CuaError::ActionError(format!("Mock {} failure", action_type)));
+        }
+        self.action_history.write().unwrap().push(ActionRecord::new(action_type, params));
+        Ok(())
+    }
+    
+    /// Set the HTML `get_page_source` returns in browser environments
+    pub fn set_page_source(&self, html: &str) {
+        *self.page_source.write().unwrap() = html.to_string();
     }
     
     /// Set current URL (for browser environments)
@@ -56,63 +414,179 @@ impl Computer for MockComputer {
         self.dimensions
     }
     
+    fn cursor_position(&self) -> (i32, i32) {
+        *self.cursor_position.read().unwrap()
+    }
+    
     async fn screenshot(&self) -> Result<String, CuaError> {
         // Return a mock base64-encoded string
-        println!("MockComputer: Taking screenshot");
-        Ok("bW9ja3NjcmVlbnNob3Q=".to_string()) // "mockscreenshot" in base64
+        tracing::debug!("MockComputer: Taking screenshot");
+        self.check_expected("screenshot", &json!({}));
+        self.simulate_conditions().await?;
+        if self.is_failing("screenshot") {
+            return Err(CuaError::ScreenshotError("Mock screenshot failure".to_string()));
+        }
+        if let Some(screenshot) = self.screenshot_queue.write().unwrap().pop_front() {
+            return Ok(screenshot);
+        }
+        Ok(self.screenshot.read().unwrap().clone())
+    }
+    
+    async fn capture_all_screens(&self) -> Result<Vec<(usize, String)>, CuaError> {
+        // Every screen set with `set_screens` shows the current screenshot
+        let screens = self.screens.read().unwrap().len().max(1);
+        let screenshot = self.screenshot().await?;
+        Ok((0..screens).map(|index| (index, screenshot.clone())).collect())
+    }
+    
+    async fn screen_bounds(&self) -> Result<Vec<ScreenArea>, CuaError> {
+        let screens = self.screens.read().unwrap().clone();
+        if screens.is_empty() {
+            let (width, height) = self.dimensions;
+            return Ok(vec![(0, 0, width, height)]);
+        }
+        Ok(screens)
+    }
+    
+    async fn screenshot_as_array(&self, width: &mut u32, height: &mut u32) -> Result<Vec<u8>, CuaError> {
+        let raw = self.raw_screenshot.read().unwrap().clone();
+        let (data, raw_width, raw_height) = match raw {
+            Some(raw) => raw,
+            None => {
+                let image = decode_screenshot(&self.screenshot().await?)?.to_rgba8();
+                let (raw_width, raw_height) = image.dimensions();
+                (image.into_raw(), raw_width, raw_height)
+            }
+        };
+        (*width, *height) = (raw_width, raw_height);
+        Ok(data)
     }
     
     async fn click(&self, x: i32, y: i32, button: &str) -> Result<(), CuaError> {
-        println!("MockComputer: Clicking at ({}, {}) with button: {}", x, y, button);
+        tracing::debug!(x, y, button, "MockComputer: Clicking");
+        self.record("click", json!({ "x": x, "y": y, "button": button })).await?;
         // Update cursor position
         *self.cursor_position.write().unwrap() = (x, y);
+        // A click inside the dialog presses one of its buttons, which closes it
+        let mut dialog = self.dialog.write().unwrap();
+        if dialog.as_ref().is_some_and(|d| bounds_contain(d.bounds, (x, y, 1, 1))) {
+            *dialog = None;
+        }
         Ok(())
     }
     
     async fn double_click(&self, x: i32, y: i32) -> Result<(), CuaError> {
-        println!("MockComputer: Double-clicking at ({}, {})", x, y);
+        tracing::debug!(x, y, "MockComputer: Double-clicking");
+        self.record("double_click", json!({ "x": x, "y": y })).await?;
         // Update cursor position
         *self.cursor_position.write().unwrap() = (x, y);
         Ok(())
     }
     
     async fn scroll(&self, x: i32, y: i32, scroll_x: i32, scroll_y: i32) -> Result<(), CuaError> {
-        println!("MockComputer: Scrolling at ({}, {}) with delta ({}, {})", 
-                x, y, scroll_x, scroll_y);
+        tracing::debug!(x, y, scroll_x, scroll_y, "MockComputer: Scrolling");
+        self.record("scroll", json!({ "x": x, "y": y, "scroll_x": scroll_x, "scroll_y": scroll_y })).await?;
         // Update cursor position
         *self.cursor_position.write().unwrap() = (x, y);
         Ok(())
     }
     
     async fn type_text(&self, text: &str) -> Result<(), CuaError> {
-        println!("MockComputer: Typing text: {}", text);
+        tracing::debug!(text, "MockComputer: Typing text");
+        self.record("type", json!({ "text": text })).await?;
         Ok(())
     }
     
     async fn wait(&self, ms: u32) -> Result<(), CuaError> {
-        println!("MockComputer: Waiting for {} ms", ms);
+        tracing::debug!(ms, "MockComputer: Waiting");
+        self.record("wait", json!({ "ms": ms })).await?;
         sleep(Duration::from_millis(ms as u64)).await;
         Ok(())
     }
     
     async fn move_cursor(&self, x: i32, y: i32) -> Result<(), CuaError> {
-        println!("MockComputer: Moving cursor to ({}, {})", x, y);
+        tracing::debug!(x, y, "MockComputer: Moving cursor");
+        self.record("move", json!({ "x": x, "y": y })).await?;
         // Update cursor position
         *self.cursor_position.write().unwrap() = (x, y);
+        if !self.held_buttons.read().unwrap().is_empty() {
+            self.pressed_path.write().unwrap().push((x, y));
+        }
+        Ok(())
+    }
+    
+    async fn hover(&self, x: i32, y: i32, duration_ms: u32) -> Result<(), CuaError> {
+        tracing::debug!(x, y, duration_ms, "MockComputer: Hovering");
+        self.record("hover", json!({ "x": x, "y": y, "duration_ms": duration_ms })).await?;
+        *self.cursor_position.write().unwrap() = (x, y);
+        sleep(Duration::from_millis(duration_ms as u64)).await;
+        Ok(())
+    }
+    
+    async fn press_and_hold(&self, key: &str, duration_ms: u32) -> Result<(), CuaError> {
+        tracing::debug!(key, duration_ms, "MockComputer: Holding key");
+        self.record("press_and_hold", json!({ "key": key, "duration_ms": duration_ms })).await?;
+        sleep(Duration::from_millis(duration_ms as u64)).await;
+        Ok(())
+    }
+    
+    async fn zoom(&self, factor: f32, cx: i32, cy: i32) -> Result<(), CuaError> {
+        tracing::debug!(factor, cx, cy, "MockComputer: Zooming");
+        self.record("zoom", json!({ "factor": factor, "cx": cx, "cy": cy })).await?;
+        let zoom = self.zoom_state().zoom_to(factor, cx, cy);
+        *self.zoom.write().unwrap() = zoom;
         Ok(())
     }
     
+    fn zoom_state(&self) -> ZoomState {
+        *self.zoom.read().unwrap()
+    }
+    
+    fn screenshot_format_support(&self) -> Vec<ScreenshotFormat> {
+        vec![
+            ScreenshotFormat::Png,
+            ScreenshotFormat::Jpeg(vision::MAX_JPEG_QUALITY),
+            #[cfg(feature = "webp")]
+            ScreenshotFormat::WebP(100),
+        ]
+    }
+    
+    async fn ocr_extract(&self, screenshot_base64: &str) -> Result<Vec<OcrWord>, CuaError> {
+        Ok(self.ocr_results.read().unwrap().get(screenshot_base64).cloned()
+            .unwrap_or_else(|| self.visible_texts.read().unwrap().clone()))
+    }
+    
+    async fn read_screen_text(&self, x: u32, y: u32, w: u32, h: u32) -> Result<String, CuaError> {
+        Ok(self.region_texts.read().unwrap().get(&(x, y, w, h)).cloned().unwrap_or_default())
+    }
+    
+    async fn current_dialog(&self) -> Result<Option<DialogInfo>, CuaError> {
+        Ok(self.dialog.read().unwrap().clone())
+    }
+    
     async fn keypress(&self, keys: &[String]) -> Result<(), CuaError> {
-        println!("MockComputer: Pressing keys: {:?}", keys);
+        tracing::debug!(?keys, "MockComputer: Pressing keys");
+        self.record("keypress", json!({ "keys": keys })).await?;
+        
+        let copy = PasteKey::for_environment(&self.environment).shortcut("c");
+        let focused_text = self.focused_text.read().unwrap().clone();
+        if keys == copy.as_slice() && !focused_text.is_empty() {
+            self.set_clipboard(&focused_text).await?;
+        }
         Ok(())
     }
     
     async fn drag(&self, path: &[HashMap<String, i32>]) -> Result<(), CuaError> {
-        println!("MockComputer: Dragging along path with {} points", path.len());
+        tracing::debug!(points = path.len(), "MockComputer: Dragging along path");
+        self.record("drag", json!({ "path": path })).await?;
+        *self.pressed_path.write().unwrap() = path
+            .iter()
+            .map(|point| (*point.get("x").unwrap_or(&0), *point.get("y").unwrap_or(&0)))
+            .collect();
         for (i, point) in path.iter().enumerate() {
             let x = *point.get("x").unwrap_or(&0);
             let y = *point.get("y").unwrap_or(&0);
-            println!("  Point {}: ({}, {})", i, x, y);
+            tracing::trace!(point = i, x, y, "MockComputer: Drag point");
             
             // Update cursor position for the last point
             if i == path.len() - 1 {
@@ -122,22 +596,223 @@ impl Computer for MockComputer {
         Ok(())
     }
     
+    async fn mouse_button_down(&self, button: &str) -> Result<(), CuaError> {
+        tracing::debug!(button, "MockComputer: Pressing mouse button");
+        self.record("mouse_down", json!({ "button": button })).await?;
+        let mut held = self.held_buttons.write().unwrap();
+        if held.is_empty() {
+            *self.pressed_path.write().unwrap() = vec![*self.cursor_position.read().unwrap()];
+        }
+        held.insert(button.to_string());
+        Ok(())
+    }
+    
+    async fn mouse_button_up(&self, button: &str) -> Result<(), CuaError> {
+        tracing::debug!(button, "MockComputer: Releasing mouse button");
+        if !self.held_buttons.read().unwrap().contains(button) {
+            return Err(CuaError::ActionError(format!("Mouse button '{}' is not held", button)));
+        }
+        // A failed release leaves the button held
+        self.record("mouse_up", json!({ "button": button })).await?;
+        self.held_buttons.write().unwrap().remove(button);
+        Ok(())
+    }
+    
     async fn get_current_url(&self) -> Result<String, CuaError> {
         // Return the current URL or an empty string for non-browser environments
         let url = self.current_url.read().unwrap().clone();
-        println!("MockComputer: Getting current URL: {}", url);
+        tracing::debug!(%url, "MockComputer: Getting current URL");
         Ok(url)
     }
     
+    async fn get_page_source(&self) -> Result<String, CuaError> {
+        if self.environment != "browser" {
+            return Err(CuaError::ActionError("Page source not supported by this computer".to_string()));
+        }
+        Ok(self.page_source.read().unwrap().clone())
+    }
+    
+    async fn get_clipboard(&self) -> Result<String, CuaError> {
+        let due: Vec<String> = {
+            let mut scheduled = self.scheduled_clipboard.write().unwrap();
+            let now = tokio::time::Instant::now();
+            let count = scheduled.iter().take_while(|(at, _)| *at <= now).count();
+            scheduled.drain(..count).map(|(_, content)| content).collect()
+        };
+        for value in due {
+            self.set_clipboard(&value).await?;
+        }
+        let queued = self.clipboard_queue.write().unwrap().pop_front();
+        if let Some(value) = queued {
+            self.set_clipboard(&value).await?;
+        }
+        // Rich content put on by `set_clipboard_html` leaves no plain text behind
+        let clipboard = self.clipboard.read().unwrap();
+        Ok(clipboard
+            .last()
+            .filter(|entry| entry.content_type == ClipboardContentType::Text)
+            .map(|entry| entry.content.clone())
+            .unwrap_or_default())
+    }
+    
+    async fn set_clipboard(&self, text: &str) -> Result<(), CuaError> {
+        tracing::debug!(text, "MockComputer: Setting clipboard");
+        // New content replaces every format on the clipboard
+        *self.clipboard_html.write().unwrap() = None;
+        self.clipboard.write().unwrap().push(ClipboardEntry {
+            content: text.to_string(),
+            content_type: ClipboardContentType::Text,
+            timestamp: SystemTime::now(),
+        });
+        Ok(())
+    }
+    
+    async fn clipboard_history(&self, max_entries: usize) -> Result<Vec<ClipboardEntry>, CuaError> {
+        let clipboard = self.clipboard.read().unwrap();
+        Ok(clipboard.iter().rev().take(max_entries).cloned().collect())
+    }
+    
+    async fn get_clipboard_format(&self) -> Result<ClipboardFormat, CuaError> {
+        if let Some(format) = *self.clipboard_format.read().unwrap() {
+            return Ok(format);
+        }
+        let text = !self.get_clipboard().await?.is_empty();
+        let html = self.clipboard_html.read().unwrap().is_some();
+        Ok(ClipboardFormat { text, html, ..Default::default() })
+    }
+    
+    async fn get_screen_dpi(&self) -> Result<f32, CuaError> {
+        Ok(*self.screen_dpi.read().unwrap())
+    }
+    
+    async fn get_window_list(&self) -> Result<Vec<WindowInfo>, CuaError> {
+        Ok(self.window_list.read().unwrap().clone())
+    }
+    
+    async fn highlight_region(&self, x: u32, y: u32, w: u32, h: u32, color: &str, duration_ms: u32) -> Result<(), CuaError> {
+        self.record("highlight_region", json!({
+            "x": x,
+            "y": y,
+            "width": w,
+            "height": h,
+            "color": color,
+            "duration_ms": duration_ms,
+        })).await
+    }
+    
+    async fn read_env_var(&self, name: &str) -> Result<String, CuaError> {
+        read_allowed_env_var(name, &self.env_allowlist.read().unwrap())
+    }
+    
+    async fn set_clipboard_html(&self, html: &str) -> Result<(), CuaError> {
+        tracing::debug!(html, "MockComputer: Setting clipboard HTML");
+        // Like a real clipboard, the HTML replaces whatever plain text was there
+        *self.clipboard_html.write().unwrap() = Some(html.to_string());
+        self.clipboard.write().unwrap().push(ClipboardEntry {
+            content: html.to_string(),
+            content_type: ClipboardContentType::Other,
+            timestamp: SystemTime::now(),
+        });
+        Ok(())
+    }
+    
+    async fn get_text_cursor_position(&self) -> Result<(i32, i32), CuaError> {
+        self.text_cursor_position.read().unwrap().ok_or_else(|| {
+            CuaError::ActionError("No text cursor position set on MockComputer".to_string())
+        })
+    }
+    
+    async fn get_screen_recording_area(&self) -> Result<ScreenArea, CuaError> {
+        if self.is_failing("get_screen_recording_area") {
+            return Err(CuaError::ActionError("Mock get_screen_recording_area failure".to_string()));
+        }
+        let (width, height) = self.dimensions;
+        Ok(union_bounds(&self.screens.read().unwrap()).unwrap_or((0, 0, width, height)))
+    }
+    
+    async fn get_focused_application(&self) -> Result<ApplicationInfo, CuaError> {
+        self.focused_application.read().unwrap().clone().ok_or_else(|| {
+            CuaError::ActionError("No focused application set on MockComputer".to_string())
+        })
+    }
+    
+    async fn get_running_processes_memory(&self) -> Result<Vec<(String, u64)>, CuaError> {
+        let mut processes = self.process_memory.read().unwrap().clone();
+        processes.sort_by_key(|(_, bytes)| std::cmp::Reverse(*bytes));
+        Ok(processes)
+    }
+    
+    async fn execute_script(&self, script: &str, lang: &str) -> Result<String, CuaError> {
+        tracing::debug!(lang, "MockComputer: Executing script");
+        self.record("execute_script", json!({ "script": script, "lang": lang })).await?;
+        Ok(String::new())
+    }
+    
+    async fn notify(&self, message: &str, duration_ms: u32) -> Result<(), CuaError> {
+        tracing::debug!(message, duration_ms, "MockComputer: Showing notification");
+        self.record("notify", json!({ "message": message, "duration_ms": duration_ms })).await
+    }
+    
+    async fn get_battery_level(&self) -> Result<Option<f32>, CuaError> {
+        Ok(*self.battery_level.read().unwrap())
+    }
+    
+    fn supports_network_emulation(&self) -> bool {
+        *self.network_emulation.read().unwrap()
+    }
+    
+    async fn simulate_network_condition(&self, latency_ms: u32, packet_loss: f32) -> Result<(), CuaError> {
+        if !self.supports_network_emulation() {
+            return Err(CuaError::ActionError("Network emulation not supported by this computer".to_string()));
+        }
+        if !(0.0..=1.0).contains(&packet_loss) {
+            return Err(CuaError::ActionError(format!("Packet loss {} is outside 0.0..=1.0", packet_loss)));
+        }
+        tracing::debug!(latency_ms, packet_loss, "MockComputer: Simulating network condition");
+        self.record("simulate_network_condition", json!({ "latency_ms": latency_ms, "packet_loss": packet_loss })).await
+    }
+    
+    async fn clear_network_conditions(&self) -> Result<(), CuaError> {
+        if !self.supports_network_emulation() {
+            return Err(CuaError::ActionError("Network emulation not supported by this computer".to_string()));
+        }
+        tracing::debug!("MockComputer: Clearing network conditions");
+        self.record("clear_network_conditions", json!({})).await
+    }
+    
+    async fn accessibility_tree(&self) -> Result<AccessibilityNode, CuaError> {
+        self.accessibility_tree.read().unwrap().clone().ok_or_else(|| {
+            CuaError::ActionError("No accessibility tree set on MockComputer".to_string())
+        })
+    }
+    
+    async fn measure_latency(&self) -> Result<Duration, CuaError> {
+        let start = Instant::now();
+        let latency = *self.latency.read().unwrap();
+        sleep(latency).await;
+        Ok(start.elapsed())
+    }
+    
+    async fn screen_color_at(&self, x: u32, y: u32) -> Result<(u8, u8, u8), CuaError> {
+        if x >= self.dimensions.0 || y >= self.dimensions.1 {
+            return Err(CuaError::ActionError(format!(
+                "Pixel ({}, {}) is outside the {}x{} screen",
+                x, y, self.dimensions.0, self.dimensions.1
+            )));
+        }
+        let colors = self.pixel_colors.read().unwrap();
+        Ok(colors.get(&(x, y)).copied().unwrap_or((255, 255, 255)))
+    }
+    
     // Add a new method to handle browser navigation
     // This is not in the Computer trait, but we'll add it to enable graceful handling
     async fn goto(&self, url: &str) -> Result<(), CuaError> {
         if self.environment == "browser" {
-            println!("MockComputer: Navigating to URL: {}", url);
+            tracing::debug!(url, "MockComputer: Navigating to URL");
             *self.current_url.write().unwrap() = url.to_string();
             Ok(())
         } else {
-            println!("MockComputer: Cannot navigate to URL in non-browser environment");
+            tracing::warn!("MockComputer: Cannot navigate to URL in non-browser environment");
             Err(CuaError::ActionError("Cannot navigate to URL in non-browser environment".to_string()))
         }
     }
@@ -177,4 +852,408 @@ mod tests {
         computer.goto("https://google.com").await.unwrap();
         assert_eq!(computer.get_current_url().await.unwrap(), "https://google.com");
     }
-}
\ No newline at end of file
+    
+    #[tokio::test]
+    async fn test_mock_clipboard_history() {
+        let computer = MockComputer::new("linux", 1024, 768);
+        
+        computer.set_clipboard("first").await.unwrap();
+        computer.set_clipboard("second").await.unwrap();
+        computer.set_clipboard("third").await.unwrap();
+        
+        let history = computer.clipboard_history(10).await.unwrap();
+        let contents: Vec<&str> = history.iter().map(|e| e.content.as_str()).collect();
+        assert_eq!(contents, vec!["third", "second", "first"]);
+        assert_eq!(computer.get_clipboard().await.unwrap(), "third");
+        assert_eq!(computer.clipboard_history(2).await.unwrap().len(), 2);
+    }
+    
+    #[tokio::test]
+    async fn test_mock_measure_latency() {
+        let computer = MockComputer::new("linux", 1024, 768);
+        computer.set_latency(Duration::from_millis(50));
+        
+        let latency = computer.measure_latency().await.unwrap();
+        
+        assert!(latency >= Duration::from_millis(50));
+    }
+    
+    #[tokio::test]
+    async fn test_mock_screen_color_at() {
+        let computer = MockComputer::new("linux", 1024, 768);
+        computer.set_pixel_color(10, 20, (255, 0, 0));
+        
+        assert_eq!(computer.screen_color_at(0, 0).await.unwrap(), (255, 255, 255));
+        assert_eq!(computer.screen_color_at(10, 20).await.unwrap(), (255, 0, 0));
+        assert!(matches!(computer.screen_color_at(1024, 0).await, Err(CuaError::ActionError(_))));
+    }
+    
+    #[tokio::test]
+    async fn test_paste_from_clipboard_shortcut_per_environment() {
+        for (environment, modifier) in [("linux", "ctrl"), ("windows", "ctrl"), ("mac", "super")] {
+            let computer = MockComputer::new(environment, 1024, 768);
+            computer.paste_from_clipboard().await.unwrap();
+            computer.copy_selection().await.unwrap();
+            
+            let history = computer.action_history();
+            assert_eq!(history[0].params["keys"], json!([modifier, "v"]), "{}", environment);
+            assert_eq!(history[1].params["keys"], json!([modifier, "c"]), "{}", environment);
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_get_focused_text() {
+        for (environment, modifier) in [("linux", "ctrl"), ("mac", "super")] {
+            let computer = MockComputer::new(environment, 1024, 768);
+            computer.set_focused_text("Dear team,\nthe build is green.");
+            
+            let text = computer.get_focused_text().await.unwrap();
+            
+            assert_eq!(text, "Dear team,\nthe build is green.");
+            let history = computer.action_history();
+            assert_eq!(history[0].params["keys"], json!([modifier, "a"]), "{}", environment);
+            assert_eq!(history[1].params["keys"], json!([modifier, "c"]), "{}", environment);
+            assert_eq!(computer.get_clipboard().await.unwrap(), text);
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_highlight_region_records_call() {
+        let computer = MockComputer::new("linux", 1024, 768);
+        
+        computer.highlight_region(100, 200, 50, 30, "red", 800).await.unwrap();
+        
+        let history = computer.action_history();
+        assert_eq!(history[0].action_type, "highlight_region");
+        assert_eq!(history[0].params, json!({ "x": 100, "y": 200, "width": 50, "height": 30, "color": "red", "duration_ms": 800 }));
+    }
+    
+    #[tokio::test]
+    async fn test_read_env_var_allowlist() {
+        let computer = MockComputer::new("linux", 1024, 768);
+        
+        assert_eq!(computer.read_env_var("HOME").await.unwrap(), std::env::var("HOME").unwrap());
+        assert!(matches!(computer.read_env_var("OPENAI_API_KEY").await, Err(CuaError::SafetyError(_))));
+        
+        computer.set_env_allowlist(vec!["LANG".to_string()]);
+        assert!(matches!(computer.read_env_var("HOME").await, Err(CuaError::SafetyError(_))));
+    }
+    
+    #[tokio::test]
+    async fn test_mouse_wheel_click_records_middle_button() {
+        let computer = MockComputer::new("linux", 1024, 768);
+        
+        computer.click(10, 20, "left").await.unwrap();
+        computer.mouse_wheel_click(30, 40).await.unwrap();
+        
+        let history = computer.action_history();
+        assert_eq!(history[0].params, json!({ "x": 10, "y": 20, "button": "left" }));
+        assert_eq!(history[1].params, json!({ "x": 30, "y": 40, "button": "middle" }));
+    }
+    
+    #[tokio::test(start_paused = true)]
+    async fn test_press_and_hold_records_key_and_duration() {
+        let computer = MockComputer::new("linux", 1024, 768);
+        let start = tokio::time::Instant::now();
+        
+        computer.press_and_hold("space", 750).await.unwrap();
+        
+        assert_eq!(start.elapsed(), Duration::from_millis(750));
+        let history = computer.action_history();
+        assert_eq!(history[0].action_type, "press_and_hold");
+        assert_eq!(history[0].params, json!({ "key": "space", "duration_ms": 750 }));
+    }
+    
+    #[tokio::test(start_paused = true)]
+    async fn test_monitor_clipboard_changes() {
+        let computer = MockComputer::new("linux", 1024, 768);
+        computer.queue_clipboard_values(&["hunter2", "hunter2", "s3cr3t-Pa55", "https://example.com/reset"]);
+        
+        let values = computer.monitor_clipboard_changes(500).await.unwrap();
+        
+        assert_eq!(values, vec!["hunter2", "s3cr3t-Pa55", "https://example.com/reset"]);
+    }
+    
+    #[tokio::test(start_paused = true)]
+    async fn test_wait_for_clipboard_change() {
+        let computer = MockComputer::new("linux", 1024, 768);
+        computer.set_clipboard("old").await.unwrap();
+        computer.schedule_clipboard_change(250, "copied text");
+        
+        let started = tokio::time::Instant::now();
+        assert_eq!(computer.wait_for_clipboard_change(1000).await.unwrap(), "copied text");
+        assert!(started.elapsed() < Duration::from_millis(400));
+        assert!(!computer.clipboard_is_empty().await.unwrap());
+        
+        let timed_out = computer.wait_for_clipboard_change(300).await;
+        assert!(matches!(timed_out, Err(CuaError::ActionError(msg)) if msg.starts_with("timeout")));
+    }
+    
+    #[tokio::test]
+    async fn test_assert_text_visible() {
+        let computer = MockComputer::new("linux", 1920, 1080);
+        computer.set_visible_texts(&[("Order confirmed", (800, 100, 300, 40)), ("Help", (20, 1040, 60, 20))]);
+        
+        assert!(computer.assert_text_visible("order confirmed").await.unwrap());
+        assert!(!computer.assert_text_visible("Payment failed").await.unwrap());
+        assert!(computer.assert_text_visible_within("Confirmed", 700, 50, 500, 200).await.unwrap());
+        assert!(!computer.assert_text_visible_within("Help", 700, 50, 500, 200).await.unwrap());
+        assert!(!computer.assert_text_visible_within("Order confirmed", 900, 50, 500, 200).await.unwrap());
+        assert!(!computer.assert_text_visible_within("Help", u32::MAX, u32::MAX, u32::MAX, u32::MAX).await.unwrap());
+        assert!(!computer.assert_text_visible("").await.unwrap());
+        assert!(!computer.assert_text_visible_within("", 0, 0, 1920, 1080).await.unwrap());
+    }
+    
+    #[tokio::test(start_paused = true)]
+    async fn test_detect_dialog() {
+        let computer = MockComputer::new("linux", 1920, 1080);
+        assert_eq!(computer.detect_dialog(1000).await.unwrap(), None);
+        
+        let dialog = DialogInfo {
+            title: "File changed".to_string(),
+            message: "The file has changed on disk. Reload it?".to_string(),
+            buttons: vec!["Reload".to_string(), "Keep".to_string()],
+            bounds: (760, 440, 400, 200),
+        };
+        computer.inject_dialog(dialog.clone());
+        
+        assert_eq!(computer.detect_dialog(1000).await.unwrap(), Some(dialog.clone()));
+        assert_eq!(computer.detect_dialog(0).await.unwrap(), Some(dialog));
+        computer.click(100, 100, "left").await.unwrap();
+        assert!(computer.detect_dialog(0).await.unwrap().is_some());
+        computer.click(800, 500, "left").await.unwrap();
+        assert_eq!(computer.detect_dialog(0).await.unwrap(), None);
+    }
+    
+    #[tokio::test]
+    async fn test_wait_until_returns_matching_screenshot() {
+        let computer = MockComputer::new("linux", 1920, 1080);
+        computer.inject_sequence(vec!["bG9hZGluZw==".to_string(), "cmVhZHk=".to_string()]);
+        let checked = Arc::new(RwLock::new(Vec::new()));
+        let seen = checked.clone();
+        
+        let screenshot = computer
+            .wait_until(Box::new(move |s: &str| {
+                seen.write().unwrap().push(s.to_string());
+                s == "cmVhZHk="
+            }), 1000)
+            .await
+            .unwrap();
+        
+        assert_eq!(screenshot, "cmVhZHk=");
+        assert_eq!(*checked.read().unwrap(), vec!["bG9hZGluZw==", "cmVhZHk="]);
+        assert!(matches!(computer.wait_until(Box::new(|_: &str| false), 300).await, Err(CuaError::ActionError(_))));
+    }
+    
+    #[tokio::test]
+    async fn test_dismiss_dialog_clicks_button() {
+        let computer = MockComputer::new("linux", 1920, 1080);
+        computer.inject_dialog(DialogInfo {
+            title: "Delete file?".to_string(),
+            message: String::new(),
+            buttons: vec!["Cancel".to_string(), "OK".to_string()],
+            bounds: (760, 440, 400, 200),
+        });
+        computer.set_visible_texts(&[("OK", (20, 20, 40, 20)), ("Cancel", (900, 590, 80, 24)), ("OK", (1020, 590, 40, 24))]);
+        
+        computer.dismiss_dialog("ok").await.unwrap();
+        
+        let history = computer.action_history();
+        assert_eq!(history[0].action_type, "click");
+        assert_eq!((history[0].params["x"].as_i64(), history[0].params["y"].as_i64()), (Some(1040), Some(602)));
+        assert!(matches!(computer.dismiss_dialog("OK").await, Err(CuaError::ActionError(_))));
+    }
+    
+    #[tokio::test]
+    async fn test_dismiss_dialog_prefers_accessibility_bounds() {
+        let computer = MockComputer::new("linux", 1920, 1080);
+        let node = |role: &str, name: &str, bounds, children| AccessibilityNode {
+            role: role.to_string(),
+            name: name.to_string(),
+            value: None,
+            bounds,
+            children,
+        };
+        computer.set_accessibility_tree(node("application", "Editor", (0, 0, 1920, 1080), vec![
+            node("push button", "Save", (10, 10, 60, 24), vec![]),
+            node("dialog", "Save changes?", (760, 440, 400, 200), vec![
+                node("push button", "Save", (1060, 600, 80, 30), vec![]),
+            ]),
+        ]));
+        computer.inject_dialog(DialogInfo {
+            title: "Save changes?".to_string(),
+            message: String::new(),
+            buttons: Vec::new(),
+            bounds: (760, 440, 400, 200),
+        });
+        
+        computer.dismiss_dialog("Save").await.unwrap();
+        
+        let history = computer.action_history();
+        assert_eq!((history[0].params["x"].as_i64(), history[0].params["y"].as_i64()), (Some(1100), Some(615)));
+    }
+    
+    #[tokio::test]
+    async fn test_notify_recorded() {
+        let computer = MockComputer::new("linux", 1920, 1080);
+        
+        computer.notify("I need your input to proceed", 4000).await.unwrap();
+        
+        let history = computer.action_history();
+        assert_eq!(history[0].action_type, "notify");
+        assert_eq!(history[0].params, json!({ "message": "I need your input to proceed", "duration_ms": 4000 }));
+    }
+    
+    #[tokio::test]
+    async fn test_simulate_network_condition_recorded() {
+        let computer = MockComputer::new("browser", 1280, 800);
+        
+        computer.simulate_network_condition(500, 0.25).await.unwrap();
+        computer.clear_network_conditions().await.unwrap();
+        
+        let history = computer.action_history();
+        assert_eq!(history[0].action_type, "simulate_network_condition");
+        assert_eq!(history[0].params, json!({ "latency_ms": 500, "packet_loss": 0.25 }));
+        assert_eq!(history[1].action_type, "clear_network_conditions");
+        assert!(computer.simulate_network_condition(0, 1.5).await.is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_mock_hover() {
+        let computer = MockComputer::new("linux", 1024, 768);
+        computer.hover(100, 200, 500).await.unwrap();
+        
+        assert_eq!(computer.cursor_position(), (100, 200));
+        let history = computer.action_history();
+        assert_eq!(history[0].action_type, "hover");
+        assert_eq!(history[0].params["duration_ms"], 500);
+    }
+    
+    #[tokio::test]
+    async fn test_button_down_move_up_matches_drag() {
+        let computer = MockComputer::new("linux", 1024, 768);
+        computer.move_cursor(10, 20).await.unwrap();
+        computer.mouse_button_down("left").await.unwrap();
+        assert!(computer.held_buttons().contains("left"));
+        computer.move_cursor(50, 60).await.unwrap();
+        computer.move_cursor(90, 100).await.unwrap();
+        computer.mouse_button_up("left").await.unwrap();
+        assert!(computer.held_buttons().is_empty());
+        let manual = computer.pressed_path();
+        assert_eq!(manual, vec![(10, 20), (50, 60), (90, 100)]);
+        
+        let path: Vec<HashMap<String, i32>> = [(10, 20), (50, 60), (90, 100)]
+            .iter()
+            .map(|&(x, y)| HashMap::from([("x".to_string(), x), ("y".to_string(), y)]))
+            .collect();
+        computer.drag(&path).await.unwrap();
+        
+        assert_eq!(manual, computer.pressed_path());
+        assert_eq!(computer.cursor_position(), (90, 100));
+        assert!(computer.mouse_button_up("left").await.is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_failed_button_up_keeps_button_held() {
+        let computer = MockComputer::new("linux", 1024, 768);
+        computer.mouse_button_down("left").await.unwrap();
+        computer.fail_action("mouse_up");
+        
+        assert!(computer.mouse_button_up("left").await.is_err());
+        
+        assert!(computer.held_buttons().contains("left"));
+    }
+    
+    fn click_type_screenshot() -> Vec<ExpectedAction> {
+        vec![
+            ExpectedAction::matching("click", Box::new(|params| params["button"] == "left")),
+            ExpectedAction::matching("type", Box::new(|params| params["text"] == "hello")),
+            ExpectedAction::new("screenshot"),
+        ]
+    }
+    
+    #[tokio::test]
+    async fn test_expect_sequence_in_order() {
+        let computer = MockComputer::new("linux", 1024, 768);
+        computer.expect_sequence(click_type_screenshot());
+        
+        computer.click(10, 20, "left").await.unwrap();
+        computer.type_text("hello").await.unwrap();
+        computer.screenshot().await.unwrap();
+        
+        computer.verify();
+    }
+    
+    #[tokio::test]
+    #[should_panic(expected = "expected click but got type")]
+    async fn test_expect_sequence_wrong_order_panics() {
+        let computer = MockComputer::new("linux", 1024, 768);
+        computer.expect_sequence(click_type_screenshot());
+        
+        computer.type_text("hello").await.unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_screenshot_as_array_is_rgba() {
+        let computer = MockComputer::new("linux", 1024, 768);
+        let image = image::RgbaImage::from_pixel(32, 24, image::Rgba([10, 20, 30, 255]));
+        computer.set_screenshot(&crate::vision::encode_png(&image.into()).unwrap());
+        
+        let (mut width, mut height) = (0, 0);
+        let data = computer.screenshot_as_array(&mut width, &mut height).await.unwrap();
+        assert_eq!((width, height), (32, 24));
+        assert_eq!(data.len(), (width * height * 4) as usize);
+        assert_eq!(data[..4], [10, 20, 30, 255]);
+        
+        computer.set_raw_screenshot(vec![0; 4 * 3 * 4], 4, 3);
+        let data = computer.screenshot_as_array(&mut width, &mut height).await.unwrap();
+        assert_eq!(data.len(), (width * height * 4) as usize);
+        let png = crate::vision::raw_to_base64_png(&data, width, height).unwrap();
+        assert_eq!(decode_screenshot(&png).unwrap().width(), 4);
+    }
+    
+    #[tokio::test]
+    async fn test_mock_zoom_translates_cursor() {
+        let computer = MockComputer::new("linux", 1024, 768);
+        computer.zoom(2.0, 500, 400).await.unwrap();
+        computer.move_cursor(600, 350).await.unwrap();
+        
+        let (x, y) = computer.cursor_position();
+        let zoom = computer.zoom_state();
+        assert_eq!(zoom.factor, 2.0);
+        assert_eq!(zoom.apply(x, y), ((x - 500) * 2 + 500, (y - 400) * 2 + 400));
+        assert_eq!(zoom.apply(x, y), (700, 300));
+    }
+    
+    #[tokio::test(start_paused = true)]
+    async fn test_simulated_latency() {
+        let computer = MockComputer::new("linux", 1024, 768);
+        computer.simulate_latency(200, 400);
+        
+        for _ in 0..10 {
+            let start = tokio::time::Instant::now();
+            computer.click(10, 20, "left").await.unwrap();
+            let elapsed = start.elapsed();
+            assert!(elapsed >= Duration::from_millis(200) && elapsed <= Duration::from_millis(400), "{:?}", elapsed);
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_simulated_failure_rate() {
+        let computer = MockComputer::new("linux", 1024, 768);
+        
+        computer.simulate_failure_rate(1.0);
+        assert!(matches!(computer.click(10, 20, "left").await, Err(CuaError::ActionError(msg)) if msg == "simulated failure"));
+        assert!(computer.screenshot().await.is_err());
+        
+        computer.simulate_failure_rate(0.5);
+        let mut failures = 0;
+        for _ in 0..400 {
+            if computer.type_text("a").await.is_err() {
+                failures += 1;
+            }
+        }
+        assert!((100..300).contains(&failures), "{} failures", failures);
+        assert_eq!(computer.action_history().len(), 400 - failures);
+    }
+}
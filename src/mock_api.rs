@@ -0,0 +1,165 @@
+//! Local HTTP server standing in for the OpenAI API in tests
+//!
+//! Responses are queued with [`MockApi::push_response`] and served in order.
+//! Every request the server receives is recorded so tests can assert on the
+//! exact JSON body the client sent.
+
+use crate::api::OpenAIClient;
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// A request received by the mock server
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub path: String,
+    pub body: Value,
+}
+
+/// Mock OpenAI API server bound to a random local port
+pub struct MockApi {
+    port: u16,
+    requests: Arc<Mutex<Vec<RecordedRequest>>>,
+    responses: Arc<Mutex<VecDeque<QueuedResponse>>>,
+}
+
+/// Status, extra headers and JSON body of a queued response
+type QueuedResponse = (u16, Vec<(String, String)>, Value);
+
+impl MockApi {
+    /// Start the server in a background task
+    pub async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let responses = Arc::new(Mutex::new(VecDeque::new()));
+
+        let requests_clone = requests.clone();
+        let responses_clone = responses.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let requests = requests_clone.clone();
+                let responses = responses_clone.clone();
+                tokio::spawn(async move {
+                    let _ = handle_connection(stream, requests, responses).await;
+                });
+            }
+        });
+
+        Self { port, requests, responses }
+    }
+
+    /// Base URL to pass to [`OpenAIClient::with_base_url`]
+    pub fn base_url(&self) -> String {
+        format!("http://127.0.0.1:{}/v1", self.port)
+    }
+
+    /// Create a client that talks to this server
+    pub fn client(&self) -> OpenAIClient {
+        OpenAIClient::new("test_key".to_string(), None, Some("test_model".to_string()))
+            .with_base_url(&self.base_url())
+    }
+
+    /// Queue a response to be served to the next request
+    pub fn push_response(&self, status: u16, body: Value) {
+        self.push_response_with_headers(status, &[], body);
+    }
+
+    /// Queue a response with extra headers (e.g. `Retry-After`)
+    pub fn push_response_with_headers(&self, status: u16, headers: &[(&str, &str)], body: Value) {
+        let headers = headers.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect();
+        self.responses.lock().unwrap().push_back((status, headers, body));
+    }
+
+    /// All requests received so far
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+/// Build a Responses API body with the given output items
+pub fn response_body(output: Vec<Value>) -> Value {
+    json!({ "output": output })
+}
+
+/// Build an assistant message output item
+pub fn assistant_message(text: &str) -> Value {
+    json!({
+        "type": "message",
+        "role": "assistant",
+        "content": [{ "type": "output_text", "text": text }],
+    })
+}
+
+/// Build a `computer_call` output item for the given action
+pub fn computer_call(call_id: &str, action: Value) -> Value {
+    json!({
+        "type": "computer_call",
+        "id": format!("cc_{}", call_id),
+        "call_id": call_id,
+        "action": action,
+        "pending_safety_checks": [],
+    })
+}
+
+async fn handle_connection(
+    mut stream: TcpStream,
+    requests: Arc<Mutex<Vec<RecordedRequest>>>,
+    responses: Arc<Mutex<VecDeque<QueuedResponse>>>,
+) -> std::io::Result<()> {
+    // Read until the end of the headers
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 4096];
+    let header_end = loop {
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            return Ok(());
+        }
+        buffer.extend_from_slice(&chunk[..n]);
+        if let Some(pos) = buffer.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos + 4;
+        }
+    };
+
+    let head = String::from_utf8_lossy(&buffer[..header_end]).to_string();
+    let mut request_line = head.lines().next().unwrap_or("").split_whitespace();
+    let method = request_line.next().unwrap_or("").to_string();
+    let path = request_line.next().unwrap_or("").to_string();
+    let content_length = head
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, value)| value.trim().parse::<usize>().ok())
+        .unwrap_or(0);
+
+    // Read the rest of the body
+    while buffer.len() < header_end + content_length {
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            break;
+        }
+        buffer.extend_from_slice(&chunk[..n]);
+    }
+    let body = serde_json::from_slice(&buffer[header_end..]).unwrap_or(Value::Null);
+    requests.lock().unwrap().push(RecordedRequest { method, path, body });
+
+    let (status, headers, body) = responses
+        .lock()
+        .unwrap()
+        .pop_front()
+        .unwrap_or((500, Vec::new(), json!({ "error": "no mock response queued" })));
+    let body = body.to_string();
+    let extra_headers: String = headers.iter().map(|(name, value)| format!("{}: {}\r\n", name, value)).collect();
+    let response = format!(
+        "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\n{}Connection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        extra_headers,
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
@@ -0,0 +1,221 @@
+//! Event-driven monitoring of an agent run
+//!
+//! Observers registered on the [`Agent`](crate::agent::Agent) are notified of
+//! each API request and response, action, function tool call, screenshot,
+//! model message, error and completed turn. Every method has an empty default,
+//! so an observer only implements the events it cares about.
+
+use crate::api::ApiResponse;
+use crate::error::CuaError;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::fs::File;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Token usage and estimated cost accumulated over a session
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct UsageStats {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    /// Estimated cost in USD
+    pub estimated_cost: f64,
+}
+
+/// Receives agent events as they happen
+pub trait AgentObserver: Send + Sync {
+    /// A computer action is about to be performed
+    fn on_action(&self, _action_type: &str, _params: &Value) {}
+    
+    /// A computer action finished after `duration`, with `error` if it failed
+    fn on_action_complete(&self, _action_type: &str, _duration: Duration, _error: Option<&CuaError>) {}
+    
+    /// A function tool call was handled, producing `output` for the model
+    fn on_function_call(&self, _name: &str, _arguments: &Value, _output: &str) {}
+    
+    /// A request with `items` input items is about to be sent to the API
+    fn on_api_request(&self, _items: usize, _previous_response_id: Option<&str>) {}
+    
+    /// The API answered a request, or the request failed
+    fn on_api_response(&self, _response: Result<&ApiResponse, &CuaError>) {}
+    
+    /// A screenshot was taken to send to the model during `turn`
+    fn on_screenshot(&self, _base64: &str, _turn: usize) {}
+    
+    /// The model replied with a message
+    fn on_model_output(&self, _text: &str) {}
+    
+    /// A turn failed with `err`
+    fn on_error(&self, _err: &CuaError) {}
+    
+    /// The output of API response number `turn` has been handled
+    fn on_turn_complete(&self, _turn: usize, _usage: &UsageStats) {}
+}
+
+/// Observer that forwards every event to each registered observer, in registration order
+///
+/// Clones share the registered observers, so an agent's forks report to the
+/// same observers as the agent.
+#[derive(Default, Clone)]
+pub struct CompositeObserver {
+    observers: Vec<Arc<dyn AgentObserver>>,
+}
+
+impl CompositeObserver {
+    /// Create an observer with no registered observers
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Register an observer
+    pub fn add(&mut self, observer: Box<dyn AgentObserver>) {
+        self.observers.push(Arc::from(observer));
+    }
+    
+    /// Unregister the most recently added observer
+    pub fn pop(&mut self) -> Option<Arc<dyn AgentObserver>> {
+        self.observers.pop()
+    }
+    
+    /// Number of registered observers
+    pub fn len(&self) -> usize {
+        self.observers.len()
+    }
+    
+    /// Whether no observers are registered
+    pub fn is_empty(&self) -> bool {
+        self.observers.is_empty()
+    }
+}
+
+impl AgentObserver for CompositeObserver {
+    fn on_action(&self, action_type: &str, params: &Value) {
+        self.observers.iter().for_each(|o| o.on_action(action_type, params));
+    }
+    
+    fn on_action_complete(&self, action_type: &str, duration: Duration, error: Option<&CuaError>) {
+        self.observers.iter().for_each(|o| o.on_action_complete(action_type, duration, error));
+    }
+    
+    fn on_function_call(&self, name: &str, arguments: &Value, output: &str) {
+        self.observers.iter().for_each(|o| o.on_function_call(name, arguments, output));
+    }
+    
+    fn on_api_request(&self, items: usize, previous_response_id: Option<&str>) {
+        self.observers.iter().for_each(|o| o.on_api_request(items, previous_response_id));
+    }
+    
+    fn on_api_response(&self, response: Result<&ApiResponse, &CuaError>) {
+        self.observers.iter().for_each(|o| o.on_api_response(response));
+    }
+    
+    fn on_screenshot(&self, base64: &str, turn: usize) {
+        self.observers.iter().for_each(|o| o.on_screenshot(base64, turn));
+    }
+    
+    fn on_model_output(&self, text: &str) {
+        self.observers.iter().for_each(|o| o.on_model_output(text));
+    }
+    
+    fn on_error(&self, err: &CuaError) {
+        self.observers.iter().for_each(|o| o.on_error(err));
+    }
+    
+    fn on_turn_complete(&self, turn: usize, usage: &UsageStats) {
+        self.observers.iter().for_each(|o| o.on_turn_complete(turn, usage));
+    }
+}
+
+/// Observer that logs events like the agent's `print_steps` output
+pub struct PrintObserver;
+
+impl AgentObserver for PrintObserver {
+    fn on_action(&self, action_type: &str, _params: &Value) {
+        tracing::info!(action = action_type, "Computer action");
+    }
+    
+    fn on_model_output(&self, text: &str) {
+        tracing::info!("{}", text);
+    }
+    
+    fn on_error(&self, err: &CuaError) {
+        tracing::error!(error = %err, "Turn failed");
+    }
+    
+    fn on_turn_complete(&self, turn: usize, usage: &UsageStats) {
+        tracing::debug!(turn, input_tokens = usage.input_tokens, output_tokens = usage.output_tokens, "Turn complete");
+    }
+}
+
+/// Observer that writes one JSON object per event to a file (JSON Lines)
+///
+/// Screenshots are recorded by size only, to keep the file small.
+pub struct JsonObserver(Mutex<File>);
+
+impl JsonObserver {
+    /// Write events to `file`
+    pub fn new(file: File) -> Self {
+        Self(Mutex::new(file))
+    }
+    
+    /// Append one event line, logging rather than failing on IO errors
+    fn write_event(&self, event: Value) {
+        let mut file = self.0.lock().unwrap();
+        if let Err(e) = writeln!(file, "{}", event) {
+            tracing::warn!(error = %e, "Failed to write observer event");
+        }
+    }
+}
+
+impl AgentObserver for JsonObserver {
+    fn on_action(&self, action_type: &str, params: &Value) {
+        self.write_event(json!({ "event": "action", "action_type": action_type, "params": params }));
+    }
+    
+    fn on_screenshot(&self, base64: &str, turn: usize) {
+        self.write_event(json!({ "event": "screenshot", "turn": turn, "base64_len": base64.len() }));
+    }
+    
+    fn on_model_output(&self, text: &str) {
+        self.write_event(json!({ "event": "model_output", "text": text }));
+    }
+    
+    fn on_error(&self, err: &CuaError) {
+        self.write_event(json!({ "event": "error", "error": err.into_json() }));
+    }
+    
+    fn on_turn_complete(&self, turn: usize, usage: &UsageStats) {
+        self.write_event(json!({ "event": "turn_complete", "turn": turn, "usage": usage }));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    
+    #[test]
+    fn test_json_observer_writes_one_line_per_event() {
+        let path = std::env::temp_dir().join(format!("cua_observer_{}.jsonl", uuid::Uuid::new_v4()));
+        let observer = JsonObserver::new(File::create(&path).unwrap());
+        
+        observer.on_action("click", &json!({ "x": 10, "y": 20 }));
+        observer.on_screenshot("c2NyZWVu", 1);
+        observer.on_model_output("Line one\nline two");
+        observer.on_error(&CuaError::ActionError("click failed".to_string()));
+        observer.on_turn_complete(1, &UsageStats { input_tokens: 100, output_tokens: 20, estimated_cost: 0.001 });
+        
+        let mut contents = String::new();
+        File::open(&path).unwrap().read_to_string(&mut contents).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        
+        let events: Vec<Value> = contents.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(events.len(), 5);
+        assert_eq!(events[0]["params"]["x"], 10);
+        assert_eq!(events[1]["base64_len"], 8);
+        assert_eq!(events[2]["text"], "Line one\nline two");
+        assert_eq!(events[3]["error"]["type"], "ActionError");
+        assert_eq!(events[4]["usage"]["input_tokens"], 100);
+    }
+}
@@ -0,0 +1,190 @@
+//! Text recognition on screenshots through the `tesseract` command line tool
+
+use crate::command::run_command;
+use crate::error::CuaError;
+use base64::{engine::general_purpose, Engine};
+use serde::{Deserialize, Serialize};
+
+/// A word recognized on the screen
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OcrWord {
+    pub text: String,
+    /// Bounding box as (x, y, width, height)
+    pub bounds: (i32, i32, u32, u32),
+    /// Line the word belongs to; words on the same line share this number
+    pub line: u32,
+}
+
+impl OcrWord {
+    /// Create a word on the given line
+    pub fn new(text: &str, bounds: (i32, i32, u32, u32), line: u32) -> Self {
+        Self {
+            text: text.to_string(),
+            bounds,
+            line,
+        }
+    }
+}
+
+/// Recognize the words on a base64 PNG screenshot
+///
+/// Requires `tesseract` to be installed and on the `PATH`.
+pub async fn ocr_extract(screenshot_base64: &str) -> Result<Vec<OcrWord>, CuaError> {
+    let png = general_purpose::STANDARD.decode(screenshot_base64)
+        .map_err(|e| CuaError::ScreenshotError(format!("Invalid base64 screenshot: {}", e)))?;
+    let path = std::env::temp_dir().join(format!("cua_ocr_{}.png", uuid::Uuid::new_v4().simple()));
+    tokio::fs::write(&path, png).await?;
+    
+    let result = run_command("tesseract", &[&path.to_string_lossy(), "stdout", "tsv"], None).await;
+    let _ = tokio::fs::remove_file(&path).await;
+    
+    Ok(parse_tsv(&result?))
+}
+
+/// Parse tesseract's TSV output into words
+pub fn parse_tsv(tsv: &str) -> Vec<OcrWord> {
+    tsv.lines()
+        .skip(1)
+        .filter_map(|row| {
+            // level page block paragraph line word left top width height confidence text
+            let fields: Vec<&str> = row.split('\t').collect();
+            if fields.len() < 12 || fields[0] != "5" || fields[11].trim().is_empty() {
+                return None;
+            }
+            let number = |i: usize| fields[i].parse::<i64>().ok();
+            let (block, paragraph, line) = (number(2)?, number(3)?, number(4)?);
+            Some(OcrWord {
+                text: fields[11].trim().to_string(),
+                bounds: (number(6)? as i32, number(7)? as i32, number(8)? as u32, number(9)? as u32),
+                // Unique per line across blocks and paragraphs
+                line: (block * 1_000_000 + paragraph * 1_000 + line) as u32,
+            })
+        })
+        .collect()
+}
+
+/// Join recognized words into text, one line per OCR line
+pub fn words_to_text(words: &[OcrWord]) -> String {
+    let mut text = String::new();
+    for (i, word) in words.iter().enumerate() {
+        if i > 0 {
+            text.push(if word.line == words[i - 1].line { ' ' } else { '\n' });
+        }
+        text.push_str(&word.text);
+    }
+    text
+}
+
+/// Keep the words lying entirely inside the `width`x`height` region at (left, top)
+pub fn words_within(words: Vec<OcrWord>, left: i64, top: i64, width: i64, height: i64) -> Vec<OcrWord> {
+    words
+        .into_iter()
+        .filter(|word| {
+            let (x, y) = (i64::from(word.bounds.0), i64::from(word.bounds.1));
+            x >= left && y >= top
+                && x + i64::from(word.bounds.2) <= left + width
+                && y + i64::from(word.bounds.3) <= top + height
+        })
+        .collect()
+}
+
+/// Find `description` among the recognized words and return the center of the match
+///
+/// Matching is case-insensitive and may span several consecutive words on a line.
+pub fn find_text(words: &[OcrWord], description: &str) -> Option<(i32, i32)> {
+    let target = description.trim().to_lowercase();
+    if target.is_empty() {
+        return None;
+    }
+    
+    for start in 0..words.len() {
+        let mut joined = String::new();
+        for end in start..words.len() {
+            if words[end].line != words[start].line {
+                break;
+            }
+            if end > start {
+                joined.push(' ');
+            }
+            joined.push_str(&words[end].text.to_lowercase());
+            
+            if joined.contains(&target) {
+                return Some(center_of(&words[start..=end]));
+            }
+        }
+    }
+    
+    None
+}
+
+/// Center of the box around the given words
+fn center_of(words: &[OcrWord]) -> (i32, i32) {
+    let left = words.iter().map(|w| w.bounds.0).min().unwrap_or(0);
+    let top = words.iter().map(|w| w.bounds.1).min().unwrap_or(0);
+    let right = words.iter().map(|w| w.bounds.0 + w.bounds.2 as i32).max().unwrap_or(0);
+    let bottom = words.iter().map(|w| w.bounds.1 + w.bounds.3 as i32).max().unwrap_or(0);
+    ((left + right) / 2, (top + bottom) / 2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_parse_tsv_and_find_text() {
+        let tsv = "level\tpage_num\tblock_num\tpar_num\tline_num\tword_num\tleft\ttop\twidth\theight\tconf\ttext\n\
+                   4\t1\t1\t1\t1\t0\t10\t20\t200\t16\t-1\t\n\
+                   5\t1\t1\t1\t1\t1\t10\t20\t60\t16\t95.5\tSave\n\
+                   5\t1\t1\t1\t1\t2\t80\t20\t70\t16\t96.1\tChanges\n\
+                   5\t1\t1\t1\t2\t1\t10\t50\t50\t16\t91.0\tCancel\n";
+        
+        let words = parse_tsv(tsv);
+        
+        assert_eq!(words.len(), 3);
+        assert_eq!(words[1], OcrWord::new("Changes", (80, 20, 70, 16), 1_001_001));
+        assert_eq!(find_text(&words, "save changes"), Some((80, 28)));
+        assert_eq!(find_text(&words, "Cancel"), Some((35, 58)));
+        assert_eq!(find_text(&words, "Changes Cancel"), None);
+        assert_eq!(find_text(&words, "Delete"), None);
+        assert_eq!(words_to_text(&words), "Save Changes\nCancel");
+    }
+    
+    #[tokio::test]
+    #[ignore] // Requires tesseract to be installed
+    async fn test_ocr_sample_text() {
+        let sample = general_purpose::STANDARD.encode(include_bytes!("../assets/ocr_sample.png"));
+        
+        let words = ocr_extract(&sample).await.unwrap();
+        
+        assert_eq!(words_to_text(&words), "Invoice 1042\nTotal due 12.50");
+    }
+    
+    #[tokio::test]
+    #[ignore] // Requires tesseract to be installed
+    async fn test_region_ocr_faster_than_full_screen() {
+        use crate::vision;
+        use image::{Rgba, RgbaImage};
+        use imageproc::drawing::draw_filled_rect_mut;
+        use imageproc::rect::Rect;
+        
+        let mut screen = RgbaImage::from_pixel(1920, 1080, Rgba([255, 255, 255, 255]));
+        for row in 0..40 {
+            draw_filled_rect_mut(&mut screen, Rect::at(40, 20 + row * 26).of_size(1800, 12), Rgba([0, 0, 0, 255]));
+        }
+        let full = vision::encode_png(&screen.into()).unwrap();
+        
+        let start = std::time::Instant::now();
+        ocr_extract(&full).await.unwrap();
+        let full_latency = start.elapsed();
+        
+        let start = std::time::Instant::now();
+        let region = vision::crop_screenshot(&full, 0, 0, 400, 100).unwrap();
+        ocr_extract(&region).await.unwrap();
+        let region_latency = start.elapsed();
+        
+        assert!(
+            region_latency < full_latency,
+            "region OCR took {:?}, full screen {:?}", region_latency, full_latency
+        );
+    }
+}
@@ -0,0 +1,102 @@
+//! Token bucket rate limiting for computer actions
+
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::{sleep, Instant};
+
+use crate::error::CuaError;
+
+/// Actions that may always run back to back, however low the rate
+const MIN_BURST: f64 = 2.0;
+
+/// Current fill level of the bucket
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+/// Limits how many actions may run per minute
+///
+/// Tokens refill continuously at the configured rate; each action takes one,
+/// waiting for the next token if the bucket is empty.
+pub struct ActionRateLimiter {
+    capacity: f64,
+    tokens_per_second: f64,
+    bucket: Mutex<Bucket>,
+}
+
+impl ActionRateLimiter {
+    /// Create a limiter allowing `actions_per_minute` actions, starting with a full bucket
+    ///
+    /// The bucket holds one second's worth of actions (at least two), which
+    /// bounds how many actions can run back to back. Fails if the rate is not
+    /// a positive, finite number.
+    pub fn new(actions_per_minute: f64) -> Result<Self, CuaError> {
+        if !actions_per_minute.is_finite() || actions_per_minute <= 0.0 {
+            return Err(CuaError::Other(format!(
+                "Action rate must be a positive number of actions per minute, got {}",
+                actions_per_minute
+            )));
+        }
+        let tokens_per_second = actions_per_minute / 60.0;
+        let capacity = tokens_per_second.ceil().max(MIN_BURST);
+        Ok(Self {
+            capacity,
+            tokens_per_second,
+            bucket: Mutex::new(Bucket {
+                tokens: capacity,
+                last_refill: Instant::now(),
+            }),
+        })
+    }
+    
+    /// Wait until an action is allowed, then take a token
+    pub async fn acquire(&self) {
+        loop {
+            let wait = {
+                let mut bucket = self.bucket.lock().unwrap();
+                let now = Instant::now();
+                let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+                bucket.tokens = (bucket.tokens + elapsed * self.tokens_per_second).min(self.capacity);
+                bucket.last_refill = now;
+                
+                if bucket.tokens >= 1.0 {
+                    bucket.tokens -= 1.0;
+                    return;
+                }
+                Duration::from_secs_f64((1.0 - bucket.tokens) / self.tokens_per_second)
+            };
+            
+            tracing::debug!(wait_ms = wait.as_millis() as u64, "Rate limiting action");
+            sleep(wait).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[tokio::test(start_paused = true)]
+    async fn test_third_action_waits_for_refill() {
+        let limiter = ActionRateLimiter::new(60.0).unwrap();
+        let start = Instant::now();
+        
+        limiter.acquire().await;
+        limiter.acquire().await;
+        let second = start.elapsed();
+        limiter.acquire().await;
+        let third = start.elapsed();
+        
+        assert!(second.as_secs_f64() < 0.01, "second action delayed {}s", second.as_secs_f64());
+        let delay = (third - second).as_secs_f64();
+        assert!(delay > 0.99 && delay < 1.01, "third action delayed {}s", delay);
+    }
+    
+    #[test]
+    fn test_rejects_invalid_rate() {
+        for rate in [0.0, -5.0, f64::NAN, f64::INFINITY] {
+            assert!(ActionRateLimiter::new(rate).is_err(), "accepted rate {}", rate);
+        }
+    }
+}
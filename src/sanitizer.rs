@@ -0,0 +1,96 @@
+//! Sanitizers that scrub sensitive data from typed text before it is logged
+
+/// Function that maps typed text to the version that is safe to log
+///
+/// Receives the typed text and its context: the model's latest message
+/// before the action, which usually says what field is being filled in.
+pub type InputSanitizer = Box<dyn Fn(&str, &str) -> String + Send + Sync>;
+
+/// Built-in sanitizer for personally identifiable information
+///
+/// Email addresses are replaced with `<EMAIL>`. Text typed into a password
+/// field is replaced with `*****` entirely.
+pub struct PiiSanitizer;
+
+impl PiiSanitizer {
+    /// Replace every email address in `text` with `<EMAIL>`
+    pub fn sanitize(text: &str) -> String {
+        let mut result = String::with_capacity(text.len());
+        let mut word = String::new();
+        
+        for c in text.chars() {
+            if c.is_whitespace() {
+                result.push_str(&Self::sanitize_word(&word));
+                result.push(c);
+                word.clear();
+            } else {
+                word.push(c);
+            }
+        }
+        result.push_str(&Self::sanitize_word(&word));
+        
+        result
+    }
+    
+    /// Sanitize `text` typed into a field described by `context`
+    ///
+    /// If the context mentions a password, the whole text is masked.
+    pub fn sanitize_in_context(text: &str, context: &str) -> String {
+        if context.to_lowercase().contains("password") {
+            "*****".to_string()
+        } else {
+            Self::sanitize(text)
+        }
+    }
+    
+    /// Create a boxed sanitizer for `Agent::with_input_sanitizer`, see `sanitize_in_context`
+    pub fn boxed() -> InputSanitizer {
+        Box::new(Self::sanitize_in_context)
+    }
+    
+    /// Replace a single word if it is an email address, keeping surrounding punctuation
+    fn sanitize_word(word: &str) -> String {
+        let is_punctuation = |c: char| matches!(c, ',' | ';' | ':' | '(' | ')' | '<' | '>' | '"' | '\'' | '.' | '!' | '?');
+        let core = word.trim_matches(is_punctuation);
+        if core.is_empty() || !is_email(core) {
+            return word.to_string();
+        }
+        
+        let start = word.find(core).unwrap_or(0);
+        format!("{}<EMAIL>{}", &word[..start], &word[start + core.len()..])
+    }
+}
+
+/// Check whether `s` looks like an email address
+fn is_email(s: &str) -> bool {
+    let Some((local, domain)) = s.split_once('@') else {
+        return false;
+    };
+    
+    !local.is_empty()
+        && local.chars().all(|c| c.is_alphanumeric() || "._%+-".contains(c))
+        && domain.contains('.')
+        && !domain.starts_with('.')
+        && !domain.ends_with('.')
+        && domain.chars().all(|c| c.is_alphanumeric() || c == '.' || c == '-')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_pii_sanitizer_masks_emails() {
+        assert_eq!(
+            PiiSanitizer::sanitize("Send it to alice@example.com, then bob.smith@mail.example.org."),
+            "Send it to <EMAIL>, then <EMAIL>."
+        );
+        assert_eq!(PiiSanitizer::sanitize("no email @ here"), "no email @ here");
+    }
+    
+    #[test]
+    fn test_pii_sanitizer_masks_password_fields() {
+        assert_eq!(PiiSanitizer::sanitize_in_context("hunter2", "Password field"), "*****");
+        assert_eq!(PiiSanitizer::sanitize_in_context("hunter2", "Search box"), "hunter2");
+    }
+}
@@ -0,0 +1,109 @@
+//! Scripted multi-step tasks for the agent
+//!
+//! A script is a sequence of prompts run one after another in the same
+//! conversation, each with its own timeout. Scripts can be loaded from JSON:
+//!
+//! ```json
+//! { "steps": [
+//!     { "input": "Open the settings app", "timeout_secs": 60 },
+//!     { "input": "Enable dark mode", "continue_on_error": true }
+//! ] }
+//! ```
+
+use crate::error::CuaError;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::Duration;
+
+/// A sequence of subtasks for the agent
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AgentScript {
+    pub steps: Vec<ScriptStep>,
+}
+
+impl AgentScript {
+    /// Build a script from (input, timeout) pairs that stop on the first error
+    pub fn new(steps: &[(&str, Option<Duration>)]) -> Self {
+        Self {
+            steps: steps
+                .iter()
+                .map(|(input, timeout)| ScriptStep {
+                    input: input.to_string(),
+                    timeout: *timeout,
+                    continue_on_error: false,
+                })
+                .collect(),
+        }
+    }
+    
+    /// Parse a script from JSON
+    pub fn from_json(json: &str) -> Result<Self, CuaError> {
+        Ok(serde_json::from_str(json)?)
+    }
+}
+
+/// A single subtask of an [`AgentScript`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScriptStep {
+    /// User input for this step
+    pub input: String,
+    /// Maximum time the step may take
+    #[serde(default, rename = "timeout_secs", with = "timeout_secs")]
+    pub timeout: Option<Duration>,
+    /// Whether to run the remaining steps if this one fails
+    #[serde(default)]
+    pub continue_on_error: bool,
+}
+
+/// Outcome of one script step
+#[derive(Debug)]
+pub struct StepResult {
+    /// User input of the step
+    pub input: String,
+    /// Items the step added to the conversation, or the error it failed with
+    pub outcome: Result<Vec<Value>, CuaError>,
+}
+
+impl StepResult {
+    /// Whether the step completed successfully
+    pub fn succeeded(&self) -> bool {
+        self.outcome.is_ok()
+    }
+}
+
+/// Serialize an optional timeout as fractional seconds
+mod timeout_secs {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+    
+    pub fn serialize<S: Serializer>(timeout: &Option<Duration>, serializer: S) -> Result<S::Ok, S::Error> {
+        match timeout {
+            Some(timeout) => serializer.serialize_some(&timeout.as_secs_f64()),
+            None => serializer.serialize_none(),
+        }
+    }
+    
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Duration>, D::Error> {
+        let secs = Option::<f64>::deserialize(deserializer)?;
+        secs.map(|secs| Duration::try_from_secs_f64(secs).map_err(serde::de::Error::custom))
+            .transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_parse_script_json() {
+        let script = AgentScript::from_json(r#"{ "steps": [
+            { "input": "Open the settings app", "timeout_secs": 1.5 },
+            { "input": "Enable dark mode", "continue_on_error": true }
+        ] }"#).unwrap();
+        
+        assert_eq!(script.steps[0].timeout, Some(Duration::from_millis(1500)));
+        assert!(!script.steps[0].continue_on_error);
+        assert_eq!(script.steps[1].timeout, None);
+        assert!(script.steps[1].continue_on_error);
+    }
+}
@@ -0,0 +1,105 @@
+//! Streaming event log written while the agent runs
+//!
+//! Every event is one JSON object per line:
+//! `{ "ts": <epoch ms>, "type": "...", "data": {...} }`. Lines are written and
+//! flushed by a background thread, so logging never blocks the agent and the
+//! log can be tailed during a session.
+
+use crate::api::ApiResponse;
+use crate::error::CuaError;
+use crate::observer::AgentObserver;
+use serde_json::{json, Value};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::mpsc::{self, Sender};
+use std::thread::JoinHandle;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Newline-delimited JSON event log, installed as an [`AgentObserver`]
+///
+/// Dropping the log waits for the pending lines to be written.
+pub struct EventLog {
+    sender: Option<Sender<String>>,
+    writer: Option<JoinHandle<()>>,
+}
+
+impl EventLog {
+    /// Create (or truncate) the log file at `path`
+    pub fn create(path: &Path) -> Result<Self, CuaError> {
+        let mut file = BufWriter::new(File::create(path)?);
+        let (sender, receiver) = mpsc::channel::<String>();
+        let writer = std::thread::Builder::new()
+            .name("event-log".to_string())
+            .spawn(move || {
+                for line in receiver {
+                    if let Err(e) = writeln!(file, "{}", line).and_then(|_| file.flush()) {
+                        tracing::warn!(error = %e, "Failed to write event log");
+                    }
+                }
+            })?;
+        Ok(Self { sender: Some(sender), writer: Some(writer) })
+    }
+    
+    /// Queue an event for the writer thread
+    pub fn emit(&self, event_type: &str, data: Value) {
+        let ts = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64;
+        let event = json!({ "ts": ts, "type": event_type, "data": data });
+        if let Some(sender) = &self.sender {
+            let _ = sender.send(event.to_string());
+        }
+    }
+}
+
+impl Drop for EventLog {
+    fn drop(&mut self) {
+        // Closing the channel ends the writer once it has written everything queued
+        self.sender.take();
+        if let Some(writer) = self.writer.take() {
+            let _ = writer.join();
+        }
+    }
+}
+
+impl AgentObserver for EventLog {
+    fn on_action(&self, action_type: &str, params: &Value) {
+        self.emit("action_start", json!({ "action_type": action_type, "params": params }));
+    }
+    
+    fn on_action_complete(&self, action_type: &str, duration: Duration, error: Option<&CuaError>) {
+        let duration_ms = duration.as_millis() as u64;
+        match error {
+            None => self.emit("action_complete", json!({ "action_type": action_type, "duration_ms": duration_ms })),
+            Some(e) => self.emit("action_failed", json!({
+                "action_type": action_type,
+                "duration_ms": duration_ms,
+                "error": e.into_json(),
+            })),
+        }
+    }
+    
+    fn on_function_call(&self, name: &str, arguments: &Value, output: &str) {
+        self.emit("function_call", json!({ "name": name, "arguments": arguments, "output_len": output.len() }));
+    }
+    
+    fn on_screenshot(&self, base64: &str, turn: usize) {
+        self.emit("screenshot", json!({ "turn": turn, "base64_len": base64.len() }));
+    }
+    
+    fn on_api_request(&self, items: usize, previous_response_id: Option<&str>) {
+        self.emit("api_request", json!({ "items": items, "previous_response_id": previous_response_id }));
+    }
+    
+    fn on_api_response(&self, response: Result<&ApiResponse, &CuaError>) {
+        match response {
+            Ok(response) => self.emit("api_response", json!({
+                "id": response.id,
+                "model": response.model,
+                "output_items": response.output.len(),
+                "input_tokens": response.usage.map(|usage| usage.input_tokens),
+                "output_tokens": response.usage.map(|usage| usage.output_tokens),
+            })),
+            Err(e) => self.emit("api_response", json!({ "error": e.to_string() })),
+        }
+    }
+}
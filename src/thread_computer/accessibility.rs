@@ -0,0 +1,157 @@
+//! Accessibility tree inspection through AT-SPI2 on Linux
+//!
+//! Walks the tree exposed by the AT-SPI registry over D-Bus. Only compiled
+//! with the `accessibility` feature.
+
+use crate::computer::AccessibilityNode;
+use crate::error::CuaError;
+use atspi::proxy::accessible::AccessibleProxy;
+use atspi::proxy::component::ComponentProxy;
+use atspi::proxy::text::TextProxy;
+use atspi::proxy::value::ValueProxy;
+use atspi::zbus::proxy::CacheProperties;
+use atspi::zbus::Connection;
+use atspi::{AccessibilityConnection, CoordType, Interface, State};
+use futures::future::BoxFuture;
+
+/// Stop descending below this depth; application trees can be very deep
+const MAX_DEPTH: usize = 12;
+
+/// Bus name and object path of the accessibility tree root
+const REGISTRY: &str = "org.a11y.atspi.Registry";
+const ROOT_PATH: &str = "/org/a11y/atspi/accessible/root";
+
+/// Read the accessibility tree starting at the AT-SPI registry root
+pub async fn accessibility_tree() -> Result<AccessibilityNode, CuaError> {
+    let connection = AccessibilityConnection::new().await.map_err(atspi_error)?;
+    build_node(connection.connection(), REGISTRY.to_string(), ROOT_PATH.to_string(), 0).await
+}
+
+/// Screen position of the caret in the focused text element, at the vertical middle of the line
+pub async fn text_cursor_position() -> Result<(i32, i32), CuaError> {
+    let connection = AccessibilityConnection::new().await.map_err(atspi_error)?;
+    let conn = connection.connection();
+    let (destination, path) = find_focused_text(conn, REGISTRY.to_string(), ROOT_PATH.to_string(), 0)
+        .await?
+        .ok_or_else(|| CuaError::ActionError("No focused text element".to_string()))?;
+    
+    let text = TextProxy::builder(conn)
+        .destination(destination.as_str())
+        .and_then(|b| b.path(path.as_str()))
+        .map_err(atspi_error)?
+        .cache_properties(CacheProperties::No)
+        .build()
+        .await
+        .map_err(atspi_error)?;
+    let offset = text.caret_offset().await.map_err(atspi_error)?;
+    let (x, y, _, height) = text.get_character_extents(offset, CoordType::Screen).await.map_err(atspi_error)?;
+    if height > 0 {
+        return Ok((x, y + height / 2));
+    }
+    
+    // At the end of the text there is no character under the caret; use the right edge of the previous one
+    let (x, y, width, height) = text.get_character_extents(offset - 1, CoordType::Screen).await.map_err(atspi_error)?;
+    Ok((x + width, y + height / 2))
+}
+
+/// Find the focused object implementing the Text interface, as (bus name, path)
+fn find_focused_text(
+    conn: &Connection,
+    destination: String,
+    path: String,
+    depth: usize,
+) -> BoxFuture<'_, Result<Option<(String, String)>, CuaError>> {
+    Box::pin(async move {
+        let accessible = AccessibleProxy::builder(conn)
+            .destination(destination.as_str())
+            .and_then(|b| b.path(path.as_str()))
+            .map_err(atspi_error)?
+            .cache_properties(CacheProperties::No)
+            .build()
+            .await
+            .map_err(atspi_error)?;
+        
+        let state = accessible.get_state().await.unwrap_or_default();
+        if state.contains(State::Focused) && accessible.get_interfaces().await.map_err(atspi_error)?.contains(Interface::Text) {
+            return Ok(Some((destination, path)));
+        }
+        
+        if depth < MAX_DEPTH {
+            for child in accessible.get_children().await.unwrap_or_default() {
+                // Skip children of applications that stop responding
+                if let Ok(Some(found)) = find_focused_text(conn, child.name.to_string(), child.path.to_string(), depth + 1).await {
+                    return Ok(Some(found));
+                }
+            }
+        }
+        Ok(None)
+    })
+}
+
+/// Build the node for one accessible object and its children
+fn build_node(
+    conn: &Connection,
+    destination: String,
+    path: String,
+    depth: usize,
+) -> BoxFuture<'_, Result<AccessibilityNode, CuaError>> {
+    Box::pin(async move {
+        let accessible = AccessibleProxy::builder(conn)
+            .destination(destination.as_str())
+            .and_then(|b| b.path(path.as_str()))
+            .map_err(atspi_error)?
+            .cache_properties(CacheProperties::No)
+            .build()
+            .await
+            .map_err(atspi_error)?;
+        
+        let role = accessible.get_role_name().await.unwrap_or_default();
+        let name = accessible.name().await.unwrap_or_default();
+        let interfaces = accessible.get_interfaces().await.map_err(atspi_error)?;
+        
+        let bounds = if interfaces.contains(Interface::Component) {
+            let component = ComponentProxy::builder(conn)
+                .destination(destination.as_str())
+                .and_then(|b| b.path(path.as_str()))
+                .map_err(atspi_error)?
+                .cache_properties(CacheProperties::No)
+                .build()
+                .await
+                .map_err(atspi_error)?;
+            let (x, y, w, h) = component.get_extents(CoordType::Screen).await.unwrap_or_default();
+            (x, y, w.max(0) as u32, h.max(0) as u32)
+        } else {
+            (0, 0, 0, 0)
+        };
+        
+        let value = if interfaces.contains(Interface::Value) {
+            let value = ValueProxy::builder(conn)
+                .destination(destination.as_str())
+                .and_then(|b| b.path(path.as_str()))
+                .map_err(atspi_error)?
+                .cache_properties(CacheProperties::No)
+                .build()
+                .await
+                .map_err(atspi_error)?;
+            value.current_value().await.ok().map(|v| v.to_string())
+        } else {
+            None
+        };
+        
+        let mut children = Vec::new();
+        if depth < MAX_DEPTH {
+            for child in accessible.get_children().await.unwrap_or_default() {
+                // Skip children of applications that stop responding
+                if let Ok(node) = build_node(conn, child.name.to_string(), child.path.to_string(), depth + 1).await {
+                    children.push(node);
+                }
+            }
+        }
+        
+        Ok(AccessibilityNode { role, name, value, bounds, children })
+    })
+}
+
+fn atspi_error(e: impl std::fmt::Display) -> CuaError {
+    CuaError::ActionError(format!("AT-SPI error: {}", e))
+}
@@ -0,0 +1,128 @@
+//! Focused application lookup through the platform's command line utilities
+//!
+//! Uses `xdotool` and `/proc` on Linux, `NSWorkspace` through JavaScript for
+//! Automation on macOS, and the Win32 API through PowerShell on Windows.
+
+use crate::command::run_command;
+use crate::computer::ApplicationInfo;
+use crate::error::CuaError;
+
+/// Prints the frontmost application's name, bundle id, pid and executable, one per line
+const MACOS_SCRIPT: &str = r#"
+ObjC.import("AppKit");
+const app = $.NSWorkspace.sharedWorkspace.frontmostApplication;
+[app.localizedName.js, app.bundleIdentifier.js || "", app.processIdentifier, app.executableURL.path.js].join("\n");
+"#;
+
+/// Prints the foreground window's pid, process name and executable, one per line
+const WINDOWS_SCRIPT: &str = r#"
+Add-Type @"
+using System;
+using System.Runtime.InteropServices;
+public static class Foreground {
+    [DllImport("user32.dll")] public static extern IntPtr GetForegroundWindow();
+    [DllImport("user32.dll")] public static extern uint GetWindowThreadProcessId(IntPtr hWnd, out uint pid);
+}
+"@
+$procId = 0
+[void][Foreground]::GetWindowThreadProcessId([Foreground]::GetForegroundWindow(), [ref]$procId)
+$process = Get-Process -Id $procId
+"$procId`n$($process.ProcessName)`n$($process.Path)"
+"#;
+
+/// Get the application that owns the focused window
+pub async fn focused_application() -> Result<ApplicationInfo, CuaError> {
+    if cfg!(target_os = "macos") {
+        let output = run_command("osascript", &["-l", "JavaScript", "-e", MACOS_SCRIPT], None).await?;
+        parse_macos_output(&output)
+    } else if cfg!(target_os = "windows") {
+        let output = run_command("powershell", &["-NoProfile", "-Command", WINDOWS_SCRIPT], None).await?;
+        parse_windows_output(&output)
+    } else if cfg!(target_os = "linux") {
+        let pid = run_command("xdotool", &["getactivewindow", "getwindowpid"], None).await?;
+        let pid = parse_pid(&pid)?;
+        let comm = tokio::fs::read_to_string(format!("/proc/{}/comm", pid)).await?;
+        let exe = tokio::fs::read_link(format!("/proc/{}/exe", pid)).await?;
+        Ok(linux_application(pid, &comm, &exe.to_string_lossy()))
+    } else {
+        Err(CuaError::ActionError("Focused application lookup not supported on this platform".to_string()))
+    }
+}
+
+fn parse_pid(pid: &str) -> Result<u32, CuaError> {
+    pid.trim()
+        .parse()
+        .map_err(|_| CuaError::ActionError(format!("Invalid process id '{}'", pid.trim())))
+}
+
+/// Build the application info from `/proc/<pid>/comm` and the `/proc/<pid>/exe` link target
+fn linux_application(pid: u32, comm: &str, exe: &str) -> ApplicationInfo {
+    ApplicationInfo {
+        name: comm.trim().to_string(),
+        bundle_id: None,
+        pid,
+        executable_path: exe.to_string(),
+    }
+}
+
+/// Split command output into exactly `count` lines
+fn output_lines(output: &str, count: usize) -> Result<Vec<&str>, CuaError> {
+    let lines: Vec<&str> = output.trim_end().lines().map(|line| line.trim_end_matches('\r')).collect();
+    if lines.len() != count {
+        return Err(CuaError::ActionError(format!(
+            "Expected {} lines describing the focused application, got {}",
+            count, lines.len()
+        )));
+    }
+    Ok(lines)
+}
+
+fn parse_macos_output(output: &str) -> Result<ApplicationInfo, CuaError> {
+    let lines = output_lines(output, 4)?;
+    Ok(ApplicationInfo {
+        name: lines[0].to_string(),
+        bundle_id: Some(lines[1].to_string()).filter(|id| !id.is_empty()),
+        pid: parse_pid(lines[2])?,
+        executable_path: lines[3].to_string(),
+    })
+}
+
+fn parse_windows_output(output: &str) -> Result<ApplicationInfo, CuaError> {
+    let lines = output_lines(output, 3)?;
+    Ok(ApplicationInfo {
+        name: lines[1].to_string(),
+        bundle_id: None,
+        pid: parse_pid(lines[0])?,
+        executable_path: lines[2].to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_parse_platform_output() {
+        let linux = linux_application(parse_pid("4242\n").unwrap(), "firefox\n", "/usr/lib/firefox/firefox");
+        assert_eq!(linux, ApplicationInfo {
+            name: "firefox".to_string(),
+            bundle_id: None,
+            pid: 4242,
+            executable_path: "/usr/lib/firefox/firefox".to_string(),
+        });
+        
+        let macos = parse_macos_output("Safari\ncom.apple.Safari\n812\n/Applications/Safari.app/Contents/MacOS/Safari\n").unwrap();
+        assert_eq!(macos.name, "Safari");
+        assert_eq!(macos.bundle_id.as_deref(), Some("com.apple.Safari"));
+        assert_eq!(macos.pid, 812);
+        assert_eq!(macos.executable_path, "/Applications/Safari.app/Contents/MacOS/Safari");
+        
+        let windows = parse_windows_output("10236\r\nnotepad\r\nC:\\Windows\\System32\\notepad.exe\r\n").unwrap();
+        assert_eq!(windows.name, "notepad");
+        assert_eq!(windows.pid, 10236);
+        assert_eq!(windows.executable_path, "C:\\Windows\\System32\\notepad.exe");
+        
+        assert!(parse_macos_output("Safari\n").is_err());
+        assert!(parse_pid("abc").is_err());
+    }
+}
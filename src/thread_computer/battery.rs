@@ -0,0 +1,112 @@
+//! Battery charge through the platform's power supply interfaces
+//!
+//! Reads `/sys/class/power_supply` on Linux, `pmset -g batt` (the command line
+//! front end of `IOPSCopyPowerSourcesInfo`) on macOS and `GetSystemPowerStatus`
+//! through PowerShell on Windows.
+
+use crate::command::run_command;
+use crate::error::CuaError;
+use std::path::Path;
+
+/// Linux power supply class directory
+const POWER_SUPPLY_DIR: &str = "/sys/class/power_supply";
+
+/// Prints `<BatteryFlag>\t<BatteryLifePercent>` from `GetSystemPowerStatus`
+const WINDOWS_POWER_SCRIPT: &str = r#"
+Add-Type @"
+using System.Runtime.InteropServices;
+public static class Power {
+    [StructLayout(LayoutKind.Sequential)]
+    public struct Status { public byte ACLineStatus, BatteryFlag, BatteryLifePercent, SystemStatusFlag; public int BatteryLifeTime, BatteryFullLifeTime; }
+    [DllImport("kernel32.dll")] public static extern bool GetSystemPowerStatus(out Status status);
+}
+"@
+$status = New-Object Power+Status
+[void][Power]::GetSystemPowerStatus([ref]$status)
+"$($status.BatteryFlag)`t$($status.BatteryLifePercent)"
+"#;
+
+/// `BatteryFlag` bit meaning the system has no battery
+const NO_SYSTEM_BATTERY: u8 = 128;
+
+/// Charge of the first battery as a fraction from 0.0 to 1.0, or `None` without a battery
+pub async fn battery_level() -> Result<Option<f32>, CuaError> {
+    if cfg!(target_os = "macos") {
+        let output = run_command("pmset", &["-g", "batt"], None).await?;
+        Ok(parse_pmset(&output))
+    } else if cfg!(target_os = "windows") {
+        let output = run_command("powershell", &["-NoProfile", "-Command", WINDOWS_POWER_SCRIPT], None).await?;
+        Ok(parse_power_status(&output))
+    } else if cfg!(target_os = "linux") {
+        linux_battery_level(Path::new(POWER_SUPPLY_DIR)).await
+    } else {
+        Err(CuaError::ActionError("Battery level not supported on this platform".to_string()))
+    }
+}
+
+/// Read `BAT*/capacity` from the power supply directory, preferring BAT0
+async fn linux_battery_level(power_supply_dir: &Path) -> Result<Option<f32>, CuaError> {
+    let mut batteries = Vec::new();
+    let mut entries = match tokio::fs::read_dir(power_supply_dir).await {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    while let Some(entry) = entries.next_entry().await? {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.starts_with("BAT") {
+            batteries.push(name);
+        }
+    }
+    batteries.sort();
+    
+    match batteries.first() {
+        Some(battery) => {
+            let capacity = tokio::fs::read_to_string(power_supply_dir.join(battery).join("capacity")).await?;
+            Ok(parse_percent(&capacity))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Parse a percentage such as "87" or "87%" into a fraction
+fn parse_percent(text: &str) -> Option<f32> {
+    let percent: f32 = text.trim().trim_end_matches('%').parse().ok()?;
+    Some((percent / 100.0).clamp(0.0, 1.0))
+}
+
+/// Parse `pmset -g batt`, whose battery lines are
+/// ` -InternalBattery-0 (id=4653155)`, a tab, and `87%; charging; 1:02 remaining present: true`
+fn parse_pmset(output: &str) -> Option<f32> {
+    let line = output.lines().find(|line| line.contains("InternalBattery"))?;
+    let (_, status) = line.split_once('\t')?;
+    parse_percent(status.split(';').next()?)
+}
+
+/// Parse the output of `WINDOWS_POWER_SCRIPT`; a life percent of 255 means unknown
+fn parse_power_status(output: &str) -> Option<f32> {
+    let (flag, percent) = output.trim().split_once('\t')?;
+    let flag: u8 = flag.parse().ok()?;
+    let percent: u8 = percent.parse().ok()?;
+    if flag & NO_SYSTEM_BATTERY != 0 || percent == 255 {
+        return None;
+    }
+    Some((percent as f32 / 100.0).min(1.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_parse_battery_outputs() {
+        assert_eq!(parse_percent("87\n"), Some(0.87));
+        
+        let pmset = "Now drawing from 'Battery Power'\n -InternalBattery-0 (id=4653155)\t42%; discharging; 3:10 remaining present: true\n";
+        assert_eq!(parse_pmset(pmset), Some(0.42));
+        assert_eq!(parse_pmset("Now drawing from 'AC Power'\n"), None);
+        
+        assert_eq!(parse_power_status("9\t15\r\n"), Some(0.15));
+        assert_eq!(parse_power_status("128\t255\r\n"), None);
+    }
+}
@@ -0,0 +1,78 @@
+//! Text caret position lookup through the platform accessibility APIs
+//!
+//! Uses AT-SPI2 on Linux (with the `accessibility` feature) and the AX API
+//! through JavaScript for Automation on macOS.
+
+use crate::command::run_command;
+use crate::error::CuaError;
+
+/// Prints the caret bounds in the focused element as x, y and height, one per line
+///
+/// The caret is the zero-length selected text range, whose bounds
+/// `AXBoundsForRange` reports.
+const MACOS_SCRIPT: &str = r#"
+ObjC.import("ApplicationServices");
+function copyAttribute(element, attribute) {
+    const value = Ref();
+    if ($.AXUIElementCopyAttributeValue(element, attribute, value) !== 0) {
+        throw new Error("Missing " + attribute);
+    }
+    return value[0];
+}
+const focused = copyAttribute($.AXUIElementCreateSystemWide(), "AXFocusedUIElement");
+const range = copyAttribute(focused, "AXSelectedTextRange");
+const bounds = Ref();
+if ($.AXUIElementCopyParameterizedAttributeValue(focused, "AXBoundsForRange", range, bounds) !== 0) {
+    throw new Error("Missing AXBoundsForRange");
+}
+const rect = Ref();
+$.AXValueGetValue(bounds[0], $.kAXValueCGRectType, rect);
+[rect[0].origin.x, rect[0].origin.y, rect[0].size.height].join("\n");
+"#;
+
+/// Get the on-screen position of the text caret in the focused element
+pub async fn text_cursor_position() -> Result<(i32, i32), CuaError> {
+    if cfg!(target_os = "macos") {
+        let output = run_command("osascript", &["-l", "JavaScript", "-e", MACOS_SCRIPT], None).await?;
+        parse_macos_output(&output)
+    } else {
+        platform_text_cursor_position().await
+    }
+}
+
+#[cfg(all(target_os = "linux", feature = "accessibility"))]
+async fn platform_text_cursor_position() -> Result<(i32, i32), CuaError> {
+    super::accessibility::text_cursor_position().await
+}
+
+#[cfg(not(all(target_os = "linux", feature = "accessibility")))]
+async fn platform_text_cursor_position() -> Result<(i32, i32), CuaError> {
+    Err(CuaError::ActionError(
+        "Text cursor lookup needs the `accessibility` feature on Linux and is not supported on this platform".to_string(),
+    ))
+}
+
+/// Caret position as the vertical middle of its bounds
+fn parse_macos_output(output: &str) -> Result<(i32, i32), CuaError> {
+    let values: Vec<f64> = output
+        .split_whitespace()
+        .map(|value| value.parse::<f64>())
+        .collect::<Result<_, _>>()
+        .map_err(|_| CuaError::ActionError(format!("Invalid caret bounds '{}'", output.trim())))?;
+    match values[..] {
+        [x, y, height] => Ok((x.round() as i32, (y + height / 2.0).round() as i32)),
+        _ => Err(CuaError::ActionError(format!("Invalid caret bounds '{}'", output.trim()))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_parse_macos_output() {
+        assert_eq!(parse_macos_output("412.5\n230\n17\n").unwrap(), (413, 239));
+        assert!(parse_macos_output("412\n230\n").is_err());
+        assert!(parse_macos_output("").is_err());
+    }
+}
@@ -0,0 +1,191 @@
+//! Clipboard access through the platform's command line utilities
+//!
+//! Enigo has no clipboard support, so the desktop implementation shells out to
+//! `xclip` / `copyq` on Linux and `pbcopy` / `pbpaste` on macOS.
+//! Content types are listed with `xclip`, `NSPasteboard` (through JXA) and the
+//! .NET clipboard (through PowerShell).
+
+use crate::command::run_command;
+use crate::computer::{ClipboardContentType, ClipboardEntry, ClipboardFormat};
+use crate::error::CuaError;
+use std::time::SystemTime;
+
+/// Read the current clipboard text
+pub async fn get_clipboard() -> Result<String, CuaError> {
+    if cfg!(target_os = "macos") {
+        run_command("pbpaste", &[], None).await
+    } else if cfg!(target_os = "linux") {
+        run_command("xclip", &["-selection", "clipboard", "-o"], None).await
+    } else {
+        Err(CuaError::ActionError("Clipboard access not supported on this platform".to_string()))
+    }
+}
+
+/// Types of the first pasteboard item, one per line
+const MACOS_TYPES_SCRIPT: &str = "ObjC.import('AppKit'); \
+    const items = $.NSPasteboard.generalPasteboard.pasteboardItems; \
+    items.count ? ObjC.deepUnwrap(items.objectAtIndex(0).types).join('\\n') : ''";
+
+/// Formats of the clipboard data, one per line
+const WINDOWS_FORMATS_SCRIPT: &str = "Add-Type -AssemblyName System.Windows.Forms; \
+    $data = [System.Windows.Forms.Clipboard]::GetDataObject(); \
+    if ($data) { $data.GetFormats() }";
+
+/// Detect which kinds of content the clipboard offers
+pub async fn get_clipboard_format() -> Result<ClipboardFormat, CuaError> {
+    let types = if cfg!(target_os = "macos") {
+        run_command("osascript", &["-l", "JavaScript", "-e", MACOS_TYPES_SCRIPT], None).await?
+    } else if cfg!(target_os = "windows") {
+        run_command("powershell", &["-NoProfile", "-Command", WINDOWS_FORMATS_SCRIPT], None).await?
+    } else if cfg!(target_os = "linux") {
+        // xclip fails when nothing owns the clipboard
+        run_command("xclip", &["-selection", "clipboard", "-t", "TARGETS", "-o"], None)
+            .await
+            .unwrap_or_default()
+    } else {
+        return Err(CuaError::ActionError("Clipboard access not supported on this platform".to_string()));
+    };
+    Ok(parse_clipboard_types(&types))
+}
+
+/// Classify clipboard types as listed by xclip (MIME types and atoms),
+/// NSPasteboard (UTIs) or .NET (format names), one per line
+fn parse_clipboard_types(types: &str) -> ClipboardFormat {
+    let mut format = ClipboardFormat::default();
+    for kind in types.lines().map(str::trim).filter(|kind| !kind.is_empty()) {
+        match kind {
+            "UTF8_STRING" | "STRING" | "TEXT" | "Text" | "UnicodeText" | "System.String" => format.text = true,
+            "text/html" | "public.html" | "HTML Format" => format.html = true,
+            "text/uri-list" | "x-special/gnome-copied-files" | "public.file-url" | "FileDrop" => format.files = true,
+            "Bitmap" | "System.Drawing.Bitmap" | "PNG" => format.image = true,
+            _ if kind.starts_with("text/plain") || kind == "public.utf8-plain-text" => format.text = true,
+            _ if kind.starts_with("image/") || kind == "public.png" || kind == "public.tiff" => format.image = true,
+            _ => {}
+        }
+    }
+    format
+}
+
+/// Replace the clipboard text
+pub async fn set_clipboard(text: &str) -> Result<(), CuaError> {
+    if cfg!(target_os = "macos") {
+        run_command("pbcopy", &[], Some(text)).await?;
+    } else if cfg!(target_os = "linux") {
+        run_command("xclip", &["-selection", "clipboard"], Some(text)).await?;
+    } else {
+        return Err(CuaError::ActionError("Clipboard access not supported on this platform".to_string()));
+    }
+    Ok(())
+}
+
+/// Put HTML (read from stdin) on the pasteboard as `public.html`
+const MACOS_SET_HTML_SCRIPT: &str = "ObjC.import('AppKit'); \
+    const data = $.NSFileHandle.fileHandleWithStandardInput.readDataToEndOfFile; \
+    const html = $.NSString.alloc.initWithDataEncoding(data, $.NSUTF8StringEncoding); \
+    const pasteboard = $.NSPasteboard.generalPasteboard; \
+    pasteboard.clearContents; \
+    pasteboard.setStringForType(html, $.NSPasteboardTypeHTML)";
+
+/// Put CF_HTML (read from stdin) on the clipboard in the HTML format
+const WINDOWS_SET_HTML_SCRIPT: &str = "[Console]::InputEncoding = [Text.Encoding]::UTF8; \
+    $html = [Console]::In.ReadToEnd(); \
+    Add-Type -AssemblyName System.Windows.Forms; \
+    [System.Windows.Forms.Clipboard]::SetText($html, [System.Windows.Forms.TextDataFormat]::Html)";
+
+/// Replace the clipboard content with HTML, for pasting into rich-text editors
+pub async fn set_clipboard_html(html: &str) -> Result<(), CuaError> {
+    if cfg!(target_os = "macos") {
+        run_command("osascript", &["-l", "JavaScript", "-e", MACOS_SET_HTML_SCRIPT], Some(html)).await?;
+    } else if cfg!(target_os = "windows") {
+        // The clipboard is only accessible from a single-threaded apartment
+        run_command("powershell", &["-NoProfile", "-STA", "-Command", WINDOWS_SET_HTML_SCRIPT], Some(&cf_html(html))).await?;
+    } else if cfg!(target_os = "linux") {
+        run_command("xclip", &["-selection", "clipboard", "-t", "text/html"], Some(html)).await?;
+    } else {
+        return Err(CuaError::ActionError("Clipboard access not supported on this platform".to_string()));
+    }
+    Ok(())
+}
+
+/// Wrap an HTML fragment in the Windows CF_HTML format
+///
+/// The header gives the UTF-8 byte offsets of the document and of the
+/// fragment within it, each padded to ten digits so the header length is fixed.
+fn cf_html(fragment: &str) -> String {
+    const HEADER_LEN: usize = "Version:0.9\r\nStartHTML:0000000000\r\nEndHTML:0000000000\r\nStartFragment:0000000000\r\nEndFragment:0000000000\r\n".len();
+    const PREFIX: &str = "<html><body>\r\n<!--StartFragment-->";
+    const SUFFIX: &str = "<!--EndFragment-->\r\n</body></html>";
+    let start_fragment = HEADER_LEN + PREFIX.len();
+    let end_fragment = start_fragment + fragment.len();
+    let end_html = end_fragment + SUFFIX.len();
+    format!(
+        "Version:0.9\r\nStartHTML:{:010}\r\nEndHTML:{:010}\r\nStartFragment:{:010}\r\nEndFragment:{:010}\r\n{}{}{}",
+        HEADER_LEN, end_html, start_fragment, end_fragment, PREFIX, fragment, SUFFIX
+    )
+}
+
+/// Read up to `max_entries` clipboard history entries, most recent first
+///
+/// Only `copyq` keeps a history. When it is not available the current
+/// clipboard content is returned as a single entry.
+pub async fn clipboard_history(max_entries: usize) -> Result<Vec<ClipboardEntry>, CuaError> {
+    if max_entries == 0 {
+        return Ok(Vec::new());
+    }
+    
+    if cfg!(target_os = "linux") {
+        if let Ok(count) = run_command("copyq", &["count"], None).await {
+            let count = count.trim().parse::<usize>().unwrap_or(0);
+            let mut entries = Vec::new();
+            for row in 0..count.min(max_entries) {
+                let row = row.to_string();
+                let content = run_command("copyq", &["read", &row], None).await?;
+                entries.push(text_entry(content));
+            }
+            return Ok(entries);
+        }
+    }
+    
+    // No history manager available, fall back to the current content
+    Ok(vec![text_entry(get_clipboard().await?)])
+}
+
+fn text_entry(content: String) -> ClipboardEntry {
+    ClipboardEntry {
+        content,
+        content_type: ClipboardContentType::Text,
+        timestamp: SystemTime::now(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_parse_clipboard_types() {
+        let xclip = parse_clipboard_types("TIMESTAMP\nTARGETS\nUTF8_STRING\ntext/html\ntext/plain;charset=utf-8\n");
+        assert_eq!(xclip, ClipboardFormat { text: true, image: false, html: true, files: false });
+        
+        let nspasteboard = parse_clipboard_types("public.file-url\npublic.png\n");
+        assert_eq!(nspasteboard, ClipboardFormat { text: false, image: true, html: false, files: true });
+        
+        let dotnet = parse_clipboard_types("System.Drawing.Bitmap\nBitmap\nDeviceIndependentBitmap\n");
+        assert_eq!(dotnet, ClipboardFormat { image: true, ..Default::default() });
+        assert_eq!(parse_clipboard_types(""), ClipboardFormat::default());
+    }
+    
+    #[test]
+    fn test_cf_html_offsets() {
+        let fragment = "<b>Café</b>";
+        let data = cf_html(fragment);
+        let offset = |name: &str| -> usize {
+            let line = data.lines().find(|line| line.starts_with(name)).unwrap();
+            line[name.len() + 1..].trim_end().parse().unwrap()
+        };
+        
+        assert_eq!(&data[offset("StartFragment")..offset("EndFragment")], fragment);
+        assert!(data[offset("StartHTML")..].starts_with("<html>"));
+        assert_eq!(offset("EndHTML"), data.len());
+    }
+}
@@ -1,14 +1,30 @@
 //! Thread-based implementation of the Computer trait using Enigo
 
-use crate::computer::Computer;
+use super::application;
+use super::battery;
+use super::caret;
+use super::clipboard;
+use super::dialog;
+use super::display::{self, XvfbServer};
+use super::notification;
+use super::processes;
+use super::scripting::{self, DEFAULT_SCRIPT_TIMEOUT};
+use super::element_cache::{ElementScreenshotCache, DEFAULT_ELEMENT_CACHE_TTL};
+use super::highlight;
+use super::window;
+use crate::compression::ScreenshotFormat;
+use crate::computer::{buttons_within, canonical_key_name, keyboard_shortcuts_for_environment, read_allowed_env_var, union_bounds, ApplicationInfo, ClipboardEntry, ClipboardFormat, Computer, DialogInfo, PasteKey, ScreenArea, WindowInfo, ZoomState, BASE_DPI, CLIPBOARD_POLL_INTERVAL, DEFAULT_ENV_ALLOWLIST, MAX_KEY_HOLD_MS, SPECIAL_KEYS};
 use crate::error::CuaError;
+use crate::ocr;
+use crate::vision;
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 use std::thread;
 use tokio::sync::mpsc::{self, Sender};
 use tokio::sync::oneshot;
 use tokio::time::sleep;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+use serde::Serialize;
 use enigo::{Enigo, MouseControllable, KeyboardControllable, MouseButton, Key};
 use screenshots::Screen;
 use base64::{engine::general_purpose, Engine};
@@ -16,18 +32,25 @@ use async_trait::async_trait;
 use image::codecs::png::PngEncoder;
 use image::ImageEncoder;
 use std::io::Cursor;
+use std::fmt;
 
 /// Commands that can be sent to the input thread
+///
+/// Serializes to the command name and its arguments, without the response channel.
+#[derive(Serialize)]
+#[serde(tag = "command")]
 enum InputCommand {
     Click {
         x: i32, 
         y: i32, 
         button: String,
+        #[serde(skip)]
         response: oneshot::Sender<Result<(), CuaError>>,
     },
     DoubleClick {
         x: i32,
         y: i32,
+        #[serde(skip)]
         response: oneshot::Sender<Result<(), CuaError>>,
     },
     Scroll {
@@ -35,68 +58,274 @@ enum InputCommand {
         y: i32,
         scroll_x: i32,
         scroll_y: i32,
+        #[serde(skip)]
         response: oneshot::Sender<Result<(), CuaError>>,
     },
     TypeText {
+        #[serde(serialize_with = "serialize_redacted")]
         text: String,
+        #[serde(skip)]
         response: oneshot::Sender<Result<(), CuaError>>,
     },
     MoveCursor {
         x: i32,
         y: i32,
+        #[serde(skip)]
         response: oneshot::Sender<Result<(), CuaError>>,
     },
     Keypress {
         keys: Vec<String>,
+        #[serde(skip)]
+        response: oneshot::Sender<Result<(), CuaError>>,
+    },
+    PressAndHold {
+        key: String,
+        duration_ms: u32,
+        #[serde(skip)]
         response: oneshot::Sender<Result<(), CuaError>>,
     },
     Drag {
         path: Vec<HashMap<String, i32>>,
+        #[serde(skip)]
+        response: oneshot::Sender<Result<(), CuaError>>,
+    },
+    MouseButtonDown {
+        button: String,
+        #[serde(skip)]
+        response: oneshot::Sender<Result<(), CuaError>>,
+    },
+    MouseButtonUp {
+        button: String,
+        #[serde(skip)]
         response: oneshot::Sender<Result<(), CuaError>>,
     },
     Screenshot {
+        #[serde(skip)]
+        response: oneshot::Sender<Result<String, CuaError>>,
+    },
+    ScreenshotJpeg {
+        quality: u8,
+        #[serde(skip)]
+        response: oneshot::Sender<Result<String, CuaError>>,
+    },
+    ScreenshotRaw {
+        #[serde(skip)]
+        response: oneshot::Sender<Result<RawScreenshot, CuaError>>,
+    },
+    CaptureAllScreens {
+        #[serde(skip)]
+        response: oneshot::Sender<Result<Vec<(usize, String)>, CuaError>>,
+    },
+    PixelColor {
+        x: u32,
+        y: u32,
+        #[serde(skip)]
+        response: oneshot::Sender<Result<(u8, u8, u8), CuaError>>,
+    },
+    CaptureRegion {
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        #[serde(skip)]
         response: oneshot::Sender<Result<String, CuaError>>,
     },
     Shutdown,
 }
 
+impl InputCommand {
+    /// Name of the command variant, e.g. "Click"
+    fn name(&self) -> &'static str {
+        match self {
+            InputCommand::Click { .. } => "Click",
+            InputCommand::DoubleClick { .. } => "DoubleClick",
+            InputCommand::Scroll { .. } => "Scroll",
+            InputCommand::TypeText { .. } => "TypeText",
+            InputCommand::MoveCursor { .. } => "MoveCursor",
+            InputCommand::Keypress { .. } => "Keypress",
+            InputCommand::PressAndHold { .. } => "PressAndHold",
+            InputCommand::Drag { .. } => "Drag",
+            InputCommand::MouseButtonDown { .. } => "MouseButtonDown",
+            InputCommand::MouseButtonUp { .. } => "MouseButtonUp",
+            InputCommand::Screenshot { .. } => "Screenshot",
+            InputCommand::ScreenshotJpeg { .. } => "ScreenshotJpeg",
+            InputCommand::ScreenshotRaw { .. } => "ScreenshotRaw",
+            InputCommand::CaptureAllScreens { .. } => "CaptureAllScreens",
+            InputCommand::PixelColor { .. } => "PixelColor",
+            InputCommand::CaptureRegion { .. } => "CaptureRegion",
+            InputCommand::Shutdown => "Shutdown",
+        }
+    }
+    
+    /// Whether the command only reads the screen, so cannot change it
+    fn is_capture(&self) -> bool {
+        matches!(
+            self,
+            InputCommand::Screenshot { .. }
+                | InputCommand::ScreenshotJpeg { .. }
+                | InputCommand::ScreenshotRaw { .. }
+                | InputCommand::CaptureAllScreens { .. }
+                | InputCommand::PixelColor { .. }
+                | InputCommand::CaptureRegion { .. }
+        )
+    }
+}
+
+impl fmt::Display for InputCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl fmt::Debug for InputCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match serde_json::to_string(self) {
+            Ok(json) => write!(f, "{}", json),
+            Err(_) => write!(f, "{}", self.name()),
+        }
+    }
+}
+
+/// Serialize typed text as its length only, so secrets never reach the logs
+fn serialize_redacted<S: serde::Serializer>(text: &str, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&format!("<{} chars>", text.chars().count()))
+}
+
+/// Raw RGBA pixels of a capture with its width and height
+type RawScreenshot = (Vec<u8>, u32, u32);
+
+/// Scroll distance in pixels that one wheel notch covers
+const PIXELS_PER_SCROLL_NOTCH: i32 = 10;
+
+/// Convert a scroll distance in pixels to wheel notches for Enigo, keeping its sign
+///
+/// Positive values scroll right (x) or down (y), as in the CUA scroll action.
+fn scroll_notches(pixels: i32) -> i32 {
+    pixels / PIXELS_PER_SCROLL_NOTCH
+}
+
+/// Timing of a command on its way to the input thread
+#[derive(Debug, Clone)]
+struct CommandMetrics {
+    command_name: String,
+    queued_at: Instant,
+    dispatched_at: Option<Instant>,
+}
+
+impl CommandMetrics {
+    /// Time the command spent waiting in the channel, once dispatched
+    fn queue_latency(&self) -> Option<Duration> {
+        self.dispatched_at.map(|dispatched_at| dispatched_at.duration_since(self.queued_at))
+    }
+}
+
+/// A command in the input thread's channel, with its timing
+struct QueuedCommand {
+    command: InputCommand,
+    metrics: CommandMetrics,
+}
+
+impl QueuedCommand {
+    /// Wrap a command, marking it as queued now
+    fn new(command: InputCommand) -> Self {
+        let metrics = CommandMetrics {
+            command_name: command.name().to_string(),
+            queued_at: Instant::now(),
+            dispatched_at: None,
+        };
+        Self { command, metrics }
+    }
+}
+
+/// Look up the screen with index `screen_id`
+fn screen_by_id(screen_id: usize) -> Result<Screen, CuaError> {
+    let mut screens = Screen::all().map_err(|e| 
+        CuaError::ScreenshotError(format!("Failed to get screen information: {}", e))
+    )?;
+    if screen_id >= screens.len() {
+        return Err(CuaError::ScreenshotError(format!(
+            "Invalid screen ID: {}, only {} screens available",
+            screen_id, screens.len()
+        )));
+    }
+    Ok(screens.swap_remove(screen_id))
+}
+
+/// Capture the whole screen with index `screen_id`
+fn capture_screen(screen_id: usize) -> Result<screenshots::Image, CuaError> {
+    screen_by_id(screen_id)?.capture().map_err(|e| 
+        CuaError::ScreenshotError(format!("Failed to capture screenshot: {}", e))
+    )
+}
+
+/// Map a CUA button name to Enigo's, defaulting to the left button
+///
+/// The API calls the middle button "wheel".
+fn map_button(button: &str) -> MouseButton {
+    match button.to_lowercase().as_str() {
+        "right" => MouseButton::Right,
+        "middle" | "wheel" => MouseButton::Middle,
+        _ => MouseButton::Left,
+    }
+}
+
+/// Enigo key for each of `SPECIAL_KEYS`, in the same order
+const SPECIAL_KEY_CODES: [Key; SPECIAL_KEYS.len()] = [
+    Key::Alt, Key::Backspace, Key::CapsLock, Key::Control, Key::Delete, Key::End, Key::Return, Key::Escape, Key::Home,
+    Key::Shift, Key::Space, Key::Meta, Key::Tab, Key::DownArrow, Key::LeftArrow, Key::RightArrow, Key::UpArrow,
+];
+
 /// Map keys from CUA format to Enigo format
 fn map_key(key: &str) -> Option<Key> {
-    match key.to_lowercase().as_str() {
-        "alt" => Some(Key::Alt),
-        "backspace" => Some(Key::Backspace),
-        "capslock" => Some(Key::CapsLock),
-        "ctrl" => Some(Key::Control),
-        "delete" => Some(Key::Delete),
-        "end" => Some(Key::End),
-        "enter" => Some(Key::Return),
-        "esc" => Some(Key::Escape),
-        "home" => Some(Key::Home),
-        "option" => Some(Key::Alt),
-        "shift" => Some(Key::Shift),
-        "space" => Some(Key::Space),
-        "super" | "win" | "cmd" => Some(Key::Meta),
-        "tab" => Some(Key::Tab),
-        "arrowdown" => Some(Key::DownArrow),
-        "arrowleft" => Some(Key::LeftArrow),
-        "arrowright" => Some(Key::RightArrow),
-        "arrowup" => Some(Key::UpArrow),
-        _ => None,
+    let key = canonical_key_name(key);
+    SPECIAL_KEYS.iter().position(|name| *name == key).map(|i| SPECIAL_KEY_CODES[i])
+}
+
+/// Resolve a CUA key name or single character to an Enigo key
+fn resolve_key(key: &str) -> Result<Key, CuaError> {
+    if let Some(special_key) = map_key(key) {
+        Ok(special_key)
+    } else if let (Some(c), 1) = (key.chars().next(), key.chars().count()) {
+        Ok(Key::Layout(c))
+    } else {
+        Err(CuaError::ActionError(format!("Unknown key: {}", key)))
     }
 }
 
+/// Hold `key` down on the input thread, for at most [`MAX_KEY_HOLD_MS`]
+fn hold_key(keyboard: &mut impl KeyboardControllable, key: Key, duration_ms: u32) {
+    keyboard.key_down(key);
+    thread::sleep(Duration::from_millis(duration_ms.min(MAX_KEY_HOLD_MS) as u64));
+    keyboard.key_up(key);
+}
+
 /// Thread-safe computer implementation
 pub struct ThreadComputer {
     /// Channel to send commands to the input thread
-    command_sender: Sender<InputCommand>,
+    command_sender: Sender<QueuedCommand>,
     /// Environment type
     environment: String,
     /// Screen dimensions
-    dimensions: (u32, u32),
+    dimensions: Mutex<(u32, u32)>,
     /// Screen ID
+    #[allow(dead_code)]
     screen_id: usize,
     /// Cursor position
     cursor_position: Arc<Mutex<(i32, i32)>>,
+    /// Modifier for the clipboard shortcuts on the host OS
+    paste_key: PasteKey,
+    /// Zoom applied through the zoom shortcuts
+    zoom: Mutex<ZoomState>,
+    /// Labeled captures for `capture_element_screenshot`
+    element_cache: ElementScreenshotCache,
+    /// How long `execute_script` lets a script run
+    script_timeout: Duration,
+    /// Last window list and when it was read, reused for `window::WINDOW_LIST_CACHE_TTL`
+    window_list: Mutex<Option<(Instant, Vec<WindowInfo>)>>,
+    /// Environment variables `read_env_var` may read
+    env_allowlist: Vec<String>,
+    /// Xvfb server started by `with_virtual_display`, stopped with this computer
+    virtual_display: Option<XvfbServer>,
 }
 
 impl ThreadComputer {
@@ -117,7 +346,7 @@ impl ThreadComputer {
         let screen_id = 0;
         
         // Create a channel for sending commands to the input thread
-        let (tx, mut rx) = mpsc::channel::<InputCommand>(100);
+        let (tx, mut rx) = mpsc::channel::<QueuedCommand>(100);
         
         // Shared cursor position
         let cursor_position = Arc::new(Mutex::new((0, 0)));
@@ -128,38 +357,38 @@ impl ThreadComputer {
             // Create Enigo instance for mouse/keyboard control
             let mut enigo = Enigo::new();
             
-            println!("Input thread started");
+            tracing::info!("Input thread started");
             
             // Process commands from the channel
-            while let Some(cmd) = rx.blocking_recv() {
-                match cmd {
+            while let Some(QueuedCommand { command, mut metrics }) = rx.blocking_recv() {
+                metrics.dispatched_at = Some(Instant::now());
+                tracing::debug!(
+                    command = %metrics.command_name,
+                    queue_latency = ?metrics.queue_latency().unwrap_or_default(),
+                    "Dispatching command"
+                );
+                
+                match command {
                     InputCommand::Click { x, y, button, response } => {
-                        println!("DEBUG: Processing InputCommand::Click at ({}, {}) with button: {}", x, y, button);
-                        let result = (|| {
+                        tracing::debug!(x, y, %button, "Processing click");
+                        let result: Result<(), CuaError> = {
                             // Move to position first
                             enigo.mouse_move_to(x, y);
                             
                             // Update cursor position
                             *cursor_position_clone.lock().unwrap() = (x, y);
                             
-                            // Determine which button to click
-                            let mouse_button = match button.to_lowercase().as_str() {
-                                "right" => MouseButton::Right,
-                                "middle" => MouseButton::Middle,
-                                _ => MouseButton::Left, // Default to left click for any other value
-                            };
-                            
                             // Click the button
-                            enigo.mouse_click(mouse_button);
+                            enigo.mouse_click(map_button(&button));
                             
                             Ok(())
-                        })();
+                        };
                         
                         let _ = response.send(result);
                     }
                     
                     InputCommand::DoubleClick { x, y, response } => {
-                        let result = (|| {
+                        let result: Result<(), CuaError> = {
                             // Move to position first
                             enigo.mouse_move_to(x, y);
                             
@@ -172,13 +401,13 @@ impl ThreadComputer {
                             enigo.mouse_click(MouseButton::Left);
                             
                             Ok(())
-                        })();
+                        };
                         
                         let _ = response.send(result);
                     }
                     
                     InputCommand::Scroll { x, y, scroll_x, scroll_y, response } => {
-                        let result = (|| {
+                        let result: Result<(), CuaError> = {
                             // Move to position first
                             enigo.mouse_move_to(x, y);
                             
@@ -186,58 +415,53 @@ impl ThreadComputer {
                             *cursor_position_clone.lock().unwrap() = (x, y);
                             
                             if scroll_x != 0 {
-                                enigo.mouse_scroll_x(scroll_x / 10);
+                                enigo.mouse_scroll_x(scroll_notches(scroll_x));
                             }
                             
                             if scroll_y != 0 {
-                                enigo.mouse_scroll_y(scroll_y / 10);
+                                enigo.mouse_scroll_y(scroll_notches(scroll_y));
                             }
                             
                             Ok(())
-                        })();
+                        };
                         
                         let _ = response.send(result);
                     }
                     
                     InputCommand::TypeText { text, response } => {
-                        println!("DEBUG: Processing InputCommand::TypeText with text: {}", text);
-                        let result = (|| {
+                        tracing::debug!(chars = text.chars().count(), "Processing typed text");
+                        let result: Result<(), CuaError> = {
                             enigo.key_sequence(&text);
                             Ok(())
-                        })();
+                        };
                         
                         let _ = response.send(result);
                     }
                     
                     InputCommand::MoveCursor { x, y, response } => {
-                        let result = (|| {
+                        let result: Result<(), CuaError> = {
                             enigo.mouse_move_to(x, y);
                             
                             // Update cursor position
                             *cursor_position_clone.lock().unwrap() = (x, y);
                             
                             Ok(())
-                        })();
+                        };
                         
                         let _ = response.send(result);
                     }
                     
                     InputCommand::Keypress { keys, response } => {
                         let result = (|| {
-                            for key in keys {
-                                // Try to map to a special key
-                                if let Some(special_key) = map_key(&key) {
-                                    enigo.key_down(special_key);
-                                    enigo.key_up(special_key);
-                                } else if key.len() == 1 {
-                                    // Single character, just type it
-                                    enigo.key_sequence(&key);
-                                } else {
-                                    // Unknown key
-                                    return Err(CuaError::ActionError(format!(
-                                        "Unknown key: {}", key
-                                    )));
-                                }
+                            // Resolve every key first so nothing is left held down on error
+                            let enigo_keys = keys.iter().map(|key| resolve_key(key)).collect::<Result<Vec<_>, _>>()?;
+                            
+                            // Press the keys together as a combination, releasing in reverse order
+                            for key in &enigo_keys {
+                                enigo.key_down(*key);
+                            }
+                            for key in enigo_keys.iter().rev() {
+                                enigo.key_up(*key);
                             }
                             
                             Ok(())
@@ -246,6 +470,12 @@ impl ThreadComputer {
                         let _ = response.send(result);
                     }
                     
+                    InputCommand::PressAndHold { key, duration_ms, response } => {
+                        let result = resolve_key(&key).map(|key| hold_key(&mut enigo, key, duration_ms));
+                        
+                        let _ = response.send(result);
+                    }
+                    
                     InputCommand::Drag { path, response } => {
                         let result = (|| {
                             if path.is_empty() {
@@ -290,25 +520,21 @@ impl ThreadComputer {
                         let _ = response.send(result);
                     }
                     
+                    InputCommand::MouseButtonDown { button, response } => {
+                        tracing::debug!(%button, "Pressing mouse button");
+                        enigo.mouse_down(map_button(&button));
+                        let _ = response.send(Ok(()));
+                    }
+                    
+                    InputCommand::MouseButtonUp { button, response } => {
+                        tracing::debug!(%button, "Releasing mouse button");
+                        enigo.mouse_up(map_button(&button));
+                        let _ = response.send(Ok(()));
+                    }
+                    
                     InputCommand::Screenshot { response } => {
                         let result = (|| {
-                            // Get all screens
-                            let screens = Screen::all().map_err(|e| 
-                                CuaError::ScreenshotError(format!("Failed to get screen information: {}", e))
-                            )?;
-                            
-                            // Capture the screen with the specified ID
-                            if screen_id >= screens.len() {
-                                return Err(CuaError::ScreenshotError(format!(
-                                    "Invalid screen ID: {}, only {} screens available",
-                                    screen_id, screens.len()
-                                )));
-                            }
-                            
-                            let screen = &screens[screen_id];
-                            let image = screen.capture().map_err(|e| 
-                                CuaError::ScreenshotError(format!("Failed to capture screenshot: {}", e))
-                            )?;
+                            let image = capture_screen(screen_id)?;
                             
                             // Convert image to PNG using the image crate
                             let mut buffer = Vec::new();
@@ -336,35 +562,155 @@ impl ThreadComputer {
                         let _ = response.send(result);
                     }
                     
+                    InputCommand::ScreenshotJpeg { quality, response } => {
+                        // Encode the raw capture directly, skipping the PNG round trip
+                        let result = capture_screen(screen_id).and_then(|image| {
+                            vision::encode_jpeg(image.rgba(), image.width(), image.height(), quality)
+                        });
+                        let _ = response.send(result);
+                    }
+                    
+                    InputCommand::ScreenshotRaw { response } => {
+                        let result = capture_screen(screen_id)
+                            .map(|image| (image.rgba().to_vec(), image.width(), image.height()));
+                        let _ = response.send(result);
+                    }
+                    
+                    InputCommand::CaptureAllScreens { response } => {
+                        let result = Screen::all()
+                            .map_err(|e| CuaError::ScreenshotError(format!("Failed to get screen information: {}", e)))
+                            .and_then(|screens| {
+                                screens
+                                    .iter()
+                                    .enumerate()
+                                    .map(|(index, screen)| {
+                                        let image = screen.capture().map_err(|e| {
+                                            CuaError::ScreenshotError(format!("Failed to capture screen {}: {}", index, e))
+                                        })?;
+                                        Ok((index, vision::raw_to_base64_png(image.rgba(), image.width(), image.height())?))
+                                    })
+                                    .collect()
+                            });
+                        let _ = response.send(result);
+                    }
+                    
+                    InputCommand::PixelColor { x, y, response } => {
+                        let result = (|| {
+                            let screen = screen_by_id(screen_id)?;
+                            
+                            // Capture just the one pixel
+                            let image = screen.capture_area(x as i32, y as i32, 1, 1).map_err(|e| 
+                                CuaError::ScreenshotError(format!("Failed to capture pixel: {}", e))
+                            )?;
+                            match image.rgba().as_slice() {
+                                [r, g, b, ..] => Ok((*r, *g, *b)),
+                                _ => Err(CuaError::ScreenshotError("Captured an empty region".to_string())),
+                            }
+                        })();
+                        
+                        let _ = response.send(result);
+                    }
+                    
+                    InputCommand::CaptureRegion { x, y, width, height, response } => {
+                        let result = (|| {
+                            let screen = screen_by_id(screen_id)?;
+                            
+                            let image = screen.capture_area(x as i32, y as i32, width, height).map_err(|e| 
+                                CuaError::ScreenshotError(format!("Failed to capture region: {}", e))
+                            )?;
+                            let region = image::RgbaImage::from_raw(image.width(), image.height(), image.rgba().to_vec())
+                                .ok_or_else(|| CuaError::ScreenshotError("Captured region has an invalid size".to_string()))?;
+                            vision::encode_png(&region.into())
+                        })();
+                        
+                        let _ = response.send(result);
+                    }
+                    
                     InputCommand::Shutdown => {
-                        println!("Input thread shutting down");
+                        tracing::info!("Input thread shutting down");
                         break;
                     }
                 }
             }
             
-            println!("Input thread terminated");
+            tracing::info!("Input thread terminated");
         });
         
         Ok(Self {
             command_sender: tx,
             environment: "linux".to_string(),
-            dimensions,
+            dimensions: Mutex::new(dimensions),
             screen_id,
             cursor_position,
+            paste_key: PasteKey::host(),
+            zoom: Mutex::new(ZoomState::default()),
+            element_cache: ElementScreenshotCache::new(DEFAULT_ELEMENT_CACHE_TTL),
+            script_timeout: DEFAULT_SCRIPT_TIMEOUT,
+            window_list: Mutex::new(None),
+            env_allowlist: DEFAULT_ENV_ALLOWLIST.iter().map(|name| name.to_string()).collect(),
+            virtual_display: None,
         })
     }
     
-    /// Get the current cursor position
-    pub fn cursor_position(&self) -> (i32, i32) {
-        *self.cursor_position.lock().unwrap()
+    /// Create a ThreadComputer on the X display `display` (e.g. ":99")
+    ///
+    /// Starts an Xvfb server on the display unless one is already running. The
+    /// process must have been started with `DISPLAY` set to `display`.
+    pub async fn with_virtual_display(display: &str) -> Result<Self, CuaError> {
+        let server = display::start_virtual_display(display).await?;
+        let mut computer = Self::new()?;
+        computer.virtual_display = server;
+        Ok(computer)
+    }
+    
+    /// Set how long `capture_element_screenshot` reuses a labeled capture (default 30s)
+    pub fn with_element_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.element_cache = ElementScreenshotCache::new(ttl);
+        self
+    }
+    
+    /// Set how long `execute_script` lets a script run (default 30s)
+    pub fn with_script_timeout(mut self, timeout: Duration) -> Self {
+        self.script_timeout = timeout;
+        self
+    }
+    
+    /// Set the environment variables `read_env_var` may read (default `DEFAULT_ENV_ALLOWLIST`)
+    pub fn with_env_allowlist(mut self, vars: Vec<String>) -> Self {
+        self.env_allowlist = vars;
+        self
+    }
+    
+    /// Send a command to the input thread and wait for its response
+    ///
+    /// `make_command` receives the oneshot sender the input thread will reply on.
+    async fn send_command<T>(
+        &self,
+        make_command: impl FnOnce(oneshot::Sender<Result<T, CuaError>>) -> InputCommand,
+    ) -> Result<T, CuaError> {
+        let (tx, rx) = oneshot::channel();
+        let command = make_command(tx);
+        let command_name = command.to_string();
+        if !command.is_capture() {
+            self.element_cache.invalidate();
+        }
+        
+        self.command_sender.send(QueuedCommand::new(command)).await.map_err(|_| CuaError::ChannelError {
+            command: command_name.clone(),
+            reason: "desktop input thread is shutting down".to_string(),
+        })?;
+        
+        rx.await.map_err(|_| CuaError::ChannelError {
+            command: command_name,
+            reason: "desktop input thread is not available".to_string(),
+        })?
     }
 }
 
 impl Drop for ThreadComputer {
     fn drop(&mut self) {
         // Send shutdown command to the input thread
-        let _ = self.command_sender.try_send(InputCommand::Shutdown);
+        let _ = self.command_sender.try_send(QueuedCommand::new(InputCommand::Shutdown));
     }
 }
 
@@ -375,67 +721,132 @@ impl Computer for ThreadComputer {
     }
     
     fn dimensions(&self) -> (u32, u32) {
-        self.dimensions
+        *self.dimensions.lock().unwrap()
+    }
+    
+    fn cursor_position(&self) -> (i32, i32) {
+        *self.cursor_position.lock().unwrap()
+    }
+    
+    fn keyboard_shortcut_map(&self) -> HashMap<String, Vec<String>> {
+        // The reported environment is always "linux", so use the host OS instead
+        let host = if cfg!(target_os = "macos") {
+            "mac"
+        } else if cfg!(target_os = "windows") {
+            "windows"
+        } else {
+            "linux"
+        };
+        keyboard_shortcuts_for_environment(host)
     }
     
     async fn screenshot(&self) -> Result<String, CuaError> {
-        let (tx, rx) = oneshot::channel();
-        
-        self.command_sender.send(InputCommand::Screenshot {
-            response: tx,
-        }).await.map_err(|_| CuaError::ActionError("Failed to send screenshot command: desktop input thread is shutting down".to_string()))?;
-        
-        rx.await.map_err(|_| CuaError::ActionError("Failed to receive screenshot response: desktop input thread is not available".to_string()))?
+        self.send_command(|response| InputCommand::Screenshot { response }).await
+    }
+    
+    async fn set_display_resolution(&self, width: u32, height: u32) -> Result<(), CuaError> {
+        display::set_display_resolution(width, height).await?;
+        *self.dimensions.lock().unwrap() = (width, height);
+        Ok(())
+    }
+    
+    async fn screenshot_jpeg(&self, quality: u8) -> Result<String, CuaError> {
+        self.send_command(|response| InputCommand::ScreenshotJpeg { quality, response }).await
+    }
+    
+    async fn capture_all_screens(&self) -> Result<Vec<(usize, String)>, CuaError> {
+        self.send_command(|response| InputCommand::CaptureAllScreens { response }).await
+    }
+    
+    fn screenshot_format_support(&self) -> Vec<ScreenshotFormat> {
+        // Captures are PNG; the agent encodes JPEG and WebP uploads from them
+        vec![
+            ScreenshotFormat::Png,
+            ScreenshotFormat::Jpeg(vision::MAX_JPEG_QUALITY),
+            #[cfg(feature = "webp")]
+            ScreenshotFormat::WebP(100),
+        ]
+    }
+    
+    async fn screenshot_as_array(&self, width: &mut u32, height: &mut u32) -> Result<Vec<u8>, CuaError> {
+        let (data, captured_width, captured_height) = self.send_command(|response| InputCommand::ScreenshotRaw { response }).await?;
+        (*width, *height) = (captured_width, captured_height);
+        Ok(data)
+    }
+    
+    async fn get_screen_dpi(&self) -> Result<f32, CuaError> {
+        Ok(screen_by_id(self.screen_id)?.display_info.scale_factor * BASE_DPI)
+    }
+    
+    async fn get_text_cursor_position(&self) -> Result<(i32, i32), CuaError> {
+        caret::text_cursor_position().await
+    }
+    
+    async fn get_screen_recording_area(&self) -> Result<ScreenArea, CuaError> {
+        union_bounds(&self.screen_bounds().await?).ok_or_else(|| CuaError::ScreenshotError("No screens detected".to_string()))
+    }
+    
+    async fn screen_bounds(&self) -> Result<Vec<ScreenArea>, CuaError> {
+        let screens = Screen::all()
+            .map_err(|e| CuaError::ScreenshotError(format!("Failed to get screen information: {}", e)))?;
+        Ok(screens
+            .iter()
+            .map(|screen| (screen.display_info.x, screen.display_info.y, screen.display_info.width, screen.display_info.height))
+            .collect())
+    }
+    
+    fn primary_screen(&self) -> usize {
+        self.screen_id
+    }
+    
+    async fn capture_element_screenshot(&self, x: u32, y: u32, w: u32, h: u32, label: &str) -> Result<String, CuaError> {
+        let (width, height) = (w, h);
+        self.element_cache
+            .get_or_capture(label, (x, y, w, h), || {
+                self.send_command(|response| InputCommand::CaptureRegion { x, y, width, height, response })
+            })
+            .await
+    }
+    
+    async fn window_screenshot(&self, title_pattern: &str) -> Result<String, CuaError> {
+        window::window_screenshot(title_pattern).await
+    }
+    
+    async fn highlight_region(&self, x: u32, y: u32, w: u32, h: u32, color: &str, duration_ms: u32) -> Result<(), CuaError> {
+        highlight::highlight_region(x, y, w, h, color, duration_ms)
+    }
+    
+    async fn read_env_var(&self, name: &str) -> Result<String, CuaError> {
+        read_allowed_env_var(name, &self.env_allowlist)
+    }
+    
+    async fn get_window_list(&self) -> Result<Vec<WindowInfo>, CuaError> {
+        if let Some((read_at, windows)) = self.window_list.lock().unwrap().as_ref() {
+            if read_at.elapsed() < window::WINDOW_LIST_CACHE_TTL {
+                return Ok(windows.clone());
+            }
+        }
+        let windows = window::list_windows().await?;
+        *self.window_list.lock().unwrap() = Some((Instant::now(), windows.clone()));
+        Ok(windows)
     }
     
     async fn click(&self, x: i32, y: i32, button: &str) -> Result<(), CuaError> {
-        let (tx, rx) = oneshot::channel();
-        
-        self.command_sender.send(InputCommand::Click {
-            x,
-            y,
-            button: button.to_string(),
-            response: tx,
-        }).await.map_err(|_| CuaError::ActionError("Failed to send click command: desktop input thread is shutting down".to_string()))?;
-        
-        rx.await.map_err(|_| CuaError::ActionError("Failed to receive click response: desktop input thread is not available".to_string()))?
+        let button = button.to_string();
+        self.send_command(|response| InputCommand::Click { x, y, button, response }).await
     }
     
     async fn double_click(&self, x: i32, y: i32) -> Result<(), CuaError> {
-        let (tx, rx) = oneshot::channel();
-        
-        self.command_sender.send(InputCommand::DoubleClick {
-            x,
-            y,
-            response: tx,
-        }).await.map_err(|_| CuaError::ActionError("Failed to send double click command: desktop input thread is shutting down".to_string()))?;
-        
-        rx.await.map_err(|_| CuaError::ActionError("Failed to receive double click response: desktop input thread is not available".to_string()))?
+        self.send_command(|response| InputCommand::DoubleClick { x, y, response }).await
     }
     
     async fn scroll(&self, x: i32, y: i32, scroll_x: i32, scroll_y: i32) -> Result<(), CuaError> {
-        let (tx, rx) = oneshot::channel();
-        
-        self.command_sender.send(InputCommand::Scroll {
-            x,
-            y,
-            scroll_x,
-            scroll_y,
-            response: tx,
-        }).await.map_err(|_| CuaError::ActionError("Failed to send scroll command: desktop input thread is shutting down".to_string()))?;
-        
-        rx.await.map_err(|_| CuaError::ActionError("Failed to receive scroll response: desktop input thread is not available".to_string()))?
+        self.send_command(|response| InputCommand::Scroll { x, y, scroll_x, scroll_y, response }).await
     }
     
     async fn type_text(&self, text: &str) -> Result<(), CuaError> {
-        let (tx, rx) = oneshot::channel();
-        
-        self.command_sender.send(InputCommand::TypeText {
-            text: text.to_string(),
-            response: tx,
-        }).await.map_err(|_| CuaError::ActionError("Failed to send type text command: desktop input thread is shutting down".to_string()))?;
-        
-        rx.await.map_err(|_| CuaError::ActionError("Failed to receive type text response: desktop input thread is not available".to_string()))?
+        let text = text.to_string();
+        self.send_command(|response| InputCommand::TypeText { text, response }).await
     }
     
     async fn wait(&self, ms: u32) -> Result<(), CuaError> {
@@ -444,41 +855,342 @@ impl Computer for ThreadComputer {
     }
     
     async fn move_cursor(&self, x: i32, y: i32) -> Result<(), CuaError> {
-        let (tx, rx) = oneshot::channel();
-        
-        self.command_sender.send(InputCommand::MoveCursor {
-            x,
-            y,
-            response: tx,
-        }).await.map_err(|_| CuaError::ActionError("Failed to send move cursor command: desktop input thread is shutting down".to_string()))?;
+        self.send_command(|response| InputCommand::MoveCursor { x, y, response }).await
+    }
+    
+    async fn mouse_button_down(&self, button: &str) -> Result<(), CuaError> {
+        let button = button.to_string();
+        self.send_command(|response| InputCommand::MouseButtonDown { button, response }).await
+    }
+    
+    async fn mouse_button_up(&self, button: &str) -> Result<(), CuaError> {
+        let button = button.to_string();
+        self.send_command(|response| InputCommand::MouseButtonUp { button, response }).await
+    }
+    
+    async fn hover(&self, x: i32, y: i32, duration_ms: u32) -> Result<(), CuaError> {
+        // Glide to the target so hover-sensitive widgets see the pointer enter
+        const STEPS: i32 = 10;
+        let (start_x, start_y) = self.cursor_position();
+        for step in 1..=STEPS {
+            let step_x = start_x + (x - start_x) * step / STEPS;
+            let step_y = start_y + (y - start_y) * step / STEPS;
+            self.move_cursor(step_x, step_y).await?;
+            sleep(Duration::from_millis(10)).await;
+        }
         
-        rx.await.map_err(|_| CuaError::ActionError("Failed to receive move cursor response: desktop input thread is not available".to_string()))?
+        self.wait(duration_ms).await
     }
     
-    async fn keypress(&self, keys: &[String]) -> Result<(), CuaError> {
-        let (tx, rx) = oneshot::channel();
+    async fn zoom(&self, factor: f32, cx: i32, cy: i32) -> Result<(), CuaError> {
+        if factor <= 0.0 {
+            return Err(CuaError::ActionError(format!("Invalid zoom factor: {}", factor)));
+        }
+        
+        // Each zoom shortcut press changes the zoom by roughly this step
+        const ZOOM_STEP: f32 = 1.25;
+        let current = self.zoom_state();
+        let shortcuts = self.keyboard_shortcut_map();
         
-        self.command_sender.send(InputCommand::Keypress {
-            keys: keys.to_vec(),
-            response: tx,
-        }).await.map_err(|_| CuaError::ActionError("Failed to send keypress command: desktop input thread is shutting down".to_string()))?;
+        self.move_cursor(cx, cy).await?;
+        let reached = if (factor - 1.0).abs() < f32::EPSILON {
+            self.keypress(&shortcuts["reset_zoom"]).await?;
+            1.0
+        } else {
+            let presses = ((factor / current.factor).ln() / ZOOM_STEP.ln()).round() as i32;
+            let keys = &shortcuts[if presses > 0 { "zoom_in" } else { "zoom_out" }];
+            for _ in 0..presses.abs() {
+                self.keypress(keys).await?;
+            }
+            current.factor * ZOOM_STEP.powi(presses)
+        };
         
-        rx.await.map_err(|_| CuaError::ActionError("Failed to receive keypress response: desktop input thread is not available".to_string()))?
+        *self.zoom.lock().unwrap() = current.zoom_to(reached, cx, cy);
+        Ok(())
+    }
+    
+    fn zoom_state(&self) -> ZoomState {
+        *self.zoom.lock().unwrap()
+    }
+    
+    async fn keypress(&self, keys: &[String]) -> Result<(), CuaError> {
+        let keys = keys.to_vec();
+        self.send_command(|response| InputCommand::Keypress { keys, response }).await
+    }
+    
+    async fn press_and_hold(&self, key: &str, duration_ms: u32) -> Result<(), CuaError> {
+        let key = key.to_string();
+        self.send_command(|response| InputCommand::PressAndHold { key, duration_ms, response }).await
     }
     
     async fn drag(&self, path: &[HashMap<String, i32>]) -> Result<(), CuaError> {
-        let (tx, rx) = oneshot::channel();
-        
-        self.command_sender.send(InputCommand::Drag {
-            path: path.to_vec(),
-            response: tx,
-        }).await.map_err(|_| CuaError::ActionError("Failed to send drag command: desktop input thread is shutting down".to_string()))?;
-        
-        rx.await.map_err(|_| CuaError::ActionError("Failed to receive drag response: desktop input thread is not available".to_string()))?
+        let path = path.to_vec();
+        self.send_command(|response| InputCommand::Drag { path, response }).await
+    }
+    
+    async fn current_dialog(&self) -> Result<Option<DialogInfo>, CuaError> {
+        let mut found = dialog::find_modal_dialog().await?;
+        if let Some(dialog) = found.as_mut().filter(|dialog| dialog.buttons.is_empty()) {
+            // X11 does not expose button labels; read them from the accessibility tree if available
+            if let Ok(tree) = self.accessibility_tree().await {
+                dialog.buttons = buttons_within(&tree, dialog.bounds).into_iter().map(|(name, _)| name).collect();
+            }
+        }
+        Ok(found)
     }
     
     async fn get_current_url(&self) -> Result<String, CuaError> {
         // Not applicable for desktop environments
         Ok("".to_string())
     }
+    
+    async fn screen_color_at(&self, x: u32, y: u32) -> Result<(u8, u8, u8), CuaError> {
+        let (width, height) = self.dimensions();
+        if x >= width || y >= height {
+            return Err(CuaError::ActionError(format!(
+                "Pixel ({}, {}) is outside the {}x{} screen",
+                x, y, width, height
+            )));
+        }
+        self.send_command(|response| InputCommand::PixelColor { x, y, response }).await
+    }
+    
+    async fn read_screen_text(&self, x: u32, y: u32, w: u32, h: u32) -> Result<String, CuaError> {
+        let (width, height) = (w, h);
+        let region = self.send_command(|response| InputCommand::CaptureRegion { x, y, width, height, response }).await?;
+        Ok(ocr::words_to_text(&self.ocr_extract(&region).await?))
+    }
+    
+    async fn paste_from_clipboard(&self) -> Result<(), CuaError> {
+        self.keypress(&self.paste_key.shortcut("v")).await
+    }
+    
+    async fn copy_selection(&self) -> Result<(), CuaError> {
+        self.keypress(&self.paste_key.shortcut("c")).await
+    }
+    
+    async fn select_all_text(&self) -> Result<(), CuaError> {
+        self.keypress(&self.paste_key.shortcut("a")).await
+    }
+    
+    async fn get_clipboard(&self) -> Result<String, CuaError> {
+        clipboard::get_clipboard().await
+    }
+    
+    async fn set_clipboard(&self, text: &str) -> Result<(), CuaError> {
+        clipboard::set_clipboard(text).await
+    }
+    
+    async fn clipboard_history(&self, max_entries: usize) -> Result<Vec<ClipboardEntry>, CuaError> {
+        clipboard::clipboard_history(max_entries).await
+    }
+    
+    async fn get_clipboard_format(&self) -> Result<ClipboardFormat, CuaError> {
+        clipboard::get_clipboard_format().await
+    }
+    
+    async fn set_clipboard_html(&self, html: &str) -> Result<(), CuaError> {
+        clipboard::set_clipboard_html(html).await
+    }
+    
+    async fn monitor_clipboard_changes(&self, duration_ms: u32) -> Result<Vec<String>, CuaError> {
+        // Poll on a background task so slow clipboard reads don't delay the next poll
+        let (tx, mut rx) = mpsc::channel(16);
+        let polls = duration_ms as u128 / CLIPBOARD_POLL_INTERVAL.as_millis();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(CLIPBOARD_POLL_INTERVAL);
+            for _ in 0..=polls {
+                interval.tick().await;
+                let result = clipboard::get_clipboard().await;
+                let failed = result.is_err();
+                if tx.send(result).await.is_err() || failed {
+                    break;
+                }
+            }
+        });
+        
+        let mut values = Vec::new();
+        while let Some(result) = rx.recv().await {
+            let value = result?;
+            if !value.is_empty() && !values.contains(&value) {
+                values.push(value);
+            }
+        }
+        Ok(values)
+    }
+    
+    async fn get_focused_application(&self) -> Result<ApplicationInfo, CuaError> {
+        application::focused_application().await
+    }
+    
+    async fn get_running_processes_memory(&self) -> Result<Vec<(String, u64)>, CuaError> {
+        processes::processes_by_memory().await
+    }
+    
+    async fn execute_script(&self, script: &str, lang: &str) -> Result<String, CuaError> {
+        scripting::execute_script(script, lang, self.script_timeout).await
+    }
+    
+    async fn notify(&self, message: &str, duration_ms: u32) -> Result<(), CuaError> {
+        notification::notify(message, duration_ms).await
+    }
+    
+    async fn get_battery_level(&self) -> Result<Option<f32>, CuaError> {
+        battery::battery_level().await
+    }
+    
+    #[cfg(all(target_os = "linux", feature = "accessibility"))]
+    async fn accessibility_tree(&self) -> Result<crate::computer::AccessibilityNode, CuaError> {
+        super::accessibility::accessibility_tree().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    /// Build a ThreadComputer whose input thread has already gone away
+    fn disconnected_computer() -> ThreadComputer {
+        let (tx, rx) = mpsc::channel(1);
+        drop(rx);
+        ThreadComputer {
+            command_sender: tx,
+            environment: "linux".to_string(),
+            dimensions: Mutex::new((1920, 1080)),
+            screen_id: 0,
+            cursor_position: Arc::new(Mutex::new((0, 0))),
+            paste_key: PasteKey::host(),
+            zoom: Mutex::new(ZoomState::default()),
+            element_cache: ElementScreenshotCache::new(DEFAULT_ELEMENT_CACHE_TTL),
+            script_timeout: DEFAULT_SCRIPT_TIMEOUT,
+            window_list: Mutex::new(None),
+            env_allowlist: DEFAULT_ENV_ALLOWLIST.iter().map(|name| name.to_string()).collect(),
+            virtual_display: None,
+        }
+    }
+    
+    /// Keyboard that records key events and when they happened
+    #[derive(Default)]
+    struct RecordingKeyboard {
+        events: Vec<(&'static str, Key, Instant)>,
+    }
+    
+    impl KeyboardControllable for RecordingKeyboard {
+        fn key_sequence(&mut self, _sequence: &str) {}
+        
+        fn key_down(&mut self, key: Key) {
+            self.events.push(("down", key, Instant::now()));
+        }
+        
+        fn key_up(&mut self, key: Key) {
+            self.events.push(("up", key, Instant::now()));
+        }
+        
+        fn key_click(&mut self, _key: Key) {}
+    }
+    
+    #[test]
+    fn test_hold_key_waits_between_down_and_up() {
+        let mut keyboard = RecordingKeyboard::default();
+        
+        hold_key(&mut keyboard, Key::Space, 50);
+        
+        let events = &keyboard.events;
+        assert_eq!(events.len(), 2);
+        assert_eq!((events[0].0, events[0].1), ("down", Key::Space));
+        assert_eq!((events[1].0, events[1].1), ("up", Key::Space));
+        assert!(events[1].2 - events[0].2 >= Duration::from_millis(50));
+    }
+    
+    #[test]
+    fn test_screenshot_format_support() {
+        let computer = disconnected_computer();
+        #[cfg(not(feature = "webp"))]
+        assert_eq!(computer.screenshot_format_support(), vec![ScreenshotFormat::Png, ScreenshotFormat::Jpeg(95)]);
+        #[cfg(feature = "webp")]
+        assert!(ScreenshotFormat::WebP(80).is_supported_by(&computer.screenshot_format_support()));
+        assert!(ScreenshotFormat::Jpeg(60).is_supported_by(&computer.screenshot_format_support()));
+    }
+    
+    #[test]
+    fn test_scroll_notches_keep_direction() {
+        assert_eq!(scroll_notches(100), 10);
+        assert_eq!(scroll_notches(-50), -5);
+        assert_eq!(scroll_notches(0), 0);
+    }
+    
+    #[test]
+    fn test_map_key_aliases() {
+        assert_eq!(map_key("Enter"), Some(Key::Return));
+        assert_eq!(map_key("cmd"), Some(Key::Meta));
+        assert_eq!(map_key("option"), Some(Key::Alt));
+        assert_eq!(map_key("banana"), None);
+    }
+    
+    #[test]
+    fn test_map_button_middle() {
+        assert_eq!(map_button("middle"), MouseButton::Middle);
+        assert_eq!(map_button("wheel"), MouseButton::Middle);
+        assert_eq!(map_button("Right"), MouseButton::Right);
+        assert_eq!(map_button("back"), MouseButton::Left);
+    }
+    
+    #[test]
+    fn test_command_names() {
+        fn reply<T>() -> oneshot::Sender<Result<T, CuaError>> {
+            oneshot::channel().0
+        }
+        
+        let commands = vec![
+            (InputCommand::Click { x: 1, y: 2, button: "left".to_string(), response: reply() }, "Click"),
+            (InputCommand::DoubleClick { x: 1, y: 2, response: reply() }, "DoubleClick"),
+            (InputCommand::Scroll { x: 1, y: 2, scroll_x: 0, scroll_y: 3, response: reply() }, "Scroll"),
+            (InputCommand::TypeText { text: "hi".to_string(), response: reply() }, "TypeText"),
+            (InputCommand::MoveCursor { x: 1, y: 2, response: reply() }, "MoveCursor"),
+            (InputCommand::Keypress { keys: vec!["ctrl".to_string()], response: reply() }, "Keypress"),
+            (InputCommand::PressAndHold { key: "space".to_string(), duration_ms: 500, response: reply() }, "PressAndHold"),
+            (InputCommand::Drag { path: Vec::new(), response: reply() }, "Drag"),
+            (InputCommand::MouseButtonDown { button: "left".to_string(), response: reply() }, "MouseButtonDown"),
+            (InputCommand::MouseButtonUp { button: "left".to_string(), response: reply() }, "MouseButtonUp"),
+            (InputCommand::Screenshot { response: reply() }, "Screenshot"),
+            (InputCommand::ScreenshotJpeg { quality: 75, response: reply() }, "ScreenshotJpeg"),
+            (InputCommand::ScreenshotRaw { response: reply() }, "ScreenshotRaw"),
+            (InputCommand::CaptureAllScreens { response: reply() }, "CaptureAllScreens"),
+            (InputCommand::PixelColor { x: 1, y: 2, response: reply() }, "PixelColor"),
+            (InputCommand::CaptureRegion { x: 1, y: 2, width: 3, height: 4, response: reply() }, "CaptureRegion"),
+            (InputCommand::Shutdown, "Shutdown"),
+        ];
+        
+        for (command, name) in commands {
+            assert_eq!(command.name(), name);
+            assert_eq!(command.to_string(), name);
+            assert!(format!("{:?}", command).contains(&format!("\"command\":\"{}\"", name)));
+        }
+    }
+    
+    #[test]
+    fn test_debug_elides_response_channel() {
+        let (response, _) = oneshot::channel();
+        let command = InputCommand::Click { x: 10, y: 20, button: "left".to_string(), response };
+        
+        assert_eq!(format!("{:?}", command), r#"{"command":"Click","x":10,"y":20,"button":"left"}"#);
+    }
+    
+    #[test]
+    fn test_debug_redacts_typed_text() {
+        let (response, _) = oneshot::channel();
+        let command = InputCommand::TypeText { text: "hunter2".to_string(), response };
+        
+        assert_eq!(format!("{:?}", command), r#"{"command":"TypeText","text":"<7 chars>"}"#);
+    }
+    
+    #[tokio::test]
+    async fn test_screenshot_send_failure_names_command() {
+        let computer = disconnected_computer();
+        
+        match computer.screenshot().await {
+            Err(CuaError::ChannelError { command, .. }) => assert_eq!(command, "Screenshot"),
+            other => panic!("expected ChannelError, got {:?}", other),
+        }
+    }
 }
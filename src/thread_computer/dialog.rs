@@ -0,0 +1,139 @@
+//! Modal dialog detection through the platform's command line utilities
+//!
+//! On Linux the X11 window list is queried with `xprop` / `xwininfo`, which
+//! only exposes the dialog's title and geometry; the computer fills in the
+//! buttons from the accessibility tree. On macOS `osascript` asks
+//! System Events for windows with a dialog subrole, including their text and buttons.
+
+use crate::command::run_command;
+use crate::computer::DialogInfo;
+use crate::error::CuaError;
+
+/// Separator between fields in the AppleScript output
+const FIELD_SEPARATOR: &str = "\u{1f}";
+
+/// Lists the first dialog window of any visible process as separated fields:
+/// title, message, buttons (comma separated), x, y, width, height
+const MACOS_DIALOG_SCRIPT: &str = r#"
+set sep to character id 31
+tell application "System Events"
+    repeat with proc in (application processes whose visible is true)
+        repeat with win in windows of proc
+            try
+                if subrole of win is in {"AXDialog", "AXSystemDialog"} then
+                    set msg to ""
+                    try
+                        set msg to value of static text 1 of win
+                    end try
+                    set AppleScript's text item delimiters to ","
+                    set buttonNames to (name of buttons of win) as text
+                    set AppleScript's text item delimiters to ""
+                    set {x, y} to position of win
+                    set {w, h} to size of win
+                    return (name of win) & sep & msg & sep & buttonNames & sep & x & sep & y & sep & w & sep & h
+                end if
+            end try
+        end repeat
+    end repeat
+end tell
+return ""
+"#;
+
+/// Find a modal dialog window, if one is open
+pub async fn find_modal_dialog() -> Result<Option<DialogInfo>, CuaError> {
+    if cfg!(target_os = "macos") {
+        let output = run_command("osascript", &["-e", MACOS_DIALOG_SCRIPT], None).await?;
+        Ok(parse_macos_dialog(&output))
+    } else if cfg!(target_os = "linux") {
+        let client_list = run_command("xprop", &["-root", "_NET_CLIENT_LIST"], None).await?;
+        for window in parse_window_ids(&client_list) {
+            let state = run_command("xprop", &["-id", &window, "_NET_WM_STATE"], None).await?;
+            if !state.contains("_NET_WM_STATE_MODAL") {
+                continue;
+            }
+            
+            let name = run_command("xprop", &["-id", &window, "_NET_WM_NAME"], None).await?;
+            let geometry = run_command("xwininfo", &["-id", &window], None).await?;
+            return Ok(Some(DialogInfo {
+                title: parse_quoted_value(&name).unwrap_or_default(),
+                message: String::new(),
+                buttons: Vec::new(),
+                bounds: parse_geometry(&geometry),
+            }));
+        }
+        Ok(None)
+    } else {
+        Err(CuaError::ActionError("Dialog detection not supported on this platform".to_string()))
+    }
+}
+
+/// Parse window ids from `_NET_CLIENT_LIST(WINDOW): window id # 0x1a00003, 0x2400007`
+fn parse_window_ids(client_list: &str) -> Vec<String> {
+    client_list
+        .split_once('#')
+        .map(|(_, ids)| ids.split(',').map(|id| id.trim().to_string()).filter(|id| !id.is_empty()).collect())
+        .unwrap_or_default()
+}
+
+/// Parse the string from `_NET_WM_NAME(UTF8_STRING) = "Title"`
+fn parse_quoted_value(property: &str) -> Option<String> {
+    let (_, value) = property.split_once('=')?;
+    Some(value.trim().trim_matches('"').to_string())
+}
+
+/// Parse (x, y, width, height) from `xwininfo` output
+fn parse_geometry(xwininfo: &str) -> (i32, i32, u32, u32) {
+    let field = |name: &str| {
+        xwininfo
+            .lines()
+            .find_map(|line| line.trim().strip_prefix(name))
+            .and_then(|value| value.trim().parse::<i64>().ok())
+            .unwrap_or(0)
+    };
+    (
+        field("Absolute upper-left X:") as i32,
+        field("Absolute upper-left Y:") as i32,
+        field("Width:") as u32,
+        field("Height:") as u32,
+    )
+}
+
+/// Parse the fields printed by `MACOS_DIALOG_SCRIPT`
+fn parse_macos_dialog(output: &str) -> Option<DialogInfo> {
+    let fields: Vec<&str> = output.trim_end_matches('\n').split(FIELD_SEPARATOR).collect();
+    if fields.len() != 7 {
+        return None;
+    }
+    let number = |i: usize| fields[i].trim().parse::<i64>().unwrap_or(0);
+    Some(DialogInfo {
+        title: fields[0].to_string(),
+        message: fields[1].to_string(),
+        buttons: fields[2].split(',').map(|b| b.trim().to_string()).filter(|b| !b.is_empty()).collect(),
+        bounds: (number(3) as i32, number(4) as i32, number(5) as u32, number(6) as u32),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_parse_x11_output() {
+        assert_eq!(
+            parse_window_ids("_NET_CLIENT_LIST(WINDOW): window id # 0x1a00003, 0x2400007\n"),
+            vec!["0x1a00003", "0x2400007"]
+        );
+        assert_eq!(parse_quoted_value("_NET_WM_NAME(UTF8_STRING) = \"Save changes?\"\n").unwrap(), "Save changes?");
+        
+        let xwininfo = "\nxwininfo: Window id: 0x2400007 \"Save changes?\"\n\n  \
+                        Absolute upper-left X:  760\n  Absolute upper-left Y:  440\n  \
+                        Relative upper-left X:  0\n  Width: 400\n  Height: 200\n";
+        assert_eq!(parse_geometry(xwininfo), (760, 440, 400, 200));
+        
+        let macos = "Save changes?\u{1f}Your changes will be lost.\u{1f}Don't Save, Cancel, Save\u{1f}760\u{1f}440\u{1f}400\u{1f}200\n";
+        let dialog = parse_macos_dialog(macos).unwrap();
+        assert_eq!(dialog.buttons, vec!["Don't Save", "Cancel", "Save"]);
+        assert_eq!(dialog.bounds, (760, 440, 400, 200));
+        assert_eq!(parse_macos_dialog("\n"), None);
+    }
+}
@@ -0,0 +1,209 @@
+//! Display resolution and virtual displays (Xvfb) on Linux
+//!
+//! The resolution is changed with `xrandr`, adding the mode from a `cvt`
+//! modeline when the X server does not offer it yet.
+
+use crate::command::run_command;
+use crate::error::CuaError;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::process::{Child, Command};
+
+/// Output whose mode `set_display_resolution` changes
+const XRANDR_OUTPUT: &str = "default";
+
+/// Screen size and color depth of a virtual display started by `start_virtual_display`
+const VIRTUAL_SCREEN: &str = "1920x1080x24";
+
+/// How long to wait for a new Xvfb server to accept connections
+const XVFB_STARTUP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How often to check whether a new Xvfb server is up
+const XVFB_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Switch the display to `width`x`height`
+pub async fn set_display_resolution(width: u32, height: u32) -> Result<(), CuaError> {
+    if !cfg!(target_os = "linux") {
+        return Err(CuaError::ActionError("Setting the display resolution is only supported on Linux".to_string()));
+    }
+    apply_resolution("xrandr", "cvt", width, height).await
+}
+
+/// Set the mode with `xrandr`, creating it from the modeline `cvt` prints if it is unknown
+async fn apply_resolution(xrandr: &str, cvt: &str, width: u32, height: u32) -> Result<(), CuaError> {
+    let mode = mode_name(width, height);
+    if run(xrandr, set_mode_args(&mode)).await.is_ok() {
+        return Ok(());
+    }
+    
+    tracing::debug!(%mode, "Adding display mode");
+    let cvt_output = run(cvt, vec![width.to_string(), height.to_string()]).await?;
+    let timings = parse_cvt_modeline(&cvt_output).ok_or_else(|| {
+        CuaError::ActionError(format!("No modeline in cvt output: {}", cvt_output.trim()))
+    })?;
+    run(xrandr, newmode_args(&mode, timings)).await?;
+    run(xrandr, addmode_args(&mode)).await?;
+    run(xrandr, set_mode_args(&mode)).await.map(|_| ())
+}
+
+/// Run `program` with owned arguments
+async fn run(program: &str, args: Vec<String>) -> Result<String, CuaError> {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    run_command(program, &args, None).await
+}
+
+/// Name of the xrandr mode for a resolution, e.g. "1920x1080"
+fn mode_name(width: u32, height: u32) -> String {
+    format!("{}x{}", width, height)
+}
+
+/// Arguments for `xrandr` to switch the output to `mode`
+fn set_mode_args(mode: &str) -> Vec<String> {
+    vec!["--output".to_string(), XRANDR_OUTPUT.to_string(), "--mode".to_string(), mode.to_string()]
+}
+
+/// Arguments for `xrandr` to define `mode` with the given timings
+fn newmode_args(mode: &str, timings: Vec<String>) -> Vec<String> {
+    let mut args = vec!["--newmode".to_string(), mode.to_string()];
+    args.extend(timings);
+    args
+}
+
+/// Arguments for `xrandr` to make `mode` available on the output
+fn addmode_args(mode: &str) -> Vec<String> {
+    vec!["--addmode".to_string(), XRANDR_OUTPUT.to_string(), mode.to_string()]
+}
+
+/// The timings of the `Modeline` line in `cvt` output, without the mode name
+fn parse_cvt_modeline(output: &str) -> Option<Vec<String>> {
+    let line = output.lines().find(|line| line.trim_start().starts_with("Modeline"))?;
+    let timings: Vec<String> = line.split_whitespace().skip(2).map(str::to_string).collect();
+    (!timings.is_empty()).then_some(timings)
+}
+
+/// Xvfb server started by [`start_virtual_display`], stopped and reaped when dropped
+pub struct XvfbServer {
+    _child: Child,
+}
+
+/// Start an Xvfb server on `display_name` (e.g. ":99") unless one is running
+///
+/// `DISPLAY` must already name the display, since the X libraries read it when
+/// connecting and the environment cannot be changed safely once threads are
+/// running; the CLI sets it before starting the runtime. Returns the server if
+/// this call started it.
+pub async fn start_virtual_display(display_name: &str) -> Result<Option<XvfbServer>, CuaError> {
+    let number = display_number(display_name)
+        .ok_or_else(|| CuaError::Other(format!("Invalid X display: {}", display_name)))?;
+    if std::env::var("DISPLAY").ok().as_deref() != Some(display_name) {
+        return Err(CuaError::Other(format!("Set DISPLAY={} before using the virtual display", display_name)));
+    }
+    
+    if tokio::fs::try_exists(format!("/tmp/.X{}-lock", number)).await.unwrap_or(false) {
+        tracing::debug!(display = display_name, "Virtual display already running");
+        return Ok(None);
+    }
+    
+    tracing::info!(display = display_name, screen = VIRTUAL_SCREEN, "Starting Xvfb");
+    let mut child = Command::new("Xvfb")
+        .args(xvfb_args(display_name))
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| CuaError::Other(format!("Failed to start Xvfb: {}", e)))?;
+    
+    // The server accepts connections once its socket exists
+    let socket = PathBuf::from(format!("/tmp/.X11-unix/X{}", number));
+    let wait_for_socket = async {
+        while !tokio::fs::try_exists(&socket).await.unwrap_or(false) {
+            if let Some(status) = child.try_wait()? {
+                return Err(CuaError::Other(format!("Xvfb exited on {} with {}", display_name, status)));
+            }
+            tokio::time::sleep(XVFB_POLL_INTERVAL).await;
+        }
+        Ok(())
+    };
+    tokio::time::timeout(XVFB_STARTUP_TIMEOUT, wait_for_socket).await.map_err(|_| {
+        CuaError::Other(format!("Xvfb did not start on {} within {:?}", display_name, XVFB_STARTUP_TIMEOUT))
+    })??;
+    Ok(Some(XvfbServer { _child: child }))
+}
+
+/// The display number of an X display name like ":99" or ":99.0"
+fn display_number(display: &str) -> Option<u32> {
+    display.strip_prefix(':')?.split('.').next()?.parse().ok()
+}
+
+/// Arguments for `Xvfb` to serve `display` with one screen
+fn xvfb_args(display: &str) -> Vec<String> {
+    vec![
+        display.to_string(),
+        "-screen".to_string(),
+        "0".to_string(),
+        VIRTUAL_SCREEN.to_string(),
+        "-nolisten".to_string(),
+        "tcp".to_string(),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    /// Write an executable script named `name` that appends its arguments to `log`, then runs `body`
+    #[cfg(unix)]
+    fn fake_command(dir: &std::path::Path, name: &str, log: &std::path::Path, body: &str) -> String {
+        use std::os::unix::fs::PermissionsExt;
+        let path = dir.join(name);
+        std::fs::write(&path, format!("#!/bin/sh\necho \"$@\" >> {}\n{}\n", log.display(), body)).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        path.to_string_lossy().to_string()
+    }
+    
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_apply_resolution_adds_missing_mode() {
+        let dir = std::env::temp_dir().join(format!("cua_xrandr_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let log = dir.join("calls.log");
+        // Fails to set the mode until it has been added
+        let xrandr = fake_command(&dir, "xrandr", &log, &format!(
+            "case \"$1\" in --addmode) touch {0}/added ;; --output) [ -e {0}/added ] || exit 1 ;; esac",
+            dir.display()
+        ));
+        let cvt = fake_command(&dir, "cvt", &log, concat!(
+            "echo '# 1280x720 59.86 Hz (CVT 0.92M9) hsync: 44.77 kHz; pclk: 74.50 MHz'\n",
+            "echo 'Modeline \"1280x720_60.00\"   74.50  1280 1344 1472 1664  720 723 728 748 -hsync +vsync'"
+        ));
+        
+        apply_resolution(&xrandr, &cvt, 1280, 720).await.unwrap();
+        
+        let calls = std::fs::read_to_string(&log).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        let calls: Vec<&str> = calls.lines().collect();
+        assert_eq!(calls, vec![
+            "--output default --mode 1280x720",
+            "1280 720",
+            "--newmode 1280x720 74.50 1280 1344 1472 1664 720 723 728 748 -hsync +vsync",
+            "--addmode default 1280x720",
+            "--output default --mode 1280x720",
+        ]);
+    }
+    
+    #[tokio::test]
+    async fn test_virtual_display_needs_display_set() {
+        let result = start_virtual_display(":4242").await;
+        assert!(matches!(result, Err(CuaError::Other(msg)) if msg.contains("DISPLAY=:4242")));
+    }
+    
+    #[test]
+    fn test_display_number() {
+        assert_eq!(display_number(":99"), Some(99));
+        assert_eq!(display_number(":1.0"), Some(1));
+        assert_eq!(display_number("99"), None);
+        assert_eq!(xvfb_args(":99")[..4], [":99", "-screen", "0", "1920x1080x24"]);
+    }
+}
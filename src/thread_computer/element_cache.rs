@@ -0,0 +1,150 @@
+//! Cache of labeled element screenshots for `capture_element_screenshot`
+
+use crate::error::CuaError;
+use crate::vision::decode_screenshot;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::future::Future;
+use std::hash::{Hash, Hasher};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How long a cached element screenshot is reused by default
+pub const DEFAULT_ELEMENT_CACHE_TTL: Duration = Duration::from_secs(30);
+
+/// Region as (x, y, width, height)
+type Region = (u32, u32, u32, u32);
+
+/// A cached element screenshot
+struct CacheEntry {
+    region: Region,
+    /// Base64 PNG of the region
+    image: String,
+    /// Hash of the region's pixels
+    hash: u64,
+    captured_at: Instant,
+    /// Set when input may have changed the screen since the capture
+    stale: bool,
+}
+
+/// Labeled element screenshots, reused until they expire or input may have changed the screen
+///
+/// A stale or expired entry is recaptured; if the region's pixels hash the
+/// same as before, the cached image is kept and returned.
+pub struct ElementScreenshotCache {
+    entries: Mutex<HashMap<String, CacheEntry>>,
+    ttl: Duration,
+}
+
+impl ElementScreenshotCache {
+    /// Create an empty cache whose entries expire after `ttl`
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            entries: Mutex::new(HashMap::new()),
+            ttl,
+        }
+    }
+    
+    /// Mark every entry stale, e.g. after a click or key press
+    pub fn invalidate(&self) {
+        for entry in self.entries.lock().unwrap().values_mut() {
+            entry.stale = true;
+        }
+    }
+    
+    /// Return the cached image for `label`, or capture `region` with `capture` and cache it
+    pub async fn get_or_capture<F, Fut>(&self, label: &str, region: Region, capture: F) -> Result<String, CuaError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<String, CuaError>>,
+    {
+        if let Some(entry) = self.entries.lock().unwrap().get(label) {
+            if entry.region == region && !entry.stale && entry.captured_at.elapsed() < self.ttl {
+                return Ok(entry.image.clone());
+            }
+        }
+        
+        let image = capture().await?;
+        let hash = pixel_hash(&image)?;
+        let mut entries = self.entries.lock().unwrap();
+        match entries.get_mut(label) {
+            Some(entry) if entry.region == region && entry.hash == hash => {
+                entry.captured_at = Instant::now();
+                entry.stale = false;
+                Ok(entry.image.clone())
+            }
+            _ => {
+                entries.insert(label.to_string(), CacheEntry {
+                    region,
+                    image: image.clone(),
+                    hash,
+                    captured_at: Instant::now(),
+                    stale: false,
+                });
+                Ok(image)
+            }
+        }
+    }
+}
+
+/// Hash of an image's size and pixels, independent of how it was encoded
+fn pixel_hash(base64_image: &str) -> Result<u64, CuaError> {
+    let pixels = decode_screenshot(base64_image)?.to_rgba8();
+    let mut hasher = DefaultHasher::new();
+    pixels.dimensions().hash(&mut hasher);
+    pixels.as_raw().hash(&mut hasher);
+    Ok(hasher.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vision::encode_png;
+    use image::{Rgba, RgbaImage};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    
+    fn solid(color: [u8; 4]) -> String {
+        encode_png(&RgbaImage::from_pixel(20, 10, Rgba(color)).into()).unwrap()
+    }
+    
+    #[tokio::test]
+    async fn test_cache_reuses_unchanged_capture() {
+        let cache = ElementScreenshotCache::new(DEFAULT_ELEMENT_CACHE_TTL);
+        let captures = AtomicUsize::new(0);
+        let capture = |image: String| {
+            captures.fetch_add(1, Ordering::SeqCst);
+            async move { Ok(image) }
+        };
+        let region = (10, 10, 20, 10);
+        
+        let first = cache.get_or_capture("button", region, || capture(solid([0, 0, 255, 255]))).await.unwrap();
+        let second = cache.get_or_capture("button", region, || capture(solid([0, 0, 255, 255]))).await.unwrap();
+        assert_eq!(first, second);
+        assert_eq!(captures.load(Ordering::SeqCst), 1);
+        
+        // After input the region is recaptured, and a change replaces the entry
+        cache.invalidate();
+        let changed = cache.get_or_capture("button", region, || capture(solid([255, 0, 0, 255]))).await.unwrap();
+        assert_eq!(captures.load(Ordering::SeqCst), 2);
+        assert_eq!(changed, solid([255, 0, 0, 255]));
+        let cached = cache.get_or_capture("button", region, || capture(solid([0, 255, 0, 255]))).await.unwrap();
+        assert_eq!(cached, changed);
+        assert_eq!(captures.load(Ordering::SeqCst), 2);
+    }
+    
+    #[tokio::test]
+    async fn test_cache_entries_expire() {
+        let cache = ElementScreenshotCache::new(Duration::ZERO);
+        let captures = AtomicUsize::new(0);
+        let capture = || {
+            captures.fetch_add(1, Ordering::SeqCst);
+            async { Ok(solid([0, 0, 0, 255])) }
+        };
+        
+        cache.get_or_capture("logo", (0, 0, 20, 10), capture).await.unwrap();
+        cache.get_or_capture("logo", (0, 0, 20, 10), capture).await.unwrap();
+        
+        assert_eq!(captures.load(Ordering::SeqCst), 2);
+        assert!(cache.get_or_capture("logo", (0, 0, 20, 10), || async { Ok("bm90IGEgcG5n".to_string()) }).await.is_err());
+    }
+}
@@ -0,0 +1,156 @@
+//! Temporary on-screen outlines drawn with the platform's GUI toolkits
+//!
+//! Draws four borderless Tk windows with `python3` on Linux, a borderless
+//! `NSPanel` through JXA on macOS and a WinForms form with a transparent
+//! center through PowerShell on Windows. Each script runs in the background
+//! and closes its windows after the requested duration.
+
+use crate::command::spawn_command;
+use crate::error::CuaError;
+
+/// Width of the outline in pixels
+const BORDER_WIDTH: u32 = 4;
+
+/// Takes `x y width height duration_ms color border_width`
+const LINUX_SCRIPT: &str = r#"
+import sys, tkinter as tk
+x, y, w, h, ms = (int(v) for v in sys.argv[1:6])
+color, t = sys.argv[6], int(sys.argv[7])
+root = tk.Tk()
+root.withdraw()
+for bx, by, bw, bh in [(x, y, w, t), (x, y + h - t, w, t), (x, y, t, h), (x + w - t, y, t, h)]:
+    edge = tk.Toplevel(root, bg=color)
+    edge.overrideredirect(True)
+    edge.attributes("-topmost", True)
+    edge.geometry(f"{max(bw, 1)}x{max(bh, 1)}+{bx}+{by}")
+root.after(ms, root.destroy)
+root.mainloop()
+"#;
+
+/// Takes `x y width height duration_ms color border_width`
+const MACOS_SCRIPT: &str = r#"
+ObjC.import("Cocoa");
+function run(argv) {
+    const [x, y, w, h, ms] = argv.slice(0, 5).map(Number);
+    const component = i => parseInt(argv[5].substr(i, 2), 16) / 255;
+    $.NSApplication.sharedApplication;
+    const screenHeight = $.NSScreen.mainScreen.frame.size.height;
+    const panel = $.NSPanel.alloc.initWithContentRectStyleMaskBackingDefer(
+        $.NSMakeRect(x, screenHeight - y - h, w, h),
+        $.NSWindowStyleMaskBorderless | $.NSWindowStyleMaskNonactivatingPanel,
+        $.NSBackingStoreBuffered,
+        false
+    );
+    panel.opaque = false;
+    panel.backgroundColor = $.NSColor.clearColor;
+    panel.level = $.NSStatusWindowLevel;
+    panel.ignoresMouseEvents = true;
+    panel.contentView.wantsLayer = true;
+    panel.contentView.layer.borderWidth = Number(argv[6]);
+    panel.contentView.layer.borderColor =
+        $.NSColor.colorWithSRGBRedGreenBlueAlpha(component(1), component(3), component(5), 1).CGColor;
+    panel.orderFrontRegardless;
+    $.NSRunLoop.currentRunLoop.runUntilDate($.NSDate.dateWithTimeIntervalSinceNow(ms / 1000));
+}
+"#;
+
+/// Takes `x y width height duration_ms color border_width`; magenta marks the transparent center
+///
+/// The form is shown without activating it, so keyboard focus stays in the
+/// window the agent is typing into.
+const WINDOWS_SCRIPT: &str = r#"
+Add-Type -AssemblyName System.Windows.Forms, System.Drawing
+Add-Type -ReferencedAssemblies System.Windows.Forms -TypeDefinition @"
+using System.Windows.Forms;
+public class OutlineForm : Form {
+    protected override bool ShowWithoutActivation { get { return true; } }
+    protected override CreateParams CreateParams {
+        get {
+            CreateParams p = base.CreateParams;
+            // WS_EX_NOACTIVATE | WS_EX_TOOLWINDOW | WS_EX_TRANSPARENT
+            p.ExStyle |= 0x08000000 | 0x00000080 | 0x00000020;
+            return p;
+        }
+    }
+}
+"@
+$x, $y, $w, $h, $ms = $args[0..4] | ForEach-Object { [int]$_ }
+$t = [int]$args[6]
+$form = New-Object OutlineForm
+$form.FormBorderStyle = 'None'
+$form.TopMost = $true
+$form.ShowInTaskbar = $false
+$form.StartPosition = 'Manual'
+$form.Bounds = New-Object System.Drawing.Rectangle($x, $y, $w, $h)
+$form.BackColor = [System.Drawing.ColorTranslator]::FromHtml($args[5])
+$form.TransparencyKey = [System.Drawing.Color]::Magenta
+$center = New-Object System.Windows.Forms.Panel
+$center.BackColor = [System.Drawing.Color]::Magenta
+$center.SetBounds($t, $t, [Math]::Max($w - 2 * $t, 0), [Math]::Max($h - 2 * $t, 0))
+$form.Controls.Add($center)
+$timer = New-Object System.Windows.Forms.Timer
+$timer.Interval = [Math]::Max($ms, 1)
+$timer.Add_Tick({ $form.Close(); [System.Windows.Forms.Application]::ExitThread() })
+$timer.Start()
+$form.Show()
+[System.Windows.Forms.Application]::Run()
+"#;
+
+/// Outline the `w`x`h` region at (x, y) in `color` for `duration_ms` without waiting for it
+///
+/// Only a missing GUI toolkit is reported; the outline closes by itself.
+pub fn highlight_region(x: u32, y: u32, w: u32, h: u32, color: &str, duration_ms: u32) -> Result<(), CuaError> {
+    let color = parse_color(color)?;
+    let args: Vec<String> = vec![
+        x.to_string(),
+        y.to_string(),
+        w.to_string(),
+        h.to_string(),
+        duration_ms.to_string(),
+        color,
+        BORDER_WIDTH.to_string(),
+    ];
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    
+    if cfg!(target_os = "macos") {
+        spawn_command("osascript", &[&["-l", "JavaScript", "-e", MACOS_SCRIPT], args.as_slice()].concat())?;
+    } else if cfg!(target_os = "windows") {
+        spawn_command("powershell", &[&["-NoProfile", "-Command", WINDOWS_SCRIPT], args.as_slice()].concat())?;
+    } else if cfg!(target_os = "linux") {
+        spawn_command("python3", &[&["-c", LINUX_SCRIPT], args.as_slice()].concat())?;
+    } else {
+        return Err(CuaError::ActionError("Region highlighting not supported on this platform".to_string()));
+    }
+    Ok(())
+}
+
+/// Normalize a color name or `#rrggbb` to lowercase `#rrggbb`
+fn parse_color(color: &str) -> Result<String, CuaError> {
+    let color = color.trim().to_lowercase();
+    let hex = match color.as_str() {
+        "red" => "#ff0000",
+        "green" => "#00c000",
+        "blue" => "#0000ff",
+        "yellow" => "#ffff00",
+        "orange" => "#ff8000",
+        hex if hex.len() == 7 && hex.starts_with('#') && hex[1..].chars().all(|c| c.is_ascii_hexdigit()) => hex,
+        _ => return Err(CuaError::ActionError(format!(
+            "Unsupported highlight color {:?}, use red, green, blue, yellow, orange or #rrggbb",
+            color
+        ))),
+    };
+    Ok(hex.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_parse_color() {
+        assert_eq!(parse_color("Red").unwrap(), "#ff0000");
+        assert_eq!(parse_color("#1E90FF").unwrap(), "#1e90ff");
+        assert!(parse_color("#12345").is_err());
+        assert!(parse_color("chartreuse").is_err());
+    }
+}
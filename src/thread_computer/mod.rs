@@ -1,4 +1,20 @@
 //! Thread-based implementation of the Computer trait using Enigo
 
+#[cfg(all(target_os = "linux", feature = "accessibility"))]
+mod accessibility;
+mod application;
+mod battery;
+mod caret;
+mod clipboard;
 mod computer;
+mod dialog;
+mod display;
+mod element_cache;
+mod highlight;
+mod notification;
+mod processes;
+mod scripting;
+mod window;
 pub use computer::ThreadComputer;
+pub use element_cache::{ElementScreenshotCache, DEFAULT_ELEMENT_CACHE_TTL};
+pub use scripting::DEFAULT_SCRIPT_TIMEOUT;
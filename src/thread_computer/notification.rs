@@ -0,0 +1,90 @@
+//! Desktop notifications through the platform's command line utilities
+//!
+//! Uses `notify-send` on Linux, AppleScript's `display notification` on macOS
+//! and a tray balloon tip through PowerShell on Windows.
+
+use crate::command::{run_command, spawn_command};
+use crate::error::CuaError;
+
+/// Title shown above every notification
+const TITLE: &str = "Computer-use agent";
+
+/// Show `message` as a desktop notification for about `duration_ms`
+///
+/// On Windows the PowerShell script keeps the tray icon alive for the whole
+/// duration, so it runs in the background instead of being waited for.
+pub async fn notify(message: &str, duration_ms: u32) -> Result<(), CuaError> {
+    let (program, args) = if cfg!(target_os = "macos") {
+        ("osascript", macos_args(message))
+    } else if cfg!(target_os = "windows") {
+        let args = windows_args(message, duration_ms);
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        return spawn_command("powershell", &args);
+    } else if cfg!(target_os = "linux") {
+        ("notify-send", linux_args(message, duration_ms))
+    } else {
+        return Err(CuaError::ActionError("Notifications not supported on this platform".to_string()));
+    };
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    run_command(program, &args, None).await.map(|_| ())
+}
+
+/// Arguments for `notify-send`, which takes the expiry time in milliseconds
+fn linux_args(message: &str, duration_ms: u32) -> Vec<String> {
+    vec![
+        "--expire-time".to_string(),
+        duration_ms.to_string(),
+        "--".to_string(),
+        TITLE.to_string(),
+        message.to_string(),
+    ]
+}
+
+/// Arguments for `osascript`; macOS decides how long the banner stays up
+fn macos_args(message: &str) -> Vec<String> {
+    let quote = |text: &str| text.replace('\\', "\\\\").replace('"', "\\\"");
+    vec![
+        "-e".to_string(),
+        format!("display notification \"{}\" with title \"{}\"", quote(message), quote(TITLE)),
+    ]
+}
+
+/// Arguments for PowerShell, showing a balloon tip and keeping the tray icon until it expires
+fn windows_args(message: &str, duration_ms: u32) -> Vec<String> {
+    let quote = |text: &str| text.replace('\'', "''");
+    let script = format!(
+        "Add-Type -AssemblyName System.Windows.Forms; \
+         $icon = New-Object System.Windows.Forms.NotifyIcon; \
+         $icon.Icon = [System.Drawing.SystemIcons]::Information; \
+         $icon.Visible = $true; \
+         $icon.ShowBalloonTip({ms}, '{}', '{}', 'Info'); \
+         Start-Sleep -Milliseconds {ms}; \
+         $icon.Dispose()",
+        quote(TITLE),
+        quote(message),
+        ms = duration_ms,
+    );
+    vec!["-NoProfile".to_string(), "-Command".to_string(), script]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_platform_commands() {
+        assert_eq!(
+            linux_args("-v needs input", 5000),
+            vec!["--expire-time", "5000", "--", "Computer-use agent", "-v needs input"]
+        );
+        assert_eq!(
+            macos_args("Click \"OK\" to continue"),
+            vec!["-e", "display notification \"Click \\\"OK\\\" to continue\" with title \"Computer-use agent\""]
+        );
+        
+        let windows = windows_args("It's waiting", 3000);
+        assert_eq!(windows[..2], ["-NoProfile", "-Command"]);
+        assert!(windows[2].contains("ShowBalloonTip(3000, 'Computer-use agent', 'It''s waiting', 'Info')"));
+        assert!(windows[2].contains("Start-Sleep -Milliseconds 3000"));
+    }
+}
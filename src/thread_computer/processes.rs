@@ -0,0 +1,21 @@
+//! Memory use of the running processes, read with the `sysinfo` crate
+
+use crate::error::CuaError;
+use sysinfo::System;
+
+/// Name and resident memory in bytes of every running process, largest first
+pub async fn processes_by_memory() -> Result<Vec<(String, u64)>, CuaError> {
+    tokio::task::spawn_blocking(|| {
+        let mut system = System::new();
+        system.refresh_processes();
+        let mut processes: Vec<(String, u64)> = system
+            .processes()
+            .values()
+            .map(|process| (process.name().to_string(), process.memory()))
+            .collect();
+        processes.sort_by_key(|(_, bytes)| std::cmp::Reverse(*bytes));
+        processes
+    })
+    .await
+    .map_err(|e| CuaError::ActionError(format!("Failed to list processes: {}", e)))
+}
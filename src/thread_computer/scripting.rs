@@ -0,0 +1,60 @@
+//! Running AppleScript, PowerShell and bash scripts for `Computer::execute_script`
+//!
+//! Scripts run unchecked; the agent only offers the tool when script execution
+//! is enabled and asks the safety check callback before each script.
+
+use crate::command::run_command;
+use crate::error::CuaError;
+use std::time::Duration;
+
+/// How long a script may run by default
+pub const DEFAULT_SCRIPT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Run `script` with the interpreter for `lang`, returning its stdout
+pub async fn execute_script(script: &str, lang: &str, timeout: Duration) -> Result<String, CuaError> {
+    let (program, args) = interpreter_command(script, lang)?;
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    tokio::time::timeout(timeout, run_command(program, &args, None))
+        .await
+        .map_err(|_| CuaError::ActionError(format!("Script timed out after {:?}", timeout)))?
+}
+
+/// Program and arguments that run `script` as `lang`
+fn interpreter_command(script: &str, lang: &str) -> Result<(&'static str, Vec<String>), CuaError> {
+    match lang.to_lowercase().as_str() {
+        #[cfg(target_os = "macos")]
+        "applescript" => Ok(("osascript", vec!["-e".to_string(), script.to_string()])),
+        #[cfg(not(target_os = "macos"))]
+        "applescript" => Err(CuaError::ActionError("AppleScript is only available on macOS".to_string())),
+        "powershell" => Ok((
+            "powershell",
+            vec!["-NoProfile".to_string(), "-NonInteractive".to_string(), "-Command".to_string(), script.to_string()],
+        )),
+        "bash" => Ok(("bash", vec!["-c".to_string(), script.to_string()])),
+        _ => Err(CuaError::ActionError(format!(
+            "Unknown script language '{}', expected applescript, powershell or bash",
+            lang
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_interpreter_per_language() {
+        assert_eq!(interpreter_command("echo hi", "bash").unwrap(), ("bash", vec!["-c".to_string(), "echo hi".to_string()]));
+        let (program, args) = interpreter_command("Get-Date", "PowerShell").unwrap();
+        assert_eq!(program, "powershell");
+        assert_eq!(args.last().unwrap(), "Get-Date");
+        
+        let applescript = interpreter_command("tell application \"Finder\" to activate", "applescript");
+        if cfg!(target_os = "macos") {
+            assert_eq!(applescript.unwrap().0, "osascript");
+        } else {
+            assert!(applescript.is_err());
+        }
+        assert!(interpreter_command("print(1)", "python").is_err());
+    }
+}
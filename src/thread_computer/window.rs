@@ -0,0 +1,422 @@
+//! Window lists and single-window screenshots through the platform's command line utilities
+//!
+//! Windows are listed with `wmctrl` (or `xdotool` and `xprop` for details) and
+//! captured with ImageMagick's `import` on Linux, listed through
+//! `CGWindowListCopyWindowInfo` and captured with `screencapture` on macOS,
+//! and listed with `EnumWindows` and captured with `PrintWindow` on Windows.
+
+use crate::command::run_command;
+use crate::computer::WindowInfo;
+use crate::error::CuaError;
+use base64::{engine::general_purpose, Engine};
+use serde::Deserialize;
+use std::future::Future;
+use std::path::Path;
+use std::time::Duration;
+
+/// How long `ThreadComputer::get_window_list` reuses a window list
+pub const WINDOW_LIST_CACHE_TTL: Duration = Duration::from_millis(500);
+
+/// A top-level window as (platform window id, title)
+type WindowEntry = (String, String);
+
+/// Prints `<window number>\t<owner> <title>` for every on-screen window
+const MACOS_LIST_SCRIPT: &str = r#"
+ObjC.import("CoreGraphics");
+const windows = ObjC.deepUnwrap($.CGWindowListCopyWindowInfo($.kCGWindowListOptionOnScreenOnly, $.kCGNullWindowID));
+windows.map(w => w.kCGWindowNumber + "\t" + (w.kCGWindowOwnerName || "") + " " + (w.kCGWindowName || "")).join("\n");
+"#;
+
+/// Prints a JSON array of every on-screen window, frontmost first
+const MACOS_DETAILS_SCRIPT: &str = r#"
+ObjC.import("CoreGraphics");
+const windows = ObjC.deepUnwrap($.CGWindowListCopyWindowInfo($.kCGWindowListOptionOnScreenOnly, $.kCGNullWindowID));
+JSON.stringify(windows.filter(w => w.kCGWindowLayer === 0));
+"#;
+
+/// Prints `<handle>\t<class>\t<pid>\t<left>\t<top>\t<right>\t<bottom>\t<title>` for every
+/// visible top-level window, topmost first as `EnumWindows` returns them
+const WINDOWS_DETAILS_SCRIPT: &str = r#"
+Add-Type @"
+using System;
+using System.Collections.Generic;
+using System.Runtime.InteropServices;
+using System.Text;
+public static class WindowList {
+    [StructLayout(LayoutKind.Sequential)] public struct Rect { public int Left, Top, Right, Bottom; }
+    public delegate bool EnumProc(IntPtr hWnd, IntPtr lParam);
+    [DllImport("user32.dll")] static extern bool EnumWindows(EnumProc callback, IntPtr lParam);
+    [DllImport("user32.dll")] static extern bool IsWindowVisible(IntPtr hWnd);
+    [DllImport("user32.dll", CharSet = CharSet.Unicode)] static extern int GetWindowText(IntPtr hWnd, StringBuilder text, int max);
+    [DllImport("user32.dll", CharSet = CharSet.Unicode)] static extern int GetClassName(IntPtr hWnd, StringBuilder name, int max);
+    [DllImport("user32.dll")] static extern uint GetWindowThreadProcessId(IntPtr hWnd, out uint pid);
+    [DllImport("user32.dll")] static extern bool GetWindowRect(IntPtr hWnd, out Rect rect);
+    public static List<string> List() {
+        var lines = new List<string>();
+        EnumWindows((hWnd, _) => {
+            var title = new StringBuilder(512);
+            if (!IsWindowVisible(hWnd) || GetWindowText(hWnd, title, title.Capacity) == 0) return true;
+            var cls = new StringBuilder(256);
+            GetClassName(hWnd, cls, cls.Capacity);
+            uint pid;
+            GetWindowThreadProcessId(hWnd, out pid);
+            Rect r;
+            GetWindowRect(hWnd, out r);
+            lines.Add(string.Join("\t", hWnd, cls, pid, r.Left, r.Top, r.Right, r.Bottom, title));
+            return true;
+        }, IntPtr.Zero);
+        return lines;
+    }
+}
+"@
+[WindowList]::List()
+"#;
+
+/// Prints `<handle>\t<title>` for every process with a main window
+const WINDOWS_LIST_SCRIPT: &str = r#"
+Get-Process | Where-Object { $_.MainWindowHandle -ne 0 } | ForEach-Object { "$($_.MainWindowHandle)`t$($_.MainWindowTitle)" }
+"#;
+
+/// Saves the window with handle `$args[0]` to the PNG file `$args[1]` using `PrintWindow`
+const WINDOWS_CAPTURE_SCRIPT: &str = r#"
+Add-Type -AssemblyName System.Drawing
+Add-Type @"
+using System;
+using System.Runtime.InteropServices;
+public static class WindowCapture {
+    [StructLayout(LayoutKind.Sequential)] public struct Rect { public int Left, Top, Right, Bottom; }
+    [DllImport("user32.dll")] public static extern bool GetWindowRect(IntPtr hWnd, out Rect rect);
+    [DllImport("user32.dll")] public static extern bool PrintWindow(IntPtr hWnd, IntPtr hdc, uint flags);
+}
+"@
+$handle = [IntPtr][long]$args[0]
+$rect = New-Object WindowCapture+Rect
+[void][WindowCapture]::GetWindowRect($handle, [ref]$rect)
+$bitmap = New-Object System.Drawing.Bitmap ($rect.Right - $rect.Left), ($rect.Bottom - $rect.Top)
+$graphics = [System.Drawing.Graphics]::FromImage($bitmap)
+$hdc = $graphics.GetHdc()
+[void][WindowCapture]::PrintWindow($handle, $hdc, 2)
+$graphics.ReleaseHdc($hdc)
+$bitmap.Save($args[1], [System.Drawing.Imaging.ImageFormat]::Png)
+"#;
+
+/// Capture the first window whose title contains `title_pattern` (case-insensitive) as a base64 PNG
+pub async fn window_screenshot(title_pattern: &str) -> Result<String, CuaError> {
+    if cfg!(target_os = "macos") {
+        let list = run_command("osascript", &["-l", "JavaScript", "-e", MACOS_LIST_SCRIPT], None).await?;
+        capture_matching(title_pattern, parse_window_list(&list), |id| {
+            capture_to_png(move |path| async move {
+                run_command("screencapture", &["-x", "-o", "-l", &id, &path], None).await
+            })
+        })
+        .await
+    } else if cfg!(target_os = "windows") {
+        let list = run_command("powershell", &["-NoProfile", "-Command", WINDOWS_LIST_SCRIPT], None).await?;
+        capture_matching(title_pattern, parse_window_list(&list), |id| {
+            capture_to_png(move |path| async move {
+                run_command("powershell", &["-NoProfile", "-Command", WINDOWS_CAPTURE_SCRIPT, &id, &path], None).await
+            })
+        })
+        .await
+    } else if cfg!(target_os = "linux") {
+        let list = run_command("wmctrl", &["-l"], None).await?;
+        capture_matching(title_pattern, parse_wmctrl(&list), |id| {
+            capture_to_png(move |path| async move {
+                run_command("import", &["-window", &id, &path], None).await
+            })
+        })
+        .await
+    } else {
+        Err(CuaError::ScreenshotError("Window capture not supported on this platform".to_string()))
+    }
+}
+
+/// List the visible top-level windows, topmost first
+pub async fn list_windows() -> Result<Vec<WindowInfo>, CuaError> {
+    if cfg!(target_os = "macos") {
+        let json = run_command("osascript", &["-l", "JavaScript", "-e", MACOS_DETAILS_SCRIPT], None).await?;
+        parse_cg_window_list(&json)
+    } else if cfg!(target_os = "windows") {
+        let list = run_command("powershell", &["-NoProfile", "-Command", WINDOWS_DETAILS_SCRIPT], None).await?;
+        Ok(parse_enum_windows(&list))
+    } else if cfg!(target_os = "linux") {
+        list_x11_windows().await
+    } else {
+        Err(CuaError::ActionError("Window listing not supported on this platform".to_string()))
+    }
+}
+
+/// List X11 windows: names and geometry from `xdotool`, class and PID from `xprop`
+async fn list_x11_windows() -> Result<Vec<WindowInfo>, CuaError> {
+    let output = run_command(
+        "xdotool",
+        &["search", "--onlyvisible", "--name", "", "getwindowname", "%@", "getwindowgeometry", "--shell", "%@"],
+        None,
+    )
+    .await?;
+    let mut windows = parse_xdotool_windows(&output);
+    
+    for window in &mut windows {
+        // Windows without these properties keep the defaults
+        if let Ok(properties) = run_command("xprop", &["-id", &window.id, "WM_CLASS", "_NET_WM_PID"], None).await {
+            let (class, pid) = parse_xprop_class_and_pid(&properties);
+            window.class = class.unwrap_or_default();
+            window.pid = pid.unwrap_or_default();
+        }
+    }
+    
+    let stacking = run_command("xprop", &["-root", "_NET_CLIENT_LIST_STACKING"], None).await.unwrap_or_default();
+    sort_by_stacking(&mut windows, &parse_stacking_order(&stacking));
+    Ok(windows)
+}
+
+/// Parse the output of `xdotool search ... getwindowname %@ getwindowgeometry --shell %@`:
+/// every window name, followed by a `WINDOW=`/`X=`/`Y=`/`WIDTH=`/`HEIGHT=`/`SCREEN=` block per window
+fn parse_xdotool_windows(output: &str) -> Vec<WindowInfo> {
+    let lines: Vec<&str> = output.lines().collect();
+    let Some(first_block) = lines.iter().position(|line| line.starts_with("WINDOW=")) else {
+        return Vec::new();
+    };
+    let (names, geometry) = lines.split_at(first_block);
+    
+    let mut windows = Vec::new();
+    let mut current: Option<WindowInfo> = None;
+    for line in geometry {
+        let Some((key, value)) = line.split_once('=') else { continue };
+        if key == "WINDOW" {
+            windows.extend(current.take());
+            current = Some(WindowInfo {
+                // xprop and _NET_CLIENT_LIST_STACKING use hex ids
+                id: value.parse::<u64>().map(|id| format!("0x{:x}", id)).unwrap_or_else(|_| value.to_string()),
+                title: names.get(windows.len()).unwrap_or(&"").to_string(),
+                class: String::new(),
+                pid: 0,
+                bounds: (0, 0, 0, 0),
+                z_order: 0,
+            });
+        } else if let Some(window) = current.as_mut() {
+            match key {
+                "X" => window.bounds.0 = value.parse().unwrap_or(0),
+                "Y" => window.bounds.1 = value.parse().unwrap_or(0),
+                "WIDTH" => window.bounds.2 = value.parse().unwrap_or(0),
+                "HEIGHT" => window.bounds.3 = value.parse().unwrap_or(0),
+                _ => {}
+            }
+        }
+    }
+    windows.extend(current);
+    windows
+}
+
+/// Parse `xprop -id <id> WM_CLASS _NET_WM_PID` into (class, pid)
+///
+/// `WM_CLASS` holds the instance and class names; the class is the second.
+fn parse_xprop_class_and_pid(output: &str) -> (Option<String>, Option<u32>) {
+    let mut class = None;
+    let mut pid = None;
+    for line in output.lines() {
+        let Some((name, value)) = line.split_once(" = ") else { continue };
+        if name.starts_with("WM_CLASS") {
+            class = value.split(", ").last().map(|class| class.trim_matches('"').to_string());
+        } else if name.starts_with("_NET_WM_PID") {
+            pid = value.trim().parse().ok();
+        }
+    }
+    (class, pid)
+}
+
+/// Parse `xprop -root _NET_CLIENT_LIST_STACKING` into window ids, bottom first
+fn parse_stacking_order(output: &str) -> Vec<String> {
+    output
+        .split_once('#')
+        .map(|(_, ids)| ids.split(',').map(|id| id.trim().to_string()).filter(|id| !id.is_empty()).collect())
+        .unwrap_or_default()
+}
+
+/// Sort windows topmost first and number their z-order; windows missing from `stacking` go last
+fn sort_by_stacking(windows: &mut [WindowInfo], stacking: &[String]) {
+    let depth = |window: &WindowInfo| {
+        let id = u64::from_str_radix(window.id.trim_start_matches("0x"), 16).ok();
+        stacking
+            .iter()
+            .rposition(|entry| u64::from_str_radix(entry.trim_start_matches("0x"), 16).ok() == id)
+            .map_or(usize::MAX, |position| stacking.len() - 1 - position)
+    };
+    windows.sort_by_key(depth);
+    for (z_order, window) in windows.iter_mut().enumerate() {
+        window.z_order = z_order;
+    }
+}
+
+/// A window as listed by `CGWindowListCopyWindowInfo`
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct CgWindow {
+    #[serde(rename = "kCGWindowNumber")]
+    number: u64,
+    #[serde(rename = "kCGWindowName", default)]
+    name: String,
+    #[serde(rename = "kCGWindowOwnerName", default)]
+    owner_name: String,
+    #[serde(rename = "kCGWindowOwnerPID", default)]
+    owner_pid: u32,
+    #[serde(rename = "kCGWindowBounds")]
+    bounds: CgBounds,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct CgBounds {
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+}
+
+/// Parse the JSON window list printed by `MACOS_DETAILS_SCRIPT`, which is frontmost first
+fn parse_cg_window_list(json: &str) -> Result<Vec<WindowInfo>, CuaError> {
+    let windows: Vec<CgWindow> = serde_json::from_str(json.trim())
+        .map_err(|e| CuaError::ActionError(format!("Failed to parse window list: {}", e)))?;
+    Ok(windows
+        .into_iter()
+        .enumerate()
+        .map(|(z_order, window)| WindowInfo {
+            id: window.number.to_string(),
+            title: window.name,
+            // macOS has no window classes; the owning application stands in
+            class: window.owner_name,
+            pid: window.owner_pid,
+            bounds: (window.bounds.x as i32, window.bounds.y as i32, window.bounds.width as u32, window.bounds.height as u32),
+            z_order,
+        })
+        .collect())
+}
+
+/// Parse the tab-separated lines printed by `WINDOWS_DETAILS_SCRIPT`
+fn parse_enum_windows(output: &str) -> Vec<WindowInfo> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.trim_end_matches('\r').splitn(8, '\t').collect();
+            let [id, class, pid, left, top, right, bottom, title] = fields[..] else { return None };
+            let (left, top, right, bottom): (i32, i32, i32, i32) =
+                (left.parse().ok()?, top.parse().ok()?, right.parse().ok()?, bottom.parse().ok()?);
+            Some((id, class, pid, (left, top, (right - left).max(0) as u32, (bottom - top).max(0) as u32), title))
+        })
+        .enumerate()
+        .map(|(z_order, (id, class, pid, bounds, title))| WindowInfo {
+            id: id.to_string(),
+            title: title.to_string(),
+            class: class.to_string(),
+            pid: pid.parse().unwrap_or(0),
+            bounds,
+            z_order,
+        })
+        .collect()
+}
+
+/// Capture the first window in `windows` whose title matches, passing its id to `capture`
+async fn capture_matching<F, Fut>(title_pattern: &str, windows: Vec<WindowEntry>, capture: F) -> Result<String, CuaError>
+where
+    F: FnOnce(String) -> Fut,
+    Fut: Future<Output = Result<String, CuaError>>,
+{
+    let pattern = title_pattern.to_lowercase();
+    let (id, title) = windows
+        .into_iter()
+        .find(|(_, title)| title.to_lowercase().contains(&pattern))
+        .ok_or_else(|| CuaError::ScreenshotError(format!("No window title matches '{}'", title_pattern)))?;
+    tracing::debug!(%id, %title, "Capturing window");
+    capture(id).await
+}
+
+/// Run a capture command writing to a temporary PNG file and return the file as base64
+async fn capture_to_png<F, Fut>(capture: F) -> Result<String, CuaError>
+where
+    F: FnOnce(String) -> Fut,
+    Fut: Future<Output = Result<String, CuaError>>,
+{
+    let path = std::env::temp_dir().join(format!("cua_window_{}.png", uuid::Uuid::new_v4().simple()));
+    let result = capture(path.to_string_lossy().to_string()).await;
+    let png = read_and_remove(&path).await;
+    result?;
+    Ok(general_purpose::STANDARD.encode(png?))
+}
+
+async fn read_and_remove(path: &Path) -> Result<Vec<u8>, CuaError> {
+    let png = tokio::fs::read(path).await;
+    let _ = tokio::fs::remove_file(path).await;
+    Ok(png?)
+}
+
+/// Parse `<id>\t<title>` lines
+fn parse_window_list(output: &str) -> Vec<WindowEntry> {
+    output
+        .lines()
+        .filter_map(|line| line.trim_end_matches('\r').split_once('\t'))
+        .map(|(id, title)| (id.trim().to_string(), title.trim().to_string()))
+        .collect()
+}
+
+/// Parse `wmctrl -l` lines: `<id> <desktop> <host> <title>`
+fn parse_wmctrl(output: &str) -> Vec<WindowEntry> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let id = fields.first()?;
+            Some((id.to_string(), fields.get(3..).unwrap_or_default().join(" ")))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    
+    #[tokio::test]
+    async fn test_capture_matching_passes_window_id() {
+        let wmctrl = "0x03a00007  0 desk Terminal\n0x04400003  0 desk Invoice.pdf - Document Viewer\n";
+        let captured = Arc::new(Mutex::new(None));
+        
+        let recorder = captured.clone();
+        let image = capture_matching("document viewer", parse_wmctrl(wmctrl), |id| async move {
+            *recorder.lock().unwrap() = Some(id);
+            Ok("cG5n".to_string())
+        })
+        .await
+        .unwrap();
+        
+        assert_eq!(image, "cG5n");
+        assert_eq!(captured.lock().unwrap().as_deref(), Some("0x04400003"));
+        
+        let macos = parse_window_list("812\tSafari Apple\n977\tFinder \n");
+        assert_eq!(macos[0], ("812".to_string(), "Safari Apple".to_string()));
+        let missing = capture_matching("Mail", macos, |_| async { Ok(String::new()) }).await;
+        assert!(matches!(missing, Err(CuaError::ScreenshotError(_))));
+    }
+    
+    #[test]
+    fn test_parse_xdotool_window_list() {
+        let xdotool = "Terminal\nInbox - Mail\nDesktop\n\
+            WINDOW=60817415\nX=10\nY=40\nWIDTH=800\nHEIGHT=600\nSCREEN=0\n\
+            WINDOW=71303171\nX=200\nY=100\nWIDTH=1200\nHEIGHT=800\nSCREEN=0\n\
+            WINDOW=16777220\nX=0\nY=0\nWIDTH=1920\nHEIGHT=1080\nSCREEN=0\n";
+        let mut windows = parse_xdotool_windows(xdotool);
+        
+        assert_eq!(windows.len(), 3);
+        assert_eq!(windows[1].id, "0x4400003");
+        assert_eq!(windows[1].title, "Inbox - Mail");
+        assert_eq!(windows[1].bounds, (200, 100, 1200, 800));
+        
+        let stacking = parse_stacking_order("_NET_CLIENT_LIST_STACKING(WINDOW): window id # 0x1000004, 0x4400003, 0x3a00007");
+        sort_by_stacking(&mut windows, &stacking);
+        let titles: Vec<&str> = windows.iter().map(|window| window.title.as_str()).collect();
+        assert_eq!(titles, vec!["Terminal", "Inbox - Mail", "Desktop"]);
+        assert_eq!(windows[2].z_order, 2);
+        
+        let xprop = "WM_CLASS(STRING) = \"navigator\", \"Firefox\"\n_NET_WM_PID(CARDINAL) = 4242\n";
+        assert_eq!(parse_xprop_class_and_pid(xprop), (Some("Firefox".to_string()), Some(4242)));
+    }
+}
@@ -0,0 +1,889 @@
+//! Function tools exposed to the model alongside the `computer-preview` tool
+//!
+//! The computer-preview tool only covers basic mouse and keyboard actions.
+//! Other `Computer` capabilities are offered to the model as function tools,
+//! and the agent dispatches the model's `function_call` items through
+//! [`call_computer_tool`]. Tools that don't need the computer implement
+//! [`ToolFn`] and are registered on the agent individually.
+
+pub mod registry;
+pub mod search;
+
+use crate::computer::{
+    limit_page_source, strip_script_tags, Computer, DEFAULT_PAGE_SOURCE_LIMIT, DRAG_AND_DROP_STEPS, MAX_HIGHLIGHT_MS,
+    MAX_KEY_HOLD_MS,
+    WAIT_UNTIL_POLL_INTERVAL,
+};
+use crate::error::CuaError;
+use crate::ocr;
+use async_trait::async_trait;
+use serde_json::{json, Value};
+
+/// A function tool the agent handles itself rather than through the `Computer`
+#[async_trait]
+pub trait ToolFn: Send + Sync {
+    /// Tool definition sent to the model, usually built with [`function_tool`]
+    fn definition(&self) -> Value;
+    
+    /// Run the tool with the arguments of the model's `function_call`
+    async fn call(&self, args: &Value) -> Result<Value, CuaError>;
+}
+
+/// Build a function tool definition in the Responses API format
+pub fn function_tool(name: &str, description: &str, parameters: Value) -> Value {
+    json!({
+        "type": "function",
+        "name": name,
+        "description": description,
+        "parameters": parameters,
+    })
+}
+
+/// Name of the tool the agent answers by diffing the screen against its last screenshot
+pub const SCREENSHOT_DIFF_TOOL: &str = "screenshot_diff";
+
+/// Definition of the screenshot diff tool, which the agent handles itself
+pub fn screenshot_diff_tool() -> Value {
+    function_tool(
+        SCREENSHOT_DIFF_TOOL,
+        "Compare the screen now with the last screenshot you received. Returns the bounding box \
+         of the changed area (null if nothing changed) and shows you a diff image where changed \
+         pixels are red and unchanged ones gray.",
+        json!({ "type": "object", "properties": {} }),
+    )
+}
+
+/// Name of the script tool, offered only when the agent enables script execution
+pub const SCRIPT_TOOL: &str = "execute_script";
+
+/// Definition of the script tool, which the agent handles itself
+pub fn script_tool() -> Value {
+    function_tool(
+        SCRIPT_TOOL,
+        "Run a short script and return its output. Use AppleScript to drive macOS apps, \
+         PowerShell on Windows and bash on Linux. The user must approve every script.",
+        json!({
+            "type": "object",
+            "properties": {
+                "script": { "type": "string" },
+                "lang": { "type": "string", "enum": ["applescript", "powershell", "bash"] },
+            },
+            "required": ["script", "lang"],
+        }),
+    )
+}
+
+/// Computer tools whose `x`, `y`, `width` and `height` arguments are screenshot coordinates
+pub const SCREEN_AREA_ARGUMENT_TOOLS: &[&str] = &[
+    "screen_color_at",
+    "read_screen_text",
+    "assert_text_visible_within",
+    "focus_input_field",
+    "capture_element",
+    "highlight_region",
+    "type_at",
+    "middle_click",
+    "drag_by_delta",
+];
+
+/// Computer tools whose output is a base64 image, which the agent sends as an image input
+pub const IMAGE_OUTPUT_TOOLS: &[&str] = &["window_screenshot", "capture_element"];
+
+/// Computer tools whose output is an `{ "x", "y" }` screen position
+pub const SCREEN_POSITION_OUTPUT_TOOLS: &[&str] = &["scroll_to_element", "get_text_cursor_position"];
+
+/// Multiply the coordinate and size fields of `value` by `factor`, rounding
+///
+/// Used by the agent to map between the screenshot space the model sees and screen space.
+pub fn scale_coordinates(value: &mut Value, factor: f64) {
+    for field in ["x", "y", "width", "height", "start_x", "start_y", "delta_x", "delta_y"] {
+        if let Some(n) = value.get(field).and_then(|n| n.as_f64()) {
+            value[field] = json!((n * factor).round() as i64);
+        }
+    }
+}
+
+/// Computer tools offered only when [`Computer::supports_network_emulation`] is true
+pub const NETWORK_TOOLS: &[&str] = &["simulate_network_condition", "clear_network_conditions"];
+
+/// Definitions of the [`computer_tools`] that `computer` supports
+pub fn computer_tools_for<C: Computer + ?Sized>(computer: &C) -> Vec<Value> {
+    let network = computer.supports_network_emulation();
+    computer_tools()
+        .into_iter()
+        .filter(|tool| network || !NETWORK_TOOLS.iter().any(|name| tool["name"] == *name))
+        .collect()
+}
+
+/// Definitions of the function tools backed by `Computer` methods
+pub fn computer_tools() -> Vec<Value> {
+    vec![
+        function_tool(
+            "clipboard_history",
+            "List recently copied clipboard items, most recent first.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "max_entries": {
+                        "type": "integer",
+                        "description": "Maximum number of entries to return (default 10)",
+                    },
+                },
+            }),
+        ),
+        function_tool(
+            "get_clipboard",
+            "Read the text on the clipboard.",
+            json!({ "type": "object", "properties": {} }),
+        ),
+        function_tool(
+            "get_clipboard_format",
+            "Check what the clipboard holds (text, image, html, files) and which method reads it best.",
+            json!({ "type": "object", "properties": {} }),
+        ),
+        function_tool(
+            "set_clipboard_html",
+            "Put HTML on the clipboard, so that pasting into a rich-text editor keeps the formatting.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "html": { "type": "string", "description": "HTML fragment, e.g. \"<b>Total:</b> 42\"" },
+                },
+                "required": ["html"],
+            }),
+        ),
+        function_tool(
+            "get_screen_dpi",
+            "Get the screen's logical DPI (96 at 100% scaling), to convert sizes given in CSS pixels or points to screen pixels.",
+            json!({ "type": "object", "properties": {} }),
+        ),
+        function_tool(
+            "accessibility_tree",
+            "Get the accessibility tree of the desktop as JSON: role, name, value, bounds (x, y, width, height) and children of every element.",
+            json!({ "type": "object", "properties": {} }),
+        ),
+        function_tool(
+            "screen_color_at",
+            "Get the RGB color of the screen pixel at (x, y), e.g. to check whether a button is highlighted.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "x": { "type": "integer" },
+                    "y": { "type": "integer" },
+                },
+                "required": ["x", "y"],
+            }),
+        ),
+        function_tool(
+            "scroll_to_element",
+            "Scroll down until the given text is visible on screen and return its center coordinates.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "element_description": {
+                        "type": "string",
+                        "description": "Text shown on the element, e.g. a button label",
+                    },
+                    "max_scrolls": {
+                        "type": "integer",
+                        "description": "Maximum number of scrolls (default 5)",
+                    },
+                },
+                "required": ["element_description"],
+            }),
+        ),
+        function_tool(
+            "get_page_source",
+            "Get the HTML source of the current browser page, e.g. to find form field IDs. \
+             Long pages are cut.",
+            json!({ "type": "object", "properties": {} }),
+        ),
+        function_tool(
+            "read_screen_text",
+            "Read the text in a rectangular region of the screen using OCR.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "x": { "type": "integer", "description": "Left edge of the region" },
+                    "y": { "type": "integer", "description": "Top edge of the region" },
+                    "width": { "type": "integer" },
+                    "height": { "type": "integer" },
+                },
+                "required": ["x", "y", "width", "height"],
+            }),
+        ),
+        function_tool(
+            "assert_text_visible",
+            "Check whether the given text is currently visible on screen. Returns true or false.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "text": { "type": "string" },
+                },
+                "required": ["text"],
+            }),
+        ),
+        function_tool(
+            "assert_text_visible_within",
+            "Check whether the given text is currently visible inside a rectangular screen region. Returns true or false.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "text": { "type": "string" },
+                    "x": { "type": "integer", "description": "Left edge of the region" },
+                    "y": { "type": "integer", "description": "Top edge of the region" },
+                    "width": { "type": "integer" },
+                    "height": { "type": "integer" },
+                },
+                "required": ["text", "x", "y", "width", "height"],
+            }),
+        ),
+        function_tool(
+            "wait_until_text_visible",
+            "Wait until the given text appears on screen, e.g. after a page load or while a spinner runs.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "text": { "type": "string", "description": "Text to wait for (case-insensitive)" },
+                    "timeout_ms": {
+                        "type": "integer",
+                        "description": "How long to wait for the text (default 5000)",
+                    },
+                },
+                "required": ["text"],
+            }),
+        ),
+        function_tool(
+            "detect_dialog",
+            "Wait for a modal dialog (alert, permission prompt) and return its title, message, buttons and bounds, or null if none appears.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "timeout_ms": {
+                        "type": "integer",
+                        "description": "How long to wait for a dialog (default 1000)",
+                    },
+                },
+            }),
+        ),
+        function_tool(
+            "dismiss_dialog",
+            "Click the button with the given label on the current modal dialog.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "button_text": { "type": "string", "description": "Button label, e.g. \"OK\"" },
+                },
+                "required": ["button_text"],
+            }),
+        ),
+        function_tool(
+            "focus_input_field",
+            "Click the text field at (x, y) and make sure it has keyboard focus, retrying with a double click if needed.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "x": { "type": "integer" },
+                    "y": { "type": "integer" },
+                },
+                "required": ["x", "y"],
+            }),
+        ),
+        function_tool(
+            "get_focused_application",
+            "Get the name, process id and executable of the application that has keyboard focus.",
+            json!({ "type": "object", "properties": {} }),
+        ),
+        function_tool(
+            "get_text_cursor_position",
+            "Get the screen coordinates of the text insertion caret in the focused text field.",
+            json!({ "type": "object", "properties": {} }),
+        ),
+        function_tool(
+            "notify",
+            "Show the user a desktop notification, e.g. when you need their input to proceed.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "message": { "type": "string" },
+                    "duration_ms": {
+                        "type": "integer",
+                        "description": "How long to show the notification (default 5000)",
+                    },
+                },
+                "required": ["message"],
+            }),
+        ),
+        function_tool(
+            "simulate_network_condition",
+            "Emulate a slow or unreliable network in the browser.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "latency_ms": { "type": "integer", "description": "Added round-trip latency in milliseconds" },
+                    "packet_loss": { "type": "number", "description": "Fraction of requests dropped, 0.0 to 1.0" },
+                },
+                "required": ["latency_ms"],
+            }),
+        ),
+        function_tool(
+            "clear_network_conditions",
+            "Restore normal network conditions in the browser.",
+            json!({ "type": "object", "properties": {} }),
+        ),
+        function_tool(
+            "window_screenshot",
+            "Capture only the window whose title contains the given text; the image follows the call output.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "title_pattern": { "type": "string", "description": "Part of the window title" },
+                },
+                "required": ["title_pattern"],
+            }),
+        ),
+        function_tool(
+            "list_windows",
+            "List the visible windows, topmost first, with their title, class, process ID, \
+             bounds (x, y, width, height) and z-order.",
+            json!({ "type": "object", "properties": {} }),
+        ),
+        function_tool(
+            "highlight_region",
+            "Briefly outline a screen region so a person watching can see what you are looking at. \
+             Colors are red, green, blue, yellow, orange or #rrggbb.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "x": { "type": "integer" },
+                    "y": { "type": "integer" },
+                    "width": { "type": "integer" },
+                    "height": { "type": "integer" },
+                    "color": { "type": "string", "description": "Outline color (default red)" },
+                    "duration_ms": { "type": "integer", "description": "How long to show the outline (default 1000, at most 5000)" },
+                },
+                "required": ["x", "y", "width", "height"],
+            }),
+        ),
+        function_tool(
+            "read_env_var",
+            "Read an environment variable such as HOME or LANG. Only allowlisted variables can be read.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "name": { "type": "string" },
+                },
+                "required": ["name"],
+            }),
+        ),
+        function_tool(
+            "capture_element",
+            "Capture a rectangular screen region; the image follows the call output. Captures are cached by label \
+             and reused until input changes the screen, so re-checking a named element is cheap.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "x": { "type": "integer" },
+                    "y": { "type": "integer" },
+                    "width": { "type": "integer" },
+                    "height": { "type": "integer" },
+                    "label": { "type": "string", "description": "Name of the element, e.g. \"submit button\"" },
+                },
+                "required": ["x", "y", "width", "height", "label"],
+            }),
+        ),
+        function_tool(
+            "paste_from_clipboard",
+            "Paste the clipboard content with the OS-native shortcut (Ctrl+V, or Cmd+V on macOS).",
+            json!({ "type": "object", "properties": {} }),
+        ),
+        function_tool(
+            "send_hotkey",
+            "Press a keyboard shortcut written as keys joined by '+', e.g. \"Ctrl+Shift+T\".",
+            json!({
+                "type": "object",
+                "properties": {
+                    "hotkey": { "type": "string" },
+                },
+                "required": ["hotkey"],
+            }),
+        ),
+        function_tool(
+            "copy_selection",
+            "Copy the current selection with the OS-native shortcut (Ctrl+C, or Cmd+C on macOS).",
+            json!({ "type": "object", "properties": {} }),
+        ),
+        function_tool(
+            "select_all_text",
+            "Select all text in the focused element (Ctrl+A, or Cmd+A on macOS).",
+            json!({ "type": "object", "properties": {} }),
+        ),
+        function_tool(
+            "copy_selected_text",
+            "Copy the current selection and return the copied text.",
+            json!({ "type": "object", "properties": {} }),
+        ),
+        function_tool(
+            "get_focused_text",
+            "Return all text in the focused element, e.g. a text field. Overwrites the clipboard.",
+            json!({ "type": "object", "properties": {} }),
+        ),
+        function_tool(
+            "type_at",
+            "Click the field at (x, y) and type text into it, in one step.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "x": { "type": "integer" },
+                    "y": { "type": "integer" },
+                    "text": { "type": "string" },
+                },
+                "required": ["x", "y", "text"],
+            }),
+        ),
+        function_tool(
+            "middle_click",
+            "Middle-click at (x, y), e.g. to open a link in a new tab or close a browser tab.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "x": { "type": "integer" },
+                    "y": { "type": "integer" },
+                },
+                "required": ["x", "y"],
+            }),
+        ),
+        function_tool(
+            "drag_by_delta",
+            "Drag from (start_x, start_y) by a relative offset, e.g. delta_x 200 to drag 200 pixels right.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "start_x": { "type": "integer" },
+                    "start_y": { "type": "integer" },
+                    "delta_x": { "type": "integer" },
+                    "delta_y": { "type": "integer" },
+                    "steps": { "type": "integer", "description": "Number of moves along the line (default 10)" },
+                },
+                "required": ["start_x", "start_y", "delta_x", "delta_y"],
+            }),
+        ),
+        function_tool(
+            "press_and_hold",
+            "Hold a key down for a while, e.g. to trigger key repeat or a long-press menu.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "key": { "type": "string" },
+                    "duration_ms": { "type": "integer", "description": "How long to hold the key (default 1000, at most 10000)" },
+                },
+                "required": ["key"],
+            }),
+        ),
+        function_tool(
+            "mouse_down",
+            "Press and keep holding a mouse button at the cursor, e.g. to start a modifier or right-button drag. \
+             Release it with mouse_up.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "button": { "type": "string", "enum": ["left", "right", "middle"] },
+                },
+                "required": ["button"],
+            }),
+        ),
+        function_tool(
+            "mouse_up",
+            "Release a mouse button held with mouse_down.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "button": { "type": "string", "enum": ["left", "right", "middle"] },
+                },
+                "required": ["button"],
+            }),
+        ),
+    ]
+}
+
+/// Get the page source for the `get_page_source` tool, cut to `max_bytes`
+///
+/// [`call_computer_tool`] uses the default limit and removes scripts; the
+/// agent passes its own options (see `Agent::with_page_source_options`).
+pub async fn page_source<C: Computer + ?Sized>(
+    computer: &C,
+    max_bytes: usize,
+    strip_scripts: bool,
+) -> Result<Value, CuaError> {
+    let html = computer.get_page_source().await?;
+    let html = if strip_scripts { strip_script_tags(&html) } else { html };
+    Ok(json!(limit_page_source(html, max_bytes)))
+}
+
+/// Call the `Computer` method behind a function tool
+///
+/// Returns `None` if `name` is not one of the [`computer_tools`].
+pub async fn call_computer_tool<C: Computer + ?Sized>(
+    computer: &C,
+    name: &str,
+    args: &Value,
+) -> Option<Result<Value, CuaError>> {
+    let result = match name {
+        "clipboard_history" => {
+            let max_entries = args.get("max_entries").and_then(|m| m.as_u64()).unwrap_or(10) as usize;
+            computer.clipboard_history(max_entries).await.map(|entries| json!(entries))
+        }
+        "get_clipboard" => computer.get_clipboard().await.map(|text| json!(text)),
+        "get_clipboard_format" => computer.get_clipboard_format().await.map(|format| {
+            let mut output = json!(format);
+            output["recommended"] = json!(format.recommended_reader());
+            output
+        }),
+        "set_clipboard_html" => {
+            let html = args.get("html").and_then(|h| h.as_str()).unwrap_or("");
+            computer.set_clipboard_html(html).await.map(|_| json!("success"))
+        }
+        "get_screen_dpi" => computer.get_screen_dpi().await.map(|dpi| json!(dpi)),
+        "accessibility_tree" => computer.accessibility_tree().await.map(|tree| json!(tree)),
+        "screen_color_at" => {
+            let x = args.get("x").and_then(|x| x.as_u64()).unwrap_or(0) as u32;
+            let y = args.get("y").and_then(|y| y.as_u64()).unwrap_or(0) as u32;
+            computer.screen_color_at(x, y).await.map(|(r, g, b)| json!({ "r": r, "g": g, "b": b }))
+        }
+        "scroll_to_element" => {
+            let description = args.get("element_description").and_then(|d| d.as_str()).unwrap_or("");
+            let max_scrolls = args.get("max_scrolls").and_then(|m| m.as_u64()).unwrap_or(5) as u32;
+            computer.scroll_to_element(description, max_scrolls).await.map(|(x, y)| json!({ "x": x, "y": y }))
+        }
+        "get_page_source" => page_source(computer, DEFAULT_PAGE_SOURCE_LIMIT, true).await,
+        "read_screen_text" => {
+            let field = |name: &str| args.get(name).and_then(|v| v.as_u64()).unwrap_or(0) as u32;
+            computer
+                .read_screen_text(field("x"), field("y"), field("width"), field("height"))
+                .await
+                .map(|text| json!(text))
+        }
+        "assert_text_visible" => {
+            let text = args.get("text").and_then(|t| t.as_str()).unwrap_or("");
+            computer.assert_text_visible(text).await.map(|visible| json!(visible))
+        }
+        "assert_text_visible_within" => {
+            let text = args.get("text").and_then(|t| t.as_str()).unwrap_or("");
+            let field = |name: &str| args.get(name).and_then(|v| v.as_u64()).unwrap_or(0) as u32;
+            computer
+                .assert_text_visible_within(text, field("x"), field("y"), field("width"), field("height"))
+                .await
+                .map(|visible| json!(visible))
+        }
+        "wait_until_text_visible" => {
+            let text = args.get("text").and_then(|t| t.as_str()).unwrap_or("").to_string();
+            let timeout_ms = args.get("timeout_ms").and_then(|t| t.as_u64()).unwrap_or(5000) as u32;
+            wait_until_text_visible(computer, &text, timeout_ms).await.map(|_| json!("success"))
+        }
+        "detect_dialog" => {
+            let timeout_ms = args.get("timeout_ms").and_then(|t| t.as_u64()).unwrap_or(1000) as u32;
+            computer.detect_dialog(timeout_ms).await.map(|dialog| json!(dialog))
+        }
+        "dismiss_dialog" => {
+            let button_text = args.get("button_text").and_then(|b| b.as_str()).unwrap_or("");
+            computer.dismiss_dialog(button_text).await.map(|_| json!("success"))
+        }
+        "focus_input_field" => {
+            let x = args.get("x").and_then(|x| x.as_i64()).unwrap_or(0) as i32;
+            let y = args.get("y").and_then(|y| y.as_i64()).unwrap_or(0) as i32;
+            computer.focus_input_field(x, y).await.map(|_| json!("success"))
+        }
+        "get_focused_application" => computer.get_focused_application().await.map(|app| json!(app)),
+        "get_text_cursor_position" => computer.get_text_cursor_position().await.map(|(x, y)| json!({ "x": x, "y": y })),
+        "notify" => {
+            let message = args.get("message").and_then(|m| m.as_str()).unwrap_or("");
+            let duration_ms = args.get("duration_ms").and_then(|d| d.as_u64()).unwrap_or(5000) as u32;
+            computer.notify(message, duration_ms).await.map(|_| json!("success"))
+        }
+        "simulate_network_condition" => {
+            let latency_ms = args.get("latency_ms").and_then(|l| l.as_u64()).unwrap_or(0) as u32;
+            let packet_loss = args.get("packet_loss").and_then(|p| p.as_f64()).unwrap_or(0.0) as f32;
+            computer.simulate_network_condition(latency_ms, packet_loss).await.map(|_| json!("success"))
+        }
+        "clear_network_conditions" => computer.clear_network_conditions().await.map(|_| json!("success")),
+        "window_screenshot" => {
+            let title_pattern = args.get("title_pattern").and_then(|t| t.as_str()).unwrap_or("");
+            computer.window_screenshot(title_pattern).await.map(|image| json!(image))
+        }
+        "list_windows" => computer.get_window_list().await.map(|windows| json!(windows)),
+        "highlight_region" => {
+            let field = |name: &str| args.get(name).and_then(|v| v.as_u64()).unwrap_or(0) as u32;
+            let color = args.get("color").and_then(|c| c.as_str()).unwrap_or("red");
+            let duration_ms = args.get("duration_ms").and_then(|d| d.as_u64()).unwrap_or(1000).min(MAX_HIGHLIGHT_MS as u64) as u32;
+            computer
+                .highlight_region(field("x"), field("y"), field("width"), field("height"), color, duration_ms)
+                .await
+                .map(|_| json!("success"))
+        }
+        "read_env_var" => {
+            let name = args.get("name").and_then(|n| n.as_str()).unwrap_or("");
+            computer.read_env_var(name).await.map(|value| json!(value))
+        }
+        "capture_element" => {
+            let x = args.get("x").and_then(|x| x.as_u64()).unwrap_or(0) as u32;
+            let y = args.get("y").and_then(|y| y.as_u64()).unwrap_or(0) as u32;
+            let width = args.get("width").and_then(|w| w.as_u64()).unwrap_or(0) as u32;
+            let height = args.get("height").and_then(|h| h.as_u64()).unwrap_or(0) as u32;
+            let label = args.get("label").and_then(|l| l.as_str()).unwrap_or("");
+            computer.capture_element_screenshot(x, y, width, height, label).await.map(|image| json!(image))
+        }
+        "paste_from_clipboard" => computer.paste_from_clipboard().await.map(|_| json!("success")),
+        "copy_selection" => computer.copy_selection().await.map(|_| json!("success")),
+        "select_all_text" => computer.select_all_text().await.map(|_| json!("success")),
+        "copy_selected_text" => computer.copy_selected_text().await.map(|text| json!(text)),
+        "get_focused_text" => computer.get_focused_text().await.map(|text| json!(text)),
+        "send_hotkey" => {
+            let hotkey = args.get("hotkey").and_then(|h| h.as_str()).unwrap_or("");
+            computer.send_hotkey(hotkey).await.map(|_| json!("success"))
+        }
+        "type_at" => {
+            let x = args.get("x").and_then(|x| x.as_i64()).unwrap_or(0) as i32;
+            let y = args.get("y").and_then(|y| y.as_i64()).unwrap_or(0) as i32;
+            let text = args.get("text").and_then(|t| t.as_str()).unwrap_or("");
+            computer.type_text_at(x, y, text).await.map(|_| json!("success"))
+        }
+        "middle_click" => {
+            let x = args.get("x").and_then(|x| x.as_i64()).unwrap_or(0) as i32;
+            let y = args.get("y").and_then(|y| y.as_i64()).unwrap_or(0) as i32;
+            computer.mouse_wheel_click(x, y).await.map(|_| json!("success"))
+        }
+        "drag_by_delta" => {
+            let field = |name: &str| args.get(name).and_then(|v| v.as_i64()).unwrap_or(0) as i32;
+            let steps = args.get("steps").and_then(|s| s.as_u64()).unwrap_or(DRAG_AND_DROP_STEPS as u64) as u32;
+            computer
+                .drag_by_delta(field("start_x"), field("start_y"), field("delta_x"), field("delta_y"), steps)
+                .await
+                .map(|_| json!("success"))
+        }
+        "press_and_hold" => {
+            let key = args.get("key").and_then(|k| k.as_str()).unwrap_or("");
+            let duration_ms = args.get("duration_ms").and_then(|d| d.as_u64()).unwrap_or(1000).min(MAX_KEY_HOLD_MS as u64) as u32;
+            computer.press_and_hold(key, duration_ms).await.map(|_| json!("success"))
+        }
+        "mouse_down" => {
+            let button = args.get("button").and_then(|b| b.as_str()).unwrap_or("left");
+            computer.mouse_button_down(button).await.map(|_| json!("success"))
+        }
+        "mouse_up" => {
+            let button = args.get("button").and_then(|b| b.as_str()).unwrap_or("left");
+            computer.mouse_button_up(button).await.map(|_| json!("success"))
+        }
+        _ => return None,
+    };
+    
+    Some(result)
+}
+
+/// Wait up to `timeout_ms` until `text` is visible on screen, checking like [`Computer::wait_until`]
+///
+/// Unlike a `wait_until` condition, this can await the computer's own `ocr_extract`.
+async fn wait_until_text_visible<C: Computer + ?Sized>(computer: &C, text: &str, timeout_ms: u32) -> Result<(), CuaError> {
+    let polls = timeout_ms as u128 / WAIT_UNTIL_POLL_INTERVAL.as_millis();
+    for poll in 0..=polls {
+        if poll > 0 {
+            tokio::time::sleep(WAIT_UNTIL_POLL_INTERVAL).await;
+        }
+        let words = computer.ocr_extract(&computer.screenshot().await?).await?;
+        if ocr::find_text(&words, text).is_some() {
+            return Ok(());
+        }
+    }
+    Err(CuaError::ActionError(format!("'{}' not visible within {}ms", text, timeout_ms)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::computer::{AccessibilityNode, ClipboardFormat};
+    use crate::mock::MockComputer;
+    use crate::ocr::OcrWord;
+    
+    #[tokio::test(start_paused = true)]
+    async fn test_wait_until_text_visible_tool() {
+        let computer = MockComputer::new("linux", 1024, 768);
+        computer.inject_sequence(vec!["bG9hZGluZw==".to_string(), "cmVhZHk=".to_string()]);
+        computer.set_ocr_result("cmVhZHk=", vec![OcrWord::new("Ready", (10, 10, 50, 16), 0)]);
+        
+        let output = call_computer_tool(&computer, "wait_until_text_visible", &json!({ "text": "ready", "timeout_ms": 1000 }))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(output, json!("success"));
+        
+        let result = call_computer_tool(&computer, "wait_until_text_visible", &json!({ "text": "Error", "timeout_ms": 400 })).await.unwrap();
+        assert!(matches!(result, Err(CuaError::ActionError(_))));
+    }
+    
+    #[tokio::test]
+    async fn test_call_clipboard_history_tool() {
+        let computer = MockComputer::new("linux", 1024, 768);
+        computer.set_clipboard("copied").await.unwrap();
+        
+        let output = call_computer_tool(&computer, "clipboard_history", &json!({ "max_entries": 5 }))
+            .await
+            .unwrap()
+            .unwrap();
+        
+        assert_eq!(output[0]["content"], "copied");
+        assert_eq!(output[0]["content_type"], "Text");
+        assert!(call_computer_tool(&computer, "unknown_tool", &json!({})).await.is_none());
+    }
+    
+    #[test]
+    fn test_network_tools_only_offered_when_supported() {
+        let computer = MockComputer::new("linux", 1024, 768);
+        let names = |tools: Vec<Value>| tools.iter().map(|t| t["name"].as_str().unwrap().to_string()).collect::<Vec<_>>();
+        
+        assert!(names(computer_tools_for(&computer)).contains(&"simulate_network_condition".to_string()));
+        computer.set_network_emulation_supported(false);
+        let offered = names(computer_tools_for(&computer));
+        assert!(NETWORK_TOOLS.iter().all(|name| !offered.contains(&name.to_string())));
+        assert_eq!(offered.len(), computer_tools().len() - NETWORK_TOOLS.len());
+    }
+    
+    #[tokio::test]
+    async fn test_clipboard_format_tool_recommends_reader() {
+        let computer = MockComputer::new("linux", 1024, 768);
+        computer.set_clipboard_format(ClipboardFormat { text: true, image: false, ..Default::default() });
+        
+        let output = call_computer_tool(&computer, "get_clipboard_format", &json!({})).await.unwrap().unwrap();
+        
+        assert_eq!(output, json!({ "text": true, "image": false, "html": false, "files": false, "recommended": "get_clipboard" }));
+        computer.set_clipboard_format(ClipboardFormat { image: true, ..Default::default() });
+        let output = call_computer_tool(&computer, "get_clipboard_format", &json!({})).await.unwrap().unwrap();
+        assert_eq!(output["recommended"], "paste_from_clipboard");
+        
+        // Both recommendations are tools the model can call
+        let tools: Vec<Value> = computer_tools().into_iter().map(|tool| tool["name"].clone()).collect();
+        assert!(tools.contains(&json!("get_clipboard")) && tools.contains(&json!("paste_from_clipboard")));
+        computer.set_clipboard("Total: 42").await.unwrap();
+        let output = call_computer_tool(&computer, "get_clipboard", &json!({})).await.unwrap().unwrap();
+        assert_eq!(output, json!("Total: 42"));
+    }
+    
+    #[tokio::test]
+    async fn test_pointer_and_key_tools() {
+        let computer = MockComputer::new("linux", 1024, 768);
+        let call = |name: &'static str, args: Value| {
+            let computer = computer.clone();
+            async move { call_computer_tool(&computer, name, &args).await.unwrap() }
+        };
+        
+        call("type_at", json!({ "x": 10, "y": 20, "text": "hi" })).await.unwrap();
+        call("middle_click", json!({ "x": 30, "y": 40 })).await.unwrap();
+        call("drag_by_delta", json!({ "start_x": 0, "start_y": 0, "delta_x": 20, "delta_y": 0, "steps": 2 })).await.unwrap();
+        call("press_and_hold", json!({ "key": "a", "duration_ms": 1 })).await.unwrap();
+        call("mouse_down", json!({ "button": "right" })).await.unwrap();
+        assert!(computer.held_buttons().contains("right"));
+        call("mouse_up", json!({ "button": "right" })).await.unwrap();
+        
+        let history: Vec<(String, Value)> = computer.action_history().into_iter().map(|r| (r.action_type, r.params)).collect();
+        assert_eq!(history, vec![
+            ("click".to_string(), json!({ "x": 10, "y": 20, "button": "left" })),
+            ("type".to_string(), json!({ "text": "hi" })),
+            ("click".to_string(), json!({ "x": 30, "y": 40, "button": "middle" })),
+            ("drag".to_string(), json!({ "path": [{ "x": 0, "y": 0 }, { "x": 10, "y": 0 }, { "x": 20, "y": 0 }] })),
+            ("press_and_hold".to_string(), json!({ "key": "a", "duration_ms": 1 })),
+            ("mouse_down".to_string(), json!({ "button": "right" })),
+            ("mouse_up".to_string(), json!({ "button": "right" })),
+        ]);
+    }
+    
+    #[tokio::test(start_paused = true)]
+    async fn test_press_and_hold_tool_caps_duration() {
+        let computer = MockComputer::new("linux", 1024, 768);
+        
+        call_computer_tool(&computer, "press_and_hold", &json!({ "key": "space", "duration_ms": 3_600_000 })).await.unwrap().unwrap();
+        
+        assert_eq!(computer.action_history()[0].params["duration_ms"], MAX_KEY_HOLD_MS);
+    }
+    
+    #[tokio::test]
+    async fn test_highlight_region_tool_caps_duration() {
+        let computer = MockComputer::new("linux", 1024, 768);
+        // Far beyond u32::MAX, which an unchecked cast would wrap
+        let args = json!({ "x": 10, "y": 10, "width": 50, "height": 50, "duration_ms": 5_000_000_000u64 });
+        
+        call_computer_tool(&computer, "highlight_region", &args).await.unwrap().unwrap();
+        
+        assert_eq!(computer.action_history()[0].params["duration_ms"], MAX_HIGHLIGHT_MS);
+    }
+    
+    #[tokio::test]
+    async fn test_set_clipboard_html_tool() {
+        let computer = MockComputer::new("linux", 1024, 768);
+        computer.set_clipboard("Total: 41").await.unwrap();
+        
+        call_computer_tool(&computer, "set_clipboard_html", &json!({ "html": "<b>Total:</b> 42" })).await.unwrap().unwrap();
+        
+        assert_eq!(computer.clipboard_html().as_deref(), Some("<b>Total:</b> 42"));
+        assert!(computer.get_clipboard().await.unwrap().is_empty());
+        let format = computer.get_clipboard_format().await.unwrap();
+        assert_eq!(format, ClipboardFormat { html: true, ..Default::default() });
+    }
+    
+    #[tokio::test]
+    async fn test_accessibility_tree_tool_serializes_tree() {
+        let leaf = |role: &str, name: &str, x: i32| AccessibilityNode {
+            role: role.to_string(),
+            name: name.to_string(),
+            value: None,
+            bounds: (x, 10, 80, 24),
+            children: Vec::new(),
+        };
+        let computer = MockComputer::new("linux", 1024, 768);
+        computer.set_accessibility_tree(AccessibilityNode {
+            role: "frame".to_string(),
+            name: "Settings".to_string(),
+            value: None,
+            bounds: (0, 0, 1024, 768),
+            children: vec![leaf("push button", "OK", 10), leaf("push button", "Cancel", 100)],
+        });
+        
+        let output = call_computer_tool(&computer, "accessibility_tree", &json!({}))
+            .await
+            .unwrap()
+            .unwrap();
+        
+        assert_eq!(output["role"], "frame");
+        assert_eq!(output["children"].as_array().unwrap().len(), 2);
+        assert_eq!(output["children"][1]["name"], "Cancel");
+        assert_eq!(output["children"][1]["bounds"], json!([100, 10, 80, 24]));
+        assert_eq!(output["children"][0]["value"], Value::Null);
+    }
+    
+    #[tokio::test]
+    async fn test_read_screen_text_tool() {
+        let computer = MockComputer::new("linux", 1920, 1080);
+        computer.set_region_text((100, 40, 300, 30), "Invoice #1042");
+        
+        let args = json!({ "x": 100, "y": 40, "width": 300, "height": 30 });
+        let output = call_computer_tool(&computer, "read_screen_text", &args).await.unwrap().unwrap();
+        
+        assert_eq!(output, json!("Invoice #1042"));
+    }
+    
+    #[tokio::test]
+    async fn test_get_page_source_tool() {
+        let computer = MockComputer::new("browser", 1280, 800);
+        computer.set_page_source("<body><form id=\"login\"></form><script>track()</script></body>");
+        
+        let output = call_computer_tool(&computer, "get_page_source", &json!({})).await.unwrap().unwrap();
+        assert_eq!(output, json!("<body><form id=\"login\"></form></body>"));
+        
+        // The model cannot raise the limit or keep scripts
+        let args = json!({ "strip_scripts": false, "max_bytes": 1_000_000_000 });
+        let output = call_computer_tool(&computer, "get_page_source", &args).await.unwrap().unwrap();
+        assert_eq!(output, json!("<body><form id=\"login\"></form></body>"));
+        let output = page_source(&computer, 6, false).await.unwrap();
+        assert_eq!(output, json!("<body>\n<!-- truncated at 6 bytes -->"));
+        
+        let desktop = MockComputer::new("linux", 1920, 1080);
+        assert!(call_computer_tool(&desktop, "get_page_source", &json!({})).await.unwrap().is_err());
+    }
+}
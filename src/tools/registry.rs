@@ -0,0 +1,141 @@
+//! Function tools loaded from a JSON schema at runtime
+//!
+//! A schema looks like `{ "tools": [ { "name", "description", "parameters" } ] }`.
+//! Each tool answers `{ "status": "not_implemented" }` until a handler is
+//! attached with [`ToolRegistry::attach_handler`].
+
+use super::{function_tool, ToolFn};
+use crate::error::CuaError;
+use async_trait::async_trait;
+use futures::future::BoxFuture;
+use serde_json::{json, Value};
+use std::path::Path;
+
+/// Handler for a schema-defined tool, called with the model's arguments
+pub type ToolHandler = Box<dyn Fn(&Value) -> BoxFuture<'static, Result<Value, CuaError>> + Send + Sync>;
+
+/// A tool from the schema and its handler, if one was attached
+struct SchemaTool {
+    definition: Value,
+    handler: Option<ToolHandler>,
+}
+
+#[async_trait]
+impl ToolFn for SchemaTool {
+    fn definition(&self) -> Value {
+        self.definition.clone()
+    }
+    
+    async fn call(&self, args: &Value) -> Result<Value, CuaError> {
+        match &self.handler {
+            Some(handler) => handler(args).await,
+            None => Ok(json!({ "status": "not_implemented" })),
+        }
+    }
+}
+
+/// Function tools defined by a JSON schema, in schema order
+#[derive(Default)]
+pub struct ToolRegistry {
+    tools: Vec<SchemaTool>,
+}
+
+impl ToolRegistry {
+    /// Parse a `{ "tools": [...] }` schema
+    ///
+    /// Every tool needs a unique, non-empty `name`; `description` defaults to
+    /// empty and `parameters` to an object with no properties.
+    pub fn from_json_schema(schema: Value) -> Result<Self, CuaError> {
+        let entries = schema["tools"]
+            .as_array()
+            .ok_or_else(|| CuaError::Other("Tool schema must have a \"tools\" array".to_string()))?;
+        
+        let mut registry = Self::default();
+        for (index, entry) in entries.iter().enumerate() {
+            let name = entry["name"].as_str().filter(|name| !name.is_empty()).ok_or_else(|| {
+                CuaError::Other(format!("Tool {} in the schema has no name", index))
+            })?;
+            if registry.names().any(|existing| existing == name) {
+                return Err(CuaError::Other(format!("Tool '{}' is defined more than once", name)));
+            }
+            let parameters = match &entry["parameters"] {
+                Value::Null => json!({ "type": "object", "properties": {} }),
+                parameters @ Value::Object(_) => parameters.clone(),
+                _ => return Err(CuaError::Other(format!("Parameters of tool '{}' must be an object", name))),
+            };
+            let description = entry["description"].as_str().unwrap_or("");
+            registry.tools.push(SchemaTool {
+                definition: function_tool(name, description, parameters),
+                handler: None,
+            });
+        }
+        Ok(registry)
+    }
+    
+    /// Read and parse a schema file
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, CuaError> {
+        let schema = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        Self::from_json_schema(schema)
+    }
+    
+    /// Handle calls to the tool `name` with `handler`, replacing any earlier handler
+    pub fn attach_handler(&mut self, name: &str, handler: ToolHandler) -> Result<(), CuaError> {
+        let tool = self.tools
+            .iter_mut()
+            .find(|tool| tool.definition["name"] == name)
+            .ok_or_else(|| CuaError::Other(format!("No tool named '{}' in the registry", name)))?;
+        tool.handler = Some(handler);
+        Ok(())
+    }
+    
+    /// Names of the registered tools
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.tools.iter().filter_map(|tool| tool.definition["name"].as_str())
+    }
+    
+    /// Definitions of the registered tools, as sent to the model
+    pub fn definitions(&self) -> Vec<Value> {
+        self.tools.iter().map(|tool| tool.definition.clone()).collect()
+    }
+    
+    /// Turn the registry into tools the agent can register with `with_tool_fn`
+    pub fn into_tool_fns(self) -> Vec<Box<dyn ToolFn>> {
+        self.tools.into_iter().map(|tool| Box::new(tool) as Box<dyn ToolFn>).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[tokio::test]
+    async fn test_stub_and_attached_handlers() {
+        let mut registry = ToolRegistry::from_json_schema(json!({
+            "tools": [
+                { "name": "lookup_ticket", "description": "Find a support ticket" },
+                { "name": "close_ticket", "parameters": { "type": "object", "properties": { "id": { "type": "string" } } } },
+            ],
+        }))
+        .unwrap();
+        registry
+            .attach_handler("close_ticket", Box::new(|args: &Value| {
+                let id = args["id"].clone();
+                Box::pin(async move { Ok(json!({ "closed": id })) })
+            }))
+            .unwrap();
+        assert!(registry.attach_handler("reopen_ticket", Box::new(|_: &Value| Box::pin(async { Ok(json!(null)) }))).is_err());
+        
+        let tools = registry.into_tool_fns();
+        assert_eq!(tools[0].definition()["parameters"], json!({ "type": "object", "properties": {} }));
+        assert_eq!(tools[0].call(&json!({})).await.unwrap(), json!({ "status": "not_implemented" }));
+        assert_eq!(tools[1].call(&json!({ "id": "T-42" })).await.unwrap(), json!({ "closed": "T-42" }));
+    }
+    
+    #[test]
+    fn test_invalid_schemas() {
+        assert!(ToolRegistry::from_json_schema(json!({ "tool": [] })).is_err());
+        assert!(ToolRegistry::from_json_schema(json!({ "tools": [{ "description": "No name" }] })).is_err());
+        assert!(ToolRegistry::from_json_schema(json!({ "tools": [{ "name": "a" }, { "name": "a" }] })).is_err());
+        assert!(ToolRegistry::from_json_schema(json!({ "tools": [{ "name": "a", "parameters": "none" }] })).is_err());
+    }
+}
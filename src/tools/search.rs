@@ -0,0 +1,193 @@
+//! Web search function tool backed by the Brave Search API
+
+use super::{function_tool, ToolFn};
+use crate::error::CuaError;
+use crate::rate_limiter::ActionRateLimiter;
+use async_trait::async_trait;
+use hyper::client::HttpConnector;
+use hyper::{body::to_bytes, Body, Client, Method, Request};
+use hyper_tls::HttpsConnector;
+use serde_json::{json, Value};
+use std::env;
+
+/// Brave Search API endpoint
+const DEFAULT_BASE_URL: &str = "https://api.search.brave.com/res/v1";
+
+/// Number of results returned to the model
+const MAX_RESULTS: usize = 5;
+
+/// Default number of searches allowed per minute
+pub const DEFAULT_SEARCHES_PER_MINUTE: f64 = 10.0;
+
+/// Percent-encode a query string value
+fn encode_query(query: &str) -> String {
+    query
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+            b' ' => "+".to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// Function tool letting the model search the web for current information
+pub struct WebSearchTool {
+    api_key: String,
+    client: Client<HttpsConnector<HttpConnector>>,
+    base_url: String,
+    rate_limiter: ActionRateLimiter,
+}
+
+impl WebSearchTool {
+    /// Create a tool using the given Brave Search API key
+    pub fn new(api_key: &str) -> Self {
+        let mut http = HttpConnector::new();
+        http.enforce_http(false);
+        Self {
+            api_key: api_key.to_string(),
+            client: Client::builder().build(HttpsConnector::new_with_connector(http)),
+            base_url: DEFAULT_BASE_URL.to_string(),
+            rate_limiter: ActionRateLimiter::new(DEFAULT_SEARCHES_PER_MINUTE)
+                .expect("default search rate is valid"),
+        }
+    }
+    
+    /// Create a tool using the `BRAVE_API_KEY` environment variable
+    pub fn from_env() -> Result<Self, CuaError> {
+        let api_key = env::var("BRAVE_API_KEY")
+            .map_err(|_| CuaError::Other("BRAVE_API_KEY environment variable not set".to_string()))?;
+        Ok(Self::new(&api_key))
+    }
+    
+    /// Set the base URL of the search API (e.g. for a local test server)
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+    
+    /// Limit how many searches may run per minute (default 10)
+    ///
+    /// Fails if the rate is not a positive, finite number.
+    pub fn with_max_searches_per_minute(mut self, searches_per_minute: f64) -> Result<Self, CuaError> {
+        self.rate_limiter = ActionRateLimiter::new(searches_per_minute)?;
+        Ok(self)
+    }
+    
+    /// Search the web and return the top results as `{ title, url, snippet }` objects
+    pub async fn search(&self, query: &str) -> Result<Vec<Value>, CuaError> {
+        if query.trim().is_empty() {
+            return Err(CuaError::ActionError("Search query is empty".to_string()));
+        }
+        self.rate_limiter.acquire().await;
+        
+        let request = Request::builder()
+            .method(Method::GET)
+            .uri(format!("{}/web/search?q={}&count={}", self.base_url, encode_query(query), MAX_RESULTS))
+            .header("Accept", "application/json")
+            .header("X-Subscription-Token", &self.api_key)
+            .body(Body::empty())?;
+        let response = self.client.request(request).await?;
+        let status = response.status();
+        let body = to_bytes(response.into_body()).await?;
+        if !status.is_success() {
+            return Err(CuaError::ApiError(format!(
+                "Search API returned error {}: {}",
+                status.as_u16(),
+                String::from_utf8_lossy(&body)
+            )));
+        }
+        
+        let body: Value = serde_json::from_slice(&body)?;
+        let results = body["web"]["results"].as_array().cloned().unwrap_or_default();
+        Ok(results
+            .iter()
+            .take(MAX_RESULTS)
+            .map(|result| json!({
+                "title": result["title"].as_str().unwrap_or(""),
+                "url": result["url"].as_str().unwrap_or(""),
+                "snippet": result["description"].as_str().unwrap_or(""),
+            }))
+            .collect())
+    }
+}
+
+#[async_trait]
+impl ToolFn for WebSearchTool {
+    fn definition(&self) -> Value {
+        function_tool(
+            "web_search",
+            "Search the web for current information you don't know, such as recent events, \
+             documentation or addresses. Returns the top 5 results as { title, url, snippet }. \
+             Use plain keywords; wrap phrases in double quotes for exact matches, prefix a word \
+             with - to exclude it, and add site:example.com to search a single site.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "query": {
+                        "type": "string",
+                        "description": "Search query, e.g. \"rust async traits\" site:docs.rs",
+                    },
+                },
+                "required": ["query"],
+            }),
+        )
+    }
+    
+    async fn call(&self, args: &Value) -> Result<Value, CuaError> {
+        let query = args.get("query").and_then(|q| q.as_str()).unwrap_or("");
+        self.search(query).await.map(Value::Array)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_api::MockApi;
+    
+    #[test]
+    fn test_encode_query() {
+        assert_eq!(encode_query("rust \"async\" site:docs.rs"), "rust+%22async%22+site%3Adocs.rs");
+    }
+    
+    #[tokio::test]
+    async fn test_search_returns_top_results() {
+        let server = MockApi::start().await;
+        let results: Vec<Value> = (1..=7)
+            .map(|n| json!({
+                "title": format!("Result {}", n),
+                "url": format!("https://example.com/{}", n),
+                "description": format!("Snippet {}", n),
+                "age": "1 day ago",
+            }))
+            .collect();
+        server.push_response(200, json!({ "type": "search", "web": { "results": results } }));
+        
+        let tool = WebSearchTool::new("brave_key").with_base_url(&server.base_url());
+        let output = tool.call(&json!({ "query": "rust news" })).await.unwrap();
+        
+        let output = output.as_array().unwrap();
+        assert_eq!(output.len(), 5);
+        assert_eq!(output[0], json!({
+            "title": "Result 1",
+            "url": "https://example.com/1",
+            "snippet": "Snippet 1",
+        }));
+        
+        let requests = server.requests();
+        assert_eq!(requests[0].method, "GET");
+        assert_eq!(requests[0].path, "/v1/web/search?q=rust+news&count=5");
+    }
+    
+    #[tokio::test]
+    async fn test_search_error_status() {
+        let server = MockApi::start().await;
+        server.push_response(401, json!({ "error": "invalid token" }));
+        
+        let tool = WebSearchTool::new("bad_key").with_base_url(&server.base_url());
+        let result = tool.call(&json!({ "query": "rust" })).await;
+        
+        assert!(matches!(result, Err(CuaError::ApiError(msg)) if msg.contains("401")));
+        assert!(tool.call(&json!({})).await.is_err());
+    }
+}
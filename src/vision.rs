@@ -0,0 +1,236 @@
+//! Image helpers shared by the screenshot processing features
+
+use crate::error::CuaError;
+use base64::{engine::general_purpose, Engine};
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::PngEncoder;
+use image::imageops;
+use image::{DynamicImage, ImageEncoder, Rgba, RgbaImage};
+use imageproc::drawing::draw_hollow_circle_mut;
+use std::sync::OnceLock;
+
+/// Arrow cursor drawn by `draw_cursor`, with its hotspot at the top-left pixel
+const CURSOR_PNG: &[u8] = include_bytes!("../assets/cursor.png");
+
+/// Decode a base64-encoded screenshot (PNG or any format `image` can guess)
+pub fn decode_screenshot(base64_image: &str) -> Result<DynamicImage, CuaError> {
+    let bytes = general_purpose::STANDARD.decode(base64_image)
+        .map_err(|e| CuaError::ScreenshotError(format!("Invalid base64 screenshot: {}", e)))?;
+    image::load_from_memory(&bytes)
+        .map_err(|e| CuaError::ScreenshotError(format!("Failed to decode screenshot: {}", e)))
+}
+
+/// Encode an image as a base64 PNG
+pub fn encode_png(image: &DynamicImage) -> Result<String, CuaError> {
+    let rgba = image.to_rgba8();
+    raw_to_base64_png(rgba.as_raw(), rgba.width(), rgba.height())
+}
+
+/// Encode raw RGBA pixels, as from `Computer::screenshot_as_array`, as a base64 PNG
+pub fn raw_to_base64_png(data: &[u8], width: u32, height: u32) -> Result<String, CuaError> {
+    let mut buffer = Vec::new();
+    PngEncoder::new(&mut buffer)
+        .write_image(data, width, height, image::ColorType::Rgba8)
+        .map_err(|e| CuaError::ScreenshotError(format!("Failed to encode PNG: {}", e)))?;
+    Ok(general_purpose::STANDARD.encode(&buffer))
+}
+
+/// Highest quality `encode_jpeg` encodes at; above it files grow with little visible gain
+pub const MAX_JPEG_QUALITY: u8 = 95;
+
+/// Encode raw RGBA pixels as a base64 JPEG, with `quality` clamped to 1-95
+///
+/// JPEG has no alpha channel, so transparency is dropped.
+pub fn encode_jpeg(rgba: &[u8], width: u32, height: u32, quality: u8) -> Result<String, CuaError> {
+    let mut buffer = Vec::new();
+    JpegEncoder::new_with_quality(&mut buffer, quality.clamp(1, MAX_JPEG_QUALITY))
+        .encode(rgba, width, height, image::ColorType::Rgba8)
+        .map_err(|e| CuaError::ScreenshotError(format!("Failed to encode JPEG: {}", e)))?;
+    Ok(general_purpose::STANDARD.encode(&buffer))
+}
+
+/// Whether a base64 image is a JPEG, judging by its SOI marker
+pub fn is_jpeg(base64_image: &str) -> bool {
+    // 0xFF 0xD8 0xFF encodes to "/9j/"
+    base64_image.starts_with("/9j/")
+}
+
+/// Scale factor (at most 1.0) that fits `width`x`height` within `max_width`x`max_height`
+pub fn fit_scale(width: u32, height: u32, max_width: u32, max_height: u32) -> f64 {
+    let scale_x = max_width as f64 / width.max(1) as f64;
+    let scale_y = max_height as f64 / height.max(1) as f64;
+    scale_x.min(scale_y).min(1.0)
+}
+
+/// Crop a base64 screenshot to the `width`x`height` region at (x, y) and return a base64 PNG
+///
+/// The region is clipped to the image bounds.
+pub fn crop_screenshot(screenshot_base64: &str, x: u32, y: u32, width: u32, height: u32) -> Result<String, CuaError> {
+    let mut image = decode_screenshot(screenshot_base64)?;
+    let region = imageops::crop(&mut image, x, y, width, height).to_image();
+    if region.width() == 0 || region.height() == 0 {
+        return Err(CuaError::ActionError(format!(
+            "Region {}x{} at ({}, {}) is outside the screenshot",
+            width, height, x, y
+        )));
+    }
+    encode_png(&region.into())
+}
+
+/// Bounding box (x, y, width, height) of the pixels that differ between two screenshots
+///
+/// Returns `None` if the screenshots are identical, and an error if their sizes differ.
+pub fn screenshot_diff(before_base64: &str, after_base64: &str) -> Result<Option<(u32, u32, u32, u32)>, CuaError> {
+    let before = decode_screenshot(before_base64)?.to_rgba8();
+    let after = decode_screenshot(after_base64)?.to_rgba8();
+    if before.dimensions() != after.dimensions() {
+        return Err(CuaError::ScreenshotError(format!(
+            "Cannot diff a {:?} screenshot against a {:?} one",
+            before.dimensions(), after.dimensions()
+        )));
+    }
+    
+    let mut changed: Option<(u32, u32, u32, u32)> = None;
+    for (x, y, pixel) in after.enumerate_pixels() {
+        if before.get_pixel(x, y) != pixel {
+            let (left, top, right, bottom) = changed.unwrap_or((x, y, x, y));
+            changed = Some((left.min(x), top.min(y), right.max(x), bottom.max(y)));
+        }
+    }
+    Ok(changed.map(|(left, top, right, bottom)| (left, top, right - left + 1, bottom - top + 1)))
+}
+
+/// Fraction of pixels that differ between two screenshots, from 0.0 (identical) to 1.0
+///
+/// Fails if the screenshots' sizes differ.
+pub fn screenshot_diff_score(before_base64: &str, after_base64: &str) -> Result<f64, CuaError> {
+    if before_base64 == after_base64 {
+        return Ok(0.0);
+    }
+    let before = decode_screenshot(before_base64)?.to_rgba8();
+    let after = decode_screenshot(after_base64)?.to_rgba8();
+    if before.dimensions() != after.dimensions() {
+        return Err(CuaError::ScreenshotError(format!(
+            "Cannot diff a {:?} screenshot against a {:?} one",
+            before.dimensions(), after.dimensions()
+        )));
+    }
+    
+    let changed = before.pixels().zip(after.pixels()).filter(|(old, new)| old != new).count();
+    Ok(changed as f64 / (after.width() as f64 * after.height() as f64).max(1.0))
+}
+
+/// How `take_screenshot_diff` renders pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiffConfig {
+    /// Alpha of the grayscale unchanged pixels, from 0 (hidden) to 255 (opaque)
+    pub unchanged_alpha: u8,
+    /// Color changed pixels are painted with
+    pub highlight_color: (u8, u8, u8),
+}
+
+impl Default for DiffConfig {
+    fn default() -> Self {
+        Self {
+            unchanged_alpha: 128,
+            highlight_color: (255, 0, 0),
+        }
+    }
+}
+
+/// Render what changed between two screenshots as a base64 PNG, with the default `DiffConfig`
+pub fn take_screenshot_diff(before_base64: &str, after_base64: &str) -> Result<String, CuaError> {
+    take_screenshot_diff_with_config(before_base64, after_base64, &DiffConfig::default())
+}
+
+/// Render what changed between two screenshots as a base64 PNG
+///
+/// Unchanged pixels are drawn in gray, changed ones in the highlight color.
+/// Fails if the screenshots' sizes differ.
+pub fn take_screenshot_diff_with_config(
+    before_base64: &str,
+    after_base64: &str,
+    config: &DiffConfig,
+) -> Result<String, CuaError> {
+    let before = decode_screenshot(before_base64)?.to_rgba8();
+    let after = decode_screenshot(after_base64)?.to_rgba8();
+    if before.dimensions() != after.dimensions() {
+        return Err(CuaError::ScreenshotError(format!(
+            "Cannot diff a {:?} screenshot against a {:?} one",
+            before.dimensions(), after.dimensions()
+        )));
+    }
+    
+    let (r, g, b) = config.highlight_color;
+    let diff = RgbaImage::from_fn(after.width(), after.height(), |x, y| {
+        let old = before.get_pixel(x, y);
+        if old != after.get_pixel(x, y) {
+            return Rgba([r, g, b, 255]);
+        }
+        let [red, green, blue, _] = old.0;
+        let gray = ((red as u32 * 299 + green as u32 * 587 + blue as u32 * 114) / 1000) as u8;
+        Rgba([gray, gray, gray, config.unchanged_alpha])
+    });
+    encode_png(&diff.into())
+}
+
+/// Draw an arrow cursor with its tip at (x, y) on a base64 screenshot and return a base64 PNG
+pub fn draw_cursor(screenshot_base64: &str, x: i32, y: i32) -> Result<String, CuaError> {
+    static CURSOR: OnceLock<DynamicImage> = OnceLock::new();
+    let cursor = CURSOR.get_or_init(|| {
+        image::load_from_memory(CURSOR_PNG).expect("bundled cursor image is a valid PNG")
+    });
+    
+    let mut image = decode_screenshot(screenshot_base64)?;
+    imageops::overlay(&mut image, cursor, x as i64, y as i64);
+    encode_png(&image)
+}
+
+/// Draw a red ring centred on (x, y) on a base64 screenshot and return a base64 PNG
+pub fn draw_target(screenshot_base64: &str, x: i32, y: i32) -> Result<String, CuaError> {
+    let mut image = decode_screenshot(screenshot_base64)?.to_rgba8();
+    for radius in 10..=13 {
+        draw_hollow_circle_mut(&mut image, (x, y), radius, Rgba([255, 0, 0, 255]));
+    }
+    encode_png(&image.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_screenshot_diff_identical_is_gray() {
+        let screen = encode_png(&RgbaImage::from_pixel(40, 30, Rgba([200, 100, 50, 255])).into()).unwrap();
+        
+        let diff = decode_screenshot(&take_screenshot_diff(&screen, &screen).unwrap()).unwrap().to_rgba8();
+        
+        assert_eq!(diff.dimensions(), (40, 30));
+        assert!(diff.pixels().all(|p| p[0] == p[1] && p[1] == p[2] && p[3] == 128));
+    }
+    
+    #[test]
+    fn test_screenshot_diff_all_changed_is_highlighted() {
+        let before = encode_png(&RgbaImage::from_pixel(40, 30, Rgba([0, 0, 0, 255])).into()).unwrap();
+        let after = encode_png(&RgbaImage::from_pixel(40, 30, Rgba([255, 255, 255, 255])).into()).unwrap();
+        let config = DiffConfig { highlight_color: (0, 255, 0), ..DiffConfig::default() };
+        
+        let diff = take_screenshot_diff_with_config(&before, &after, &config).unwrap();
+        let diff = decode_screenshot(&diff).unwrap().to_rgba8();
+        
+        assert!(diff.pixels().all(|p| *p == Rgba([0, 255, 0, 255])));
+        let small = encode_png(&RgbaImage::new(10, 10).into()).unwrap();
+        assert!(take_screenshot_diff(&before, &small).is_err());
+    }
+    
+    #[test]
+    fn test_encode_jpeg_clamps_quality() {
+        let image = RgbaImage::from_fn(64, 48, |x, y| Rgba([(x * 4) as u8, (y * 5) as u8, 90, 255]));
+        let encode = |quality| encode_jpeg(image.as_raw(), 64, 48, quality).unwrap();
+        
+        assert!(is_jpeg(&encode(75)));
+        assert_eq!(encode(255), encode(MAX_JPEG_QUALITY));
+        assert_eq!(encode(0), encode(1));
+        assert_eq!(decode_screenshot(&encode(0)).unwrap().width(), 64);
+    }
+}
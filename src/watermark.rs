@@ -0,0 +1,149 @@
+//! Text watermarks on screenshots, to tell recorded sessions apart
+
+use crate::error::CuaError;
+use crate::vision;
+use image::{Rgba, RgbaImage};
+use imageproc::drawing::{draw_filled_rect_mut, draw_text_mut, text_size};
+use imageproc::rect::Rect;
+use rusttype::{Font, Scale};
+use std::sync::OnceLock;
+
+/// Fonts tried, in order, for drawing watermark text
+const FONT_PATHS: &[&str] = &[
+    "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+    "/usr/share/fonts/TTF/DejaVuSans.ttf",
+    "/usr/share/fonts/truetype/liberation/LiberationSans-Regular.ttf",
+    "/System/Library/Fonts/Supplemental/Arial.ttf",
+    "/Library/Fonts/Arial.ttf",
+    "C:\\Windows\\Fonts\\arial.ttf",
+];
+
+/// Margin between the watermark and the image edges, and around the text
+const PADDING: i32 = 6;
+
+/// Corner of the screenshot the watermark is drawn in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+/// Settings for the screenshot watermark
+#[derive(Debug, Clone, PartialEq)]
+pub struct WatermarkConfig {
+    /// Text identifying the session
+    pub text: String,
+    /// Where to place the watermark
+    pub position: Corner,
+    /// Opacity from 0 (invisible) to 255 (opaque)
+    pub opacity: u8,
+    /// Text height in pixels
+    pub font_size: u32,
+}
+
+impl WatermarkConfig {
+    /// Watermark with the given text in the bottom-right corner
+    pub fn new(text: &str) -> Self {
+        Self {
+            text: text.to_string(),
+            position: Corner::BottomRight,
+            opacity: 160,
+            font_size: 16,
+        }
+    }
+}
+
+/// Load the first available system font, once
+fn font() -> Option<&'static Font<'static>> {
+    static FONT: OnceLock<Option<Font<'static>>> = OnceLock::new();
+    FONT.get_or_init(|| {
+        let font = FONT_PATHS
+            .iter()
+            .filter_map(|path| std::fs::read(path).ok())
+            .find_map(Font::try_from_vec);
+        if font.is_none() {
+            tracing::warn!("No font found for screenshot watermarks, drawing the label box only");
+        }
+        font
+    })
+    .as_ref()
+}
+
+/// Draw `label` over a base64 screenshot and return the result as a base64 PNG
+pub fn apply_watermark(screenshot_base64: &str, config: &WatermarkConfig, label: &str) -> Result<String, CuaError> {
+    let mut image = vision::decode_screenshot(screenshot_base64)?.to_rgba8();
+    draw_watermark(&mut image, config, label);
+    vision::encode_png(&image.into())
+}
+
+/// Draw `label` in a semi-transparent box in the configured corner
+pub(crate) fn draw_watermark(image: &mut RgbaImage, config: &WatermarkConfig, label: &str) {
+    let scale = Scale::uniform(config.font_size as f32);
+    let font = font();
+    let (text_width, text_height) = match font {
+        Some(font) => text_size(scale, font, label),
+        None => (label.chars().count() as i32 * config.font_size as i32 / 2, config.font_size as i32),
+    };
+    
+    let box_width = (text_width + 2 * PADDING).min(image.width() as i32);
+    let box_height = (text_height + 2 * PADDING).min(image.height() as i32);
+    let left = match config.position {
+        Corner::TopLeft | Corner::BottomLeft => PADDING,
+        Corner::TopRight | Corner::BottomRight => image.width() as i32 - box_width - PADDING,
+    }
+    .max(0);
+    let top = match config.position {
+        Corner::TopLeft | Corner::TopRight => PADDING,
+        Corner::BottomLeft | Corner::BottomRight => image.height() as i32 - box_height - PADDING,
+    }
+    .max(0);
+    
+    // Draw opaquely on a copy, then blend the label area back at the configured opacity
+    let mut overlay = image.clone();
+    let label_box = Rect::at(left, top).of_size(box_width.max(1) as u32, box_height.max(1) as u32);
+    draw_filled_rect_mut(&mut overlay, label_box, Rgba([0, 0, 0, 255]));
+    if let Some(font) = font {
+        draw_text_mut(&mut overlay, Rgba([255, 255, 255, 255]), left + PADDING, top + PADDING, scale, font, label);
+    }
+    
+    let alpha = config.opacity as f32 / 255.0;
+    for y in label_box.top()..=label_box.bottom().min(image.height() as i32 - 1) {
+        for x in label_box.left()..=label_box.right().min(image.width() as i32 - 1) {
+            let (x, y) = (x as u32, y as u32);
+            let over = overlay.get_pixel(x, y).0;
+            let pixel = image.get_pixel_mut(x, y);
+            for (channel, over) in pixel.0.iter_mut().zip(over).take(3) {
+                *channel = (*channel as f32 * (1.0 - alpha) + over as f32 * alpha).round() as u8;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_watermark_drawn_in_corner() {
+        let background = Rgba([200, 220, 240, 255]);
+        let screen = RgbaImage::from_pixel(400, 300, background);
+        let screenshot = vision::encode_png(&screen.into()).unwrap();
+        let config = WatermarkConfig {
+            position: Corner::TopLeft,
+            ..WatermarkConfig::new("session-42")
+        };
+        
+        let marked = apply_watermark(&screenshot, &config, "session-42 | turn 1").unwrap();
+        let marked = vision::decode_screenshot(&marked).unwrap().to_rgba8();
+        
+        let changed_in = |x_range: std::ops::Range<u32>, y_range: std::ops::Range<u32>| {
+            y_range.flat_map(|y| x_range.clone().map(move |x| (x, y)))
+                .filter(|&(x, y)| *marked.get_pixel(x, y) != background)
+                .count()
+        };
+        assert!(changed_in(0..200, 0..40) > 100);
+        assert_eq!(changed_in(200..400, 150..300), 0);
+    }
+}